@@ -0,0 +1,7 @@
+precision mediump float;
+
+uniform float uVar;
+
+void main() {
+    gl_FragColor = vec4(0, 1, 0, 1);
+}
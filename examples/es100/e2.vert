@@ -0,0 +1,13 @@
+precision mediump float;
+
+uniform mat4 uProjectionMatrix;
+uniform mat4 uModelViewMatrix;
+uniform float uTime;
+attribute vec3 aVertPos;
+varying vec3 vXYZ;
+
+void main() {
+    vec4 pos = vec4(aVertPos.x, aVertPos.y, aVertPos.z, 1.0);
+    gl_Position = uProjectionMatrix * uModelViewMatrix * pos;
+    vXYZ = aVertPos;
+}
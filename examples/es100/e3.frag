@@ -0,0 +1,14 @@
+precision mediump float;
+
+uniform mat4 uProjectionMatrix;
+uniform mat4 uModelViewMatrix;
+uniform float uTime;
+varying vec3 vXYZ;
+
+void main() {
+    float r = vXYZ[0];
+    float g = vXYZ[1];
+    float b = vXYZ[2];
+    vec4 col = vec4(r, cos(g), b, 1.0);
+    gl_FragColor = col;
+}
@@ -0,0 +1,11 @@
+precision mediump float;
+
+uniform vec4 uPos;
+uniform vec4 uColor;
+uniform float uBrightness;
+varying float vBright;
+
+void main() {
+    vec4 col = uColor;
+    gl_FragColor = col;
+}
@@ -0,0 +1,20 @@
+precision mediump float;
+
+uniform vec4 uPos;
+uniform vec4 uColor;
+uniform float uBrightness;
+attribute vec3 aVertPos;
+varying float vBright;
+
+float limit(float x) {
+    if (x > 1.0) {
+        return 1.0;
+    } else {
+        return x;
+    }
+}
+
+void main() {
+    gl_Position = uPos;
+    vBright = limit(uBrightness);
+}
@@ -0,0 +1,8 @@
+precision mediump float;
+
+uniform float uOffset;
+varying vec2 vXY;
+
+void main() {
+    gl_FragColor = vec4(vXY[0], vXY[1], 0.0, 1.0);
+}
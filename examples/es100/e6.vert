@@ -0,0 +1,10 @@
+precision mediump float;
+
+uniform float uOffset;
+attribute vec3 aVertPos;
+varying vec2 vXY;
+
+void main() {
+    gl_Position = vec4(aVertPos[0], aVertPos[1], aVertPos[2], 1.0);
+    vXY = vec2(aVertPos[0] + uOffset, aVertPos[1] + uOffset);
+}
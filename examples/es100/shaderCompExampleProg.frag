@@ -0,0 +1,16 @@
+precision mediump float;
+
+uniform mat4 uProjectionMatrix;
+uniform mat4 uModelViewMatrix;
+uniform float uTime;
+varying vec3 vXYZ;
+varying vec4 pos;
+
+void main() {
+    vec4 col = vec4(0.5, 0.5, 0.5, 1.0);
+    gl_FragColor = col;
+    float r = pos[0];
+    float g = sin(pos[1] + uTime * 3.7);
+    float b = pos[2];
+    gl_FragColor = vec4(r, g, b, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 330 core
+
+uniform mat4 uProjectionMatrix;
+uniform mat4 uModelViewMatrix;
+uniform float uTime;
+layout(location = 0) in vec3 aVertPos;
+out vec3 vXYZ;
+
+void main() {
+    vec4 pos = vec4(aVertPos.x * cos(uTime), aVertPos.y, aVertPos.z, 1.0);
+    gl_Position = uProjectionMatrix * uModelViewMatrix * pos;
+    vXYZ = aVertPos;
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+uniform mat4 uProjectionMatrix;
+uniform mat4 uModelViewMatrix;
+uniform float uTime;
+in vec3 vXYZ;
+layout(location = 0) out vec4 fdssl_FragColor;
+
+void main() {
+    float r = vXYZ[0];
+    float g = vXYZ[1] + uTime * 3.7;
+    float b = vXYZ[2];
+    vec4 col = vec4(r, cos(g), b, 1.0);
+    fdssl_FragColor = col;
+}
@@ -0,0 +1,20 @@
+#version 330 core
+
+uniform vec4 uPos;
+uniform vec4 uColor;
+uniform float uBrightness;
+layout(location = 0) in vec3 aVertPos;
+out float vBright;
+
+float limit(float x) {
+    if (x > 1.0) {
+        return 1.0;
+    } else {
+        return x;
+    }
+}
+
+void main() {
+    gl_Position = uPos;
+    vBright = limit(uBrightness);
+}
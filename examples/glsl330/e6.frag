@@ -0,0 +1,9 @@
+#version 330 core
+
+uniform float uOffset;
+in vec2 vXY;
+layout(location = 0) out vec4 fdssl_FragColor;
+
+void main() {
+    fdssl_FragColor = vec4(vXY[0], vXY[1], 0.0, 1.0);
+}
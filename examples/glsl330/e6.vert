@@ -0,0 +1,10 @@
+#version 330 core
+
+uniform float uOffset;
+layout(location = 0) in vec3 aVertPos;
+out vec2 vXY;
+
+void main() {
+    gl_Position = vec4(aVertPos[0], aVertPos[1], aVertPos[2], 1.0);
+    vXY = vec2(aVertPos[0] + uOffset, aVertPos[1] + uOffset);
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+uniform mat4 uProjectionMatrix;
+uniform mat4 uModelViewMatrix;
+uniform float uTime;
+layout(location = 0) in vec3 aVertPos;
+out vec3 vXYZ;
+out vec4 pos;
+
+void main() {
+    pos = vec4(aVertPos.x, sin(aVertPos.y + uTime * 3.7), aVertPos.z, 1.0);
+    gl_Position = pos;
+    gl_Position = uProjectionMatrix * uModelViewMatrix * pos;
+    vXYZ = vec3(pos.x, pos.y, pos.z);
+}
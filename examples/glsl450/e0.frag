@@ -0,0 +1,8 @@
+#version 450
+
+uniform float uVar;
+layout(location = 0) out vec4 fdssl_FragColor;
+
+void main() {
+    fdssl_FragColor = vec4(0, 1, 0, 1);
+}
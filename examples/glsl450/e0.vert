@@ -0,0 +1,7 @@
+#version 450
+
+uniform float uVar;
+
+void main() {
+    gl_Position = vec4(0, 1, 0, 1);
+}
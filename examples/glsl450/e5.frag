@@ -0,0 +1,12 @@
+#version 450
+
+uniform vec4 uPos;
+uniform vec4 uColor;
+uniform float uBrightness;
+layout(location = 0) in float vBright;
+layout(location = 0) out vec4 fdssl_FragColor;
+
+void main() {
+    vec4 col = uColor;
+    fdssl_FragColor = col;
+}
@@ -0,0 +1,20 @@
+#version 450
+
+uniform vec4 uPos;
+uniform vec4 uColor;
+uniform float uBrightness;
+layout(location = 0) in vec3 aVertPos;
+layout(location = 0) out float vBright;
+
+float limit(float x) {
+    if (x > 1.0) {
+        return 1.0;
+    } else {
+        return x;
+    }
+}
+
+void main() {
+    gl_Position = uPos;
+    vBright = limit(uBrightness);
+}
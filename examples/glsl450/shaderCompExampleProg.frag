@@ -0,0 +1,17 @@
+#version 450
+
+uniform mat4 uProjectionMatrix;
+uniform mat4 uModelViewMatrix;
+uniform float uTime;
+layout(location = 0) in vec3 vXYZ;
+layout(location = 1) in vec4 pos;
+layout(location = 0) out vec4 fdssl_FragColor;
+
+void main() {
+    vec4 col = vec4(0.5, 0.5, 0.5, 1.0);
+    fdssl_FragColor = col;
+    float r = pos[0];
+    float g = sin(pos[1] + uTime * 3.7);
+    float b = pos[2];
+    fdssl_FragColor = vec4(r, g, b, 1.0);
+}
@@ -0,0 +1,16 @@
+struct Uniforms {
+    uVar: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct FragmentOutput {
+    @location(0) fdssl_FragColor: vec4<f32>,
+}
+
+@fragment
+fn main() -> FragmentOutput {
+    var fdssl_out: FragmentOutput;
+    fdssl_out.fdssl_FragColor = vec4<f32>(0.0f, 1.0f, 0.0f, 1.0f);
+    return fdssl_out;
+}
@@ -0,0 +1,16 @@
+struct Uniforms {
+    uVar: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) fdssl_Position: vec4<f32>,
+}
+
+@vertex
+fn main() -> VertexOutput {
+    var fdssl_out: VertexOutput;
+    fdssl_out.fdssl_Position = vec4<f32>(0.0f, 1.0f, 0.0f, 1.0f);
+    return fdssl_out;
+}
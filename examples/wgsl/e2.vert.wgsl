@@ -0,0 +1,21 @@
+struct Uniforms {
+    uProjectionMatrix: mat4x4<f32>,
+    uModelViewMatrix: mat4x4<f32>,
+    uTime: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) fdssl_Position: vec4<f32>,
+    @location(0) vXYZ: vec3<f32>,
+}
+
+@vertex
+fn main(@location(0) aVertPos: vec3<f32>) -> VertexOutput {
+    var fdssl_out: VertexOutput;
+    var pos: vec4<f32> = vec4<f32>(aVertPos.x, aVertPos.y, aVertPos.z, 1.0f);
+    fdssl_out.fdssl_Position = fdssl_u.uProjectionMatrix * fdssl_u.uModelViewMatrix * pos;
+    fdssl_out.vXYZ = aVertPos;
+    return fdssl_out;
+}
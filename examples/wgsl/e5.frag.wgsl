@@ -0,0 +1,19 @@
+struct Uniforms {
+    uPos: vec4<f32>,
+    uColor: vec4<f32>,
+    uBrightness: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct FragmentOutput {
+    @location(0) fdssl_FragColor: vec4<f32>,
+}
+
+@fragment
+fn main(@location(0) vBright: f32) -> FragmentOutput {
+    var fdssl_out: FragmentOutput;
+    var col: vec4<f32> = fdssl_u.uColor;
+    fdssl_out.fdssl_FragColor = col;
+    return fdssl_out;
+}
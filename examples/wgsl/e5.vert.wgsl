@@ -0,0 +1,28 @@
+struct Uniforms {
+    uPos: vec4<f32>,
+    uColor: vec4<f32>,
+    uBrightness: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) fdssl_Position: vec4<f32>,
+    @location(0) vBright: f32,
+}
+
+fn limit(x: f32) -> f32 {
+    if (x > 1.0f) {
+        return 1.0f;
+    } else {
+        return x;
+    }
+}
+
+@vertex
+fn main(@location(0) aVertPos: vec3<f32>) -> VertexOutput {
+    var fdssl_out: VertexOutput;
+    fdssl_out.fdssl_Position = fdssl_u.uPos;
+    fdssl_out.vBright = limit(fdssl_u.uBrightness);
+    return fdssl_out;
+}
@@ -0,0 +1,16 @@
+struct Uniforms {
+    uOffset: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct FragmentOutput {
+    @location(0) fdssl_FragColor: vec4<f32>,
+}
+
+@fragment
+fn main(@location(0) vXY: vec2<f32>) -> FragmentOutput {
+    var fdssl_out: FragmentOutput;
+    fdssl_out.fdssl_FragColor = vec4<f32>(vXY[0i], vXY[1i], 0.0f, 1.0f);
+    return fdssl_out;
+}
@@ -0,0 +1,18 @@
+struct Uniforms {
+    uOffset: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) fdssl_Position: vec4<f32>,
+    @location(0) vXY: vec2<f32>,
+}
+
+@vertex
+fn main(@location(0) aVertPos: vec3<f32>) -> VertexOutput {
+    var fdssl_out: VertexOutput;
+    fdssl_out.fdssl_Position = vec4<f32>(aVertPos[0i], aVertPos[1i], aVertPos[2i], 1.0f);
+    fdssl_out.vXY = vec2<f32>(aVertPos[0i] + fdssl_u.uOffset, aVertPos[1i] + fdssl_u.uOffset);
+    return fdssl_out;
+}
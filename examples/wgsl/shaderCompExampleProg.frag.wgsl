@@ -0,0 +1,23 @@
+struct Uniforms {
+    uProjectionMatrix: mat4x4<f32>,
+    uModelViewMatrix: mat4x4<f32>,
+    uTime: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct FragmentOutput {
+    @location(0) fdssl_FragColor: vec4<f32>,
+}
+
+@fragment
+fn main(@location(0) vXYZ: vec3<f32>, @location(1) pos: vec4<f32>) -> FragmentOutput {
+    var fdssl_out: FragmentOutput;
+    var col: vec4<f32> = vec4<f32>(0.5f, 0.5f, 0.5f, 1.0f);
+    fdssl_out.fdssl_FragColor = col;
+    var r: f32 = pos[0i];
+    var g: f32 = sin(pos[1i] + fdssl_u.uTime * 3.7f);
+    var b: f32 = pos[2i];
+    fdssl_out.fdssl_FragColor = vec4<f32>(r, g, b, 1.0f);
+    return fdssl_out;
+}
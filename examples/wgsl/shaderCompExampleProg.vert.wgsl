@@ -0,0 +1,23 @@
+struct Uniforms {
+    uProjectionMatrix: mat4x4<f32>,
+    uModelViewMatrix: mat4x4<f32>,
+    uTime: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) fdssl_Position: vec4<f32>,
+    @location(0) vXYZ: vec3<f32>,
+    @location(1) pos: vec4<f32>,
+}
+
+@vertex
+fn main(@location(0) aVertPos: vec3<f32>) -> VertexOutput {
+    var fdssl_out: VertexOutput;
+    fdssl_out.pos = vec4<f32>(aVertPos.x, sin(aVertPos.y + fdssl_u.uTime * 3.7f), aVertPos.z, 1.0f);
+    fdssl_out.fdssl_Position = fdssl_out.pos;
+    fdssl_out.fdssl_Position = fdssl_u.uProjectionMatrix * fdssl_u.uModelViewMatrix * fdssl_out.pos;
+    fdssl_out.vXYZ = vec3<f32>(fdssl_out.pos.x, fdssl_out.pos.y, fdssl_out.pos.z);
+    return fdssl_out;
+}
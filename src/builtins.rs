@@ -0,0 +1,219 @@
+/*
+
+Built-in functions of GLSL, which every program may call w/out defining them
+
+The prelude holds the signatures of the functions of the GLSL ES 1.00 standard library (section 8 of its spec),
+& is seeded into the env that programs are typechecked in. Calls of these functions are emitted as they are,
+so each one maps directly to the GLSL built-in of the same name, & other targets translate them where needed.
+
+Most built-ins are generic over a genType, which is a float or a vector of floats, i.e. `sin : genType -> genType`.
+A generic function is overloaded w/ a signature for each of them, & a call is resolved by the types of its arguments.
+Signatures are written w/ placeholders for these families, all of the same size within a signature:
+    T   a genType, float, vec2, vec3 or vec4
+    V   a vector of floats, vec2, vec3 or vec4
+    I   a vector of ints, ivec2, ivec3 or ivec4
+    B   a vector of bools, bvec2, bvec3 or bvec4
+    M   a matrix, mat2, mat3 or mat4
+
+Some built-ins are only available in one stage of the pipeline, see `STAGED`. Shaders of the other stage may not use them,
+either directly or through any function they call. Vertex texture fetches are optional in GLSL ES 1.00 (& WebGL 1),
+so textures are only sampled by fragment shaders, w/ the explicit level of detail of the 'Lod' variants left to vertex shaders.
+
+*/
+
+use crate::syntax::{ParsedType, Stage};
+
+/// Signatures of the built-in functions, w/ the types of their params & their result
+/// A generic signature stands for one signature of each size, see the header above
+const SIGNATURES: &[(&str, &[&str], &str)] = &[
+    // angle & trigonometry
+    ("radians", &["T"], "T"),
+    ("degrees", &["T"], "T"),
+    ("sin", &["T"], "T"),
+    ("cos", &["T"], "T"),
+    ("tan", &["T"], "T"),
+    ("asin", &["T"], "T"),
+    ("acos", &["T"], "T"),
+    ("atan", &["T", "T"], "T"),
+    ("atan", &["T"], "T"),
+    // exponential
+    ("pow", &["T", "T"], "T"),
+    ("exp", &["T"], "T"),
+    ("log", &["T"], "T"),
+    ("exp2", &["T"], "T"),
+    ("log2", &["T"], "T"),
+    ("sqrt", &["T"], "T"),
+    ("inversesqrt", &["T"], "T"),
+    // common
+    ("abs", &["T"], "T"),
+    ("sign", &["T"], "T"),
+    ("floor", &["T"], "T"),
+    ("ceil", &["T"], "T"),
+    ("fract", &["T"], "T"),
+    ("mod", &["T", "float"], "T"),
+    ("mod", &["T", "T"], "T"),
+    ("min", &["T", "T"], "T"),
+    ("min", &["T", "float"], "T"),
+    ("max", &["T", "T"], "T"),
+    ("max", &["T", "float"], "T"),
+    ("clamp", &["T", "T", "T"], "T"),
+    ("clamp", &["T", "float", "float"], "T"),
+    ("mix", &["T", "T", "T"], "T"),
+    ("mix", &["T", "T", "float"], "T"),
+    ("step", &["T", "T"], "T"),
+    ("step", &["float", "T"], "T"),
+    ("smoothstep", &["T", "T", "T"], "T"),
+    ("smoothstep", &["float", "float", "T"], "T"),
+    // geometric
+    ("length", &["T"], "float"),
+    ("distance", &["T", "T"], "float"),
+    ("dot", &["T", "T"], "float"),
+    ("cross", &["vec3", "vec3"], "vec3"),
+    ("normalize", &["T"], "T"),
+    ("faceforward", &["T", "T", "T"], "T"),
+    ("reflect", &["T", "T"], "T"),
+    ("refract", &["T", "T", "float"], "T"),
+    // matrices
+    ("matrixCompMult", &["M", "M"], "M"),
+    // vector relational
+    ("lessThan", &["V", "V"], "B"),
+    ("lessThan", &["I", "I"], "B"),
+    ("lessThanEqual", &["V", "V"], "B"),
+    ("lessThanEqual", &["I", "I"], "B"),
+    ("greaterThan", &["V", "V"], "B"),
+    ("greaterThan", &["I", "I"], "B"),
+    ("greaterThanEqual", &["V", "V"], "B"),
+    ("greaterThanEqual", &["I", "I"], "B"),
+    ("equal", &["V", "V"], "B"),
+    ("equal", &["I", "I"], "B"),
+    ("equal", &["B", "B"], "B"),
+    ("notEqual", &["V", "V"], "B"),
+    ("notEqual", &["I", "I"], "B"),
+    ("notEqual", &["B", "B"], "B"),
+    ("any", &["B"], "bool"),
+    ("all", &["B"], "bool"),
+    ("not", &["B"], "B"),
+    // textures, w/ an optional bias
+    ("texture2D", &["sampler2D", "vec2"], "vec4"),
+    ("texture2D", &["sampler2D", "vec2", "float"], "vec4"),
+    ("texture2DProj", &["sampler2D", "vec3"], "vec4"),
+    ("texture2DProj", &["sampler2D", "vec4"], "vec4"),
+    ("texture2DProj", &["sampler2D", "vec3", "float"], "vec4"),
+    ("texture2DProj", &["sampler2D", "vec4", "float"], "vec4"),
+    ("texture2DLod", &["sampler2D", "vec2", "float"], "vec4"),
+    ("texture2DProjLod", &["sampler2D", "vec3", "float"], "vec4"),
+    ("texture2DProjLod", &["sampler2D", "vec4", "float"], "vec4"),
+    // derivatives, from the OES_standard_derivatives extension in GLSL ES 1.00
+    ("dFdx", &["T"], "T"),
+    ("dFdy", &["T"], "T"),
+    ("fwidth", &["T"], "T"),
+];
+
+/// Built-in variables & functions that are only available in a single stage, w/ that stage
+/// 'discard' is a statement rather than a function, but is restricted to fragment shaders all the same
+const STAGED: &[(&str, Stage)] = &[
+    ("gl_Position", Stage::Vertex),
+    ("gl_PointSize", Stage::Vertex),
+    ("texture2DLod", Stage::Vertex),
+    ("texture2DProjLod", Stage::Vertex),
+    ("gl_FragColor", Stage::Fragment),
+    ("gl_FragCoord", Stage::Fragment),
+    ("gl_FrontFacing", Stage::Fragment),
+    ("gl_PointCoord", Stage::Fragment),
+    ("texture2D", Stage::Fragment),
+    ("texture2DProj", Stage::Fragment),
+    ("dFdx", Stage::Fragment),
+    ("dFdy", Stage::Fragment),
+    ("fwidth", Stage::Fragment),
+    ("discard", Stage::Fragment),
+];
+
+/// Returns the only stage that a built-in is available in, or None if it is available in both
+pub fn stage(name: &str) -> Option<Stage> {
+    STAGED.iter().find(|(n,_)| *n == name).map(|(_,s)| *s)
+}
+
+/// Returns the type a placeholder of a signature stands for at a given size, or None if it has none of that size
+/// Any other name is a type of its own, & is the same at every size
+fn instance(p: &str, n: usize) -> Option<String> {
+    match (p, n) {
+        ("T", 1)                    => Some("float".to_string()),
+        ("V" | "I" | "B" | "M", 1)  => None,
+        ("T" | "V", _)              => Some(format!("vec{n}")),
+        ("I", _)                    => Some(format!("ivec{n}")),
+        ("B", _)                    => Some(format!("bvec{n}")),
+        ("M", _)                    => Some(format!("mat{n}")),
+        _                           => Some(p.to_string())
+    }
+}
+
+/// Returns the built-in functions, w/ every signature of each
+/// Like the type of any other function, a signature w/ several params takes a tuple of them
+pub fn prelude() -> Vec<(String, Vec<ParsedType>)> {
+    let mut fns : Vec<(String, Vec<ParsedType>)> = vec![];
+    for (name, params, ret) in SIGNATURES {
+        let i = match fns.iter().position(|(n,_)| n == name) {
+            Some(i) => i,
+            None    => {
+                fns.push((name.to_string(), vec![]));
+                fns.len() - 1
+            }
+        };
+        for n in 1..=4 {
+            let types : Option<Vec<ParsedType>> = params.iter().map(|p| instance(p, n).map(ParsedType::BaseType)).collect();
+            let (types, ret) = match (types, instance(ret, n)) {
+                (Some(t), Some(r)) => (t, ParsedType::BaseType(r)),
+                _                  => continue
+            };
+            let arg = if types.len() == 1 { types[0].clone() } else { ParsedType::Tuple(types) };
+            let sig = ParsedType::Function(Box::new(arg), Box::new(ret));
+            // a generic signature is the same at every size when it has no placeholders, & a genType
+            // of size 1 is a float, so some signatures are given more than once
+            if !fns[i].1.contains(&sig) {
+                fns[i].1.push(sig);
+            }
+        }
+    }
+    fns
+}
+
+//
+//
+// BUILTINS TESTS
+//
+//
+
+#[test]
+fn test_prelude() {
+    let fns = prelude();
+    let sigs = |n: &str| fns.iter().find(|(f,_)| f == n).map(|(_,s)| s.clone()).unwrap_or_default();
+    let t = |s: &str| ParsedType::BaseType(s.to_string());
+    let f = |a: Vec<ParsedType>, r: ParsedType| ParsedType::Function(Box::new(if a.len() == 1 { a[0].clone() } else { ParsedType::Tuple(a) }), Box::new(r));
+
+    // a genType has a signature for floats & each size of vector
+    assert_eq!(sigs("sin"), vec![f(vec![t("float")], t("float")), f(vec![t("vec2")], t("vec2")), f(vec![t("vec3")], t("vec3")), f(vec![t("vec4")], t("vec4"))], "Wrong signatures for 'sin'");
+    // w/out repeating a signature where a float param is the same as a genType of size 1
+    assert_eq!(sigs("mix").len(), 7, "Wrong # of signatures for 'mix'");
+    assert!(sigs("mix").contains(&f(vec![t("vec3"), t("vec3"), t("float")], t("vec3"))), "Missing a signature of 'mix' w/ a scalar weight");
+    // functions of vectors alone have no signature for floats
+    assert_eq!(sigs("lessThan").len(), 6, "Wrong # of signatures for 'lessThan'");
+    assert!(sigs("lessThan").contains(&f(vec![t("ivec2"), t("ivec2")], t("bvec2"))), "Missing a signature of 'lessThan' for ints");
+    assert_eq!(sigs("cross"), vec![f(vec![t("vec3"), t("vec3")], t("vec3"))], "Wrong signatures for 'cross'");
+    // every name is given once
+    assert!(fns.iter().all(|(n,_)| fns.iter().filter(|(m,_)| m == n).count() == 1), "A built-in is given more than once");
+}
+
+#[test]
+fn test_stage() {
+    assert_eq!(stage("gl_Position"), Some(Stage::Vertex), "Wrong stage for 'gl_Position'");
+    assert_eq!(stage("texture2DLod"), Some(Stage::Vertex), "Wrong stage for 'texture2DLod'");
+    assert_eq!(stage("dFdx"), Some(Stage::Fragment), "Wrong stage for 'dFdx'");
+    assert_eq!(stage("discard"), Some(Stage::Fragment), "Wrong stage for 'discard'");
+    // most built-ins are available in both stages
+    assert_eq!(stage("sin"), None, "Restricted 'sin' to a stage");
+    // & every function that is restricted is a built-in
+    let fns = prelude();
+    for (n, s) in STAGED.iter().filter(|(n,_)| !n.starts_with("gl_") && *n != "discard") {
+        assert!(fns.iter().any(|(f,_)| f == n), "'{}' is restricted to '{}' shaders, but is not a built-in", n, s);
+    }
+}
@@ -0,0 +1,789 @@
+/*
+
+Compiler from a typechecked FDSSL program to the GLSL AST
+
+Parser produces Program
+TC takes Program, produces TC'd Program
+Compiler takes TC'd Program, produces GLSL AST
+
+Each program declared w/ mkProg is compiled into a vertex & fragment shader pair.
+Every shader gets all uniforms, its own inputs & outputs, and only the top-level
+definitions that it (transitively) references, so helpers that cannot be expressed
+in GLSL (such as higher-order functions) are fine as long as no shader uses them.
+
+Expression-oriented bodies are lowered into GLSL statements by passing along
+where the value of the last expression should go (a return, an assignment, or nowhere).
+
+*/
+
+use crate::syntax;
+use crate::glsl_syntax as glsl;
+use crate::typechecker::{const_index, fits_float, link_programs, shader_env, tc_type_of, LinkedProg, TCEnv};
+
+use syntax::Expr;
+use syntax::ExprKind;
+use syntax::BOp;
+use syntax::UOp;
+use syntax::Program;
+use syntax::ParsedType;
+use syntax::Stage;
+use syntax::AccessType;
+use std::collections::{HashMap, HashSet};
+
+// Simple compiler error, much like the typechecker's errors
+pub type CompileError = String;
+
+/// Where the value of the last expression in a body goes
+#[derive(Clone)]
+enum Tail {
+    // returned from the enclosing function
+    Return,
+    // assigned to a target, such as a variable declared to hold the value of a branch
+    Assign(glsl::Expr),
+    // dropped
+    Discard,
+}
+
+/// State of the compiler while compiling a single shader
+struct Compiler {
+    // structs generated for tuple types, in order of first use
+    structs: Vec<(ParsedType, glsl::StructDecl)>,
+}
+
+/// Compiles every program declared in a typechecked program into a pair of GLSL shaders
+/// Expects the env produced by typechecking that program
+pub fn compile(p: &Program, env: &TCEnv) -> Result<Vec<glsl::Program>,CompileError> {
+    link_programs(p, env).map_err(|e| e.to_string())?.iter().map(|lp| compile_prog(lp, p, env)).collect()
+}
+
+/// Compiles a single linked program into its vertex & fragment shaders
+fn compile_prog(lp: &LinkedProg, p: &Program, env: &TCEnv) -> Result<glsl::Program,CompileError> {
+    Ok(glsl::Program{
+        name: lp.name.clone(),
+        vert: compile_shader(Stage::Vertex, &lp.attributes, &lp.varyings, &lp.vert_body, p, env)?,
+        frag: compile_shader(Stage::Fragment, &lp.varyings, &lp.outputs, &lp.frag_body, p, env)?,
+    })
+}
+
+/// Compiles a shader w/ the given inputs, outputs & body
+/// For a fragment shader the inputs are all varyings, even those it does not use, so both stages always agree
+fn compile_shader(stage: Stage, inputs: &[(String,ParsedType)], outputs: &[(String,ParsedType)], body: &[Expr], p: &Program, env: &TCEnv) -> Result<glsl::Shader,CompileError> {
+    let mut c = Compiler{ structs: vec![] };
+    let mut decls = vec![];
+
+    // uniforms, once each
+    let mut uniforms = HashSet::new();
+    for e in p {
+        if let ExprKind::Uniform{name, typ} = &e.kind {
+            if uniforms.insert(name.clone()) {
+                decls.push(glsl::Decl::Var(glsl::VarDecl{
+                    qualifier: Some(glsl::Qualifier::Uniform),
+                    typ: c.glsl_type(&typ.typ)?,
+                    name: name.clone(),
+                    value: None
+                }));
+            }
+        }
+    }
+
+    // inputs & outputs of this shader
+    for (q, named) in [(glsl::Qualifier::In, inputs), (glsl::Qualifier::Out, outputs)] {
+        for (n,t) in named {
+            decls.push(glsl::Decl::Var(glsl::VarDecl{
+                qualifier: Some(q),
+                typ: c.glsl_type(t)?,
+                name: n.clone(),
+                value: None
+            }));
+        }
+    }
+
+    // top-level definitions used by this shader, in the order they were declared
+    // GLSL has no shadowing at the top level, so a name may only be defined again as an overload of a function
+    let used = used_defs(body, p);
+    let mut defined = HashMap::new();
+    for e in p {
+        match &e.kind {
+            ExprKind::Def{name, typ, ..} | ExprKind::DefMut{name, typ, ..} if used.contains(name) => {
+                let overload = matches!(e.kind, ExprKind::Def{..}) && matches!(typ.typ, ParsedType::Function(..));
+                if defined.insert(name, overload).is_some_and(|prior| !(prior && overload)) {
+                    return Err(format!("'{name}' is defined more than once at the top level, which GLSL does not allow, so one of them must have another name"));
+                }
+                decls.push(c.compile_def(e, env)?)
+            },
+            _ => ()
+        }
+    }
+
+    let scope = shader_env(stage, inputs.to_vec(), outputs.to_vec(), env);
+    decls.push(glsl::Decl::Function(glsl::Function{
+        name: "main".to_string(),
+        ret: glsl::Type::Void,
+        params: vec![],
+        body: c.compile_block(body, Tail::Discard, &scope)?
+    }));
+
+    // structs must be declared before anything that uses them
+    let mut structs : Vec<glsl::Decl> = c.structs.into_iter().map(|(_,s)| glsl::Decl::Struct(s)).collect();
+    structs.extend(decls);
+    Ok(glsl::Shader{ stage, decls: structs })
+}
+
+/// Returns the names of all top-level definitions reachable from a body
+/// Over-approximates when a local binding shadows a definition, which only costs an unused declaration
+fn used_defs(body: &[Expr], p: &Program) -> HashSet<String> {
+    let mut used = HashSet::new();
+    let mut todo = vec![];
+    body.iter().for_each(|e| refs(e, &mut todo));
+
+    while let Some(n) = todo.pop() {
+        if used.contains(&n) {
+            continue;
+        }
+        for e in p {
+            match &e.kind {
+                ExprKind::Def{name, value, ..} | ExprKind::DefMut{name, value, ..} if *name == n => {
+                    refs(value, &mut todo);
+                    used.insert(n.clone());
+                },
+                _ => ()
+            }
+        }
+    }
+    used
+}
+
+/// Collects every name referenced in an expression
+fn refs(e: &Expr, out: &mut Vec<String>) {
+    let all = |es: &[Expr], out: &mut Vec<String>| es.iter().for_each(|e| refs(e, out));
+    match &e.kind {
+        ExprKind::Ref(n)                            => out.push(n.clone()),
+        ExprKind::Access(b, at)                     => {
+            refs(b, out);
+            if let AccessType::Idx(i) = at {
+                refs(i, out);
+            }
+        },
+        ExprKind::App{fname, arguments}             => {
+            out.push(fname.clone());
+            all(arguments, out);
+        },
+        ExprKind::Update{target, value, ..}        => {
+            out.push(target.clone());
+            refs(value, out);
+        },
+        ExprKind::Return(e) | ExprKind::UnaryOp{e, ..}  => refs(e, out),
+        ExprKind::Def{value, ..} | ExprKind::DefMut{value, ..} => refs(value, out),
+        ExprKind::BinOp{e1, e2, ..}                 => {
+            refs(e1, out);
+            refs(e2, out);
+        },
+        ExprKind::Branch{condition, b1, b2}         => {
+            refs(condition, out);
+            all(b1, out);
+            all(b2, out);
+        },
+        ExprKind::For{init, cond, post, body}       => {
+            refs(init, out);
+            refs(cond, out);
+            refs(post, out);
+            all(body, out);
+        },
+        ExprKind::While{cond, body} | ExprKind::DoWhile{body, cond} => {
+            refs(cond, out);
+            all(body, out);
+        },
+        ExprKind::Vect(v)                           => all(v, out),
+        ExprKind::NamedVect(v)                      => v.iter().for_each(|(_,e)| refs(e, out)),
+        ExprKind::Abs{body, ..}                     => all(body, out),
+        _                                       => ()
+    }
+}
+
+/// Returns the GLSL binary operator for an FDSSL one
+fn glsl_bop(b: BOp) -> Result<glsl::BinOp,CompileError> {
+    Ok(match b {
+        BOp::Add     => glsl::BinOp::Add,
+        BOp::Sub     => glsl::BinOp::Sub,
+        BOp::Mul     => glsl::BinOp::Mul,
+        BOp::Div     => glsl::BinOp::Div,
+        BOp::Mod     => glsl::BinOp::Mod,
+        BOp::And     => glsl::BinOp::And,
+        BOp::Or      => glsl::BinOp::Or,
+        BOp::Eq      => glsl::BinOp::Eq,
+        BOp::Neq     => glsl::BinOp::Neq,
+        BOp::Gt      => glsl::BinOp::Gt,
+        BOp::Gte     => glsl::BinOp::Gte,
+        BOp::Lt      => glsl::BinOp::Lt,
+        BOp::Lte     => glsl::BinOp::Lte,
+        BOp::BitAnd  => glsl::BinOp::BitAnd,
+        BOp::BitOr   => glsl::BinOp::BitOr,
+        BOp::BitXor  => glsl::BinOp::BitXor,
+        BOp::Compose => return Err("Function composition has no equivalent in GLSL".to_string())
+    })
+}
+
+impl Compiler {
+
+    /// Returns the GLSL type for a parsed type, generating a struct for any tuple
+    fn glsl_type(&mut self, t: &ParsedType) -> Result<glsl::Type,CompileError> {
+        match t {
+            ParsedType::BaseType(s) => match s.as_str() {
+                "bool"      => Ok(glsl::Type::Bool),
+                "int"       => Ok(glsl::Type::Int),
+                "float"     => Ok(glsl::Type::Float),
+                "double"    => Ok(glsl::Type::Double),
+                "vec2"      => Ok(glsl::Type::Vec(2)),
+                "vec3"      => Ok(glsl::Type::Vec(3)),
+                "vec4"      => Ok(glsl::Type::Vec(4)),
+                "ivec2"     => Ok(glsl::Type::IVec(2)),
+                "ivec3"     => Ok(glsl::Type::IVec(3)),
+                "ivec4"     => Ok(glsl::Type::IVec(4)),
+                "bvec2"     => Ok(glsl::Type::BVec(2)),
+                "bvec3"     => Ok(glsl::Type::BVec(3)),
+                "bvec4"     => Ok(glsl::Type::BVec(4)),
+                "mat2"      => Ok(glsl::Type::Mat(2)),
+                "mat3"      => Ok(glsl::Type::Mat(3)),
+                "mat4"      => Ok(glsl::Type::Mat(4)),
+                "sampler2D" => Ok(glsl::Type::Sampler2D),
+                _           => Err(format!("Type '{t}' has no equivalent in GLSL"))
+            },
+            // the unit type is only sensible as the result of a function
+            ParsedType::Tuple(v) if v.is_empty() => Ok(glsl::Type::Void),
+            ParsedType::Tuple(v) => {
+                let fields = v.iter().enumerate().map(|(i,t)| (format!("_{i}"), t.clone())).collect();
+                self.tuple_struct(t, fields)
+            },
+            ParsedType::NamedTuple(v) => {
+                let fields = v.iter().map(|(n,t)| (n.clone(), (**t).clone())).collect();
+                self.tuple_struct(t, fields)
+            },
+            ParsedType::Array(t, n) => Ok(glsl::Type::Array(Box::new(self.glsl_type(t)?), *n)),
+            _ => Err(format!("Type '{t}' has no equivalent in GLSL"))
+        }
+    }
+
+    /// Returns the struct for a tuple type, declaring it on first use
+    fn tuple_struct(&mut self, t: &ParsedType, fields: Vec<(String,ParsedType)>) -> Result<glsl::Type,CompileError> {
+        if let Some((_,s)) = self.structs.iter().find(|(st,_)| st == t) {
+            return Ok(glsl::Type::Struct(s.name.clone()));
+        }
+
+        // fields are declared first, so any nested structs come before this one
+        let fields = fields.iter().map(|(n,t)| Ok((n.clone(), self.glsl_type(t)?))).collect::<Result<Vec<_>,CompileError>>()?;
+        let name = format!("Tuple{}", self.structs.len());
+        self.structs.push((t.clone(), glsl::StructDecl{ name: name.clone(), fields }));
+        Ok(glsl::Type::Struct(name))
+    }
+
+    /// Compiles a top-level definition into a function, or a global for any other value
+    fn compile_def(&mut self, e: &Expr, env: &TCEnv) -> Result<glsl::Decl,CompileError> {
+        let (name, t, value, qualifier) = match &e.kind {
+            ExprKind::Def{name, typ, value}    => (name, &typ.typ, value, Some(glsl::Qualifier::Const)),
+            ExprKind::DefMut{name, typ, value} => (name, &typ.typ, value, None),
+            _                              => return Err(format!("Expected a definition, but got '{:?}' instead", e))
+        };
+
+        match &value.kind {
+            ExprKind::Abs{params, body} => {
+                let ret = match t {
+                    ParsedType::Function(_, r) => r,
+                    _                          => t
+                };
+                let mut scope = env.clone();
+                let mut ps = vec![];
+                for (n,pt) in params.iter().map(|(n,a)| (n, &a.typ)) {
+                    if let ParsedType::Function(_,_) = pt {
+                        return Err(format!("Function '{name}' takes a function as parameter '{n}', which is not supported by GLSL"));
+                    }
+                    ps.push((n.clone(), self.glsl_type(pt)?));
+                    scope.insert(n.clone(), pt.clone());
+                }
+                let ret = self.glsl_type(ret)?;
+                let tail = if ret == glsl::Type::Void { Tail::Discard } else { Tail::Return };
+
+                Ok(glsl::Decl::Function(glsl::Function{
+                    name: name.clone(),
+                    ret,
+                    params: ps,
+                    body: self.compile_block(body, tail, &scope)?
+                }))
+            },
+            _ => Ok(glsl::Decl::Var(glsl::VarDecl{
+                qualifier,
+                typ: self.glsl_type(t)?,
+                name: name.clone(),
+                value: Some(self.compile_value(value, t, env)?)
+            }))
+        }
+    }
+
+    /// Compiles a body in its own scope, sending the value of the last expression to the tail
+    fn compile_block(&mut self, body: &[Expr], tail: Tail, env: &TCEnv) -> Result<Vec<glsl::Stmt>,CompileError> {
+        let mut scope = env.clone();
+        let mut stmts = vec![];
+        for (i,e) in body.iter().enumerate() {
+            let t = if i + 1 == body.len() { tail.clone() } else { Tail::Discard };
+            stmts.extend(self.compile_stmt(e, t, &mut scope)?);
+        }
+        Ok(stmts)
+    }
+
+    /// Compiles an expression in statement position, extending the scope w/ any bindings it introduces
+    fn compile_stmt(&mut self, e: &Expr, tail: Tail, scope: &mut TCEnv) -> Result<Vec<glsl::Stmt>,CompileError> {
+        match &e.kind {
+            ExprKind::Comment(_) => Ok(vec![]),
+
+            ExprKind::Def{name, typ, value} | ExprKind::DefMut{name, typ, value} => {
+                if let ExprKind::Abs{..} = value.kind {
+                    return Err(format!("Local function '{name}' is not supported by GLSL, it must be declared at the top level"));
+                }
+                let t = self.glsl_type(&typ.typ)?;
+                let var = glsl::Expr{ kind: glsl::ExprKind::Var(name.clone()), typ: t.clone() };
+
+                // branches are not values in GLSL, so they assign to the declared variable instead
+                let mut stmts = match &value.kind {
+                    ExprKind::Branch{..} => {
+                        let mut s = vec![glsl::Stmt::Decl(glsl::VarDecl{ qualifier: None, typ: t, name: name.clone(), value: None })];
+                        s.extend(self.compile_stmt(value, Tail::Assign(var.clone()), scope)?);
+                        s
+                    },
+                    _ => vec![glsl::Stmt::Decl(glsl::VarDecl{ qualifier: None, typ: t, name: name.clone(), value: Some(self.compile_value(value, &typ.typ, scope)?) })]
+                };
+                scope.insert(name.clone(), typ.typ.clone());
+                stmts.extend(self.tail_stmt(var, tail));
+                Ok(stmts)
+            },
+
+            ExprKind::Update{target, swizzle, value} => {
+                let target = match swizzle {
+                    Some(sw) => ExprKind::Access(Box::new(ExprKind::Ref(target.clone()).into()), AccessType::Name(sw.clone())),
+                    None     => ExprKind::Ref(target.clone())
+                };
+                let target : Expr = target.into();
+                let var = self.compile_expr(&target, scope)?;
+                let tt = tc_type_of(&target, scope).map_err(|e| e.to_string())?;
+                let mut stmts = match &value.kind {
+                    ExprKind::Branch{..} => self.compile_stmt(value, Tail::Assign(var.clone()), scope)?,
+                    _                => vec![glsl::Stmt::Assign{ target: var.clone(), value: self.compile_value(value, &tt, scope)? }]
+                };
+                stmts.extend(self.tail_stmt(var, tail));
+                Ok(stmts)
+            },
+
+            ExprKind::Branch{condition, b1, b2} => Ok(vec![glsl::Stmt::If{
+                condition: self.compile_expr(condition, scope)?,
+                b1: self.compile_block(b1, tail.clone(), scope)?,
+                b2: self.compile_block(b2, tail, scope)?
+            }]),
+
+            ExprKind::For{init, cond, post, body} => {
+                if !matches!(tail, Tail::Discard) {
+                    return Err("A 'for' loop has no value, and cannot be used as one in GLSL".to_string());
+                }
+                let mut loop_scope = scope.clone();
+                let init = self.single_stmt(init, &mut loop_scope)?;
+                let cond = self.compile_expr(cond, &loop_scope)?;
+                let post = self.single_stmt(post, &mut loop_scope)?;
+                Ok(vec![glsl::Stmt::For{
+                    init: Box::new(init),
+                    cond,
+                    post: Box::new(post),
+                    body: self.compile_block(body, Tail::Discard, &loop_scope)?
+                }])
+            },
+
+            ExprKind::While{cond, body} | ExprKind::DoWhile{body, cond} => {
+                if !matches!(tail, Tail::Discard) {
+                    return Err("A loop has no value, and cannot be used as one in GLSL".to_string());
+                }
+                let cond = self.compile_expr(cond, scope)?;
+                let body = self.compile_block(body, Tail::Discard, scope)?;
+                Ok(vec![match e.kind {
+                    ExprKind::While{..} => glsl::Stmt::While{ cond, body },
+                    _                   => glsl::Stmt::DoWhile{ body, cond }
+                }])
+            },
+
+            ExprKind::Return(v) => Ok(vec![glsl::Stmt::Return(Some(self.compile_expr(v, scope)?))]),
+
+            ExprKind::Discard => Ok(vec![glsl::Stmt::Discard]),
+
+            _ => {
+                let v = self.compile_expr(e, scope)?;
+                Ok(match tail {
+                    Tail::Return      => vec![glsl::Stmt::Return(Some(v))],
+                    Tail::Assign(t)   => vec![glsl::Stmt::Assign{ target: t, value: v }],
+                    Tail::Discard     => vec![glsl::Stmt::Expr(v)]
+                })
+            }
+        }
+    }
+
+    /// Compiles an expression that must produce exactly one statement, such as the init & post of a loop
+    fn single_stmt(&mut self, e: &Expr, scope: &mut TCEnv) -> Result<glsl::Stmt,CompileError> {
+        let mut stmts = self.compile_stmt(e, Tail::Discard, scope)?;
+        match stmts.len() {
+            1 => Ok(stmts.remove(0)),
+            _ => Err(format!("Expected a single statement for the loop header, but got '{:?}' instead", e))
+        }
+    }
+
+    /// Sends the value of a variable to the tail, after it has been declared or updated
+    fn tail_stmt(&self, var: glsl::Expr, tail: Tail) -> Option<glsl::Stmt> {
+        match tail {
+            Tail::Return    => Some(glsl::Stmt::Return(Some(var))),
+            Tail::Assign(t) => Some(glsl::Stmt::Assign{ target: t, value: var }),
+            Tail::Discard   => None
+        }
+    }
+
+    /// Compiles an expression in value position
+    fn compile_expr(&mut self, e: &Expr, scope: &TCEnv) -> Result<glsl::Expr,CompileError> {
+        let pt = tc_type_of(e, scope).map_err(|e| e.to_string())?;
+        let typ = self.glsl_type(&pt)?;
+
+        let kind = match &e.kind {
+            ExprKind::I(i)   => glsl::ExprKind::Int(*i),
+            ExprKind::F(f)   => glsl::ExprKind::Float(*f),
+            ExprKind::D(d)   => glsl::ExprKind::Double(*d),
+            ExprKind::B(b)   => glsl::ExprKind::Bool(*b),
+            ExprKind::Ref(n) => glsl::ExprKind::Var(n.clone()),
+
+            ExprKind::App{fname, arguments} => {
+                // the args of a constructor are converted to the type of its components
+                let ctor = !scope.contains_key(fname) && pt == ParsedType::BaseType(fname.clone());
+                glsl::ExprKind::Call{
+                    name: fname.clone(),
+                    args: arguments.iter().map(|a| if ctor { self.compile_value(a, &pt, scope) } else { self.compile_expr(a, scope) }).collect::<Result<_,_>>()?
+                }
+            },
+
+            ExprKind::BinOp{operator, e1, e2} => {
+                let t1 = tc_type_of(e1, scope).map_err(|e| e.to_string())?;
+                let t2 = tc_type_of(e2, scope).map_err(|e| e.to_string())?;
+                glsl::ExprKind::Binary{
+                    op: glsl_bop(*operator)?,
+                    lhs: Box::new(self.compile_value(e1, &t2, scope)?),
+                    rhs: Box::new(self.compile_value(e2, &t1, scope)?)
+                }
+            },
+
+            ExprKind::UnaryOp{operator, e} => glsl::ExprKind::Unary{
+                op: match operator {
+                    UOp::Negative => glsl::UnOp::Neg,
+                    UOp::Negate   => glsl::UnOp::Not
+                },
+                e: Box::new(self.compile_expr(e, scope)?)
+            },
+
+            ExprKind::Access(b, at) => {
+                let base = self.compile_expr(b, scope)?;
+                let bt = tc_type_of(b, scope).map_err(|e| e.to_string())?;
+                match (at, bt) {
+                    (AccessType::Name(p), _) => glsl::ExprKind::Field(Box::new(base), p.clone()),
+                    // a component named in brackets, as the typechecker resolves it
+                    (AccessType::Idx(i), _) if i.named_component().is_some_and(|c| !scope.contains_key(c)) => {
+                        glsl::ExprKind::Field(Box::new(base), i.describe())
+                    },
+                    // tuples are structs, so their elements are fields
+                    (AccessType::Idx(i), ParsedType::Tuple(_)) => match const_index(i) {
+                        Some(i) => glsl::ExprKind::Field(Box::new(base), format!("_{i}")),
+                        None    => return Err(format!("Tuple '{}' may only be indexed by a constant", b.describe()))
+                    },
+                    (AccessType::Idx(i), _) => glsl::ExprKind::Index(Box::new(base), Box::new(self.compile_expr(i, scope)?))
+                }
+            },
+
+            // tuples are built w/ the constructor of their struct
+            ExprKind::Vect(v) => glsl::ExprKind::Call{
+                name: self.struct_name(&typ)?,
+                args: v.iter().map(|a| self.compile_expr(a, scope)).collect::<Result<_,_>>()?
+            },
+            ExprKind::NamedVect(v) => glsl::ExprKind::Call{
+                name: self.struct_name(&typ)?,
+                args: v.iter().map(|(_,a)| self.compile_expr(a, scope)).collect::<Result<_,_>>()?
+            },
+
+            _ => return Err(format!("Expression '{:?}' cannot be used as a value in GLSL", e))
+        };
+
+        Ok(glsl::Expr{ kind, typ })
+    }
+
+    /// Compiles an expression in value position where a value of the expected type is needed
+    /// A decimal literal is a float wherever floats are expected, see `fits_float`
+    fn compile_value(&mut self, e: &Expr, expected: &ParsedType, scope: &TCEnv) -> Result<glsl::Expr,CompileError> {
+        if fits_float(e, expected) {
+            self.compile_expr(&as_float(e), scope)
+        } else {
+            self.compile_expr(e, scope)
+        }
+    }
+
+    /// Returns the name of a struct type
+    fn struct_name(&self, t: &glsl::Type) -> Result<String,CompileError> {
+        match t {
+            glsl::Type::Struct(n) => Ok(n.clone()),
+            _                     => Err(format!("Expected a struct type, but got '{:?}' instead", t))
+        }
+    }
+}
+
+/// Converts a decimal literal into a float literal, including under any negation of it
+fn as_float(e: &Expr) -> Expr {
+    let kind = match &e.kind {
+        ExprKind::D(d)                      => ExprKind::F(*d as f32),
+        ExprKind::UnaryOp{operator, e}      => ExprKind::UnaryOp{operator: *operator, e: Box::new(as_float(e))},
+        k                                   => k.clone()
+    };
+    Expr{kind, span: e.span}
+}
+
+//
+//
+// COMPILER TESTS
+//
+//
+
+/// Used during testing to parse, typecheck & compile a program
+#[cfg(test)]
+fn compile_str(prog: &str) -> Result<Vec<glsl::Program>,CompileError> {
+    let (_, p) = crate::parser::program(prog).map_err(|e| format!("{:?}", e))?;
+    let (_, env) = crate::typechecker::tc_program(p.clone()).map_err(|e| e.to_string())?;
+    compile(&p, &env)
+}
+
+/// Used during testing to build a typed GLSL expr
+#[cfg(test)]
+fn gvar(n: &str, typ: glsl::Type) -> glsl::Expr {
+    glsl::Expr{ kind: glsl::ExprKind::Var(n.to_string()), typ }
+}
+
+/// Used during testing to build a global var declaration
+#[cfg(test)]
+fn gdecl(q: glsl::Qualifier, n: &str, typ: glsl::Type) -> glsl::Decl {
+    glsl::Decl::Var(glsl::VarDecl{ qualifier: Some(q), typ, name: n.to_string(), value: None })
+}
+
+/// Compiles uniforms, inputs, outputs & shader bodies
+#[test]
+fn test_compile_shaders() {
+    let progs = compile_str("\
+uniform Vec4 uColor
+uniform Float uTime
+vert v : (Vec3 aPos) -> (Float vT) = {
+  set gl_Position uColor
+  out vT uTime
+}
+frag f : (Float vT) -> () = {
+  set gl_FragColor uColor
+}
+p : Prog = mkProg v f
+").unwrap();
+    assert_eq!(progs.len(), 1, "Failed to compile a single program");
+    assert_eq!(progs[0].name, "p");
+
+    let main = |body: Vec<glsl::Stmt>| glsl::Decl::Function(glsl::Function{ name: "main".to_string(), ret: glsl::Type::Void, params: vec![], body });
+    assert_eq!(progs[0].vert, glsl::Shader{
+        stage: Stage::Vertex,
+        decls: vec![
+            gdecl(glsl::Qualifier::Uniform, "uColor", glsl::Type::Vec(4)),
+            gdecl(glsl::Qualifier::Uniform, "uTime", glsl::Type::Float),
+            gdecl(glsl::Qualifier::In, "aPos", glsl::Type::Vec(3)),
+            gdecl(glsl::Qualifier::Out, "vT", glsl::Type::Float),
+            main(vec![
+                glsl::Stmt::Assign{ target: gvar("gl_Position", glsl::Type::Vec(4)), value: gvar("uColor", glsl::Type::Vec(4)) },
+                glsl::Stmt::Assign{ target: gvar("vT", glsl::Type::Float), value: gvar("uTime", glsl::Type::Float) },
+            ])
+        ]
+    }, "Failed to compile vertex shader");
+    assert_eq!(progs[0].frag, glsl::Shader{
+        stage: Stage::Fragment,
+        decls: vec![
+            gdecl(glsl::Qualifier::Uniform, "uColor", glsl::Type::Vec(4)),
+            gdecl(glsl::Qualifier::Uniform, "uTime", glsl::Type::Float),
+            gdecl(glsl::Qualifier::In, "vT", glsl::Type::Float),
+            main(vec![
+                glsl::Stmt::Assign{ target: gvar("gl_FragColor", glsl::Type::Vec(4)), value: gvar("uColor", glsl::Type::Vec(4)) },
+            ])
+        ]
+    }, "Failed to compile fragment shader");
+
+    // a component named in brackets is a field, unless that name is bound, as the typechecker resolves it
+    let progs = compile_str("\
+uniform Vec4 uPos
+let pick : Int -> Float = (x : Int) {
+  uPos[x]
+}
+vert v : () -> (Float vT) = {
+  out vT uPos[y] + pick(1)
+}
+frag f : () -> () = {
+  1
+}
+p : Prog = mkProg v f
+").unwrap();
+    let pos = || Box::new(gvar("uPos", glsl::Type::Vec(4)));
+    let decls = &progs[0].vert.decls;
+    assert!(
+        matches!(&decls[2], glsl::Decl::Function(f) if f.body == vec![glsl::Stmt::Return(Some(glsl::Expr{
+            kind: glsl::ExprKind::Index(pos(), Box::new(gvar("x", glsl::Type::Int))),
+            typ: glsl::Type::Float
+        }))]),
+        "Failed to compile a bound name in brackets as an index"
+    );
+    assert!(
+        matches!(&decls[3], glsl::Decl::Function(f) if matches!(&f.body[0], glsl::Stmt::Assign{ value: glsl::Expr{ kind: glsl::ExprKind::Binary{ lhs, .. }, .. }, .. }
+            if lhs.kind == glsl::ExprKind::Field(pos(), "y".to_string()))),
+        "Failed to compile a component named in brackets as a field"
+    );
+}
+
+/// Compiles the functions used by a shader, lowering their bodies into statements
+#[test]
+fn test_compile_functions() {
+    let progs = compile_str("\
+uniform Float uTime
+let unused : (Float -> Float, Float) -> Float = (f : Float -> Float, x : Float) {
+  f(x)
+}
+let pick : Float -> Float = (x : Float) {
+  mut y : Float = if x > 1.0f {
+    x
+  } else {
+    1.0f
+  }
+  y
+}
+let wave : Float -> Float = (x : Float) {
+  pick(x) * 2.0f
+}
+vert v : () -> (Float vT) = {
+  out vT wave(uTime)
+}
+frag f : () -> () = {
+  1
+}
+p : Prog = mkProg v f
+").unwrap();
+
+    let float = |f: f32| glsl::Expr{ kind: glsl::ExprKind::Float(f), typ: glsl::Type::Float };
+    let x = || gvar("x", glsl::Type::Float);
+    let decls = &progs[0].vert.decls;
+
+    // only the functions used are declared, & in order
+    assert_eq!(decls.len(), 5, "Failed to skip unused definitions");
+    assert_eq!(decls[2], glsl::Decl::Function(glsl::Function{
+        name: "pick".to_string(),
+        ret: glsl::Type::Float,
+        params: vec![("x".to_string(), glsl::Type::Float)],
+        body: vec![
+            glsl::Stmt::Decl(glsl::VarDecl{ qualifier: None, typ: glsl::Type::Float, name: "y".to_string(), value: None }),
+            glsl::Stmt::If{
+                condition: glsl::Expr{
+                    kind: glsl::ExprKind::Binary{ op: glsl::BinOp::Gt, lhs: Box::new(x()), rhs: Box::new(float(1.0)) },
+                    typ: glsl::Type::Bool
+                },
+                b1: vec![glsl::Stmt::Assign{ target: gvar("y", glsl::Type::Float), value: x() }],
+                b2: vec![glsl::Stmt::Assign{ target: gvar("y", glsl::Type::Float), value: float(1.0) }],
+            },
+            glsl::Stmt::Return(Some(gvar("y", glsl::Type::Float))),
+        ]
+    }), "Failed to compile function w/ a branch as a value");
+    assert!(matches!(&decls[3], glsl::Decl::Function(f) if f.name == "wave"), "Failed to declare 'wave' after 'pick'");
+
+    // functions are only declared in the shaders that use them
+    // (the fragment shader only has the uniform, the varying & main)
+    assert_eq!(progs[0].frag.decls.len(), 3, "Failed to skip definitions unused by the fragment shader");
+
+    // but using a higher-order function cannot be compiled
+    assert!(compile_str("\
+let apply : (Float -> Float, Float) -> Float = (f : Float -> Float, x : Float) {
+  f(x)
+}
+let half : Float -> Float = (x : Float) {
+  x / 2.0f
+}
+vert v : () -> (Float vT) = {
+  out vT apply(half, 1.0f)
+}
+frag f : () -> () = {
+  1
+}
+p : Prog = mkProg v f
+").is_err(), "Failed to reject a higher-order function");
+
+    // nor can a definition that shadows another at the top level, as GLSL only allows overloads there
+    for (redefined, used) in [("mut scale : Float -> Float = (x : Float) { x * 3.0f }", "scale(1.0f)"), ("let scale : Float = 1.0f", "scale")] {
+        let res = compile_str(&format!("\
+let scale : Float -> Float = (x : Float) {{ x * 2.0f }}
+{redefined}
+vert v : () -> (Float vT) = {{
+  out vT {used}
+}}
+frag f : () -> () = {{
+  1
+}}
+p : Prog = mkProg v f
+"));
+        assert_eq!(
+            res,
+            Err("'scale' is defined more than once at the top level, which GLSL does not allow, so one of them must have another name".to_string()),
+            "Failed to reject a shadowed definition"
+        );
+    }
+}
+
+/// Compiles tuples into structs
+#[test]
+fn test_compile_tuples() {
+    let progs = compile_str("\
+let swap : (Int, Int) -> (Int, Int) = (x : Int, y : Int) {
+  (y, x)
+}
+vert v : () -> (Int vI) = {
+  mut t : (Int, Int) = swap(1, 2)
+  out vI 1
+}
+frag f : () -> () = {
+  1
+}
+p : Prog = mkProg v f
+").unwrap();
+
+    let tuple = glsl::Type::Struct("Tuple0".to_string());
+    let int = |i: i32| glsl::Expr{ kind: glsl::ExprKind::Int(i), typ: glsl::Type::Int };
+    let decls = &progs[0].vert.decls;
+    assert_eq!(decls[0], glsl::Decl::Struct(glsl::StructDecl{
+        name: "Tuple0".to_string(),
+        fields: vec![("_0".to_string(), glsl::Type::Int), ("_1".to_string(), glsl::Type::Int)]
+    }), "Failed to declare a struct for a tuple");
+    assert_eq!(decls[2], glsl::Decl::Function(glsl::Function{
+        name: "swap".to_string(),
+        ret: tuple.clone(),
+        params: vec![("x".to_string(), glsl::Type::Int), ("y".to_string(), glsl::Type::Int)],
+        body: vec![glsl::Stmt::Return(Some(glsl::Expr{
+            kind: glsl::ExprKind::Call{ name: "Tuple0".to_string(), args: vec![gvar("y", glsl::Type::Int), gvar("x", glsl::Type::Int)] },
+            typ: tuple.clone()
+        }))]
+    }), "Failed to construct a tuple w/ its struct");
+
+    assert_eq!(decls[3], glsl::Decl::Function(glsl::Function{
+        name: "main".to_string(),
+        ret: glsl::Type::Void,
+        params: vec![],
+        body: vec![
+            glsl::Stmt::Decl(glsl::VarDecl{
+                qualifier: None,
+                typ: tuple.clone(),
+                name: "t".to_string(),
+                value: Some(glsl::Expr{
+                    kind: glsl::ExprKind::Call{ name: "swap".to_string(), args: vec![int(1), int(2)] },
+                    typ: tuple
+                })
+            }),
+            glsl::Stmt::Assign{ target: gvar("vI", glsl::Type::Int), value: int(1) },
+        ]
+    }), "Failed to declare a tuple w/ its struct");
+}
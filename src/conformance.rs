@@ -0,0 +1,370 @@
+/*
+
+Conformance of a typechecked program to the limitations of a target
+
+GLSL ES 1.00 (WebGL 1) only requires drivers to support a small subset of the language,
+given in Appendix A of its spec. Programs outside of that subset typecheck & compile fine,
+but are rejected by the driver at runtime, so these limitations are checked up front instead.
+
+Loops are limited to 'for' loops that the driver can unroll:
+    - the init declares a single int or float loop index w/ a constant value, i.e. `mut i : int = 0`
+    - the condition compares the loop index to a constant, i.e. `i < 10`
+    - the post steps the loop index by a constant, i.e. `i += 1`
+    - the loop index is not modified in the body of the loop
+'while' & 'do-while' loops are not required to be supported at all.
+
+Arrays, vectors & matrices may only be indexed by a constant-index-expression, which is built from
+constants & the indices of enclosing 'for' loops alone, i.e. `v[2]` or `v[i + 1]`.
+Uniforms are the exception, as vertex shaders may index them w/ any int.
+Functions may be called from either stage, so uniforms are held to the stricter limit within them.
+
+*/
+
+use crate::syntax;
+
+use syntax::Expr;
+use syntax::ExprKind;
+use syntax::BOp;
+use syntax::UOp;
+use syntax::Span;
+use syntax::Program;
+use syntax::ParsedType;
+use syntax::ShaderBody;
+use syntax::AccessType;
+use syntax::Stage;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A construct that the target does not support, w/ the span of source it was found at
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConformanceError {
+    pub msg: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ConformanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+/// What is in scope where an expr is checked
+#[derive(Clone)]
+struct Scope<'a> {
+    uniforms: &'a HashSet<&'a str>,
+    // indices of the enclosing 'for' loops
+    indices: Vec<&'a str>,
+    // any other names bound where the expr is, such as definitions before it & params
+    bound: Vec<&'a str>,
+    // whether the expr is in a vertex shader, rather than a fragment shader or function
+    vertex: bool,
+}
+
+/// Checks a program against the limitations of GLSL ES 1.00, returning every construct that breaks them
+pub fn check_es100(p: &Program) -> Vec<ConformanceError> {
+    let mut errs = vec![];
+    let uniforms = p.iter().filter_map(|e| match &e.kind {
+        ExprKind::Uniform{name, ..} => Some(name.as_str()),
+        _                           => None
+    }).collect();
+    let scope = Scope{ uniforms: &uniforms, indices: vec![], bound: vec![], vertex: false };
+    check_body(p, &scope, &mut errs);
+    errs
+}
+
+impl<'a> Scope<'a> {
+    /// Returns whether a name is bound where an expr in this scope is
+    fn binds(&self, n: &str) -> bool {
+        self.uniforms.contains(n) || self.indices.contains(&n) || self.bound.contains(&n)
+    }
+}
+
+/// Checks the exprs of a body in order, w/ the names that each defines in scope for those after it
+fn check_body<'a>(es: &'a [Expr], scope: &Scope<'a>, errs: &mut Vec<ConformanceError>) {
+    let mut scope = scope.clone();
+    for e in es {
+        check_expr(e, &scope, errs);
+        if let ExprKind::Def{name, ..} | ExprKind::DefMut{name, ..} = &e.kind {
+            scope.bound.push(name);
+        }
+    }
+}
+
+/// Checks an expr & every expr nested within it
+fn check_expr<'a>(e: &'a Expr, scope: &Scope<'a>, errs: &mut Vec<ConformanceError>) {
+    match &e.kind {
+        ExprKind::For{init, cond, post, body} => {
+            check_for(init, cond, post, body, errs);
+            check_expr(init, scope, errs);
+            let mut scope = scope.clone();
+            if let ExprKind::DefMut{name, ..} = &init.kind {
+                scope.indices.push(name);
+            }
+            check_expr(cond, &scope, errs);
+            check_expr(post, &scope, errs);
+            check_body(body, &scope, errs);
+        },
+        ExprKind::While{cond, body} => {
+            errs.push(ConformanceError{
+                msg: "GLSL ES 1.00 only supports 'for' loops w/ a constant bound, so this 'while' loop must be written as one".to_string(),
+                // the header runs from the start of the loop to the end of its condition
+                span: Span::new(e.span.rest, e.span.rest - cond.span.rest + cond.span.len)
+            });
+            check_expr(cond, scope, errs);
+            check_body(body, scope, errs);
+        },
+        ExprKind::DoWhile{body, cond} => {
+            errs.push(ConformanceError{
+                msg: "GLSL ES 1.00 only supports 'for' loops w/ a constant bound, so this 'do-while' loop must be written as one".to_string(),
+                span: cond.span
+            });
+            check_body(body, scope, errs);
+            check_expr(cond, scope, errs);
+        },
+        ExprKind::Def{value, ..} | ExprKind::DefMut{value, ..} | ExprKind::Update{value, ..} => check_expr(value, scope, errs),
+        ExprKind::Return(e) | ExprKind::UnaryOp{e, ..}  => check_expr(e, scope, errs),
+        ExprKind::BinOp{e1, e2, ..}                     => {
+            check_expr(e1, scope, errs);
+            check_expr(e2, scope, errs);
+        },
+        ExprKind::Branch{condition, b1, b2}             => {
+            check_expr(condition, scope, errs);
+            check_body(b1, scope, errs);
+            check_body(b2, scope, errs);
+        },
+        // a component named in brackets, `v[x]`, is not an index unless 'x' is bound, as the typechecker resolves it
+        ExprKind::Access(b, AccessType::Idx(i)) if i.named_component().is_some_and(|c| !scope.binds(c)) => check_expr(b, scope, errs),
+        ExprKind::Access(b, AccessType::Idx(i))         => {
+            check_index(b, i, scope, errs);
+            check_expr(b, scope, errs);
+            check_expr(i, scope, errs);
+        },
+        ExprKind::Access(b, AccessType::Name(_))        => check_expr(b, scope, errs),
+        ExprKind::App{arguments, ..}                    => check_body(arguments, scope, errs),
+        ExprKind::Vect(v)                               => check_body(v, scope, errs),
+        ExprKind::NamedVect(v)                          => v.iter().for_each(|(_,e)| check_expr(e, scope, errs)),
+        ExprKind::Abs{params, body}                     => {
+            let mut scope = Scope{ vertex: false, ..scope.clone() };
+            scope.bound.extend(params.iter().map(|(n,_)| n.as_str()));
+            check_body(body, &scope, errs)
+        },
+        ExprKind::Shader{stage, inputs, outputs, body: ShaderBody::Block(b), ..} => {
+            let mut scope = Scope{ vertex: *stage == Stage::Vertex, ..scope.clone() };
+            scope.bound.extend(inputs.iter().chain(outputs).map(|(n,_)| n.as_str()));
+            check_body(b, &scope, errs)
+        },
+        _                                               => ()
+    }
+}
+
+/// Checks that an index is a constant-index-expression, unless it indexes (part of) a uniform in a vertex shader
+fn check_index(b: &Expr, i: &Expr, scope: &Scope, errs: &mut Vec<ConformanceError>) {
+    let uniform = root_name(b).is_some_and(|n| scope.uniforms.contains(n));
+    let n = b.describe();
+    if (uniform && scope.vertex) || is_constant_index(i, &scope.indices) {
+        return;
+    }
+    let msg = if uniform {
+        format!("GLSL ES 1.00 requires uniform '{n}' to be indexed by a constant or the index of a 'for' loop outside of a vertex shader")
+    } else {
+        format!("GLSL ES 1.00 requires '{n}' to be indexed by a constant or the index of a 'for' loop")
+    };
+    errs.push(ConformanceError{ msg, span: i.span });
+}
+
+/// Checks the header & body of a 'for' loop, pointing at the part of the header that breaks the limitations
+fn check_for(init: &Expr, cond: &Expr, post: &Expr, body: &[Expr], errs: &mut Vec<ConformanceError>) {
+    let mut fail = |msg: String, span: Span| errs.push(ConformanceError{ msg, span });
+
+    // the loop index, w/out which the rest of the header cannot be checked
+    let index = match &init.kind {
+        ExprKind::DefMut{name, typ, value} => {
+            if !matches!(&typ.typ, ParsedType::BaseType(t) if t == "int" || t == "float") {
+                fail(format!("GLSL ES 1.00 requires the index of a 'for' loop to be an int or float, but '{name}' is a '{}'", typ.typ), typ.span);
+            }
+            if !is_constant(value) {
+                fail(format!("GLSL ES 1.00 requires the index '{name}' of a 'for' loop to start at a constant"), value.span);
+            }
+            name
+        },
+        _ => return fail("GLSL ES 1.00 requires a 'for' loop to declare a single loop index in its init, i.e. `mut i : int = 0`".to_string(), init.span)
+    };
+    let is_index = |e: &Expr| matches!(&e.kind, ExprKind::Ref(n) if n == index);
+
+    let relational = [BOp::Lt, BOp::Lte, BOp::Gt, BOp::Gte, BOp::Eq, BOp::Neq];
+    match &cond.kind {
+        ExprKind::BinOp{operator, e1, e2} if relational.contains(operator) && is_index(e1) && is_constant(e2) => (),
+        _ => fail(format!("GLSL ES 1.00 requires the condition of a 'for' loop to compare its index to a constant, i.e. `{index} < 10`"), cond.span)
+    }
+
+    match &post.kind {
+        ExprKind::Update{target, swizzle: None, value} if target == index => match &value.kind {
+            ExprKind::BinOp{operator: BOp::Add | BOp::Sub, e1, e2} if is_index(e1) && is_constant(e2) => (),
+            _ => fail(format!("GLSL ES 1.00 requires a 'for' loop to step its index by a constant, i.e. `{index} += 1`"), post.span)
+        },
+        _ => fail(format!("GLSL ES 1.00 requires a 'for' loop to step its index by a constant, i.e. `{index} += 1`"), post.span)
+    }
+
+    for e in body {
+        if let Some(span) = update_of(e, index) {
+            fail(format!("GLSL ES 1.00 does not allow the index '{index}' of a 'for' loop to be modified in its body"), span);
+        }
+    }
+}
+
+/// Returns whether an expr is a constant expression, built from literals alone
+fn is_constant(e: &Expr) -> bool {
+    match &e.kind {
+        ExprKind::I(_) | ExprKind::F(_) | ExprKind::D(_) | ExprKind::B(_) => true,
+        ExprKind::UnaryOp{operator: UOp::Negative | UOp::Negate, e} => is_constant(e),
+        ExprKind::BinOp{e1, e2, ..} => is_constant(e1) && is_constant(e2),
+        _ => false
+    }
+}
+
+/// Returns the name at the root of a chain of accesses, i.e. 'light' for `light.colors[0]`, if there is one
+fn root_name(e: &Expr) -> Option<&str> {
+    match &e.kind {
+        ExprKind::Ref(n)        => Some(n),
+        ExprKind::Access(b, _)  => root_name(b),
+        _                       => None
+    }
+}
+
+/// Returns whether an expr is a constant-index-expression, built from constants & loop indices alone
+fn is_constant_index(e: &Expr, indices: &[&str]) -> bool {
+    match &e.kind {
+        ExprKind::Ref(n) => indices.contains(&n.as_str()),
+        ExprKind::UnaryOp{e, ..} => is_constant_index(e, indices),
+        ExprKind::BinOp{e1, e2, ..} => is_constant_index(e1, indices) && is_constant_index(e2, indices),
+        _ => is_constant(e)
+    }
+}
+
+/// Returns the span of the first update of a name within an expr, if there is one
+fn update_of(e: &Expr, n: &str) -> Option<Span> {
+    let any = |es: &[Expr]| es.iter().find_map(|e| update_of(e, n));
+    match &e.kind {
+        ExprKind::Update{target, ..} if target == n     => Some(e.span),
+        ExprKind::Update{value, ..} | ExprKind::Def{value, ..} | ExprKind::DefMut{value, ..} => update_of(value, n),
+        ExprKind::Return(e) | ExprKind::UnaryOp{e, ..}  => update_of(e, n),
+        ExprKind::BinOp{e1, e2, ..}                     => update_of(e1, n).or_else(|| update_of(e2, n)),
+        ExprKind::Branch{condition, b1, b2}             => update_of(condition, n).or_else(|| any(b1)).or_else(|| any(b2)),
+        ExprKind::For{init, cond, post, body}           => update_of(init, n).or_else(|| update_of(cond, n)).or_else(|| update_of(post, n)).or_else(|| any(body)),
+        ExprKind::While{cond, body} | ExprKind::DoWhile{body, cond} => update_of(cond, n).or_else(|| any(body)),
+        ExprKind::App{arguments, ..}                    => any(arguments),
+        ExprKind::Vect(v)                               => any(v),
+        _                                               => None
+    }
+}
+
+//
+//
+// CONFORMANCE TESTS
+//
+//
+
+/// Used during testing to check a program, returning the messages & line/col of its errors
+#[cfg(test)]
+fn check_str(src: &str) -> Vec<(String, (usize, usize))> {
+    let (_, p) = crate::parser::program(src).expect("Failed to parse program");
+    check_es100(&p).into_iter().map(|e| (e.msg, e.span.line_col(src))).collect()
+}
+
+/// Accepts loops that GLSL ES 1.00 can unroll
+#[test]
+fn test_es100_loops() {
+    assert_eq!(check_str("\
+mut t : float = 0.0f
+for (mut i : int = 0, i < 4, i += 1) {
+  t += 1.0f
+}
+for (mut x : float = 1.0f, x >= -1.0f, x -= 0.5f) {
+  t *= x
+}
+"), vec![], "Rejected loops w/ a constant bound");
+}
+
+/// Rejects loops that GLSL ES 1.00 does not support, pointing at the part of their header at fault
+#[test]
+fn test_es100_loop_errors() {
+    let errs = check_str("\
+mut n : int = 4
+for (n = 0, n < 4, n += 1) {
+  1
+}
+for (mut i : int = n, i < n, i = i * 2) {
+  i += 1
+}
+while n > 0 {
+  n -= 1
+}
+do {
+  n += 1
+} while n < 4
+");
+    let at : Vec<(usize, usize)> = errs.iter().map(|(_, at)| *at).collect();
+    assert_eq!(at, vec![(2, 6), (5, 20), (5, 23), (5, 30), (6, 3), (8, 1), (13, 9)], "Wrong locations for non-conformant loops, got {:?}", errs);
+    assert_eq!(errs[0].0, "GLSL ES 1.00 requires a 'for' loop to declare a single loop index in its init, i.e. `mut i : int = 0`");
+    assert_eq!(errs[1].0, "GLSL ES 1.00 requires the index 'i' of a 'for' loop to start at a constant");
+    assert_eq!(errs[2].0, "GLSL ES 1.00 requires the condition of a 'for' loop to compare its index to a constant, i.e. `i < 10`");
+    assert_eq!(errs[3].0, "GLSL ES 1.00 requires a 'for' loop to step its index by a constant, i.e. `i += 1`");
+    assert_eq!(errs[4].0, "GLSL ES 1.00 does not allow the index 'i' of a 'for' loop to be modified in its body");
+    assert!(errs[5].0.contains("'while' loop"), "Failed to reject a while loop");
+    assert!(errs[6].0.contains("'do-while' loop"), "Failed to reject a do-while loop");
+
+    // a while loop is pointed at by its header alone
+    let src = "while n > 0 {\n  n -= 1\n}\n";
+    let (_, p) = crate::parser::program(src).unwrap();
+    assert_eq!(&src[check_es100(&p)[0].span.range(src)], "while n > 0", "Wrong span for the header of a while loop");
+
+    // loops nested in functions & shaders are checked too, as is the type of the loop index
+    let errs = check_str("\
+let f : int -> int = (x : int) {
+  for (mut b : bool = true, b == false, b = !b) {
+    x
+  }
+  x
+}
+");
+    assert_eq!(errs.len(), 2, "Wrong errors for a loop w/ a bool index, got {:?}", errs);
+    assert_eq!(errs[0], ("GLSL ES 1.00 requires the index of a 'for' loop to be an int or float, but 'b' is a 'bool'".to_string(), (2, 16)));
+}
+
+/// Accepts indices built from constants & loop indices, & any index of a uniform in a vertex shader
+#[test]
+fn test_es100_indices() {
+    assert_eq!(check_str("\
+uniform vec4[8] uColors
+vert v : (vec3 aVertPos, int aIdx) -> (vec4 vColor) = {
+  for (mut i : int = 0, i < 2, i += 1) {
+    vColor += uColors[i * 2 + 1]
+  }
+  vColor += uColors[aIdx] * aVertPos[2 - 1]
+}
+"), vec![], "Rejected indices that GLSL ES 1.00 supports");
+}
+
+/// Rejects indices that are not constant-index-expressions, pointing at the index
+#[test]
+fn test_es100_index_errors() {
+    let errs = check_str("\
+uniform vec4[8] uColors
+frag f : (vec3 vXYZ, int vIdx) -> () = {
+  mut c : vec4 = uColors[vIdx]
+  mut n : int = 1
+  for (mut i : int = 0, i < 2, i += 1) {
+    c += vXYZ[i + n]
+  }
+  c
+}
+let g : int -> vec4 = (x : int) {
+  uColors[x]
+}
+");
+    assert_eq!(errs, vec![
+        ("GLSL ES 1.00 requires uniform 'uColors' to be indexed by a constant or the index of a 'for' loop outside of a vertex shader".to_string(), (3, 26)),
+        ("GLSL ES 1.00 requires 'vXYZ' to be indexed by a constant or the index of a 'for' loop".to_string(), (6, 15)),
+        ("GLSL ES 1.00 requires uniform 'uColors' to be indexed by a constant or the index of a 'for' loop outside of a vertex shader".to_string(), (11, 11)),
+    ], "Wrong errors for dynamic indices");
+}
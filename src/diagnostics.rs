@@ -0,0 +1,55 @@
+/*
+
+Rendering of errors against the source they were found in
+
+Errors are printed much like rustc does, w/ the line & column they start at,
+followed by the offending line of source & carets under the span of the error.
+Spans over several lines are only underlined on their first line.
+
+*/
+
+use crate::syntax::Span;
+
+/// Renders an error message w/ the line of source it was found in, underlining its span
+/// Errors w/ a code, such as those of the typechecker, show it next to the message
+pub fn render(src: &str, span: Span, code: Option<&str>, msg: &str) -> String {
+    let range = span.range(src);
+    let (line, col) = span.line_col(src);
+    let text = src.lines().nth(line - 1).unwrap_or("");
+
+    // the span is cut off at the end of its first line, but always gets at least 1 caret
+    let before : String = text.chars().take(col - 1).collect();
+    let in_line = src[range].split('\n').next().unwrap_or("").chars().count();
+    let carets = "^".repeat(in_line.min(text.chars().count().saturating_sub(col - 1)).max(1));
+
+    // tabs are kept in the padding, so the carets line up w/ the source however tabs are displayed
+    let pad : String = before.chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    let gutter = " ".repeat(line.to_string().len());
+
+    let code = code.map_or(String::new(), |c| format!("[{c}]"));
+    format!("error{code}: {msg}\n{gutter}--> {line}:{col}\n{gutter} |\n{line} | {text}\n{gutter} | {pad}{carets}\n")
+}
+
+//
+//
+// DIAGNOSTICS TESTS
+//
+//
+
+#[test]
+fn test_render() {
+    let src = "let x : int = 1\n\tlet y : bool = x\n";
+    // span of the 'x' in the second definition
+    let span = Span::new(src.len() - src.rfind('x').unwrap(), 1);
+    assert_eq!(
+        render(src, span, Some("E0003"), "expected bool"),
+        "error[E0003]: expected bool\n --> 2:17\n  |\n2 | \tlet y : bool = x\n  | \t               ^\n"
+    );
+
+    // spans over several lines are cut off at the end of the first
+    let span = Span::new(src.len(), src.len() - 1);
+    assert_eq!(
+        render(src, span, None, "oops"),
+        "error: oops\n --> 1:1\n  |\n1 | let x : int = 1\n  | ^^^^^^^^^^^^^^^\n"
+    );
+}
@@ -0,0 +1,218 @@
+//! Abstract syntax for the GLSL produced by the compiler
+//!
+//! This is deliberately independent of the FDSSL syntax, so printers for each GLSL version
+//! (or other shading languages) only ever have to deal w/ this AST.
+//! Every expression carries its type, as some targets need it to emit valid code.
+
+use crate::syntax::Stage;
+use std::fmt;
+use std::str::FromStr;
+
+/// Types that can be expressed in GLSL
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Type {
+    Void,
+    Bool,
+    Int,
+    Float,
+    Double,
+    // float vector of size 2-4
+    Vec(u8),
+    // int vector of size 2-4
+    IVec(u8),
+    // bool vector of size 2-4
+    BVec(u8),
+    // square float matrix of size 2-4
+    Mat(u8),
+    Sampler2D,
+    // user defined struct, such as a lowered tuple
+    Struct(String),
+    // fixed number of elements of another type
+    Array(Box<Type>, usize),
+}
+
+/// Storage qualifiers for global variables
+/// Shader inputs & outputs are kept abstract, as each GLSL version names them differently
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Qualifier {
+    Const,
+    Uniform,
+    // input to a shader, supplied per vertex or from the vertex stage
+    In,
+    // output of a shader, passed along to the next stage
+    Out,
+}
+
+/// Binary operators available in GLSL
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Eq,
+    Neq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    BitAnd,
+    BitOr,
+    BitXor,
+}
+
+/// Unary operators available in GLSL
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+/// A typed GLSL expression
+#[derive(Debug, PartialEq, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub typ: Type,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExprKind {
+    Int(i32),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    Var(String),
+    // call of a function or a constructor, such as vec4(...) or a struct
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    Binary {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Unary {
+        op: UnOp,
+        e: Box<Expr>,
+    },
+    Index(Box<Expr>, Box<Expr>),
+    Field(Box<Expr>, String),
+}
+
+/// Declaration of a variable, w/ an optional qualifier & initial value
+#[derive(Debug, PartialEq, Clone)]
+pub struct VarDecl {
+    pub qualifier: Option<Qualifier>,
+    pub typ: Type,
+    pub name: String,
+    pub value: Option<Expr>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Stmt {
+    Decl(VarDecl),
+    Assign {
+        target: Expr,
+        value: Expr,
+    },
+    Expr(Expr),
+    If {
+        condition: Expr,
+        b1: Vec<Stmt>,
+        b2: Vec<Stmt>,
+    },
+    For {
+        init: Box<Stmt>,
+        cond: Expr,
+        post: Box<Stmt>,
+        body: Vec<Stmt>,
+    },
+    While {
+        cond: Expr,
+        body: Vec<Stmt>,
+    },
+    DoWhile {
+        body: Vec<Stmt>,
+        cond: Expr,
+    },
+    Return(Option<Expr>),
+    Discard,
+}
+
+/// A function definition, w/ typed parameters
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub name: String,
+    pub ret: Type,
+    pub params: Vec<(String, Type)>,
+    pub body: Vec<Stmt>,
+}
+
+/// A struct definition, w/ named fields in order
+#[derive(Debug, PartialEq, Clone)]
+pub struct StructDecl {
+    pub name: String,
+    pub fields: Vec<(String, Type)>,
+}
+
+/// Top-level declarations of a shader, which must be declared before they are used
+#[derive(Debug, PartialEq, Clone)]
+pub enum Decl {
+    Struct(StructDecl),
+    Var(VarDecl),
+    Function(Function),
+}
+
+/// A single shader, where the last declaration is always its 'main' function
+#[derive(Debug, PartialEq, Clone)]
+pub struct Shader {
+    pub stage: Stage,
+    pub decls: Vec<Decl>,
+}
+
+/// A vertex & fragment shader pair, compiled from a program declared w/ mkProg
+#[derive(Debug, PartialEq, Clone)]
+pub struct Program {
+    pub name: String,
+    pub vert: Shader,
+    pub frag: Shader,
+}
+
+/// Versions of GLSL that a program can be printed as
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Version {
+    // GLSL ES 1.00, for WebGL 1
+    Es100,
+    // GLSL 3.30 core profile
+    Glsl330,
+    // GLSL 4.50
+    Glsl450,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Version::Es100   => write!(f, "GLSL ES 1.00"),
+            Version::Glsl330 => write!(f, "GLSL 3.30"),
+            Version::Glsl450 => write!(f, "GLSL 4.50"),
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    /// Parses a version as given on the command line, i.e. '100', '330' or '450'
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "100" | "es100" => Ok(Version::Es100),
+            "330"           => Ok(Version::Glsl330),
+            "450"           => Ok(Version::Glsl450),
+            _               => Err(format!("Unknown GLSL version '{s}', expected one of '100', '330' or '450'"))
+        }
+    }
+}
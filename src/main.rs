@@ -1,29 +1,151 @@
 mod syntax;
 mod parser;
 mod typechecker;
+mod glsl_syntax;
+mod compiler;
+mod pretty;
+mod wgsl;
+mod spirv;
+mod diagnostics;
+mod tc_error;
+mod builtins;
+mod conformance;
 
-use parser::program;
+use parser::{program, parse_program};
 use typechecker::tc_program;
+use compiler::compile;
+use glsl_syntax::Version;
+use std::path::Path;
+use std::env;
+use std::fs;
 
 
-/// Verify a program through parsing & Typechecking
-fn verify(prog: &str) {
+/// Language that the shaders of a program are printed in
+#[derive(Clone, Copy)]
+enum Target {
+    Glsl(Version),
+    Wgsl,
+    // SPIR-V binaries, printed as their disassembly
+    Spirv,
+}
+
+/// Verify a program through parsing & Typechecking, printing the shaders for every program it declares
+/// If an output directory is given, the shaders of each program are written there as well
+fn verify(prog: &str, out: Option<&Path>, target: Target) {
     println!("Verifying program: \n\n{}", prog);
-    match program(prog) {
-        Ok((_, parsed_prog)) => {
-            println!("* program parsed successfully");
-            match tc_program(parsed_prog) {
-                Ok((_, s)) => {
-                    println!("* program typechecked successfully");
-                },
-                Err(e)  => println!("!! Failed to TC program: {:?}", e)
+    let (parsed_prog, syntax_errors) = parse_program(prog);
+    if !syntax_errors.is_empty() {
+        // every syntax error is reported, but a program w/ any of them is not checked further
+        println!("!! Failed to parse program, w/ {} error(s):", syntax_errors.len());
+        for e in syntax_errors {
+            print!("{}", diagnostics::render(prog, e.span, None, &e.to_string()));
+        }
+        return;
+    }
+    println!("* program parsed successfully");
+    match tc_program(parsed_prog.clone()) {
+        Ok((_, env)) => {
+            println!("* program typechecked successfully");
+            // GLSL ES 1.00 drivers reject anything outside of the subset in Appendix A of its spec
+            if let Target::Glsl(Version::Es100) = target {
+                let errs = conformance::check_es100(&parsed_prog);
+                if !errs.is_empty() {
+                    println!("!! Program does not conform to GLSL ES 1.00, w/ {} error(s):", errs.len());
+                    for e in errs {
+                        print!("{}", diagnostics::render(prog, e.span, None, &e.msg));
+                    }
+                    return;
+                }
+            }
+            match compile(&parsed_prog, &env) {
+                Ok(progs) => progs.iter().for_each(|p| emit(p, out, target)),
+                Err(e)    => println!("!! Failed to compile program: {:?}", e)
             }
         },
-        Err(e) => println!("!! Failed to parse program: {:?}", e)
+        Err(es) => {
+            println!("!! Failed to TC program, w/ {} error(s):", es.errors.len());
+            for e in es.errors {
+                match e.span {
+                    Some(span) => print!("{}", diagnostics::render(prog, span, Some(e.code()), &e.kind.to_string())),
+                    None       => println!("{}", e)
+                }
+            }
+        }
+    }
+}
+
+/// Prints the shaders of a compiled program, writing them out to a directory if one is given
+fn emit(p: &glsl_syntax::Program, out: Option<&Path>, target: Target) {
+    println!("* compiled program '{}'", p.name);
+    let ext = match target {
+        Target::Glsl(_) => "",
+        Target::Wgsl    => ".wgsl",
+        Target::Spirv   => ".spv"
+    };
+    for (stage, s) in [("vert", &p.vert), ("frag", &p.frag)] {
+        let src = match target {
+            Target::Glsl(v) => pretty::print_shader(s, v),
+            Target::Wgsl    => wgsl::print_shader(s),
+            Target::Spirv   => spirv::emit_shader(s).and_then(|w| spirv::disassemble(&w))
+        };
+        match src {
+            Ok(src) => println!("\n// {}.{}{}\n{}", p.name, stage, ext, src),
+            Err(e)  => println!("!! Failed to print '{}.{}{}': {}", p.name, stage, ext, e)
+        }
+    }
+    if let Some(dir) = out {
+        let res = match target {
+            Target::Glsl(v) => pretty::write_program(p, dir, v),
+            Target::Wgsl    => wgsl::write_program(p, dir),
+            Target::Spirv   => spirv::write_program(p, dir)
+        };
+        match res {
+            Ok(()) => println!("* wrote '{0}.vert{1}' & '{0}.frag{1}' to '{2}'", p.name, ext, dir.display()),
+            Err(e) => println!("!! {}", e)
+        }
     }
 }
 
 fn main() {
+    // verify a program from a file, if one was given, w/ an optional directory to write shaders to
+    // shaders default to GLSL ES 1.00, another version can be selected w/ '--glsl <100|330|450>', WGSL w/ '--wgsl'
+    // or SPIR-V w/ '--spirv'
+    // '--explain <code>' prints the long-form explanation of a typechecker error instead
+    let mut args : Vec<String> = env::args().skip(1).collect();
+    if let Some(i) = args.iter().position(|a| a == "--explain") {
+        return match args.get(i + 1) {
+            Some(code) => match tc_error::explain(code) {
+                Some(text) => println!("{}", text),
+                None       => println!("!! No error has the code '{}'", code)
+            },
+            None => println!("!! Expected an error code after '--explain'")
+        };
+    }
+    let mut target = Target::Glsl(Version::Es100);
+    if let Some(i) = args.iter().position(|a| a == "--wgsl") {
+        target = Target::Wgsl;
+        args.remove(i);
+    }
+    if let Some(i) = args.iter().position(|a| a == "--spirv") {
+        target = Target::Spirv;
+        args.remove(i);
+    }
+    if let Some(i) = args.iter().position(|a| a == "--glsl") {
+        match args.get(i + 1).map(|v| v.parse()) {
+            Some(Ok(v))  => target = Target::Glsl(v),
+            Some(Err(e)) => return println!("!! {}", e),
+            None         => return println!("!! Expected a GLSL version after '--glsl'")
+        }
+        args.drain(i..i + 2);
+    }
+    if let Some(path) = args.first() {
+        match fs::read_to_string(path) {
+            Ok(prog) => verify(&prog, args.get(1).map(Path::new), target),
+            Err(e)   => println!("!! Failed to read '{}': {}", path, e)
+        }
+        return;
+    }
+
     let prog = "\
     let swap : (int, int) -> (int, int) = (x : int, y : int) { \n\
     \t(y,x)\n\
@@ -39,7 +161,7 @@ fn main() {
     \n\
     apply(double,8)\n\
     ";
-    verify(prog);
+    verify(prog, None, Target::Glsl(Version::Es100));
     
     let res = match program(prog) {
         Ok((_, a)) => a,
@@ -48,7 +170,6 @@ fn main() {
     println!("\n\nPARSED PROGRAM: {:?}\n\n", res);
     println!("TYPECHECKED PROGRAM: {:?}\n\n", tc_program(res));
 
-    let r1 = program("(x:1, y:2)");
     let r2 = program("1+1\n");
     println!("{:?}", r2);
 
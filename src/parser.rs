@@ -1,66 +1,152 @@
 use crate::syntax;
 
 use syntax::Expr;
+use syntax::ExprKind;
+use syntax::Span;
+use syntax::Annotation;
 use syntax::ParsedType;
 use syntax::AccessType;
 use syntax::BOp;
 use syntax::UOp;
+use syntax::Stage;
+use syntax::ShaderBody;
 extern crate itertools;
 
 extern crate nom;
 
-use nom::bytes::complete::{tag};
 use nom::character::complete::{char, alpha1, alphanumeric1, i32, multispace0, space0, space1, line_ending, not_line_ending, digit1};
-use nom::{IResult};
+use nom::error::{ParseError, ContextError, ErrorKind, context};
 //use nom::character::is_alphabetic;
 use nom::branch::alt;
 use nom::multi::{many0, many1, separated_list0, separated_list1};
 use nom::sequence::{preceded, delimited, terminated, separated_pair, tuple, pair};
 use nom::number::complete::{float, double};
 use nom::combinator::{map, verify, recognize, peek, opt, fail, not, eof};
+use std::fmt;
+
+/// Failure of a parser, w/ the rest of the input at the point it failed & what was expected there
+///
+/// Of the alternatives tried by `alt`, the failure that got furthest into the input is kept,
+/// as that alternative is most likely the one that was meant.
+/// Failures at the same point merge what they expected, i.e. "expected ':' or '='".
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseFailure<I> {
+    pub input: I,
+    pub expected: Vec<String>,
+}
+
+/// Result of a parser, which fails w/ a ParseFailure
+type IResult<I, O> = nom::IResult<I, O, ParseFailure<I>>;
+
+impl<'a> ParseError<&'a str> for ParseFailure<&'a str> {
+    fn from_error_kind(input: &'a str, _: ErrorKind) -> Self {
+        ParseFailure{ input, expected: vec![] }
+    }
+
+    fn from_char(input: &'a str, c: char) -> Self {
+        ParseFailure{ input, expected: vec![format!("'{}'", c)] }
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        // inputs are the rest of the source, so the shorter one got further
+        let (here, there) = (remaining(self.input), remaining(other.input));
+        if here < there {
+            self
+        } else if there < here {
+            other
+        } else {
+            let mut expected = self.expected;
+            for e in other.expected {
+                if !expected.contains(&e) {
+                    expected.push(e);
+                }
+            }
+            ParseFailure{ input: self.input, expected }
+        }
+    }
+}
+
+impl<'a> ContextError<&'a str> for ParseFailure<&'a str> {
+    /// Describes a failure by the context it was in, when it failed at the start of that context
+    /// Failures further in keep what they expected, as that is more precise
+    fn add_context(input: &'a str, ctx: &'static str, mut other: Self) -> Self {
+        if other.expected.is_empty() || remaining(other.input) == remaining(input) {
+            other.expected = vec![ctx.to_string()];
+        }
+        other
+    }
+}
+
+/// Returns the length of input left, ignoring the spaces that any parser would skip over
+fn remaining(input: &str) -> usize {
+    input.trim_start_matches([' ', '\t']).len()
+}
+
+/// Parses a literal, which is what is expected when it is missing
+fn tag<'a>(t: &'static str) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
+    move |input: &'a str| match input.strip_prefix(t) {
+        Some(rest) => Ok((rest, &input[..t.len()])),
+        None       => Err(nom::Err::Error(ParseFailure{ input, expected: vec![format!("'{}'", t)] }))
+    }
+}
 
 
 #[macro_export]
 macro_rules! bop {
     ( $i:expr , $b:expr) => {
         {
-            map(tag($i), |s: &str| $b)
+            map(tag($i), |_: &str| $b)
         }
     };
 }
 
+/// Runs a parser, pairing its output w/ the span of source that it consumed
+/// Leading & trailing whitespace is not part of the span, so it covers exactly what was parsed
+fn with_span<'a, O>(mut p: impl FnMut(&'a str) -> IResult<&'a str, O>) -> impl FnMut(&'a str) -> IResult<&'a str, (O, Span)> {
+    move |input: &'a str| {
+        let start = input.trim_start();
+        let (rest, o) = p(input)?;
+        let consumed = &start[..start.len().saturating_sub(rest.len())];
+        Ok((rest, (o, Span::new(start.len(), consumed.trim_end().len()))))
+    }
+}
+
+/// Runs a parser for the kind of an expr, producing an expr w/ the span it was parsed from
+fn spanned<'a>(p: impl FnMut(&'a str) -> IResult<&'a str, ExprKind>) -> impl FnMut(&'a str) -> IResult<&'a str, Expr> {
+    map(with_span(p), |(kind, span)| Expr{kind, span})
+}
+
 /// name parses any valid identifier, [_a-zA-Z][_a-zA-Z0-9]*
 ///
 /// This function was /really/ based off the example from the nom documentation
 fn name(i: &str) -> IResult<&str, &str> {
-    recognize(
+    context("a name", recognize(
         pair(
             alt((alpha1, tag("_"))),
             many0(alt((alphanumeric1, tag("_"))))
         )
-    )(i)
+    ))(i)
 }
 
 /// Parses a name into a String
 fn name_str(i: &str) -> IResult<&str, String> {
-    map(recognize(
-        pair(
-            alt((alpha1, tag("_"))),
-            many0(alt((alphanumeric1, tag("_"))))
-        )
-    ), |s: &str| s.to_string())(i)
+    map(name, |s: &str| s.to_string())(i)
 }
 
 /// Parses an int, corresponding to a 32-bit int in Rust.
 /// Can be surrounded by spaces on either end
 fn parse_int(input: &str) -> IResult<&str, Expr> {
-    map(delimited(space0, i32, not(alt((alpha1, tag("_"))))), |i: i32| Expr::I(i))(input)
+    spanned(map(delimited(space0, i32, not(alt((alpha1, tag("_"))))), ExprKind::I))(input)
 }
 
 /// parse_float parses a 32-bit floating point value, preceded by 0+ spaces.
 /// Floats are always denoted by a trailing 'f'
 fn parse_float(i: &str) -> IResult<&str, Expr> {
-    map(preceded(space0, terminated(float, tag("f"))), |v: f32| Expr::F(v))(i)
+    spanned(map(preceded(space0, terminated(float, tag("f"))), ExprKind::F))(i)
 }
 
 /// parse_double parses a 64-bit floating point value, preceded by 0+ spaces.
@@ -71,7 +157,7 @@ fn parse_double(i: &str) -> IResult<&str, Expr> {
     let mut parser = peek(preceded(space0 , preceded(opt(tag("-")), preceded(digit1, preceded(tag("."), parse_int)))));
     if parser(i).is_ok() {
         // proceed to parse a double
-        map(preceded(space0, double), |v: f64| Expr::D(v))(i)
+        spanned(map(preceded(space0, double), ExprKind::D))(i)
     } else {
         // fail out on this
         fail(i)
@@ -80,76 +166,138 @@ fn parse_double(i: &str) -> IResult<&str, Expr> {
 
 /// Indicates whether a string is a keyword
 fn is_keyword(s: &str) -> bool {
-    let keywords = ["if","return","for","let","mut"];
-    return keywords.contains(&s)
+    let keywords = ["if","then","return","discard","for","while","do","let","mut","set","out","vert","frag","uniform","mkProg"];
+    keywords.contains(&s)
 }
 
 /// Parses a reference, which is defined by the `name` parser, see `name`
 fn parse_ref(i: &str) -> IResult<&str, Expr> {
-    map(preceded(space0, verify(name, |s : &str| !is_keyword(s))), |s: &str| Expr::Ref(s.to_string()))(i)
-}
-
-
-/// Parses named access of a reference
-fn parse_named_access(i: &str) -> IResult<&str, Expr> {
-    // parse name, followed by '.' and then another name
-    let (i,(n,_,a)) = tuple((
-        preceded(space0, name),
-        preceded(space0, tag(".")),
-        preceded(space0, name)
-    ))(i)?;
-    Ok((i, Expr::Access(n.to_string(), AccessType::Name(a.to_string()))))
+    spanned(map(preceded(space0, verify(name, |s : &str| !is_keyword(s))), |s: &str| ExprKind::Ref(s.to_string())))(i)
+}
+
+
+/// Runs a parser for an operand, followed by any number of accesses of it by name or index
+/// i.e. `light.color.r`, `f(x).pos` or `(a + b)[0]`
+/// No spaces are allowed before a '.', as `f . g` denotes composition instead
+/// As in the original FDSSL syntax, a component of a vector may also be named in brackets, as in `v[x]`,
+/// which is parsed as an index & only resolved to a component where 'x' is not bound, see `Expr::named_component`
+fn parse_access<'a>(mut p: impl FnMut(&'a str) -> IResult<&'a str, Expr>) -> impl FnMut(&'a str) -> IResult<&'a str, Expr> {
+    move |input: &'a str| {
+        let start = input.trim_start().len();
+        let (mut input, mut e) = p(input)?;
+        loop {
+            let access = alt((
+                map(preceded(tag("."), name), |a: &str| AccessType::Name(a.to_string())),
+                map(
+                    delimited(preceded(space0, tag("[")), parse_expr, preceded(space0, tag("]"))),
+                    |i: Expr| AccessType::Idx(Box::new(i))
+                ),
+            ))(input);
+            match access {
+                Ok((rest, at)) => {
+                    // each access spans its base as well, up to the end of the access
+                    let span = Span::new(start, start - rest.len());
+                    e = Expr{kind: ExprKind::Access(Box::new(e), at), span};
+                    input = rest;
+                },
+                Err(nom::Err::Error(_)) => return Ok((input, e)),
+                Err(err) => return Err(err)
+            }
+        }
+    }
 }
 
 
-/// Parses indexed access of a reference
-fn parse_index_access(i: &str) -> IResult<&str, Expr> {
-    // parse name followed by an expr wrapped in square brackets
-    let (i,(n,e)) = tuple((
-        preceded(space0, name),
-        delimited(
-            preceded(space0, tag("[")),
-            parse_expr,
-            preceded(space0, tag("]"))
-        )
-    ))(i)?;
-    Ok((i, Expr::Access(n.to_string(), AccessType::Idx(Box::new(e)))))
+/// Parses the name & type of a definition, written as `name : T`
+/// The original FDSSL syntax writes the type first instead, as `T name`, which is accepted as well
+fn parse_binding(input: &str) -> IResult<&str, (String, Annotation)> {
+    alt((
+        map(tuple((name_str, space0, char(':'), space0, parse_annotation)), |(name,_,_,_,t)| (name,t)),
+        map(separated_pair(parse_annotation, space1, name_str), |(t,name)| (name,t)),
+    ))(input)
 }
 
-
 /// Parses an immutable definition
 /// Constitutes a binding of a name to an expr
 fn parse_def(input: &str) -> IResult<&str, Expr> {
-    let (input, (_,name,_,_,_,t,_,_,expr)) = tuple((delimited(space0,tag("let"),space1), name_str, space0, char(':'), space0, parse_type, space0, char('='), parse_expr))(input)?;
-    Ok((input, Expr::Def{name: name, typ: t, value: Box::new(expr)}))
+    let p = tuple((delimited(space0,tag("let"),space1), parse_binding, space0, char('='), parse_expr));
+    spanned(map(p, |(_,(name,t),_,_,expr)| ExprKind::Def{name, typ: t, value: Box::new(expr)}))(input)
 }
 
 /// Parses a mutable definition
 /// Constitutes a dynamic binding of a name to an expr, which can be changed at runtime
 fn parse_mutdef(input: &str) -> IResult<&str, Expr> {
-    let (input, (_,name,_,_,_,t,_,_,expr)) = tuple((delimited(space0,tag("mut"),space1), name_str, space0, char(':'), space0, parse_type, space0, char('='), parse_expr))(input)?;
-    Ok((input, Expr::DefMut{name: name, typ: t, value: Box::new(expr)}))
+    let p = tuple((delimited(space0,tag("mut"),space1), parse_binding, space0, char('='), parse_expr));
+    spanned(map(p, |(_,(name,t),_,_,expr)| ExprKind::DefMut{name, typ: t, value: Box::new(expr)}))(input)
+}
+
+/// Parses the swizzle of the target of an update, i.e. the `.xy` of `set v.xy expr`
+fn parse_target_swizzle(input: &str) -> IResult<&str, Option<String>> {
+    opt(preceded(char('.'), name_str))(input)
+}
+
+/// Parses an update of an existing binding, in the form `set name expr`
+/// `out name expr` is also accepted, which reads better when writing to a shader's named output
+/// Only some components of a vector may be written to w/ a swizzle, i.e. `set pos.xy expr`
+fn parse_set(input: &str) -> IResult<&str, Expr> {
+    let p = tuple((delimited(space0,alt((tag("set"),tag("out"))),space1), name_str, parse_target_swizzle, parse_expr));
+    spanned(map(p, |(_,target,swizzle,expr)| ExprKind::Update{target, swizzle, value: Box::new(expr)}))(input)
+}
+
+/// Parses an assignment, in the form `name = expr`, or a compound assignment such as `name += expr`
+/// Compound assignments are sugar for an update by the binary op, i.e. `x += 1` is `x = x + 1`
+fn parse_assign(input: &str) -> IResult<&str, Expr> {
+    spanned(|input| {
+        let (input,((target,swizzle),tspan)) = with_span(preceded(space0, pair(verify(name_str, |s: &str| !is_keyword(s)), parse_target_swizzle)))(input)?;
+        let (input,op)             = preceded(space0, alt((
+            map(tag("+="), |_| Some(BOp::Add)),
+            map(tag("-="), |_| Some(BOp::Sub)),
+            map(tag("*="), |_| Some(BOp::Mul)),
+            map(tag("/="), |_| Some(BOp::Div)),
+            // not to be confused w/ equality
+            map(terminated(tag("="), not(char('='))), |_| None),
+        )))(input)?;
+        let (input,e)              = parse_expr(input)?;
+        let value = match op {
+            Some(operator) => {
+                let target = match &swizzle {
+                    Some(sw) => ExprKind::Access(Box::new(ExprKind::Ref(target.clone()).into()), AccessType::Name(sw.clone())),
+                    None     => ExprKind::Ref(target.clone())
+                };
+                let target = Expr{kind: target, span: tspan};
+                let span = tspan.to(e.span);
+                Expr{kind: ExprKind::BinOp{operator, e1: Box::new(target), e2: Box::new(e)}, span}
+            },
+            None => e
+        };
+        Ok((input, ExprKind::Update{target, swizzle, value: Box::new(value)}))
+    })(input)
 }
 
 // Parses a Boolean value w/ optional leading space
 fn parse_bool(input: &str) -> IResult<&str, Expr> {
     let vp1 = verify(preceded(space0, name), |s: &str| s == "true" || s == "false");
-    map(vp1, |s: &str| Expr::B(s == "true"))(input)
+    spanned(map(vp1, |s: &str| ExprKind::B(s == "true")))(input)
 }
 
 /// parse_return parses a return expression.
 ///
 /// The return expression takes an expression as an argument in the form `return expr`
-// TODO Should we check for nested returns here?
-// This almost makes me want to remove `return` again
+/// Returns are only valid in the body of a function, which is left to the typechecker
 fn parse_return(i: &str) -> IResult<&str, Expr> {
-    map(
+    spanned(map(
         preceded(
             delimited(space0, tag("return"), space1),
             parse_expr,
         ),
-        |expr: Expr| Expr::Return(Box::new(expr))
-    )(i)
+        |expr: Expr| ExprKind::Return(Box::new(expr))
+    ))(i)
+}
+
+/// parse_discard parses a discard, which takes no argument
+/// Discards are only valid in fragment shaders, which is left to the typechecker
+fn parse_discard(i: &str) -> IResult<&str, Expr> {
+    spanned(map(preceded(space0, verify(name, |s: &str| s == "discard")), |_| ExprKind::Discard))(i)
 }
 
 
@@ -159,7 +307,7 @@ fn parse_return(i: &str) -> IResult<&str, Expr> {
 /// is any type annotation. This allows the type annotation to nest other
 /// structural types. e.g. (t1, (t2, t3), t1 -> t2)
 fn parse_type_tuple(i: &str) -> IResult<&str, ParsedType> {
-    let mut parser =
+    let parser =
         verify(
             delimited(
                 terminated(tag("("), space0),
@@ -177,6 +325,45 @@ fn parse_type_tuple(i: &str) -> IResult<&str, ParsedType> {
     )(i)
 }
 
+/// parse_type_named_tuple parses the type of a tuple w/ named properties.
+///
+/// Each property is a name & a type, as they are in a named tuple, e.g. (pos: vec3, color: vec4)
+fn parse_type_named_tuple(i: &str) -> IResult<&str, ParsedType> {
+    let property = separated_pair(
+        preceded(space0, name_str),
+        preceded(space0, tag(":")),
+        map(parse_type, Box::new)
+    );
+    map(
+        delimited(
+            terminated(tag("("), space0),
+            separated_list1(delimited(space0, tag(","), space0), property),
+            preceded(space0, tag(")")),
+        ),
+        ParsedType::NamedTuple
+    )(i)
+}
+
+/// Names of the built-in vector & matrix types, which are also the names of their constructors
+const VECTOR_TYPES: [&str; 12] = [
+    "vec2","vec3","vec4","ivec2","ivec3","ivec4","bvec2","bvec3","bvec4",
+    "mat2","mat3","mat4"
+];
+
+/// Returns the canonical name of a type annotation.
+///
+/// Our older FDSSL programs capitalize the built-in types (`Float`, `Vec3`, `Mat4`),
+/// so these are lowered to match their GLSL names. All other names are left as is.
+fn canonical_type_name(n: &str) -> String {
+    let scalars = ["int","uint","bool","float","double"];
+    let lowered = n.to_lowercase();
+    if scalars.contains(&lowered.as_str()) || VECTOR_TYPES.contains(&lowered.as_str()) {
+        lowered
+    } else {
+        n.to_string()
+    }
+}
+
 /// parse_type_base parses the Basic Type annotation.
 ///
 /// This function parses the Basic Type annotation. This covers types including
@@ -184,7 +371,20 @@ fn parse_type_tuple(i: &str) -> IResult<&str, ParsedType> {
 /// so we simply save it.
 // TODO perhaps type names should be more limited than reference names
 fn parse_type_base(i: &str) -> IResult<&str, ParsedType> {
-    map(name, |n: &str| ParsedType::BaseType(n.to_string()))(i)
+    map(name, |n: &str| ParsedType::BaseType(canonical_type_name(n)))(i)
+}
+
+
+/// Parses the sizes of an array type after the type of its elements, i.e. the `[4]` of `float[4]`
+/// Each size wraps the type before it, so `float[4][2]` is 2 arrays of 4 floats
+fn parse_type_array(t: ParsedType, i: &str) -> IResult<&str, ParsedType> {
+    let size = delimited(
+        preceded(space0, tag("[")),
+        preceded(space0, context("an array size", verify(map(digit1, |n: &str| n.parse::<usize>().unwrap_or(0)), |n| *n > 0))),
+        preceded(space0, tag("]"))
+    );
+    let (i, sizes) = many0(size)(i)?;
+    Ok((i, sizes.into_iter().fold(t, |t, n| ParsedType::Array(Box::new(t), n))))
 }
 
 
@@ -198,7 +398,8 @@ fn parse_type_base(i: &str) -> IResult<&str, ParsedType> {
 fn parse_type(i: &str) -> IResult<&str, ParsedType> {
     let (i, _) = space0(i)?;
 
-    let (i, t) = alt((parse_type_base, parse_type_tuple))(i)?;
+    let (i, t) = context("a type", alt((parse_type_base, parse_type_named_tuple, parse_type_tuple)))(i)?;
+    let (i, t) = parse_type_array(t, i)?;
 
     let res: IResult<&str, &str> = preceded(space0, tag("->"))(i);
 
@@ -210,6 +411,12 @@ fn parse_type(i: &str) -> IResult<&str, ParsedType> {
         _ => Ok((i, t))
     }
 }
+
+/// Parses a type annotation, recording the span it was written at
+fn parse_annotation(i: &str) -> IResult<&str, Annotation> {
+    map(with_span(parse_type), |(typ, span)| Annotation{typ, span})(i)
+}
+
 // Parses a comment, which is a valid element in our abstract syntax
 // Comments are transformed from FDSSL to match the equivalent GLSL produced
 fn parse_comment(input: &str) -> IResult<&str, Expr> {
@@ -217,7 +424,7 @@ fn parse_comment(input: &str) -> IResult<&str, Expr> {
     // then, take a comment all the way to the end, ignoring the opener
     let comment = preceded(preceded(space0, tag("//")), not_line_ending);
     // map what we found into a valid comment
-    map(comment, |s: &str| Expr::Comment(vec![s.into()]) )(input)
+    spanned(map(comment, |s: &str| ExprKind::Comment(vec![s.into()])))(input)
 }
 
 /// Parses a parenthetically nested expression
@@ -239,20 +446,42 @@ fn parse_vect(input: &str) -> IResult<&str, Expr> {
     // match a vect, but be sure that it is NOT terminated w/ an opening curly brace
     // if this is the case, it would clasify as a parameterized abstraction
     // We may have gotten here because the abstraction was invalid, so we should continue to reject
-    map(terminated(p,not(tuple((multispace0,char('{'))))), |v: Vec<Expr>| Expr::Vect(v))(input)
+    spanned(map(terminated(p,not(tuple((multispace0,char('{'))))), ExprKind::Vect))(input)
 }
 
 /// Parses a function application
 fn parse_app(input: &str) -> IResult<&str, Expr> {
-    let (input,fname) = preceded(space0, verify(name, |s: &str| !is_keyword(s)))(input)?;
+    let fname = preceded(space0, verify(name, |s: &str| !is_keyword(s)));
 
-    let p = delimited(
+    let args = delimited(
         preceded(space0, char('(')),
         separated_list0(preceded(space0, tag(",")), parse_expr),
         preceded(space0, char(')'))
     );
 
-    map(p, |v: Vec<Expr>| Expr::App{fname: fname.to_string(), arguments: v})(input)
+    spanned(map(pair(fname, args), |(f, v): (&str, Vec<Expr>)| ExprKind::App{fname: f.to_string(), arguments: v}))(input)
+}
+
+/// Parses a function application w/ no space between the name & its args, i.e. `cos(g)` but not `cos (g)`
+fn parse_tight_app(input: &str) -> IResult<&str, Expr> {
+    preceded(peek(tuple((space0, name, char('(')))), parse_app)(input)
+}
+
+/// Parses a vector or matrix constructor applied to args separated by spaces, as in the original FDSSL syntax
+/// i.e. `vec4 r cos(g) b 1.0` is `vec4(r, cos(g), b, 1.0)`
+/// Each arg is a literal, a name, a call or an expr in parens, so `vec2 (a + 1) b` takes 2 args
+fn parse_constructor(input: &str) -> IResult<&str, Expr> {
+    let ctor = preceded(space0, verify(name, |s: &str| VECTOR_TYPES.contains(&s)));
+    let arg = preceded(space1, alt((
+        parse_float,
+        parse_double,
+        parse_int,
+        parse_bool,
+        parse_access(parse_nested_expr),
+        parse_access(parse_tight_app),
+        parse_access(parse_ref),
+    )));
+    spanned(map(pair(ctor, many1(arg)), |(f, v): (&str, Vec<Expr>)| ExprKind::App{fname: f.to_string(), arguments: v}))(input)
 }
 
 /// Parses scoped expressions, as part of a function body or parametrized expression
@@ -272,66 +501,183 @@ fn parse_scoped_exprs(input: &str) -> IResult<&str, Vec<Expr>> {
 
 /// Parses a branch
 fn parse_branch(input: &str) -> IResult<&str, Expr> {
-    let (input,_)       = preceded(space0, tag("if"))(input)?;
-    let (input,cond)    = preceded(space0, parse_expr)(input)?;
-    let (input,b1)      = parse_scoped_exprs(input)?;
-    let (input,_)       = preceded(space0, tag("else"))(input)?;
-    let (input,b2)      = parse_scoped_exprs(input)?;
-    Ok((input, Expr::Branch{condition: Box::new(cond), b1: b1, b2: b2}))
+    spanned(|input| {
+        let (input,_)       = preceded(space0, tag("if"))(input)?;
+        let (input,cond)    = preceded(space0, parse_expr)(input)?;
+        // the original FDSSL syntax writes `if cond then { .. }`
+        let (input,_)       = opt(preceded(space0, terminated(tag("then"), not(alphanumeric1))))(input)?;
+        let (input,b1)      = parse_scoped_exprs(input)?;
+        let (input,_)       = preceded(space0, tag("else"))(input)?;
+        let (input,b2)      = parse_scoped_exprs(input)?;
+        Ok((input, ExprKind::Branch{condition: Box::new(cond), b1, b2}))
+    })(input)
 }
 
 /// Parses a parameterized abstraction
 /// This is used for function bodies
 /// The parameters themselves lack a type here, as they are bound by the context they are assigned within
 fn parse_abs(input: &str) -> IResult<&str, Expr> {
-    // parse delimited args
-    let (input,typed_params)  = delimited(
+    spanned(|input| {
+        // parse delimited args
+        let (input,typed_params)  = delimited(
+            preceded(space0, char('(')),
+            // ugly, but works as name : Type
+            separated_list0(preceded(space0, tag(",")), separated_pair(preceded(space0, name_str), preceded(space0, tag(":")), parse_annotation)),
+            preceded(space0, char(')'))
+        )(input)?;
+        let (input,exprs)   = parse_scoped_exprs(input)?;
+        Ok((input, ExprKind::Abs{params: typed_params, body: exprs}))
+    })(input)
+}
+
+/// Parses a uniform declaration, in the form `uniform Type name`
+/// Uniforms are set from outside of the pipeline, and are visible to every function & shader
+fn parse_uniform(input: &str) -> IResult<&str, Expr> {
+    let p = tuple((delimited(space0,tag("uniform"),space1), parse_annotation, preceded(space1, name_str)));
+    spanned(map(p, |(_,typ,name)| ExprKind::Uniform{name, typ}))(input)
+}
+
+/// Parses the stage keyword of a shader, `vert` or `frag`
+fn parse_stage(input: &str) -> IResult<&str, Stage> {
+    preceded(space0, alt((
+        map(tag("vert"), |_: &str| Stage::Vertex),
+        map(tag("frag"), |_: &str| Stage::Fragment),
+    )))(input)
+}
+
+/// Parses the named inputs or outputs of a shader, in the form `(T1 n1, T2 n2)`
+/// Unlike the params of an abstraction, the type comes before the name
+fn parse_shader_params(input: &str) -> IResult<&str, Vec<(String,Annotation)>> {
+    delimited(
         preceded(space0, char('(')),
-        // ugly, but works as name : Type
-        separated_list0(preceded(space0, tag(",")), separated_pair(preceded(space0, name_str), preceded(space0, tag(":")), parse_type)),
+        separated_list0(
+            preceded(space0, tag(",")),
+            map(pair(parse_annotation, preceded(space1, name_str)), |(t,n): (Annotation, String)| (n,t))
+        ),
         preceded(space0, char(')'))
-    )(input)?;
-    let (input,exprs)   = parse_scoped_exprs(input)?;
-    Ok((input, Expr::Abs{params: typed_params, body: exprs}))
+    )(input)
 }
 
+/// Parses the body of a shader, which is either a block of exprs or a composition of other shaders
+fn parse_shader_body(input: &str) -> IResult<&str, ShaderBody> {
+    alt((
+        map(parse_scoped_exprs, ShaderBody::Block),
+        map(parse_expr, |e: Expr| ShaderBody::Composed(Box::new(e))),
+    ))(input)
+}
+
+/// Parses a shader declaration
+/// Shaders are written as functions from named inputs to named outputs, w/ a body that writes those outputs
+/// e.g. `vert v : (Vec3 aVertPos) -> (Vec4 pos) = { ... }`
+/// A shader may also be composed from others of the same stage, e.g. `vert v : (Vec3 aVertPos) -> (Vec4 pos) = v2 . v1`
+fn parse_shader(input: &str) -> IResult<&str, Expr> {
+    spanned(|input| {
+        let (input,stage)   = parse_stage(input)?;
+        let (input,name)    = preceded(space1, name_str)(input)?;
+        let (input,_)       = preceded(space0, char(':'))(input)?;
+        let (input,inputs)  = parse_shader_params(input)?;
+        let (input,_)       = preceded(space0, tag("->"))(input)?;
+        let (input,outputs) = parse_shader_params(input)?;
+        let (input,_)       = preceded(space0, char('='))(input)?;
+        let (input,body)    = parse_shader_body(input)?;
+        Ok((input, ExprKind::Shader{stage, name, inputs, outputs, body}))
+    })(input)
+}
+
+/// Parses a unary expression, whose operator binds tighter than any binary operator
+/// i.e. `-a * b` is `(-a) * b`
 fn parse_unary_expr(input: &str) -> IResult<&str, Expr> {
-    map(
-        tuple((parse_unary_op,parse_expr)),
-        |(o,e): (UOp, Expr)| Expr::UnaryOp{operator: o, e: Box::new(e)}
-    )(input)
+    spanned(map(
+        tuple((parse_unary_op,parse_operand)),
+        |(o,e): (UOp, Expr)| ExprKind::UnaryOp{operator: o, e: Box::new(e)}
+    ))(input)
 }
 
 /// Parses a unary expression
 fn parse_unary_op(input: &str) -> IResult<&str, UOp> {
     preceded(space0, alt((
-        map(tag("-"), |s: &str| UOp::Negative),
-        map(tag("!"), |s: &str| UOp::Negate),
+        map(tag("-"), |_: &str| UOp::Negative),
+        map(tag("!"), |_: &str| UOp::Negate),
     )))(input)
 }
 
 /// Parses a branch
 fn parse_forloop(input: &str) -> IResult<&str, Expr> {
-    let (input,_)           = preceded(space0, tag("for"))(input)?;
-    // parse 3 comma separate exprs
-    let (input,(e1,_,e2,_,e3)) = delimited(
-        preceded(space0, char('(')),
-        tuple((
-            parse_expr,
-            preceded(space0,char(',')),
-            parse_expr,
-            preceded(space0,char(',')),
-            parse_expr
-        )),
-        preceded(space0, char(')'))
-    )(input)?;
-    let (input, body)      = parse_scoped_exprs(input)?;
-    Ok((input, Expr::For{
-        init: Box::new(e1),
-        cond: Box::new(e2),
-        post: Box::new(e3),
-        body: body
-    }))
+    spanned(|input| {
+        let (input,_)           = preceded(space0, tag("for"))(input)?;
+        // parse 3 comma separate exprs
+        let (input,(e1,_,e2,_,e3)) = delimited(
+            preceded(space0, char('(')),
+            tuple((
+                parse_expr,
+                preceded(space0,char(',')),
+                parse_expr,
+                preceded(space0,char(',')),
+                parse_expr
+            )),
+            preceded(space0, char(')'))
+        )(input)?;
+        let (input, body)      = parse_scoped_exprs(input)?;
+        Ok((input, ExprKind::For{
+            init: Box::new(e1),
+            cond: Box::new(e2),
+            post: Box::new(e3),
+            body
+        }))
+    })(input)
+}
+
+/// Name of the index of a `for n do { .. }` loop, which is not visible to the program itself
+const LOOP_INDEX: &str = "fdssl_i";
+
+/// Parses a loop in the original FDSSL syntax, `for n do { body }`, which runs its body 'n' times
+/// This is sugar for `for (mut fdssl_i : int = 0, fdssl_i < n, fdssl_i += 1) { body }`
+fn parse_for_do(input: &str) -> IResult<&str, Expr> {
+    spanned(|input| {
+        let (input,_)       = preceded(space0, terminated(tag("for"), not(alphanumeric1)))(input)?;
+        let (input,n)       = parse_expr(input)?;
+        let (input,_)       = preceded(space0, terminated(tag("do"), not(alphanumeric1)))(input)?;
+        let (input,body)    = parse_scoped_exprs(input)?;
+        // the parts of the loop header are located at the count they were made from
+        let span = n.span;
+        let at = |kind: ExprKind| Expr{kind, span};
+        let index = || at(ExprKind::Ref(LOOP_INDEX.to_string()));
+        Ok((input, ExprKind::For{
+            init: Box::new(at(ExprKind::DefMut{
+                name: LOOP_INDEX.to_string(),
+                typ: Annotation{typ: ParsedType::BaseType("int".to_string()), span},
+                value: Box::new(at(ExprKind::I(0)))
+            })),
+            cond: Box::new(at(ExprKind::BinOp{operator: BOp::Lt, e1: Box::new(index()), e2: Box::new(n)})),
+            post: Box::new(at(ExprKind::Update{
+                target: LOOP_INDEX.to_string(),
+                swizzle: None,
+                value: Box::new(at(ExprKind::BinOp{operator: BOp::Add, e1: Box::new(index()), e2: Box::new(at(ExprKind::I(1)))}))
+            })),
+            body
+        }))
+    })(input)
+}
+
+/// Parses a while loop, in the form `while cond { body }`
+fn parse_while(input: &str) -> IResult<&str, Expr> {
+    spanned(|input| {
+        let (input,_)       = preceded(space0, terminated(tag("while"), not(alphanumeric1)))(input)?;
+        let (input,cond)    = preceded(space0, parse_expr)(input)?;
+        let (input,body)    = parse_scoped_exprs(input)?;
+        Ok((input, ExprKind::While{cond: Box::new(cond), body}))
+    })(input)
+}
+
+/// Parses a do-while loop, in the form `do { body } while cond`
+fn parse_do_while(input: &str) -> IResult<&str, Expr> {
+    spanned(|input| {
+        let (input,_)       = preceded(space0, terminated(tag("do"), not(alphanumeric1)))(input)?;
+        let (input,body)    = parse_scoped_exprs(input)?;
+        let (input,_)       = preceded(space0, tag("while"))(input)?;
+        let (input,cond)    = preceded(space0, parse_expr)(input)?;
+        Ok((input, ExprKind::DoWhile{body, cond: Box::new(cond)}))
+    })(input)
 }
 
 /// Parses a vector w/ named indices as a Boxed vector of Exprs
@@ -344,7 +690,7 @@ fn parse_named_vect(input: &str) -> IResult<&str, Expr> {
     );
     // accept a named vect that is NOT terminated by an opening curly brace
     // If so, it was a failed abstraction that we should continue to reject
-    map(terminated(p,not(tuple((multispace0,char('{'))))), |s: Vec<(String,Expr)>| Expr::NamedVect(s))(input)
+    spanned(map(terminated(p,not(tuple((multispace0,char('{'))))), ExprKind::NamedVect))(input)
 }
 
 
@@ -373,10 +719,27 @@ fn parse_binop(i: &str) -> IResult<&str, BOp> {
 }
 
 
-/// Parses a standalone expression
-/// Represents all possible expansions for parsing exprs
-fn parse_expr(input: &str) -> IResult<&str, Expr> {
-    let (i,e) = alt((
+/// Returns the precedence of a binary operator, where higher binds tighter
+/// These follow GLSL, w/ composition binding tightest as it does in Haskell
+fn precedence(op: &BOp) -> u8 {
+    match op {
+        BOp::Or                                 => 1,
+        BOp::And                                => 2,
+        BOp::BitOr                              => 3,
+        BOp::BitXor                             => 4,
+        BOp::BitAnd                             => 5,
+        BOp::Eq | BOp::Neq                      => 6,
+        BOp::Lt | BOp::Gt | BOp::Lte | BOp::Gte => 7,
+        BOp::Add | BOp::Sub                     => 8,
+        BOp::Mul | BOp::Div | BOp::Mod          => 9,
+        BOp::Compose                            => 10,
+    }
+}
+
+/// Parses the operand of a binary or unary operator
+/// Represents all possible expansions for parsing exprs, other than binary exprs
+fn parse_operand(input: &str) -> IResult<&str, Expr> {
+    context("an expression", alt((
         parse_float,
         parse_double,
         parse_int,
@@ -389,39 +752,98 @@ fn parse_expr(input: &str) -> IResult<&str, Expr> {
         // Nested Exprs must be checked after Abs, or abstractions will
         // be mangled before they can be checked
         parse_abs,
-        parse_named_vect,
-        parse_nested_expr,
-        parse_vect,
+        parse_access(parse_named_vect),
+        parse_access(parse_nested_expr),
+        parse_access(parse_vect),
 
         parse_return,
-        parse_app,
+        parse_discard,
+        // constructors w/ args separated by spaces must be checked before regular applications
+        parse_constructor,
+        parse_access(parse_app),
         parse_branch,
-        parse_forloop,
+        // loops are grouped, as nom only takes so many alternatives at once
+        alt((parse_forloop, parse_for_do, parse_while, parse_do_while)),
 
         parse_def,
         parse_mutdef,
-        parse_named_access,
-        parse_index_access,
-        parse_ref,
-    ))(input)?;
-
-    // Check to handle a potential binary expr afterwards
-    let p_bin = preceded(space0, parse_binop)(i);
-
-    if p_bin.is_ok() {
-        // decompose as a bin op
-        let (i,op) = p_bin?;
-        // parse another expr to append
-        let (i,e2) = parse_expr(i)?;
-
-        Ok((i,Expr::BinOp{operator: op, e1: Box::new(e), e2: Box::new(e2)}))
+        parse_set,
+        parse_assign,
+        parse_access(parse_ref),
+    )))(input)
+}
 
-    } else {
-        // no bin op, return as is
-        Ok((i,e))
+/// Parses a chain of binary exprs by precedence climbing
+/// Only operators that bind at least as tight as `min` are consumed, and every operator is left associative,
+/// i.e. `a - b - c` is `(a - b) - c`, and `a + b * c` is `a + (b * c)`
+fn parse_binary_expr(input: &str, min: u8) -> IResult<&str, Expr> {
+    let (mut input, mut e) = parse_operand(input)?;
+    loop {
+        match parse_binop(input) {
+            Ok((i, op)) if precedence(&op) >= min => {
+                // the right operand may only contain operators that bind tighter, keeping this one on the left
+                let (i, e2) = parse_binary_expr(i, precedence(&op) + 1)?;
+                let span = e.span.to(e2.span);
+                e = Expr{kind: ExprKind::BinOp{operator: op, e1: Box::new(e), e2: Box::new(e2)}, span};
+                input = i;
+            },
+            _ => return Ok((input, e))
+        }
     }
 }
 
+/// Parses a standalone expression
+/// Represents all possible expansions for parsing exprs
+fn parse_expr(input: &str) -> IResult<&str, Expr> {
+    parse_binary_expr(input, 0)
+}
+
+/// Parses a program declaration, in the form `name : Prog = mkProg vert frag`
+/// This links a vertex shader & a fragment shader into a single GLSL program
+fn parse_prog(input: &str) -> IResult<&str, Expr> {
+    spanned(|input| {
+        let (input,name)    = preceded(space0, verify(name_str, |s: &str| !is_keyword(s)))(input)?;
+        let (input,_)       = tuple((space0, char(':'), space0, tag("Prog"), space0, char('=')))(input)?;
+        let (input,_)       = preceded(space0, tag("mkProg"))(input)?;
+        let (input,vert)    = preceded(space1, name_str)(input)?;
+        let (input,frag)    = preceded(space1, name_str)(input)?;
+        Ok((input, ExprKind::Prog{name, vert, frag}))
+    })(input)
+}
+
+/// Parses a function declaration in the original FDSSL syntax, `name : (T1 p1, T2 p2) -> R = { body }`
+/// This is sugar for `let name : (T1, T2) -> R = (p1 : T1, p2 : T2) { body }`,
+/// where a function w/out params is declared w/ just its return type, as it is w/ `let`
+fn parse_func(input: &str) -> IResult<&str, Expr> {
+    spanned(|input| {
+        let (input,name)    = preceded(space0, verify(name_str, |s: &str| !is_keyword(s)))(input)?;
+        let (input,_)       = preceded(space0, char(':'))(input)?;
+        let (input,((params,_,ret),span)) = with_span(tuple((parse_shader_params, preceded(space0, tag("->")), parse_annotation)))(input)?;
+        let (input,_)       = preceded(space0, char('='))(input)?;
+        let (input,(body,body_span)) = with_span(parse_scoped_exprs)(input)?;
+        let mut args : Vec<ParsedType> = params.iter().map(|(_,t)| t.typ.clone()).collect();
+        let typ = match args.len() {
+            0 => ret.typ,
+            1 => ParsedType::Function(Box::new(args.remove(0)), Box::new(ret.typ)),
+            _ => ParsedType::Function(Box::new(ParsedType::Tuple(args)), Box::new(ret.typ))
+        };
+        let value = Expr{kind: ExprKind::Abs{params, body}, span: body_span};
+        Ok((input, ExprKind::Def{name, typ: Annotation{typ, span}, value: Box::new(value)}))
+    })(input)
+}
+
+/// Parses a top level declaration of a program
+/// These are regular exprs, plus the declarations that may only appear at the top level (uniforms, shaders & programs)
+fn parse_top_level(input: &str) -> IResult<&str, Expr> {
+    context("a declaration or expression", alt((
+        parse_uniform,
+        parse_shader,
+        parse_prog,
+        parse_func,
+        parse_expr,
+    )))(input)
+}
+
 /// Parses an FDSSL program, returning a vector of exprs
 pub fn program(i: &str) -> IResult<&str, Vec<Expr>> {
     // parse 1 or more exprs, then new line, until end of program
@@ -437,14 +859,107 @@ pub fn program(i: &str) -> IResult<&str, Vec<Expr>> {
     //     tuple((space0, line_ending))
     // ))))(i)
 
-    terminated(
+    delimited(
+        // skip any empty lines before the first expression
+        multispace0,
         // parse 1 or more expressions, terminated by 1 or more lines...
-        many1(terminated(parse_expr, many1(tuple((space0, line_ending))))),
+        many1(terminated(parse_top_level, many1(tuple((space0, line_ending))))),
         // followed by any number of empty lines afterwards, if any
         tuple((multispace0, eof))
     )(i)
 }
 
+/// A syntax error, w/ what was expected & what was found instead at its span
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxError {
+    pub expected: String,
+    pub found: String,
+    pub span: Span,
+}
+
+impl SyntaxError {
+    /// Builds a syntax error from a failed parse, spanning the token that parsing stopped at
+    fn new(failure: ParseFailure<&str>) -> SyntaxError {
+        let input = failure.input.trim_start_matches([' ', '\t']);
+        let (found, len) = next_token(input);
+        let expected = if failure.expected.is_empty() {
+            "valid syntax".to_string()
+        } else {
+            failure.expected.join(" or ")
+        };
+        SyntaxError{ expected, found, span: Span::new(input.len(), len) }
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+/// Describes the token at the start of some input, w/ its length
+/// Tokens are whole names or numbers, or otherwise a single character
+fn next_token(input: &str) -> (String, usize) {
+    let word = input.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(input.len());
+    match input.chars().next() {
+        None                    => ("end of file".to_string(), 0),
+        Some('\n') | Some('\r') => ("end of line".to_string(), 0),
+        Some(_) if word > 0     => (format!("'{}'", &input[..word]), word),
+        Some(c)                 => (format!("'{}'", c), c.len_utf8()),
+    }
+}
+
+/// Returns the source of a declaration that failed to parse, up to where parsing can resume
+/// This is the first line break outside of any brackets, i.e. after the closing brace of a block,
+/// or before a line that is not indented, so an unclosed bracket doesn't swallow the rest of the program
+fn resync(input: &str) -> &str {
+    let mut depth = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            '\n' => {
+                let unindented = input[i + 1..].starts_with(|c: char| !c.is_whitespace() && c != '}');
+                if depth <= 0 || unindented {
+                    return &input[..i + 1];
+                }
+            },
+            _ => ()
+        }
+    }
+    input
+}
+
+/// Parses an FDSSL program, recovering from syntax errors so that all of them are reported
+///
+/// A declaration that fails to parse is skipped up to where parsing can resume (see `resync`),
+/// & replaced by an error node that spans the skipped source.
+/// Unlike `program`, the AST is returned even w/ errors, so later stages can still check the rest of it.
+pub fn parse_program(src: &str) -> (Vec<Expr>, Vec<SyntaxError>) {
+    let mut exprs = vec![];
+    let mut errors = vec![];
+    let mut item = terminated(parse_top_level, context("end of line", preceded(space0, alt((line_ending, eof)))));
+
+    let mut input = src.trim_start();
+    while !input.is_empty() {
+        match item(input) {
+            Ok((rest, e)) => {
+                exprs.push(e);
+                input = rest;
+            },
+            Err(nom::Err::Error(f) | nom::Err::Failure(f)) => {
+                errors.push(SyntaxError::new(f));
+                let skipped = resync(input);
+                exprs.push(Expr{kind: ExprKind::Error, span: Span::new(input.len(), skipped.trim_end().len())});
+                input = &input[skipped.len()..];
+            },
+            Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers never ask for more input")
+        }
+        input = input.trim_start();
+    }
+    (exprs, errors)
+}
+
 /****************
 TESTS
 ****************/
@@ -452,57 +967,56 @@ TESTS
 /// Tests parsing various ints
 #[test]
 fn test_parse_ints() {
-    assert_eq!(parse_int("32"), Ok(("", Expr::I(32))), "Failed to parse 32");
-    assert_eq!(parse_int("0"), Ok(("", Expr::I(0))), "Failed to parse 0");
+    assert_eq!(parse_int("32"), Ok(("", ExprKind::I(32).into())), "Failed to parse 32");
+    assert_eq!(parse_int("0"), Ok(("", ExprKind::I(0).into())), "Failed to parse 0");
     assert_eq!(
         parse_int("  1"),
-        Ok(("", Expr::I(1))),
+        Ok(("", ExprKind::I(1).into())),
         "Failed to parse 1 w/ a space"
     );
     assert_eq!(
         parse_int(" -51  "),
-        Ok(("  ", Expr::I(-51))),
+        Ok(("  ", ExprKind::I(-51).into())),
         "Failed to parse a negative int w/ spaces"
     );
 }
 
 /// Used for testing, returns whether a parse failed
+#[cfg(test)]
 fn verify_parse(p: IResult<&str, Expr>) -> bool {
-    match p {
-        Ok(_)   => true, // valid parse
-        Err(_)  => false // errored out
-    }
+    // valid parse or errored out
+    p.is_ok()
 }
 
 /// Tests parsing various bools
 #[test]
 fn test_parse_bools() {
-    assert_eq!(parse_bool("true"), Ok(("", Expr::B(true))), "Failed to parse 'true'");
-    assert_eq!(parse_bool("false"), Ok(("", Expr::B(false))), "Failed to parse 'false'");
-    assert_eq!(parse_bool("  true  "), Ok(("  ", Expr::B(true))), "Failed to parse 'true' w/ spaces");
-    assert_eq!(parse_bool(" false "), Ok((" ", Expr::B(false))), "Failed to parse 'false' w/ spaces");
+    assert_eq!(parse_bool("true"), Ok(("", ExprKind::B(true).into())), "Failed to parse 'true'");
+    assert_eq!(parse_bool("false"), Ok(("", ExprKind::B(false).into())), "Failed to parse 'false'");
+    assert_eq!(parse_bool("  true  "), Ok(("  ", ExprKind::B(true).into())), "Failed to parse 'true' w/ spaces");
+    assert_eq!(parse_bool(" false "), Ok((" ", ExprKind::B(false).into())), "Failed to parse 'false' w/ spaces");
 
-    assert_eq!(verify_parse(parse_bool("trues")), false, "Failed to reject bad 'true' value");
-    assert_eq!(verify_parse(parse_bool("False")), false, "Failed to reject bad 'false' value");
-    assert_eq!(verify_parse(parse_bool("astrues")), false, "Failed to reject bad 'true' value w/ leading text");
+    assert!(!verify_parse(parse_bool("trues")), "Failed to reject bad 'true' value");
+    assert!(!verify_parse(parse_bool("False")), "Failed to reject bad 'false' value");
+    assert!(!verify_parse(parse_bool("astrues")), "Failed to reject bad 'true' value w/ leading text");
 }
 
 /// Tests parsing various floats
 #[test]
 fn test_parse_floats() {
-    assert_eq!(parse_float("12.23f"), Ok(("", Expr::F(12.23))), "Failed to parse 12.23");
-    assert_eq!(parse_float("  0.98f"), Ok(("", Expr::F(0.98))), "Failed to parse 0.98");
-    assert_eq!(parse_float(" -5.2f  "), Ok(("  ", Expr::F(-5.2))), "Failed to parse -5.2");
-    assert_eq!(parse_float(" 12").is_ok(), false, "Float parser failed. Parsed an integer, 12");
+    assert_eq!(parse_float("12.23f"), Ok(("", ExprKind::F(12.23).into())), "Failed to parse 12.23");
+    assert_eq!(parse_float("  0.98f"), Ok(("", ExprKind::F(0.98).into())), "Failed to parse 0.98");
+    assert_eq!(parse_float(" -5.2f  "), Ok(("  ", ExprKind::F(-5.2).into())), "Failed to parse -5.2");
+    assert!(parse_float(" 12").is_err(), "Float parser failed. Parsed an integer, 12");
 }
 
 /// Tests parsing various doubles
 #[test]
 fn test_parse_doubles() {
-    assert_eq!(parse_double("12.23"), Ok(("", Expr::D(12.23))), "Failed to parse 12.23");
-    assert_eq!(parse_double("  0.98"), Ok(("", Expr::D(0.98))), "Failed to parse 0.98");
-    assert_eq!(parse_double(" -5.2  "), Ok(("  ", Expr::D(-5.2))), "Failed to parse -5.2");
-    assert_eq!(parse_double(" 12").is_ok(), false, "Double parser failed. Parsed an integer, 12");
+    assert_eq!(parse_double("12.23"), Ok(("", ExprKind::D(12.23).into())), "Failed to parse 12.23");
+    assert_eq!(parse_double("  0.98"), Ok(("", ExprKind::D(0.98).into())), "Failed to parse 0.98");
+    assert_eq!(parse_double(" -5.2  "), Ok(("  ", ExprKind::D(-5.2).into())), "Failed to parse -5.2");
+    assert!(parse_double(" 12").is_err(), "Double parser failed. Parsed an integer, 12");
     // TODO dropping this test since we implicitly check for floats before doubles, this case won't come up (@montymxb)
     //assert!(!parse_double("1.5f").is_ok(), "Double parser did not stop short of parsing a float.");
 }
@@ -511,49 +1025,49 @@ fn test_parse_doubles() {
 #[test]
 fn test_parse_refs() {
     // parse a-z ref
-    assert_eq!(parse_expr("a"), Ok(("", Expr::Ref("a".to_string()))), "Failed to parse lowercased ref");
+    assert_eq!(parse_expr("a"), Ok(("", ExprKind::Ref("a".to_string()).into())), "Failed to parse lowercased ref");
     // parse upper case ref
-    assert_eq!(parse_expr("XYZ"), Ok(("", Expr::Ref("XYZ".to_string()))), "Failed to parse uppercased ref");
+    assert_eq!(parse_expr("XYZ"), Ok(("", ExprKind::Ref("XYZ".to_string()).into())), "Failed to parse uppercased ref");
     // parse mixed ref
-    assert_eq!(parse_expr("aBc"), Ok(("", Expr::Ref("aBc".to_string()))), "Failed to parse mixed case ref");
+    assert_eq!(parse_expr("aBc"), Ok(("", ExprKind::Ref("aBc".to_string()).into())), "Failed to parse mixed case ref");
     // parse ref w/ numbers
-    assert_eq!(parse_expr("c7171"), Ok(("", Expr::Ref("c7171".to_string()))), "Failed to parse mixed ref w/ numbers");
+    assert_eq!(parse_expr("c7171"), Ok(("", ExprKind::Ref("c7171".to_string()).into())), "Failed to parse mixed ref w/ numbers");
     // parse ref w/ underscores
-    assert_eq!(parse_expr("_c71_71"), Ok(("", Expr::Ref("_c71_71".to_string()))), "Failed to parse mixed ref w/ underscores");
+    assert_eq!(parse_expr("_c71_71"), Ok(("", ExprKind::Ref("_c71_71".to_string()).into())), "Failed to parse mixed ref w/ underscores");
     // verify you cannot parse with leading numbers
-    assert!(!parse_expr("1_c71_71").is_ok(), "Failed to reject ref w/ leading digit");
-    //assert_eq!(parse_expr("1_c71_71"), Ok(("", Expr::Ref("_c71_71".to_string()))), "Failed to reject ref w/ leading digit");
+    assert!(parse_expr("1_c71_71").is_err(), "Failed to reject ref w/ leading digit");
+    //assert_eq!(parse_expr("1_c71_71"), Ok(("", ExprKind::Ref("_c71_71".to_string()).into())), "Failed to reject ref w/ leading digit");
     // verify you cannot parse w/ leading negative
-    assert_eq!(parse_expr("-_c71_71"), Ok(("", Expr::UnaryOp{operator: UOp::Negative, e: Box::new(Expr::Ref("_c71_71".to_string()))})), "Failed to reject ref w/ leading dash");
+    assert_eq!(parse_expr("-_c71_71"), Ok(("", ExprKind::UnaryOp{operator: UOp::Negative, e: Box::new(ExprKind::Ref("_c71_71".to_string()).into())}.into())), "Failed to reject ref w/ leading dash");
     //assert_eq!(parse_expr("-_c71_71"), Ok(("", i(1))), "Failed to reject ref w/ leading dash");
 
     // verify you can't parse keywords as refs
-    assert!(!parse_expr("if").is_ok(), "Failed to reject 'if' as reserved keyword, not a ref");
-    assert!(!parse_expr("for").is_ok(), "Failed to reject 'for' as reserved keyword, not a ref");
+    assert!(parse_expr("if").is_err(), "Failed to reject 'if' as reserved keyword, not a ref");
+    assert!(parse_expr("for").is_err(), "Failed to reject 'for' as reserved keyword, not a ref");
 }
 
 /// Tests parsing a comment
 #[test]
 fn test_parse_comment() {
-    assert_eq!(parse_expr("//"), Ok(("", Expr::Comment(vec!["".into()]))), "Failed to parse empty comment");
-    assert_eq!(parse_expr("// this is a comment"), Ok(("", Expr::Comment(vec![" this is a comment".into()]))), "Failed to parse regular comment");
-    assert_eq!(parse_expr(" // ok"), Ok(("", Expr::Comment(vec![" ok".into()]))), "Failed to parse comment w/ leading space");
+    assert_eq!(parse_expr("//"), Ok(("", ExprKind::Comment(vec!["".into()]).into())), "Failed to parse empty comment");
+    assert_eq!(parse_expr("// this is a comment"), Ok(("", ExprKind::Comment(vec![" this is a comment".into()]).into())), "Failed to parse regular comment");
+    assert_eq!(parse_expr(" // ok"), Ok(("", ExprKind::Comment(vec![" ok".into()]).into())), "Failed to parse comment w/ leading space");
 
     // just verify we reject non-comments
-    assert!(!parse_comment("f // ok").is_ok(), "Failed to reject a non-comment");
+    assert!(parse_comment("f // ok").is_err(), "Failed to reject a non-comment");
 
-    assert_eq!(parse_expr(" // ok\nand more"), Ok(("\nand more", Expr::Comment(vec![" ok".into()]))), "Didn't stop parsing comment at \n");
+    assert_eq!(parse_expr(" // ok\nand more"), Ok(("\nand more", ExprKind::Comment(vec![" ok".into()]).into())), "Didn't stop parsing comment at \n");
 }
 
 
 /// Tests parsing a nested expr w/ parens
 #[test]
 fn test_parse_nested_expr() {
-    assert_eq!(parse_expr("(1)"), Ok(("", Expr::I(1))), "Failed to parse a nested int");
-    assert_eq!(parse_expr(" ( true ) "), Ok((" ", Expr::B(true))), "Failed to parse a nested boolean");
-    assert_eq!(parse_expr(" (( ((-5) ))) "), Ok((" ", Expr::I(-5))), "Failed to parse a deeply nested int");
-    assert!(!parse_expr(" (( ((-5) )) ").is_ok(), "Failed to reject a badly nested expr!");
-    assert!(!parse_expr("()").is_ok(), "Failed to reject empty parens w/ no expr");
+    assert_eq!(parse_expr("(1)"), Ok(("", ExprKind::I(1).into())), "Failed to parse a nested int");
+    assert_eq!(parse_expr(" ( true ) "), Ok((" ", ExprKind::B(true).into())), "Failed to parse a nested boolean");
+    assert_eq!(parse_expr(" (( ((-5) ))) "), Ok((" ", ExprKind::I(-5).into())), "Failed to parse a deeply nested int");
+    assert!(parse_expr(" (( ((-5) )) ").is_err(), "Failed to reject a badly nested expr!");
+    assert!(parse_expr("()").is_err(), "Failed to reject empty parens w/ no expr");
 }
 
 
@@ -561,74 +1075,86 @@ fn test_parse_nested_expr() {
 #[test]
 fn test_parse_expr() {
     // parse int
-    assert_eq!(parse_expr("5"), Ok(("", Expr::I(5))), "Failed to parse int as Expr");
+    assert_eq!(parse_expr("5"), Ok(("", ExprKind::I(5).into())), "Failed to parse int as Expr");
     // parse bool
-    assert_eq!(parse_expr("false"), Ok(("", Expr::B(false))), "Failed to parse bool as Expr");
+    assert_eq!(parse_expr("false"), Ok(("", ExprKind::B(false).into())), "Failed to parse bool as Expr");
     // parse comment
-    assert_eq!(parse_expr("//test"), Ok(("", Expr::Comment(vec!["test".into()]))), "Failed to parse comment as Expr");
+    assert_eq!(parse_expr("//test"), Ok(("", ExprKind::Comment(vec!["test".into()]).into())), "Failed to parse comment as Expr");
     // parse nested expr
-    assert_eq!(parse_expr("((32))"), Ok(("", Expr::I(32))), "Failed to parse nested expr as Expr");
+    assert_eq!(parse_expr("((32))"), Ok(("", ExprKind::I(32).into())), "Failed to parse nested expr as Expr");
     // parse vect
-    assert_eq!(parse_expr("(1,2,3)"), Ok(("", Expr::Vect(vec![Expr::I(1),Expr::I(2),Expr::I(3)]))), "Failed to parse a simple vect of ints as an expr");
+    assert_eq!(parse_expr("(1,2,3)"), Ok(("", ExprKind::Vect(vec![ExprKind::I(1).into(),ExprKind::I(2).into(),ExprKind::I(3).into()]).into())), "Failed to parse a simple vect of ints as an expr");
 }
 
 /// Tests parsing vectors with indices (no names)
 #[test]
 fn test_parse_vect() {
-    assert_eq!(parse_expr("(1,2,3)"), Ok(("", Expr::Vect(vec![Expr::I(1),Expr::I(2),Expr::I(3)]))), "Failed to parse a simple vect of ints");
-    assert_eq!(parse_expr("(1,true)"), Ok(("", Expr::Vect(vec![Expr::I(1),Expr::B(true)]))), "Failed to parse a mixed vect");
-    assert_eq!(parse_expr("(3,)").is_ok(), false, "Recognized an invalid tuple statement");
-    assert_eq!(parse_expr("(,3)").is_ok(), false, "Recognized another invalid tuple statement");
+    assert_eq!(parse_expr("(1,2,3)"), Ok(("", ExprKind::Vect(vec![ExprKind::I(1).into(),ExprKind::I(2).into(),ExprKind::I(3).into()]).into())), "Failed to parse a simple vect of ints");
+    assert_eq!(parse_expr("(1,true)"), Ok(("", ExprKind::Vect(vec![ExprKind::I(1).into(),ExprKind::B(true).into()]).into())), "Failed to parse a mixed vect");
+    assert!(parse_expr("(3,)").is_err(), "Recognized an invalid tuple statement");
+    assert!(parse_expr("(,3)").is_err(), "Recognized another invalid tuple statement");
 }
 
 /// Tests parsing vectors with names (akin to structs)
 #[test]
 fn test_parse_named_vect() {
     // test parsing a named one of 1 item
-    assert_eq!(parse_expr("(n:2)"), Ok(("", Expr::NamedVect(vec![("n".to_string(),Expr::I(2))]))), "Failed to parse a tiny named vect");
+    assert_eq!(parse_expr("(n:2)"), Ok(("", ExprKind::NamedVect(vec![("n".to_string(),ExprKind::I(2).into())]).into())), "Failed to parse a tiny named vect");
     // test parsing a named one of 2 items
-    assert_eq!(parse_expr("(x:12, y:false)"), Ok(("", Expr::NamedVect(vec![("x".to_string(),Expr::I(12)), ("y".to_string(), Expr::B(false))]))), "Failed to parse a named vect w/ 2 items");
+    assert_eq!(parse_expr("(x:12, y:false)"), Ok(("", ExprKind::NamedVect(vec![("x".to_string(),ExprKind::I(12).into()), ("y".to_string(), ExprKind::B(false).into())]).into())), "Failed to parse a named vect w/ 2 items");
     // test parsing w/ one bad name
-    assert_eq!(parse_expr("(x:1, -a:19)").is_ok(), false, "Failed to reject bad name for named vect");
+    assert!(parse_expr("(x:1, -a:19)").is_err(), "Failed to reject bad name for named vect");
     // test parsing w/ one missing a name
-    assert_eq!(parse_expr("(x:1, a:19, 2)").is_ok(), false, "Failed to reject w/ missing name for named vect");
+    assert!(parse_expr("(x:1, a:19, 2)").is_err(), "Failed to reject w/ missing name for named vect");
     // test parsing w/ extra comma
-    assert_eq!(parse_expr("(a:1, b:19, c:2,)").is_ok(), false, "Failed to reject w/ extra comma at end of named vect");
+    assert!(parse_expr("(a:1, b:19, c:2,)").is_err(), "Failed to reject w/ extra comma at end of named vect");
     // test parsing where name & value are same (ambiguous case that is resolved in type checker w/ an error)
-    assert_eq!(parse_expr("(a:a, b:2)"), Ok(("", Expr::NamedVect(vec![("a".to_string(),Expr::Ref("a".to_string())), ("b".to_string(), Expr::I(2))]))), "Failed to accept parsing ambiguous name case");
+    assert_eq!(parse_expr("(a:a, b:2)"), Ok(("", ExprKind::NamedVect(vec![("a".to_string(),ExprKind::Ref("a".to_string()).into()), ("b".to_string(), ExprKind::I(2).into())]).into())), "Failed to accept parsing ambiguous name case");
 }
 
 /// Tests parsing a return statement
 #[test]
 fn test_parse_return() {
-    assert_eq!(parse_expr("return true"), Ok(("", Expr::Return(Box::new(Expr::B(true))))), "Failed to parse 'return true");
-    assert_eq!(parse_expr("return 1"), Ok(("", Expr::Return(Box::new(Expr::I(1))))), "Failed to parse 'return 1");
-    assert_eq!(parse_expr("return (1)"), Ok(("", Expr::Return(Box::new(Expr::I(1))))), "Failed to parse 'return true");
+    assert_eq!(parse_expr("return true"), Ok(("", ExprKind::Return(Box::new(ExprKind::B(true).into())).into())), "Failed to parse 'return true");
+    assert_eq!(parse_expr("return 1"), Ok(("", ExprKind::Return(Box::new(ExprKind::I(1).into())).into())), "Failed to parse 'return 1");
+    assert_eq!(parse_expr("return (1)"), Ok(("", ExprKind::Return(Box::new(ExprKind::I(1).into())).into())), "Failed to parse 'return true");
     // constitues a ref instead
     //assert_eq!(parse_expr("returnsdf").is_ok(), false, "Failed to reject 'returnsdf'");
 }
 
+/// Tests parsing a discard statement
+#[test]
+fn test_parse_discard() {
+    assert_eq!(parse_expr("discard"), Ok(("", ExprKind::Discard.into())), "Failed to parse 'discard'");
+    assert_eq!(parse_expr(" discard\n"), Ok(("\n", ExprKind::Discard.into())), "Failed to parse 'discard' w/ spaces");
+    // a name that only starts w/ the keyword is a ref
+    assert_eq!(parse_expr("discarded"), Ok(("", ExprKind::Ref("discarded".to_string()).into())), "Failed to parse 'discarded' as a ref");
+}
+
 /// Helper function to build a simple parsed type for testing
+#[cfg(test)]
 fn ptype(name: &str) -> ParsedType {
     ParsedType::BaseType(name.to_string())
 }
 
 /// Used during testing to build an immutable def
+#[cfg(test)]
 fn def(name: &str, typ: ParsedType, val: Expr) -> Expr {
-    Expr::Def{
+    ExprKind::Def{
         name: name.to_string(),
-        typ: typ,
+        typ: typ.into(),
         value: Box::new(val)
-    }
+    }.into()
 }
 
 /// Used during testing to build a mutable def
+#[cfg(test)]
 fn mdef(name: &str, typ: ParsedType, val: Expr) -> Expr {
-    Expr::DefMut{
+    ExprKind::DefMut{
         name: name.to_string(),
-        typ: typ,
+        typ: typ.into(),
         value: Box::new(val)
-    }
+    }.into()
 }
 
 //
@@ -645,7 +1171,7 @@ fn test_parse_def() {
         Ok(("", def("a", ptype("int"), i(2)))),
         "Failed to parse simple def"
     );
-
+    assert_eq!(parse_expr("let Int a = 2"), Ok(("", def("a", ptype("int"), i(2)))), "Failed to parse def w/ the type first");
 }
 
 #[test]
@@ -720,23 +1246,31 @@ fn test_parse_defmut() {
         Ok(("", mdef("a", ptype("int"), i(2)))),
         "Failed to parse simple def"
     );
+    assert_eq!(
+        parse_expr("mut Float s = sin (x)"),
+        Ok(("", mdef("s", ptype("float"), app("sin", vec![ExprKind::Ref("x".to_string()).into()])))),
+        "Failed to parse mutable def w/ the type first"
+    );
+    assert!(parse_expr("mut Float = 2").is_err(), "Failed to reject mutable def w/out a name");
 }
 
 // Used to generate a mock expr object for testing
+#[cfg(test)]
 fn app(name: &str, args: Vec<Expr>) -> Expr {
-    Expr::App{fname: name.to_string(), arguments: args}
+    ExprKind::App{fname: name.to_string(), arguments: args}.into()
 }
 
 // Used to quickly wrap an integer for use
+#[cfg(test)]
 fn i(v: i32) -> Expr {
-    Expr::I(v)
+    ExprKind::I(v).into()
 }
 
 /// Tests parsing func application
 #[test]
 fn test_parse_app() {
     assert_eq!(parse_expr("a()") , Ok(("", app("a", vec![]))), "Failed to parse basic app");
-    assert_eq!(parse_expr("another_name(1,false,2.0,3.3f)") , Ok(("", app("another_name", vec![i(1),Expr::B(false),Expr::D(2.0),Expr::F(3.3)]))), "Failed to parse more complex app");
+    assert_eq!(parse_expr("another_name(1,false,2.0,3.3f)") , Ok(("", app("another_name", vec![i(1),ExprKind::B(false).into(),ExprKind::D(2.0).into(),ExprKind::F(3.3).into()]))), "Failed to parse more complex app");
     assert_eq!(parse_expr("_validFunc(32,f(1))") , Ok(("", app("_validFunc", vec![i(32),app("f", vec![i(1)])]))), "Failed to parse nested app");
 }
 
@@ -766,60 +1300,159 @@ fn test_parse_binop(){
 fn test_parse_binexp() {
     assert_eq!(
         parse_expr("1 + 2"),
-        Ok(("", Expr::BinOp{
+        Ok(("", ExprKind::BinOp{
             operator: BOp::Add,
-            e1: Box::new(Expr::I(1)),
-            e2: Box::new(Expr::I(2))
-        })),
+            e1: Box::new(ExprKind::I(1).into()),
+            e2: Box::new(ExprKind::I(2).into())
+        }.into())),
         "Failed to parse binary expresssion");
-    assert_eq!(parse_expr("1+ 2").is_ok(),true, "Failed to parse binary expresssion");
-    assert_eq!(parse_expr("1 +2").is_ok(),true, "Failed to parse binary expresssion");
-    assert_eq!(parse_expr("1+2").is_ok(),true, "Failed to parse binary expresssion");
+    assert!(parse_expr("1+ 2").is_ok(), "Failed to parse binary expresssion");
+    assert!(parse_expr("1 +2").is_ok(), "Failed to parse binary expresssion");
+    assert!(parse_expr("1+2").is_ok(), "Failed to parse binary expresssion");
+}
+
+/// Tests that binary exprs are grouped by the precedence of their operators, & associate to the left
+#[test]
+fn test_parse_precedence() {
+    let r = |n: &str| ExprKind::Ref(n.to_string()).into();
+    let bin = |op: BOp, e1: Expr, e2: Expr| ExprKind::BinOp{operator: op, e1: Box::new(e1), e2: Box::new(e2)}.into();
+
+    // left associativity
+    assert_eq!(parse_expr("1 - 2 - 3"), Ok(("", bin(BOp::Sub, bin(BOp::Sub, i(1), i(2)), i(3)))), "Failed to associate subtraction to the left");
+    assert_eq!(parse_expr("a / b * c"), Ok(("", bin(BOp::Mul, bin(BOp::Div, r("a"), r("b")), r("c")))), "Failed to associate mixed multiplicative ops to the left");
+    assert_eq!(parse_expr("a - b + c"), Ok(("", bin(BOp::Add, bin(BOp::Sub, r("a"), r("b")), r("c")))), "Failed to associate mixed additive ops to the left");
+    assert_eq!(parse_expr("f . g . h"), Ok(("", bin(BOp::Compose, bin(BOp::Compose, r("f"), r("g")), r("h")))), "Failed to associate composition to the left");
+
+    // precedence
+    assert_eq!(
+        parse_expr("a + b * c == d"),
+        Ok(("", bin(BOp::Eq, bin(BOp::Add, r("a"), bin(BOp::Mul, r("b"), r("c"))), r("d")))),
+        "Failed to bind multiplication tighter than addition, & addition tighter than equality"
+    );
+    assert_eq!(
+        parse_expr("a < b == c >= d"),
+        Ok(("", bin(BOp::Eq, bin(BOp::Lt, r("a"), r("b")), bin(BOp::Gte, r("c"), r("d"))))),
+        "Failed to bind relational ops tighter than equality"
+    );
+    assert_eq!(
+        parse_expr("a || b && c == d"),
+        Ok(("", bin(BOp::Or, r("a"), bin(BOp::And, r("b"), bin(BOp::Eq, r("c"), r("d")))))),
+        "Failed to bind && tighter than ||"
+    );
+    assert_eq!(
+        parse_expr("a | b ^ c & d"),
+        Ok(("", bin(BOp::BitOr, r("a"), bin(BOp::BitXor, r("b"), bin(BOp::BitAnd, r("c"), r("d")))))),
+        "Failed to order the bitwise ops as & then ^ then |"
+    );
+    assert_eq!(
+        parse_expr("a & b == c && d | e"),
+        Ok(("", bin(BOp::And, bin(BOp::BitAnd, r("a"), bin(BOp::Eq, r("b"), r("c"))), bin(BOp::BitOr, r("d"), r("e"))))),
+        "Failed to bind bitwise ops between equality & logical ops"
+    );
+    assert_eq!(
+        parse_expr("a % b - c"),
+        Ok(("", bin(BOp::Sub, bin(BOp::Mod, r("a"), r("b")), r("c")))),
+        "Failed to bind modulo tighter than subtraction"
+    );
+    assert_eq!(
+        parse_expr("f . g * 2"),
+        Ok(("", bin(BOp::Mul, bin(BOp::Compose, r("f"), r("g")), i(2)))),
+        "Failed to bind composition tightest"
+    );
+
+    // parens & unary ops
+    assert_eq!(parse_expr("1 - (2 - 3)"), Ok(("", bin(BOp::Sub, i(1), bin(BOp::Sub, i(2), i(3))))), "Failed to group w/ parens");
+    assert_eq!(parse_expr("(a + b) * c"), Ok(("", bin(BOp::Mul, bin(BOp::Add, r("a"), r("b")), r("c")))), "Failed to group w/ parens on the left");
+    assert_eq!(
+        parse_expr("-a * b"),
+        Ok(("", bin(BOp::Mul, ExprKind::UnaryOp{operator: UOp::Negative, e: Box::new(r("a"))}.into(), r("b")))),
+        "Failed to bind a unary op tighter than a binary op"
+    );
+    assert_eq!(
+        parse_expr("!a && b"),
+        Ok(("", bin(BOp::And, ExprKind::UnaryOp{operator: UOp::Negate, e: Box::new(r("a"))}.into(), r("b")))),
+        "Failed to bind negation tighter than &&"
+    );
+
+    assert!(parse_expr("1 + * 2").is_err(), "Failed to reject a missing operand");
 }
 
 /// Tests parsing a branch
 #[test]
 fn test_parse_branch() {
-    let mut b = Expr::Branch{condition: Box::new(Expr::B(true)), b1: vec![i(1)], b2: vec![i(2)]};
+    let b = ExprKind::Branch{condition: Box::new(ExprKind::B(true).into()), b1: vec![i(1)], b2: vec![i(2)]}.into();
     assert_eq!(parse_expr("if true { 1 } else { 2 }"), Ok(("", b)), "Failed to parse branch");
-    let mut b = Expr::Branch{condition: Box::new(app("verify", vec![Expr::B(false),Expr::B(true)])), b1: vec![i(1),i(2),Expr::B(true)], b2: vec![i(2),Expr::B(true)]};
+    let b = ExprKind::Branch{condition: Box::new(app("verify", vec![ExprKind::B(false).into(),ExprKind::B(true).into()])), b1: vec![i(1),i(2),ExprKind::B(true).into()], b2: vec![i(2),ExprKind::B(true).into()]}.into();
     assert_eq!(parse_expr("if verify(false,true) { 1\n2\ntrue } else { 2\ntrue }"), Ok(("", b)), "Failed to parse more complex branch");
-    assert_eq!(parse_expr("if oops(1,2) { 5 } else { 55").is_ok(), false, "Failed to discard badly formatted branch");
+    assert!(parse_expr("if oops(1,2) { 5 } else { 55").is_err(), "Failed to discard badly formatted branch");
+
+    // 'then' is optional, as written in the original FDSSL syntax
+    let b = ExprKind::Branch{condition: Box::new(ExprKind::Ref("c".to_string()).into()), b1: vec![i(1)], b2: vec![i(2)]}.into();
+    assert_eq!(parse_expr("if c then { 1 } else { 2 }"), Ok(("", b)), "Failed to parse branch w/ 'then'");
+    assert!(parse_expr("if c thenx { 1 } else { 2 }").is_err(), "Failed to reject branch w/ a misspelt 'then'");
 }
 
 /// Testing of parsing of scope exprs
 #[test]
 fn test_parse_scoped_exprs() {
     // parse_scoped_exprs
-    assert_eq!(parse_scoped_exprs(" x y ").is_ok(), false, "Failed to reject scoped expr that was not correct");
+    assert!(parse_scoped_exprs(" x y ").is_err(), "Failed to reject scoped expr that was not correct");
 }
 
 /// Tests parametrized abstractions
 #[test]
 fn test_parse_abs() {
-    let a = Expr::Abs{params: vec![], body: vec![i(5)]};
+    let a = ExprKind::Abs{params: vec![], body: vec![i(5)]}.into();
     assert_eq!(parse_expr("() { 5 }") , Ok(("", a)), "Failed to parse a simple abstraction");
 
-    let a = Expr::Abs{params: vec![("x".to_string(), ParsedType::BaseType("T1".to_string())), ("y".to_string(), ParsedType::BaseType("bool".to_string()))], body: vec![Expr::Ref("x".to_string()),Expr::Ref("y".to_string())]};
+    let a = ExprKind::Abs{params: vec![("x".to_string(), ParsedType::BaseType("T1".to_string()).into()), ("y".to_string(), ParsedType::BaseType("bool".to_string()).into())], body: vec![ExprKind::Ref("x".to_string()).into(),ExprKind::Ref("y".to_string()).into()]}.into();
     assert_eq!(parse_expr("(x : T1, y : bool) { x \n y\n }") , Ok(("", a)), "Failed to parse a more complex abstraction #1");
 
     // test w/ no break between exprs (invalid)
-    assert_eq!(parse_expr("(x : T1, y : T2) { x y }").is_ok(), false, "Failed to reject poorly formatted abstraction body");
+    assert!(parse_expr("(x : T1, y : T2) { x y }").is_err(), "Failed to reject poorly formatted abstraction body");
 
     // test an abstraction w/ many linebreaks between exprs, and elsewhere too
-    let a = Expr::Abs{params: vec![("x".to_string(), ParsedType::BaseType("T1".to_string())), ("y".to_string(), ParsedType::BaseType("T2".to_string()))], body: vec![Expr::Ref("x".to_string()),Expr::Ref("y".to_string())]};
+    let a = ExprKind::Abs{params: vec![("x".to_string(), ParsedType::BaseType("T1".to_string()).into()), ("y".to_string(), ParsedType::BaseType("T2".to_string()).into())], body: vec![ExprKind::Ref("x".to_string()).into(),ExprKind::Ref("y".to_string()).into()]}.into();
     assert_eq!(parse_expr("(x : T1, y : T2) \n { \n x \n\n\n y }") , Ok(("", a)), "Failed to parse a more complex abstraction #2");
 }
 
 /// Tests parsing for loops
 #[test]
 fn test_parse_forloop() {
-    let mut b = Expr::For{init: Box::new(i(1)), cond: Box::new(i(2)), post: Box::new(i(3)), body: vec![Expr::Ref("x".to_string())]};
+    let b = ExprKind::For{init: Box::new(i(1)), cond: Box::new(i(2)), post: Box::new(i(3)), body: vec![ExprKind::Ref("x".to_string()).into()]}.into();
     assert_eq!(parse_expr("for(1,2,3){ x }"), Ok(("", b)), "Failed to parse simple forloop");
 
     // test a for loop w/ multiple exprs
-    let mut b = Expr::For{init: Box::new(Expr::B(true)), cond: Box::new(Expr::B(false)), post: Box::new(Expr::F(3.0)), body: vec![app("f",vec![i(1)]), app("f2", vec![]), app("f3", vec![])]};
+    let b = ExprKind::For{init: Box::new(ExprKind::B(true).into()), cond: Box::new(ExprKind::B(false).into()), post: Box::new(ExprKind::F(3.0).into()), body: vec![app("f",vec![i(1)]), app("f2", vec![]), app("f3", vec![])]}.into();
     assert_eq!(parse_expr("for( true , false , 3.0f ) {\n\tf(1)\n\tf2()\n\tf3() }"), Ok(("", b)), "Failed to parse larger forloop");
+
+    // a loop that runs its body 'n' times counts up w/ a hidden index
+    let r = |n: &str| -> Expr { ExprKind::Ref(n.to_string()).into() };
+    let b = ExprKind::For{
+        init: Box::new(mdef(LOOP_INDEX, ptype("int"), i(0))),
+        cond: Box::new(ExprKind::BinOp{operator: BOp::Lt, e1: Box::new(r(LOOP_INDEX)), e2: Box::new(r("x"))}.into()),
+        post: Box::new(ExprKind::Update{target: LOOP_INDEX.to_string(), swizzle: None, value: Box::new(ExprKind::BinOp{operator: BOp::Add, e1: Box::new(r(LOOP_INDEX)), e2: Box::new(i(1))}.into())}.into()),
+        body: vec![app("f", vec![])]
+    }.into();
+    assert_eq!(parse_expr("for x do {\n\tf()\n}"), Ok(("", b)), "Failed to parse 'for n do' loop");
+    assert!(parse_expr("for x { f() }").is_err(), "Failed to reject 'for n' loop w/out 'do'");
+}
+
+/// Tests parsing of while & do-while loops
+#[test]
+fn test_parse_while() {
+    let cond = || -> Expr { ExprKind::BinOp{operator: BOp::Lt, e1: Box::new(ExprKind::Ref("x".to_string()).into()), e2: Box::new(i(3))}.into() };
+    let body = || vec![ExprKind::Update{target: "x".to_string(), swizzle: None, value: Box::new(ExprKind::BinOp{operator: BOp::Add, e1: Box::new(ExprKind::Ref("x".to_string()).into()), e2: Box::new(i(1))}.into())}.into()];
+
+    let w = ExprKind::While{cond: Box::new(cond()), body: body()}.into();
+    assert_eq!(parse_expr("while x < 3 {\n\tx += 1\n}"), Ok(("", w)), "Failed to parse while loop");
+
+    let d = ExprKind::DoWhile{body: body(), cond: Box::new(cond())}.into();
+    assert_eq!(parse_expr("do {\n\tx += 1\n} while x < 3"), Ok(("", d)), "Failed to parse do-while loop");
+
+    assert!(parse_expr("do { 1 }").is_err(), "Failed to reject do-while loop w/out a condition");
+    assert!(parse_expr("while { 1 }").is_err(), "Failed to reject while loop w/out a condition");
+    assert_eq!(parse_expr("doThis"), Ok(("", ExprKind::Ref("doThis".to_string()).into())), "Failed to parse a name starting w/ a keyword");
 }
 
 /// Tests parsing of unary exprs
@@ -829,7 +1462,7 @@ fn test_parse_unaryexpr() {
     assert_eq!(parse_expr("-1") , Ok(("", i(-1))), "Failed to parse a negative int normally");
 
     // verify refs parse w/ unary ops
-    assert_eq!(parse_expr("-a") , Ok(("", Expr::UnaryOp{operator: UOp::Negative, e: Box::new(Expr::Ref("a".to_string()))})), "Failed to parse a negative reference");
+    assert_eq!(parse_expr("-a") , Ok(("", ExprKind::UnaryOp{operator: UOp::Negative, e: Box::new(ExprKind::Ref("a".to_string()).into())}.into())), "Failed to parse a negative reference");
 
 }
 
@@ -837,23 +1470,65 @@ fn test_parse_unaryexpr() {
 #[test]
 fn test_parse_access_name() {
     // simple access
-    assert_eq!(parse_expr("x.y"), Ok(("", Expr::Access("x".to_string(), AccessType::Name("y".to_string())))), "Failed to parse 1st simple named access.");
-    assert_eq!(parse_expr("x._y"), Ok(("", Expr::Access("x".to_string(), AccessType::Name("_y".to_string())))), "Failed to parse 2nd simple named access.");
-    assert_eq!(parse_expr("_X._Y"), Ok(("", Expr::Access("_X".to_string(), AccessType::Name("_Y".to_string())))), "Failed to parse 3rd simple named access.");
+    assert_eq!(parse_expr("x.y"), Ok(("", ExprKind::Access(Box::new(ExprKind::Ref("x".to_string()).into()), AccessType::Name("y".to_string())).into())), "Failed to parse 1st simple named access.");
+    assert_eq!(parse_expr("x._y"), Ok(("", ExprKind::Access(Box::new(ExprKind::Ref("x".to_string()).into()), AccessType::Name("_y".to_string())).into())), "Failed to parse 2nd simple named access.");
+    assert_eq!(parse_expr("_X._Y"), Ok(("", ExprKind::Access(Box::new(ExprKind::Ref("_X".to_string()).into()), AccessType::Name("_Y".to_string())).into())), "Failed to parse 3rd simple named access.");
+
+    // components named in brackets, as in the original FDSSL syntax, are indices until the typechecker resolves them
+    assert_eq!(parse_expr("v[z]"), Ok(("", ExprKind::Access(Box::new(ExprKind::Ref("v".to_string()).into()), AccessType::Idx(Box::new(ExprKind::Ref("z".to_string()).into()))).into())), "Failed to parse component named in brackets");
+}
+
+/// Tests parsing chains of accesses, & accesses of exprs other than names
+#[test]
+fn test_parse_access_chain() {
+    let r = |n: &str| -> Expr { ExprKind::Ref(n.to_string()).into() };
+    let field = |e: Expr, p: &str| -> Expr { ExprKind::Access(Box::new(e), AccessType::Name(p.to_string())).into() };
+    let index = |e: Expr, i: Expr| -> Expr { ExprKind::Access(Box::new(e), AccessType::Idx(Box::new(i))).into() };
+
+    assert_eq!(parse_expr("light.color.r"), Ok(("", field(field(r("light"), "color"), "r"))), "Failed to parse a chain of named accesses");
+    assert_eq!(parse_expr("m[0][1]"), Ok(("", index(index(r("m"), i(0)), i(1)))), "Failed to parse a chain of indexed accesses");
+    assert_eq!(parse_expr("lights[i].color"), Ok(("", field(index(r("lights"), r("i")), "color"))), "Failed to parse a mixed chain of accesses");
+    assert_eq!(parse_expr("f(x).pos"), Ok(("", field(app("f", vec![r("x")]), "pos"))), "Failed to parse access of an application");
+    assert_eq!(
+        parse_expr("(a + b)[0]"),
+        Ok(("", index(ExprKind::BinOp{operator: BOp::Add, e1: Box::new(r("a")), e2: Box::new(r("b"))}.into(), i(0)))),
+        "Failed to parse access of a nested expr"
+    );
+    assert_eq!(parse_expr("(x: 1, y: 2).y"), Ok(("", field(ExprKind::NamedVect(vec![("x".to_string(), i(1)), ("y".to_string(), i(2))]).into(), "y"))), "Failed to parse access of a named tuple");
+    // accesses bind tighter than any operator
+    assert_eq!(
+        parse_expr("-v.x * 2"),
+        Ok(("", ExprKind::BinOp{
+            operator: BOp::Mul,
+            e1: Box::new(ExprKind::UnaryOp{operator: UOp::Negative, e: Box::new(field(r("v"), "x"))}.into()),
+            e2: Box::new(i(2))
+        }.into())),
+        "Failed to parse access under a unary op"
+    );
+
+    // every access in a chain spans its base
+    let src = "(a + b).xy[1]";
+    match parse_expr(src).unwrap().1 {
+        Expr{kind: ExprKind::Access(b, _), span} => {
+            assert_eq!(&src[span.range(src)], "(a + b).xy[1]", "Wrong span for a chain of accesses");
+            assert_eq!(&src[b.span.range(src)], "(a + b).xy", "Wrong span for the base of an access");
+        },
+        e => panic!("Expected an access, but got '{:?}'", e)
+    }
 }
 
 /// Tests parsing an vect access by index
 #[test]
 fn test_parse_access_index() {
-    assert_eq!(parse_expr("x[0]"), Ok(("", Expr::Access("x".to_string(), AccessType::Idx(Box::new(i(0)))))), "Failed to parse literal int for indexed access.");
-    assert_eq!(parse_expr("x[_y]"), Ok(("", Expr::Access("x".to_string(), AccessType::Idx(Box::new(Expr::Ref("_y".to_string())))))), "Failed to parse ref for indexed access.");
-    assert_eq!(parse_expr("xY_z[_y + 1]"), Ok(("", Expr::Access(
-        "xY_z".to_string(),
-        AccessType::Idx(Box::new(Expr::BinOp{
+    assert_eq!(parse_expr("x[0]"), Ok(("", ExprKind::Access(Box::new(ExprKind::Ref("x".to_string()).into()), AccessType::Idx(Box::new(i(0)))).into())), "Failed to parse literal int for indexed access.");
+    assert_eq!(parse_expr("x[_y]"), Ok(("", ExprKind::Access(Box::new(ExprKind::Ref("x".to_string()).into()), AccessType::Idx(Box::new(ExprKind::Ref("_y".to_string()).into()))).into())), "Failed to parse ref for indexed access.");
+    assert_eq!(parse_expr("xY_z[_y + 1]"), Ok(("", ExprKind::Access(
+        Box::new(ExprKind::Ref("xY_z".to_string()).into()),
+        AccessType::Idx(Box::new(ExprKind::BinOp{
             operator: BOp::Add,
-            e1:       Box::new(Expr::Ref("_y".to_string())),
-            e2:       Box::new(Expr::I(1))
-        }))))), "Failed to parse binary expr for indexed access.");
+            e1:       Box::new(ExprKind::Ref("_y".to_string()).into()),
+            e2:       Box::new(ExprKind::I(1).into())
+        }.into()))).into())), "Failed to parse binary expr for indexed access.");
 }
 
 /// Tests parsing a func w/ body
@@ -865,14 +1540,14 @@ fn test_parse_func() {
         parse_expr("let f1 : bool = () { true }"),
         Ok((
             "",
-            Expr::Def{
+            ExprKind::Def{
                 name:   "f1".to_string(),
-                typ:    ptype("bool"),
-                value:  Box::new(Expr::Abs{
+                typ:    ptype("bool").into(),
+                value:  Box::new(ExprKind::Abs{
                     params: vec![],
-                    body:   vec![Expr::B(true)]
-                })
-            }
+                    body:   vec![ExprKind::B(true).into()]
+                }.into())
+            }.into()
         )),
         "Failed to parse f1 boolean function w/ no args"
     );
@@ -881,14 +1556,14 @@ fn test_parse_func() {
         parse_expr("let id : int -> int = (x : int) { x }"),
         Ok((
             "",
-            Expr::Def{
+            ExprKind::Def{
                 name:   "id".to_string(),
-                typ:    ParsedType::Function(Box::new(ptype("int")), Box::new(ptype("int"))),
-                value:  Box::new(Expr::Abs{
-                    params: vec![("x".to_string(), ParsedType::BaseType("int".to_string()))],
-                    body:   vec![Expr::Ref("x".to_string())]
-                })
-            }
+                typ:    ParsedType::Function(Box::new(ptype("int")), Box::new(ptype("int"))).into(),
+                value:  Box::new(ExprKind::Abs{
+                    params: vec![("x".to_string(), ParsedType::BaseType("int".to_string()).into())],
+                    body:   vec![ExprKind::Ref("x".to_string()).into()]
+                }.into())
+            }.into()
         )),
         "Failed to parse id 'int' function"
     );
@@ -898,24 +1573,24 @@ fn test_parse_func() {
         parse_expr("let add : (int,int) -> int = (_x : int, y : int) {\n_x + y\n}"),
         Ok((
             "",
-            Expr::Def{
+            ExprKind::Def{
                 name:   "add".to_string(),
                 // (int,int) -> int
                 typ:    ParsedType::Function(
                     Box::new(ParsedType::Tuple(
                         vec![ptype("int"),
                             ptype("int")])),
-                    Box::new(ptype("int"))),
+                    Box::new(ptype("int"))).into(),
                 // _x + y
-                value:  Box::new(Expr::Abs{
-                    params: vec![("_x".to_string(), ParsedType::BaseType("int".to_string())), ("y".to_string(), ParsedType::BaseType("int".to_string()))],
-                    body:   vec![Expr::BinOp{
+                value:  Box::new(ExprKind::Abs{
+                    params: vec![("_x".to_string(), ParsedType::BaseType("int".to_string()).into()), ("y".to_string(), ParsedType::BaseType("int".to_string()).into())],
+                    body:   vec![ExprKind::BinOp{
                         operator: BOp::Add,
-                        e1:       Box::new(Expr::Ref("_x".to_string())),
-                        e2:       Box::new(Expr::Ref("y".to_string())),
-                    }]
-                })
-            }
+                        e1:       Box::new(ExprKind::Ref("_x".to_string()).into()),
+                        e2:       Box::new(ExprKind::Ref("y".to_string()).into()),
+                    }.into()]
+                }.into())
+            }.into()
         )),
         "Failed to parse inc function"
     )
@@ -940,5 +1615,372 @@ fn test_parse_small_prog() {
     \n\
     apply(add,2)\n\
     ";
-    assert_eq!(program(prog).is_ok(), true)
+    assert!(program(prog).is_ok())
+}
+
+/// Tests parsing updates w/ 'set' & 'out'
+#[test]
+fn test_parse_set() {
+    assert_eq!(
+        parse_expr("set x 5"),
+        Ok(("", ExprKind::Update{target: "x".to_string(), swizzle: None, value: Box::new(i(5))}.into())),
+        "Failed to parse simple update"
+    );
+    assert_eq!(
+        parse_expr("out vXYZ (a + 1)"),
+        Ok(("", ExprKind::Update{target: "vXYZ".to_string(), swizzle: None, value: Box::new(ExprKind::BinOp{
+            operator: BOp::Add,
+            e1:       Box::new(ExprKind::Ref("a".to_string()).into()),
+            e2:       Box::new(i(1))
+        }.into())}.into())),
+        "Failed to parse update of a shader output"
+    );
+    assert_eq!(
+        parse_expr("set pos.xy uv"),
+        Ok(("", ExprKind::Update{target: "pos".to_string(), swizzle: Some("xy".to_string()), value: Box::new(ExprKind::Ref("uv".to_string()).into())}.into())),
+        "Failed to parse update of a swizzle"
+    );
+    assert!(parse_expr("set 5").is_err(), "Failed to reject update w/out a target");
+    assert!(parse_expr("set").is_err(), "Failed to reject 'set' as reserved keyword, not a ref");
+}
+
+/// Tests parsing assignments, which are updates as well
+#[test]
+fn test_parse_assign() {
+    let r = |n: &str| -> Expr { ExprKind::Ref(n.to_string()).into() };
+    let update = |n: &str, e: Expr| -> Expr { ExprKind::Update{target: n.to_string(), swizzle: None, value: Box::new(e)}.into() };
+    let binop = |operator: BOp, e1: Expr, e2: Expr| -> Expr { ExprKind::BinOp{operator, e1: Box::new(e1), e2: Box::new(e2)}.into() };
+
+    assert_eq!(parse_expr("x = x + 1"), Ok(("", update("x", binop(BOp::Add, r("x"), i(1))))), "Failed to parse assignment");
+    assert_eq!(parse_expr("x += 2 * y"), Ok(("", update("x", binop(BOp::Add, r("x"), binop(BOp::Mul, i(2), r("y")))))), "Failed to parse '+='");
+    assert_eq!(parse_expr("x -= 1"), Ok(("", update("x", binop(BOp::Sub, r("x"), i(1))))), "Failed to parse '-='");
+    assert_eq!(parse_expr("x *= 1"), Ok(("", update("x", binop(BOp::Mul, r("x"), i(1))))), "Failed to parse '*='");
+    assert_eq!(parse_expr("x /= 1"), Ok(("", update("x", binop(BOp::Div, r("x"), i(1))))), "Failed to parse '/='");
+
+    // a swizzle is updated by the binary op on the same swizzle
+    let swizzled = ExprKind::Access(Box::new(r("v")), AccessType::Name("xz".to_string())).into();
+    assert_eq!(
+        parse_expr("v.xz *= 2.0f"),
+        Ok(("", ExprKind::Update{target: "v".to_string(), swizzle: Some("xz".to_string()), value: Box::new(binop(BOp::Mul, swizzled, ExprKind::F(2.0).into()))}.into())),
+        "Failed to parse compound assignment to a swizzle"
+    );
+
+    // equality is not an assignment
+    assert_eq!(parse_expr("x == 1"), Ok(("", binop(BOp::Eq, r("x"), i(1)))), "Failed to parse equality next to assignment");
+    assert!(parse_expr("let = 1").map_or(true, |(rest, _)| !rest.is_empty()), "Failed to reject assignment to a keyword");
+
+    // the target of a compound assignment spans its name
+    let src = "x += 1";
+    match parse_expr(src).unwrap().1.kind {
+        ExprKind::Update{value, ..} => match value.kind {
+            ExprKind::BinOp{e1, ..} => assert_eq!(&src[e1.span.range(src)], "x", "Wrong span for the target of a compound assignment"),
+            k => panic!("Expected a binary op, but got '{:?}'", k)
+        },
+        k => panic!("Expected an update, but got '{:?}'", k)
+    }
+}
+
+/// Tests that capitalized built-in type names are lowered, and others are left alone
+#[test]
+fn test_parse_type_canonical() {
+    assert_eq!(parse_type("Float"), Ok(("", ptype("float"))), "Failed to lower 'Float'");
+    assert_eq!(parse_type("Vec3"), Ok(("", ptype("vec3"))), "Failed to lower 'Vec3'");
+    assert_eq!(parse_type("Mat4"), Ok(("", ptype("mat4"))), "Failed to lower 'Mat4'");
+    assert_eq!(parse_type("Prog"), Ok(("", ptype("Prog"))), "Changed non built-in type 'Prog'");
+}
+
+/// Tests parsing the types of named tuples
+#[test]
+fn test_parse_type_named_tuple() {
+    let named = |v: Vec<(&str, ParsedType)>| ParsedType::NamedTuple(v.into_iter().map(|(n,t)| (n.to_string(), Box::new(t))).collect());
+    assert_eq!(parse_type("(x: int)"), Ok(("", named(vec![("x", ptype("int"))]))), "Failed to parse a named tuple w/ a single property");
+    assert_eq!(
+        parse_type("( pos : Vec3, color: (r: float, g: float) )"),
+        Ok(("", named(vec![("pos", ptype("vec3")), ("color", named(vec![("r", ptype("float")), ("g", ptype("float"))]))]))),
+        "Failed to parse a nested named tuple"
+    );
+    assert_eq!(
+        parse_type("(x: int, y: bool) -> int"),
+        Ok(("", ParsedType::Function(Box::new(named(vec![("x", ptype("int")), ("y", ptype("bool"))])), Box::new(ptype("int"))))),
+        "Failed to parse a named tuple as an argument"
+    );
+    assert!(parse_type("(x: int, bool)").map_or(true, |(rest, _)| !rest.is_empty()), "Failed to reject a tuple w/ a property missing its name");
+}
+
+/// Tests that array sizes wrap the type of their elements
+#[test]
+fn test_parse_type_array() {
+    let arr = |t: ParsedType, n: usize| ParsedType::Array(Box::new(t), n);
+    assert_eq!(parse_type("float[4]"), Ok(("", arr(ptype("float"), 4))), "Failed to parse an array type");
+    assert_eq!(parse_type("Vec3 [ 2 ]"), Ok(("", arr(ptype("vec3"), 2))), "Failed to parse an array type w/ spaces");
+    assert_eq!(parse_type("int[4][2]"), Ok(("", arr(arr(ptype("int"), 4), 2))), "Failed to parse an array of arrays");
+    assert_eq!(parse_type("(int, bool)[3] -> int"), Ok(("", ParsedType::Function(
+        Box::new(arr(ParsedType::Tuple(vec![ptype("int"), ptype("bool")]), 3)),
+        Box::new(ptype("int"))
+    ))), "Failed to parse an array of tuples as an argument");
+    assert!(parse_type("float[0]").map_or(true, |(rest, _)| !rest.is_empty()), "Failed to reject an empty array");
+    assert!(parse_type("float[n]").map_or(true, |(rest, _)| !rest.is_empty()), "Failed to reject an array w/ a non-constant size");
+}
+
+/// Used during testing to build a shader
+#[cfg(test)]
+fn shader(stage: Stage, name: &str, inputs: Vec<(&str,&str)>, outputs: Vec<(&str,&str)>, body: Vec<Expr>) -> Expr {
+    let named = |v: Vec<(&str,&str)>| v.into_iter().map(|(n,t)| (n.to_string(), ptype(t).into())).collect();
+    ExprKind::Shader{
+        stage,
+        name: name.to_string(),
+        inputs: named(inputs),
+        outputs: named(outputs),
+        body: ShaderBody::Block(body)
+    }.into()
+}
+
+/// Tests parsing vertex & fragment shaders
+#[test]
+fn test_parse_shader() {
+    assert_eq!(
+        parse_top_level("vert v : () -> () = { 1 }"),
+        Ok(("", shader(Stage::Vertex, "v", vec![], vec![], vec![i(1)]))),
+        "Failed to parse a simple vertex shader"
+    );
+    assert_eq!(
+        parse_top_level("frag f : (Vec3 vXYZ) -> () = {\n  set gl_FragColor c\n}"),
+        Ok(("", shader(Stage::Fragment, "f", vec![("vXYZ","vec3")], vec![], vec![
+            ExprKind::Update{target: "gl_FragColor".to_string(), swizzle: None, value: Box::new(ExprKind::Ref("c".to_string()).into())}.into()
+        ]))),
+        "Failed to parse a fragment shader w/ an input"
+    );
+    assert_eq!(
+        parse_top_level("vert v2 : (Vec3 aVertPos, Float t) -> (Vec3 vXYZ, Vec4 pos) = {\n  out vXYZ aVertPos\n  out pos p\n}"),
+        Ok(("", shader(Stage::Vertex, "v2", vec![("aVertPos","vec3"),("t","float")], vec![("vXYZ","vec3"),("pos","vec4")], vec![
+            ExprKind::Update{target: "vXYZ".to_string(), swizzle: None, value: Box::new(ExprKind::Ref("aVertPos".to_string()).into())}.into(),
+            ExprKind::Update{target: "pos".to_string(), swizzle: None, value: Box::new(ExprKind::Ref("p".to_string()).into())}.into()
+        ]))),
+        "Failed to parse a vertex shader w/ multiple inputs & outputs"
+    );
+
+    // shaders only exist at the top level
+    assert!(parse_expr("vert v : () -> () = { 1 }").is_err(), "Failed to reject shader as an expr");
+    // unknown stage
+    assert!(program("geom g : () -> () = { 1 }\n").is_err(), "Failed to reject shader w/ unknown stage");
+    // params are written as 'Type name'
+    assert!(parse_top_level("vert v : (aVertPos : Vec3) -> () = { 1 }").is_err(), "Failed to reject shader w/ badly written inputs");
+    // needs a body
+    assert!(parse_top_level("frag f : () -> () = { }").is_err(), "Failed to reject shader w/ an empty body");
+}
+
+/// Tests parsing a program w/ shaders and functions side by side
+#[test]
+fn test_parse_shader_prog() {
+    let prog = "\
+    // scales a value\n\
+    let half : float -> float = (x : float) {\n\
+    \tx / 2.0f\n\
+    }\n\
+    \n\
+    vert v : (Vec3 aVertPos) -> (Vec3 vXYZ) = {\n\
+    \tout vXYZ aVertPos\n\
+    }\n\
+    \n\
+    frag f : (Vec3 vXYZ) -> () = {\n\
+    \tmut r : Float = half(vXYZ[0])\n\
+    \tr\n\
+    }\n\
+    ";
+    let res = program(prog);
+    assert!(res.is_ok(), "Failed to parse program w/ shaders");
+    let (_, p) = res.unwrap();
+    assert_eq!(p.len(), 4, "Wrong number of top level declarations");
+    assert!(matches!(p[2].kind, ExprKind::Shader{stage: Stage::Vertex, ..}), "Expected a vertex shader");
+    assert!(matches!(p[3].kind, ExprKind::Shader{stage: Stage::Fragment, ..}), "Expected a fragment shader");
+}
+
+/// Tests parsing uniform declarations
+#[test]
+fn test_parse_uniform() {
+    assert_eq!(
+        parse_top_level("uniform Mat4 uProjectionMatrix"),
+        Ok(("", ExprKind::Uniform{name: "uProjectionMatrix".to_string(), typ: ptype("mat4").into()}.into())),
+        "Failed to parse a simple uniform"
+    );
+    assert_eq!(
+        parse_top_level("uniform Float\tuTime"),
+        Ok(("", ExprKind::Uniform{name: "uTime".to_string(), typ: ptype("float").into()}.into())),
+        "Failed to parse a uniform separated by a tab"
+    );
+    // uniforms only exist at the top level
+    assert!(parse_expr("uniform Float uTime").is_err(), "Failed to reject uniform as an expr");
+    // needs both a type & a name
+    assert!(program("uniform uTime\n").is_err(), "Failed to reject uniform w/out a type");
+}
+
+/// Tests parsing program declarations
+#[test]
+fn test_parse_prog() {
+    assert_eq!(
+        parse_top_level("e1 : Prog = mkProg v2 f2"),
+        Ok(("", ExprKind::Prog{name: "e1".to_string(), vert: "v2".to_string(), frag: "f2".to_string()}.into())),
+        "Failed to parse a simple program"
+    );
+    // only shaders are linked
+    assert!(program("e1 : Prog = mkProg v2\n").is_err(), "Failed to reject program w/out a fragment shader");
+    assert!(program("e1 : Prog = v2 f2\n").is_err(), "Failed to reject program w/out mkProg");
+}
+
+/// Tests parsing constructors w/ args separated by spaces, as in the original FDSSL syntax
+#[test]
+fn test_parse_constructor() {
+    let r = |n: &str| -> Expr { ExprKind::Ref(n.to_string()).into() };
+    assert_eq!(parse_expr("vec4 0 1 0 1"), Ok(("", app("vec4", vec![i(0), i(1), i(0), i(1)]))), "Failed to parse constructor of literals");
+    assert_eq!(
+        parse_expr("vec4 r cos(g) b 1.0"),
+        Ok(("", app("vec4", vec![r("r"), app("cos", vec![r("g")]), r("b"), ExprKind::D(1.0).into()]))),
+        "Failed to parse constructor w/ a call among its args"
+    );
+    assert_eq!(
+        parse_expr("vec2 (a + 1) v[y] * 2"),
+        Ok(("", ExprKind::BinOp{
+            operator: BOp::Mul,
+            e1: Box::new(app("vec2", vec![
+                ExprKind::BinOp{operator: BOp::Add, e1: Box::new(r("a")), e2: Box::new(i(1))}.into(),
+                ExprKind::Access(Box::new(r("v")), AccessType::Idx(Box::new(r("y")))).into()
+            ])),
+            e2: Box::new(i(2))
+        }.into())),
+        "Failed to parse constructor w/ nested args, as an operand"
+    );
+    // parens w/out a space are a regular call, & the args of a constructor stop at the end of the line
+    assert_eq!(parse_expr("vec2(a, b)"), Ok(("", app("vec2", vec![r("a"), r("b")]))), "Failed to parse regular constructor call");
+    assert_eq!(parse_expr("vec2 a b\nc"), Ok(("\nc", app("vec2", vec![r("a"), r("b")]))), "Constructor took args from the next line");
+    // only vectors & matrices are constructed this way
+    assert_eq!(parse_expr("f a"), Ok((" a", r("f"))), "Parsed a function applied w/out parens");
+}
+
+/// Tests parsing functions declared in the original FDSSL syntax, w/ their params in the signature
+#[test]
+fn test_parse_func_decl() {
+    let param = |n: &str, t: &str| (n.to_string(), ptype(t).into());
+    let r = |n: &str| -> Expr { ExprKind::Ref(n.to_string()).into() };
+    let func = |name: &str, typ: ParsedType, params: Vec<(String,Annotation)>, body: Vec<Expr>| -> Expr {
+        ExprKind::Def{name: name.to_string(), typ: typ.into(), value: Box::new(ExprKind::Abs{params, body}.into())}.into()
+    };
+    assert_eq!(
+        parse_top_level("wave : (Float x) -> Float = {\n  sin (x)\n}"),
+        Ok(("", func("wave", ParsedType::Function(Box::new(ptype("float")), Box::new(ptype("float"))), vec![param("x", "float")], vec![app("sin", vec![r("x")])]))),
+        "Failed to parse function w/ a param"
+    );
+    assert_eq!(
+        parse_top_level("add : (Int x, Int y) -> Int = { x + y }"),
+        Ok(("", func(
+            "add",
+            ParsedType::Function(Box::new(ParsedType::Tuple(vec![ptype("int"), ptype("int")])), Box::new(ptype("int"))),
+            vec![param("x", "int"), param("y", "int")],
+            vec![ExprKind::BinOp{operator: BOp::Add, e1: Box::new(r("x")), e2: Box::new(r("y"))}.into()]
+        ))),
+        "Failed to parse function w/ several params"
+    );
+    // w/out params, a function is declared w/ just its return type, as w/ 'let'
+    assert_eq!(
+        parse_top_level("one : () -> Int = { 1 }"),
+        Ok(("", func("one", ptype("int"), vec![], vec![i(1)]))),
+        "Failed to parse function w/out params"
+    );
+    assert!(program("f : (x : Int) -> Int = { x }\n").is_err(), "Failed to reject function w/ badly written params");
+    assert!(program("f : (Int x) -> Int = x\n").is_err(), "Failed to reject function w/out a body");
+}
+
+/// Tests that the example programs can all be parsed
+#[test]
+fn test_parse_examples() {
+    for f in ["e0", "e1", "e2", "e3", "e4", "e6", "shaderCompExampleProg"] {
+        let prog = std::fs::read_to_string(format!("examples/{}.fdssl", f)).unwrap();
+        assert!(program(&prog).is_ok(), "Failed to parse example program {}", f);
+    }
+}
+
+/// Tests parsing composed shaders
+#[test]
+fn test_parse_shader_compose() {
+    let compose = |e1: Expr, e2: Expr| ExprKind::BinOp{operator: BOp::Compose, e1: Box::new(e1), e2: Box::new(e2)}.into();
+    let r = |n: &str| ExprKind::Ref(n.to_string()).into();
+    let mut s = shader(Stage::Vertex, "finalVert", vec![("aVertPos","vec3")], vec![("vXYZ","vec3"),("pos","vec4")], vec![]);
+    if let ExprKind::Shader{ref mut body, ..} = s.kind {
+        *body = ShaderBody::Composed(Box::new(compose(r("v2"), r("v1"))));
+    }
+    assert_eq!(
+        parse_top_level("vert finalVert : (Vec3 aVertPos) -> (Vec3 vXYZ, Vec4 pos) = v2 . v1"),
+        Ok(("", s)),
+        "Failed to parse a composed shader"
+    );
+
+    // composition is distinguished from named access by the spaces around the '.'
+    assert_eq!(parse_expr("f . g"), Ok(("", compose(r("f"), r("g")))), "Failed to parse composition w/ spaces");
+    assert_eq!(parse_expr("f.g"), Ok(("", ExprKind::Access(Box::new(ExprKind::Ref("f".to_string()).into()), AccessType::Name("g".to_string())).into())), "Failed to parse access w/out spaces");
+}
+
+/// Tests that exprs & annotations keep the span of source they were parsed from
+#[test]
+fn test_parse_spans() {
+    let src = "let x : int = 1\nlet y : (int, bool) = (x + 2, true)\n";
+    let (_, p) = program(src).unwrap();
+    let text = |s: Span| &src[s.range(src)];
+
+    assert_eq!(text(p[0].span), "let x : int = 1", "Wrong span for a definition");
+    assert_eq!(p[1].span.line_col(src), (2, 1), "Wrong line & column for the second definition");
+    match &p[1].kind {
+        ExprKind::Def{typ, value, ..} => {
+            assert_eq!(text(typ.span), "(int, bool)", "Wrong span for a type annotation");
+            assert_eq!(text(value.span), "(x + 2, true)", "Wrong span for a tuple");
+            assert_eq!(value.span.line_col(src), (2, 23), "Wrong line & column for a tuple");
+            match &value.kind {
+                ExprKind::Vect(v) => {
+                    assert_eq!(text(v[0].span), "x + 2", "Wrong span for a binary op");
+                    assert_eq!(text(v[1].span), "true", "Wrong span for a bool");
+                },
+                k => panic!("Expected a tuple, but got '{:?}'", k)
+            }
+        },
+        k => panic!("Expected a definition, but got '{:?}'", k)
+    }
+
+    // syntax errors span the token that parsing stopped at
+    let src = "let x : int = 1\nlet y : int = )\n";
+    let (_, errs) = parse_program(src);
+    assert_eq!(errs[0].span.line_col(src), (2, 15), "Wrong line & column for a syntax error");
+    assert_eq!(text(errs[0].span), ")", "Wrong span for a syntax error");
+}
+
+/// Tests that the recovering parser reports every syntax error, & still parses the declarations around them
+#[test]
+fn test_parse_recovery() {
+    let src = "let x : int = )\nlet y = 1\nlet z : int = 2\n\nlet f : int -> int = (a : int) {\n\ta +\n}\nlet g : = 3\n1 + 2 3\nlet ok : bool = true";
+    let (p, errs) = parse_program(src);
+
+    let found : Vec<(String, (usize, usize))> = errs.iter().map(|e| (e.to_string(), e.span.line_col(src))).collect();
+    assert_eq!(found, vec![
+        ("expected an expression, found ')'".to_string(), (1, 15)),
+        // w/ the type first, `let y` is followed by the name of the binding instead
+        ("expected ':' or a name, found '='".to_string(), (2, 7)),
+        ("expected an expression, found end of line".to_string(), (6, 5)),
+        ("expected a type, found '='".to_string(), (8, 9)),
+        ("expected end of line, found '3'".to_string(), (9, 7)),
+    ], "Wrong syntax errors");
+
+    // bad declarations are replaced by error nodes, & a block is skipped up to its closing brace
+    let err : Expr = ExprKind::Error.into();
+    assert_eq!(p, vec![
+        err.clone(),
+        err.clone(),
+        def("z", ptype("int"), i(2)),
+        err.clone(),
+        err.clone(),
+        err,
+        def("ok", ptype("bool"), ExprKind::B(true).into()),
+    ], "Wrong AST after recovering from syntax errors");
+    assert_eq!(&src[p[3].span.range(src)], "let f : int -> int = (a : int) {\n\ta +\n}", "Wrong span for an error node");
+
+    // a valid program parses the same as it does w/out recovery
+    let src = "let x : int = 1\nx + 2\n";
+    assert_eq!(parse_program(src), (program(src).unwrap().1, vec![]), "Recovering parser disagrees on a valid program");
+    assert_eq!(parse_program("1 +").1[0].to_string(), "expected an expression, found end of file", "Wrong error at the end of a program");
 }
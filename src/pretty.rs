@@ -0,0 +1,848 @@
+/*
+
+Pretty printer from the GLSL AST to concrete GLSL shaders
+
+Targets one of several GLSL versions, w/ the same AST printed differently for each:
+
+- GLSL ES 1.00, as used by WebGL 1. Shader inputs & outputs are printed as 'attribute' & 'varying',
+  and every shader starts w/ the same default float precision. Fragment shaders are required
+  to declare one, and uniforms shared by both stages must agree on it. Shaders that call 'dFdx',
+  'dFdy' or 'fwidth' enable the GL_OES_standard_derivatives extension that provides them.
+- GLSL 3.30 core & 4.50, as used by desktop OpenGL. Shader inputs & outputs are printed as 'in' & 'out',
+  w/ explicit locations, and 'gl_FragColor' is replaced by a declared fragment output.
+
+Output only depends on the AST, so printing the same program always produces the same shaders.
+
+*/
+
+use crate::glsl_syntax as glsl;
+use crate::syntax::Stage;
+
+use glsl::{BinOp, Decl, Expr, ExprKind, Qualifier, Stmt, Type, UnOp, Version};
+use std::fs;
+use std::path::Path;
+
+// Simple printer error, for constructs that a GLSL version cannot express
+pub type PrintError = String;
+
+// Indentation for each nested block
+const INDENT: &str = "    ";
+
+// Fragment output that replaces writes to 'gl_FragColor' in the core profiles
+const FRAG_COLOR: &str = "fdssl_FragColor";
+
+/// What a shader is printed for, the GLSL version & the stage of the shader
+#[derive(Clone, Copy)]
+struct Ctx {
+    version: Version,
+    stage: Stage,
+}
+
+/// Prints a shader for a given GLSL version
+pub fn print_shader(s: &glsl::Shader, version: Version) -> Result<String,PrintError> {
+    let ctx = Ctx{ version, stage: s.stage };
+    let mut out = match version {
+        Version::Es100   => String::new(),
+        Version::Glsl330 => String::from("#version 330 core\n"),
+        Version::Glsl450 => String::from("#version 450\n"),
+    };
+    if version == Version::Es100 {
+        // derivatives are an extension of GLSL ES 1.00, which must be enabled before anything but another directive
+        if ["dFdx", "dFdy", "fwidth"].iter().any(|n| s.decls.iter().any(|d| decl_uses(d, n))) {
+            out.push_str("#extension GL_OES_standard_derivatives : enable\n");
+        }
+        out.push_str("precision mediump float;\n");
+    }
+
+    // the core profiles have no 'gl_FragColor', so an output is declared to replace it
+    // it goes before the other outputs, so it is located at 0 like 'gl_FragColor' is in ES 1.00
+    let mut decls = s.decls.clone();
+    if version != Version::Es100 && s.stage == Stage::Fragment && decls.iter().any(|d| decl_uses(d, "gl_FragColor")) {
+        let at = decls.iter().position(|d| matches!(d, Decl::Var(v) if v.qualifier == Some(Qualifier::Out)))
+            .or_else(|| decls.iter().rposition(|d| matches!(d, Decl::Var(_))).map(|i| i + 1))
+            .unwrap_or(0);
+        decls.insert(at, Decl::Var(glsl::VarDecl{ qualifier: Some(Qualifier::Out), typ: Type::Vec(4), name: FRAG_COLOR.to_string(), value: None }));
+    }
+
+    // inputs & outputs are located in the order they are declared
+    let mut inputs = 0;
+    let mut outputs = 0;
+    let mut prev_var = false;
+
+    for d in &decls {
+        // globals are grouped, everything else is separated by a blank line
+        let is_var = matches!(d, Decl::Var(_));
+        if !(is_var && prev_var) {
+            out.push('\n');
+        }
+        prev_var = is_var;
+
+        match d {
+            Decl::Struct(st) => {
+                out.push_str(&format!("struct {} {{\n", st.name));
+                for (n,t) in &st.fields {
+                    out.push_str(&format!("{INDENT}{};\n", print_decl(t, n, ctx)?));
+                }
+                out.push_str("};\n");
+            },
+            Decl::Var(v) => {
+                let location = match v.qualifier {
+                    Some(Qualifier::In)  => { inputs += 1; inputs - 1 },
+                    Some(Qualifier::Out) => { outputs += 1; outputs - 1 },
+                    _                    => 0
+                };
+                if let Some(l) = layout_location(v, location, ctx) {
+                    out.push_str(&format!("layout(location = {l}) "));
+                }
+                out.push_str(&print_var(v, ctx)?);
+                out.push_str(";\n");
+            },
+            Decl::Function(f) => {
+                let params = f.params.iter().map(|(n,t)| print_decl(t, n, ctx)).collect::<Result<Vec<_>,PrintError>>()?;
+                out.push_str(&format!("{} {}({}) {{\n", print_type(&f.ret, ctx)?, f.name, params.join(", ")));
+                print_block(&f.body, 1, ctx, &mut out)?;
+                out.push_str("}\n");
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Writes both shaders of a program to `<dir>/<name>.vert` & `<dir>/<name>.frag`
+pub fn write_program(p: &glsl::Program, dir: &Path, version: Version) -> Result<(),PrintError> {
+    for (ext, s) in [("vert", &p.vert), ("frag", &p.frag)] {
+        let path = dir.join(format!("{}.{}", p.name, ext));
+        fs::write(&path, print_shader(s, version)?).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Returns the explicit location of a shader input or output, if the version supports one
+/// GLSL 3.30 only locates vertex inputs & fragment outputs, varyings are matched by name instead
+fn layout_location(v: &glsl::VarDecl, location: usize, ctx: Ctx) -> Option<usize> {
+    match (ctx.version, v.qualifier, ctx.stage) {
+        (Version::Glsl330, Some(Qualifier::In), Stage::Vertex)    => Some(location),
+        (Version::Glsl330, Some(Qualifier::Out), Stage::Fragment) => Some(location),
+        (Version::Glsl450, Some(Qualifier::In | Qualifier::Out), _) => Some(location),
+        _ => None
+    }
+}
+
+/// Returns whether a declaration references a variable, or calls a function, of a given name
+pub fn decl_uses(d: &Decl, n: &str) -> bool {
+    match d {
+        Decl::Var(v)      => v.value.as_ref().is_some_and(|e| expr_uses(e, n)),
+        Decl::Function(f) => f.body.iter().any(|s| stmt_uses(s, n)),
+        Decl::Struct(_)   => false
+    }
+}
+
+/// Returns whether a statement references a variable, or calls a function, of a given name
+fn stmt_uses(s: &Stmt, n: &str) -> bool {
+    let block = |b: &[Stmt]| b.iter().any(|s| stmt_uses(s, n));
+    match s {
+        Stmt::Decl(v)                     => v.value.as_ref().is_some_and(|e| expr_uses(e, n)),
+        Stmt::Assign{target, value}       => expr_uses(target, n) || expr_uses(value, n),
+        Stmt::Expr(e)                     => expr_uses(e, n),
+        Stmt::If{condition, b1, b2}       => expr_uses(condition, n) || block(b1) || block(b2),
+        Stmt::For{init, cond, post, body} => stmt_uses(init, n) || expr_uses(cond, n) || stmt_uses(post, n) || block(body),
+        Stmt::While{cond, body}           => expr_uses(cond, n) || block(body),
+        Stmt::DoWhile{body, cond}         => block(body) || expr_uses(cond, n),
+        Stmt::Return(e)                   => e.as_ref().is_some_and(|e| expr_uses(e, n)),
+        Stmt::Discard                     => false,
+    }
+}
+
+/// Returns whether an expression references a variable, or calls a function, of a given name
+fn expr_uses(e: &Expr, n: &str) -> bool {
+    match &e.kind {
+        ExprKind::Var(v)                => v == n,
+        ExprKind::Call{name, args}      => name == n || args.iter().any(|a| expr_uses(a, n)),
+        ExprKind::Binary{lhs, rhs, ..}  => expr_uses(lhs, n) || expr_uses(rhs, n),
+        ExprKind::Unary{e, ..}          => expr_uses(e, n),
+        ExprKind::Index(b, i)           => expr_uses(b, n) || expr_uses(i, n),
+        ExprKind::Field(b, _)           => expr_uses(b, n),
+        _                               => false
+    }
+}
+
+/// Returns the GLSL name of a type
+fn print_type(t: &Type, ctx: Ctx) -> Result<String,PrintError> {
+    Ok(match t {
+        Type::Void      => "void".to_string(),
+        Type::Bool      => "bool".to_string(),
+        Type::Int       => "int".to_string(),
+        Type::Float     => "float".to_string(),
+        Type::Double    => match ctx.version {
+            Version::Glsl450 => "double".to_string(),
+            v                => return Err(format!("Type 'double' is not supported by {v}"))
+        },
+        Type::Vec(n)    => format!("vec{n}"),
+        Type::IVec(n)   => format!("ivec{n}"),
+        Type::BVec(n)   => format!("bvec{n}"),
+        Type::Mat(n)    => format!("mat{n}"),
+        Type::Sampler2D => "sampler2D".to_string(),
+        Type::Struct(n) => n.clone(),
+        Type::Array(t, n) => match ctx.version {
+            Version::Es100 => return Err("Array types are not supported by GLSL ES 1.00 outside of the declaration of a variable".to_string()),
+            _              => format!("{}[{n}]", print_type(t, ctx)?)
+        },
+    })
+}
+
+/// Prints the type & name of a declaration, i.e. 'float x'
+/// Arrays are sized after the name, i.e. 'float x[4]', as that is the only form every version supports
+fn print_decl(t: &Type, n: &str, ctx: Ctx) -> Result<String,PrintError> {
+    match t {
+        Type::Array(t, len) => Ok(format!("{}[{len}]", print_decl(t, n, ctx)?)),
+        t                   => Ok(format!("{} {}", print_type(t, ctx)?, n))
+    }
+}
+
+/// Returns the qualifier for a global in a given version & stage
+fn print_qualifier(q: Qualifier, ctx: Ctx) -> Result<&'static str,PrintError> {
+    match (q, ctx.version, ctx.stage) {
+        (Qualifier::Const, _, _)                            => Ok("const"),
+        (Qualifier::Uniform, _, _)                          => Ok("uniform"),
+        (Qualifier::In, Version::Es100, Stage::Vertex)      => Ok("attribute"),
+        (Qualifier::Out, Version::Es100, Stage::Vertex)     => Ok("varying"),
+        (Qualifier::In, Version::Es100, Stage::Fragment)    => Ok("varying"),
+        (Qualifier::Out, Version::Es100, Stage::Fragment)   => Err("Fragment shader outputs are not supported by GLSL ES 1.00, write to 'gl_FragColor' instead".to_string()),
+        (Qualifier::In, _, _)                               => Ok("in"),
+        (Qualifier::Out, _, _)                              => Ok("out"),
+    }
+}
+
+/// Returns the name of a built-in variable or function in a given version
+/// Names w/out a version specific equivalent are returned as is
+fn builtin_name(n: &str, version: Version) -> &str {
+    match (n, version) {
+        (_, Version::Es100)                 => n,
+        ("gl_FragColor", _)                 => FRAG_COLOR,
+        ("texture2D" | "textureCube", _)    => "texture",
+        ("texture2DProj", _)                => "textureProj",
+        ("texture2DLod", _)                 => "textureLod",
+        ("texture2DProjLod", _)             => "textureProjLod",
+        _                                   => n
+    }
+}
+
+/// Prints a variable declaration, w/out the trailing ';'
+fn print_var(v: &glsl::VarDecl, ctx: Ctx) -> Result<String,PrintError> {
+    let mut s = match v.qualifier {
+        Some(q) => format!("{} ", print_qualifier(q, ctx)?),
+        None    => String::new()
+    };
+    s.push_str(&print_decl(&v.typ, &v.name, ctx)?);
+    if let Some(e) = &v.value {
+        s.push_str(&format!(" = {}", print_expr(e, ctx.version)?));
+    }
+    Ok(s)
+}
+
+/// Prints a sequence of statements at a given depth of indentation
+fn print_block(body: &[Stmt], depth: usize, ctx: Ctx, out: &mut String) -> Result<(),PrintError> {
+    for s in body {
+        print_stmt(s, depth, ctx, out)?;
+    }
+    Ok(())
+}
+
+/// Prints a statement on its own line(s)
+fn print_stmt(s: &Stmt, depth: usize, ctx: Ctx, out: &mut String) -> Result<(),PrintError> {
+    let indent = INDENT.repeat(depth);
+    match s {
+        Stmt::If{condition, b1, b2} => {
+            out.push_str(&format!("{indent}if ({}) {{\n", print_expr(condition, ctx.version)?));
+            print_block(b1, depth + 1, ctx, out)?;
+            if !b2.is_empty() {
+                out.push_str(&format!("{indent}}} else {{\n"));
+                print_block(b2, depth + 1, ctx, out)?;
+            }
+            out.push_str(&format!("{indent}}}\n"));
+        },
+        Stmt::For{init, cond, post, body} => {
+            out.push_str(&format!("{indent}for ({}; {}; {}) {{\n", print_simple_stmt(init, ctx)?, print_expr(cond, ctx.version)?, print_loop_post(post, ctx)?));
+            print_block(body, depth + 1, ctx, out)?;
+            out.push_str(&format!("{indent}}}\n"));
+        },
+        Stmt::While{cond, body} => {
+            out.push_str(&format!("{indent}while ({}) {{\n", print_expr(cond, ctx.version)?));
+            print_block(body, depth + 1, ctx, out)?;
+            out.push_str(&format!("{indent}}}\n"));
+        },
+        Stmt::DoWhile{body, cond} => {
+            out.push_str(&format!("{indent}do {{\n"));
+            print_block(body, depth + 1, ctx, out)?;
+            out.push_str(&format!("{indent}}} while ({});\n", print_expr(cond, ctx.version)?));
+        },
+        _ => out.push_str(&format!("{indent}{};\n", print_simple_stmt(s, ctx)?))
+    }
+    Ok(())
+}
+
+/// Prints the post of a 'for' loop, stepping the loop index w/ a compound assignment where it can
+/// GLSL ES 1.00 only accepts a loop index stepped by `i += c` or `i -= c`, rather than `i = i + c`
+fn print_loop_post(s: &Stmt, ctx: Ctx) -> Result<String,PrintError> {
+    if let Stmt::Assign{target, value: Expr{kind: ExprKind::Binary{op: op @ (BinOp::Add | BinOp::Sub), lhs, rhs}, ..}} = s {
+        if **lhs == *target {
+            return Ok(format!("{} {}= {}", print_expr(target, ctx.version)?, bin_op(*op), print_expr(rhs, ctx.version)?));
+        }
+    }
+    print_simple_stmt(s, ctx)
+}
+
+/// Prints a statement that fits on a single line, w/out the trailing ';'
+/// These are the only statements that can appear in the header of a loop
+fn print_simple_stmt(s: &Stmt, ctx: Ctx) -> Result<String,PrintError> {
+    let v = ctx.version;
+    match s {
+        Stmt::Decl(d)               => print_var(d, ctx),
+        Stmt::Assign{target, value} => Ok(format!("{} = {}", print_expr(target, v)?, print_expr(value, v)?)),
+        Stmt::Expr(e)               => print_expr(e, v),
+        Stmt::Return(Some(e))       => Ok(format!("return {}", print_expr(e, v)?)),
+        Stmt::Return(None)          => Ok("return".to_string()),
+        Stmt::Discard               => Ok("discard".to_string()),
+        _                           => Err(format!("Expected a simple statement, but got '{:?}' instead", s))
+    }
+}
+
+/// Returns the GLSL symbol for a binary operator
+pub fn bin_op(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add    => "+",
+        BinOp::Sub    => "-",
+        BinOp::Mul    => "*",
+        BinOp::Div    => "/",
+        BinOp::Mod    => "%",
+        BinOp::And    => "&&",
+        BinOp::Or     => "||",
+        BinOp::Eq     => "==",
+        BinOp::Neq    => "!=",
+        BinOp::Gt     => ">",
+        BinOp::Gte    => ">=",
+        BinOp::Lt     => "<",
+        BinOp::Lte    => "<=",
+        BinOp::BitAnd => "&",
+        BinOp::BitOr  => "|",
+        BinOp::BitXor => "^",
+    }
+}
+
+/// Returns the precedence of an expression, following the GLSL spec (lower binds tighter)
+fn precedence(e: &Expr) -> u8 {
+    match &e.kind {
+        ExprKind::Unary{..}       => 3,
+        ExprKind::Binary{op, ..}  => match op {
+            BinOp::Mul | BinOp::Div | BinOp::Mod            => 4,
+            BinOp::Add | BinOp::Sub                         => 5,
+            BinOp::Lt | BinOp::Gt | BinOp::Lte | BinOp::Gte => 7,
+            BinOp::Eq | BinOp::Neq                          => 8,
+            BinOp::BitAnd                                   => 9,
+            BinOp::BitXor                                   => 10,
+            BinOp::BitOr                                    => 11,
+            BinOp::And                                      => 12,
+            BinOp::Or                                       => 14,
+        },
+        // literals, variables, calls & postfix access
+        _                         => 1
+    }
+}
+
+/// Prints an expression, wrapping it in parens when it binds looser than its context requires
+fn print_operand(e: &Expr, max: u8, version: Version) -> Result<String,PrintError> {
+    let s = print_expr(e, version)?;
+    Ok(if precedence(e) > max { format!("({s})") } else { s })
+}
+
+/// Prints an expression w/ as few parens as its precedence allows
+/// Binary operators are left associative, so only a right operand of the same precedence needs parens
+fn print_expr(e: &Expr, version: Version) -> Result<String,PrintError> {
+    Ok(match &e.kind {
+        ExprKind::Int(i)    => i.to_string(),
+        // Debug always includes a '.' or an exponent, either of which GLSL reads as a float
+        ExprKind::Float(f)  => format!("{:?}", f),
+        ExprKind::Double(d) => match version {
+            Version::Glsl450 => format!("{:?}lf", d),
+            v                => return Err(format!("Doubles are not supported by {v}"))
+        },
+        ExprKind::Bool(b)   => b.to_string(),
+        ExprKind::Var(n)    => builtin_name(n, version).to_string(),
+        ExprKind::Call{name, args} => {
+            let args = args.iter().map(|a| print_expr(a, version)).collect::<Result<Vec<_>,PrintError>>()?;
+            format!("{}({})", builtin_name(name, version), args.join(", "))
+        },
+        ExprKind::Binary{op, lhs, rhs} => {
+            // these are reserved, but not supported, in GLSL ES 1.00
+            if version == Version::Es100 && matches!(op, BinOp::Mod | BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor) {
+                return Err(format!("Operator '{}' is not supported by {version}", bin_op(*op)));
+            }
+            let p = precedence(e);
+            format!("{} {} {}", print_operand(lhs, p, version)?, bin_op(*op), print_operand(rhs, p - 1, version)?)
+        },
+        ExprKind::Unary{op, e: inner} => {
+            let op = match op {
+                UnOp::Neg => "-",
+                UnOp::Not => "!"
+            };
+            // a nested unary & a negative literal are wrapped as well, so '- -x' is never printed as the decrement '--x'
+            let negative = matches!(inner.kind, ExprKind::Int(i) if i < 0)
+                || matches!(inner.kind, ExprKind::Float(f) if f.is_sign_negative())
+                || matches!(inner.kind, ExprKind::Double(d) if d.is_sign_negative());
+            if negative {
+                format!("{}({})", op, print_expr(inner, version)?)
+            } else {
+                format!("{}{}", op, print_operand(inner, 1, version)?)
+            }
+        },
+        ExprKind::Index(base, i) => format!("{}[{}]", print_operand(base, 1, version)?, print_expr(i, version)?),
+        ExprKind::Field(base, f) => format!("{}.{}", print_operand(base, 1, version)?, f),
+    })
+}
+
+//
+//
+// PRETTY PRINTER TESTS
+//
+//
+
+/// Used during testing to parse, typecheck & compile a program
+#[cfg(test)]
+fn compile_str(prog: &str) -> Vec<glsl::Program> {
+    let (_, p) = crate::parser::program(prog).expect("Failed to parse program");
+    let (_, env) = crate::typechecker::tc_program(p.clone()).expect("Failed to typecheck program");
+    crate::compiler::compile(&p, &env).expect("Failed to compile program")
+}
+
+/// Used during testing to build a typed GLSL expr
+#[cfg(test)]
+fn gexpr(kind: ExprKind) -> Expr {
+    Expr{ kind, typ: Type::Int }
+}
+
+/// Used during testing to build a binary GLSL expr
+#[cfg(test)]
+fn gbin(op: BinOp, lhs: Expr, rhs: Expr) -> Expr {
+    gexpr(ExprKind::Binary{ op, lhs: Box::new(lhs), rhs: Box::new(rhs) })
+}
+
+/// Prints expressions w/ only the parens that are needed
+#[test]
+fn test_print_expr() {
+    let v = |n: &str| gexpr(ExprKind::Var(n.to_string()));
+    let es = Version::Es100;
+
+    assert_eq!(print_expr(&gexpr(ExprKind::Float(1.0)), es), Ok("1.0".to_string()), "Failed to print a float w/ a '.'");
+    assert_eq!(print_expr(&gexpr(ExprKind::Float(3.7)), es), Ok("3.7".to_string()));
+    assert_eq!(print_expr(&gexpr(ExprKind::Int(-2)), es), Ok("-2".to_string()));
+    assert!(print_expr(&gexpr(ExprKind::Double(1.0)), es).is_err(), "Failed to reject a double");
+    assert!(print_expr(&gbin(BinOp::Mod, v("a"), v("b")), es).is_err(), "Failed to reject a reserved operator");
+
+    // which are supported by later versions
+    assert_eq!(print_expr(&gexpr(ExprKind::Double(1.5)), Version::Glsl450), Ok("1.5lf".to_string()), "Failed to print a double");
+    assert!(print_expr(&gexpr(ExprKind::Double(1.5)), Version::Glsl330).is_err(), "Failed to reject a double in GLSL 3.30");
+    assert_eq!(print_expr(&gbin(BinOp::Mod, v("a"), v("b")), Version::Glsl330), Ok("a % b".to_string()), "Failed to print '%'");
+
+    // (a * b) + c & a * (b + c)
+    assert_eq!(print_expr(&gbin(BinOp::Add, gbin(BinOp::Mul, v("a"), v("b")), v("c")), es), Ok("a * b + c".to_string()));
+    assert_eq!(print_expr(&gbin(BinOp::Mul, v("a"), gbin(BinOp::Add, v("b"), v("c"))), es), Ok("a * (b + c)".to_string()));
+
+    // left associativity, (a - b) - c & a - (b - c)
+    assert_eq!(print_expr(&gbin(BinOp::Sub, gbin(BinOp::Sub, v("a"), v("b")), v("c")), es), Ok("a - b - c".to_string()));
+    assert_eq!(print_expr(&gbin(BinOp::Sub, v("a"), gbin(BinOp::Sub, v("b"), v("c"))), es), Ok("a - (b - c)".to_string()));
+
+    // unary & postfix operators bind tightest
+    let neg = |e: Expr| gexpr(ExprKind::Unary{ op: UnOp::Neg, e: Box::new(e) });
+    assert_eq!(print_expr(&neg(neg(v("a"))), es), Ok("-(-a)".to_string()), "Failed to avoid printing a decrement");
+    assert_eq!(print_expr(&neg(gexpr(ExprKind::Float(-1.0))), es), Ok("-(-1.0)".to_string()), "Failed to wrap a negative float");
+    assert_eq!(print_expr(&neg(gexpr(ExprKind::Int(-2))), es), Ok("-(-2)".to_string()), "Failed to wrap a negative int");
+    assert_eq!(print_expr(&neg(gbin(BinOp::Add, v("a"), v("b"))), es), Ok("-(a + b)".to_string()));
+    assert_eq!(
+        print_expr(&gexpr(ExprKind::Index(Box::new(gbin(BinOp::Add, v("a"), v("b"))), Box::new(gexpr(ExprKind::Int(0))))), es),
+        Ok("(a + b)[0]".to_string())
+    );
+}
+
+/// Prints whole shaders w/ the qualifiers of GLSL ES 1.00
+#[test]
+fn test_print_shader() {
+    let progs = compile_str("\
+uniform Vec4 uColor
+let clampTo : (Float, Float) -> Float = (x : Float, m : Float) {
+  if x > m {
+    m
+  } else {
+    x
+  }
+}
+vert v : (Vec3 aPos) -> (Float vT) = {
+  set gl_Position uColor
+  out vT clampTo(1.5f, 1.0f)
+}
+frag f : (Float vT) -> () = {
+  set gl_FragColor uColor
+}
+p : Prog = mkProg v f
+");
+    assert_eq!(print_shader(&progs[0].vert, Version::Es100), Ok("\
+precision mediump float;
+
+uniform vec4 uColor;
+attribute vec3 aPos;
+varying float vT;
+
+float clampTo(float x, float m) {
+    if (x > m) {
+        return m;
+    } else {
+        return x;
+    }
+}
+
+void main() {
+    gl_Position = uColor;
+    vT = clampTo(1.5, 1.0);
+}
+".to_string()), "Failed to print vertex shader");
+    assert_eq!(print_shader(&progs[0].frag, Version::Es100), Ok("\
+precision mediump float;
+
+uniform vec4 uColor;
+varying float vT;
+
+void main() {
+    gl_FragColor = uColor;
+}
+".to_string()), "Failed to print fragment shader");
+
+    // user declared fragment outputs cannot be expressed
+    let progs = compile_str("\
+vert v : () -> () = {
+  1
+}
+frag f : () -> (Vec4 color) = {
+  1
+}
+p : Prog = mkProg v f
+");
+    assert!(print_shader(&progs[0].frag, Version::Es100).is_err(), "Failed to reject a fragment output");
+}
+
+/// Prints shaders for the core profiles, w/ in/out, locations & a declared fragment output
+#[test]
+fn test_print_core() {
+    let progs = compile_str("\
+uniform Vec4 uColor
+vert v : (Vec3 aPos, Vec4 aCol) -> (Vec4 vCol, Float vT) = {
+  set gl_Position uColor
+  out vCol aCol
+  out vT 1.0f
+}
+frag f : (Vec4 vCol, Float vT) -> (Vec4 normal) = {
+  set gl_FragColor vCol
+  out normal uColor
+}
+p : Prog = mkProg v f
+");
+    // 3.30 only locates vertex inputs & fragment outputs
+    assert_eq!(print_shader(&progs[0].vert, Version::Glsl330), Ok("\
+#version 330 core
+
+uniform vec4 uColor;
+layout(location = 0) in vec3 aPos;
+layout(location = 1) in vec4 aCol;
+out vec4 vCol;
+out float vT;
+
+void main() {
+    gl_Position = uColor;
+    vCol = aCol;
+    vT = 1.0;
+}
+".to_string()), "Failed to print GLSL 3.30 vertex shader");
+    assert_eq!(print_shader(&progs[0].frag, Version::Glsl330), Ok("\
+#version 330 core
+
+uniform vec4 uColor;
+in vec4 vCol;
+in float vT;
+layout(location = 0) out vec4 fdssl_FragColor;
+layout(location = 1) out vec4 normal;
+
+void main() {
+    fdssl_FragColor = vCol;
+    normal = uColor;
+}
+".to_string()), "Failed to print GLSL 3.30 fragment shader");
+
+    // 4.50 locates every input & output, so varyings match up by location
+    assert_eq!(print_shader(&progs[0].vert, Version::Glsl450), Ok("\
+#version 450
+
+uniform vec4 uColor;
+layout(location = 0) in vec3 aPos;
+layout(location = 1) in vec4 aCol;
+layout(location = 0) out vec4 vCol;
+layout(location = 1) out float vT;
+
+void main() {
+    gl_Position = uColor;
+    vCol = aCol;
+    vT = 1.0;
+}
+".to_string()), "Failed to print GLSL 4.50 vertex shader");
+    assert_eq!(print_shader(&progs[0].frag, Version::Glsl450), Ok("\
+#version 450
+
+uniform vec4 uColor;
+layout(location = 0) in vec4 vCol;
+layout(location = 1) in float vT;
+layout(location = 0) out vec4 fdssl_FragColor;
+layout(location = 1) out vec4 normal;
+
+void main() {
+    fdssl_FragColor = vCol;
+    normal = uColor;
+}
+".to_string()), "Failed to print GLSL 4.50 fragment shader");
+
+    // no output is declared for a fragment shader that never writes 'gl_FragColor'
+    let progs = compile_str("\
+vert v : () -> () = {
+  1
+}
+frag f : () -> () = {
+  1
+}
+p : Prog = mkProg v f
+");
+    assert!(!print_shader(&progs[0].frag, Version::Glsl330).unwrap().contains(FRAG_COLOR), "Failed to skip the unused fragment output");
+}
+
+/// Prints each form of loop, w/ its body indented
+#[test]
+fn test_print_loops() {
+    let progs = compile_str("\
+uniform Vec4 uColor
+vert v : () -> (Float vT) = {
+  mut t : Float = 0.0f
+  for (mut i : Int = 0, i < 4, i += 1) {
+    t += 1.0f
+  }
+  while t > 1.0f {
+    t -= 1.0f
+  }
+  do {
+    t *= 2.0f
+  } while t < 1.0f
+  out vT t
+}
+frag f : (Float vT) -> () = {
+  set gl_FragColor uColor
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert, Version::Glsl330).unwrap();
+    assert!(vert.contains("\
+    for (int i = 0; i < 4; i += 1) {
+        t = t + 1.0;
+    }
+    while (t > 1.0) {
+        t = t - 1.0;
+    }
+    do {
+        t = t * 2.0;
+    } while (t < 1.0);
+"), "Failed to print loops, got:\n{}", vert);
+}
+
+/// Tests that arrays are sized after their names in declarations, & that vectors & tuples are indexed
+#[test]
+fn test_print_index() {
+    let progs = compile_str("\
+uniform Float[4] uWeights
+uniform Vec4 uColor
+let weight : (Float[4], Int) -> Float = (w : Float[4], i : Int) {
+  w[i]
+}
+vert v : (Vec3 aVertPos) -> (Float vT) = {
+  let t : (Float, Int) = (aVertPos[2], 1)
+  out vT weight(uWeights, t[1]) * t[0]
+}
+frag f : (Float vT) -> () = {
+  set gl_FragColor uColor
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert, Version::Es100).unwrap();
+    assert!(vert.contains("uniform float uWeights[4];\n"), "Failed to print a uniform array, got:\n{}", vert);
+    assert!(vert.contains("float weight(float w[4], int i) {\n"), "Failed to print an array parameter, got:\n{}", vert);
+    assert!(vert.contains("Tuple0 t = Tuple0(aVertPos[2], 1);\n"), "Failed to print an indexed vector, got:\n{}", vert);
+    assert!(vert.contains("vT = weight(uWeights, t._1) * t._0;\n"), "Failed to print indexed tuples, got:\n{}", vert);
+}
+
+/// Tests that named tuples are built w/ their structs, & that accesses chain on any expr
+#[test]
+fn test_print_named_access() {
+    let progs = compile_str("\
+uniform Vec4 uColor
+let light : Float -> (pos: (x: Float, y: Float), w: Float) = (w : Float) {
+  (pos: (x: 1.0f, y: 2.0f), w: w)
+}
+vert v : (Vec3 aVertPos) -> (Float vT) = {
+  out vT light(aVertPos[0]).pos.y + (light(1.0f).w, 2)[0]
+}
+frag f : (Float vT) -> () = {
+  set gl_FragColor uColor
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert, Version::Glsl330).unwrap();
+    assert!(vert.contains("return Tuple1(Tuple0(1.0, 2.0), w);\n"), "Failed to build nested named tuples, got:\n{}", vert);
+    assert!(vert.contains("vT = light(aVertPos[0]).pos.y + Tuple2(light(1.0).w, 2)._0;\n"), "Failed to print chained accesses, got:\n{}", vert);
+}
+
+/// Swizzles are printed as fields of their vector, whether they are read or assigned to
+#[test]
+fn test_print_swizzles() {
+    let progs = compile_str("\
+vert v : (Vec3 aPos) -> (Vec2 vUV) = {
+  mut p : Vec4 = vec4(aPos, 1.0f)
+  set p.xy (p.yx * 2.0f)
+  p.z += 1.0f
+  set gl_Position p
+  out vUV aPos.st
+}
+frag f : (Vec2 vUV) -> () = {
+  set gl_FragColor vec4(vUV.rg, 0.0f, 1.0f)
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert, Version::Es100).unwrap();
+    assert!(vert.contains("    p.xy = p.yx * 2.0;\n    p.z = p.z + 1.0;\n"), "Failed to print assignments to swizzles, got:\n{}", vert);
+    assert!(vert.contains("vUV = aPos.st;\n"), "Failed to print a swizzle, got:\n{}", vert);
+}
+
+/// Built-in functions are called as they are, except for texture lookups, which are renamed after GLSL ES 1.00
+#[test]
+fn test_print_builtins() {
+    let progs = compile_str("\
+uniform sampler2D uTex
+uniform Vec3 uLight
+vert v : (Vec3 aPos, Vec3 aNormal) -> (Float vShade) = {
+  set gl_Position vec4(aPos, 1.0f)
+  out vShade clamp(dot(normalize(aNormal), uLight), 0.0f, 1.0f)
+}
+frag f : (Float vShade) -> () = {
+  set gl_FragColor mix(texture2D(uTex, vec2(vShade)), vec4(1.0f), 0.5f)
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert, Version::Es100).unwrap();
+    assert!(vert.contains("vShade = clamp(dot(normalize(aNormal), uLight), 0.0, 1.0);\n"), "Failed to print built-in calls, got:\n{}", vert);
+    let frag = print_shader(&progs[0].frag, Version::Es100).unwrap();
+    assert!(frag.contains("mix(texture2D(uTex, vec2(vShade)), vec4(1.0), 0.5)"), "Failed to print a texture lookup, got:\n{}", frag);
+    let frag = print_shader(&progs[0].frag, Version::Glsl330).unwrap();
+    assert!(frag.contains("mix(texture(uTex, vec2(vShade)), vec4(1.0), 0.5)"), "Failed to rename a texture lookup, got:\n{}", frag);
+}
+
+/// Built-in variables of each stage are printed as they are, as is a discard
+#[test]
+fn test_print_stages() {
+    let progs = compile_str("\
+uniform sampler2D uTex
+vert v : (Vec2 aUV) -> (Vec2 vUV) = {
+  set gl_Position vec4(aUV, 0.0f, 1.0f)
+  set gl_PointSize 4.0f
+  out vUV aUV
+}
+frag f : (Vec2 vUV) -> () = {
+  if gl_FrontFacing { discard } else { set gl_FragColor texture2D(uTex, gl_FragCoord.xy * vUV) }
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert, Version::Es100).unwrap();
+    assert!(vert.contains("gl_PointSize = 4.0;\n"), "Failed to print a write to 'gl_PointSize', got:\n{}", vert);
+    let frag = print_shader(&progs[0].frag, Version::Es100).unwrap();
+    assert!(frag.contains("if (gl_FrontFacing) {\n        discard;\n    } else {\n"), "Failed to print a discard, got:\n{}", frag);
+    assert!(frag.contains("gl_FragColor = texture2D(uTex, gl_FragCoord.xy * vUV);\n"), "Failed to print 'gl_FragCoord', got:\n{}", frag);
+}
+
+/// Derivatives are an extension of GLSL ES 1.00, which is enabled by shaders that call them, but are core in later versions
+#[test]
+fn test_print_derivatives() {
+    let progs = compile_str("\
+let edge : Float -> Float = (x : Float) {
+  fwidth(x)
+}
+vert v : (Vec2 aUV) -> (Vec2 vUV) = {
+  set gl_Position vec4(aUV, 0.0f, 1.0f)
+  out vUV aUV
+}
+frag f : (Vec2 vUV) -> () = {
+  set gl_FragColor vec4(dFdx(vUV.x), dFdy(vUV.y), edge(vUV.x), 1.0f)
+}
+p : Prog = mkProg v f
+");
+    assert_eq!(print_shader(&progs[0].frag, Version::Es100), Ok("\
+#extension GL_OES_standard_derivatives : enable
+precision mediump float;
+
+varying vec2 vUV;
+
+float edge(float x) {
+    return fwidth(x);
+}
+
+void main() {
+    gl_FragColor = vec4(dFdx(vUV.x), dFdy(vUV.y), edge(vUV.x), 1.0);
+}
+".to_string()), "Failed to enable derivatives");
+    let vert = print_shader(&progs[0].vert, Version::Es100).unwrap();
+    assert!(!vert.contains("#extension"), "Enabled derivatives in a shader that does not use them, got:\n{}", vert);
+    let frag = print_shader(&progs[0].frag, Version::Glsl330).unwrap();
+    assert!(!frag.contains("#extension"), "Enabled derivatives in GLSL 3.30, got:\n{}", frag);
+}
+
+/// Every overload of a function is printed, as GLSL resolves calls of them itself
+#[test]
+fn test_print_overloads() {
+    let progs = compile_str("\
+let scale : Float -> Float = (x : Float) { x * 2.0f }
+let scale : Vec4 -> Vec4 = (v : Vec4) { v * 2.0f }
+vert v : (Vec4 aPos) -> () = {
+  set gl_Position scale(aPos) * scale(0.5f)
+}
+frag f : () -> () = {
+  set gl_FragColor vec4(1.0f)
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert, Version::Es100).unwrap();
+    assert!(vert.contains("float scale(float x) {\n"), "Failed to print the first overload, got:\n{}", vert);
+    assert!(vert.contains("vec4 scale(vec4 v) {\n"), "Failed to print the second overload, got:\n{}", vert);
+    assert!(vert.contains("gl_Position = scale(aPos) * scale(0.5);\n"), "Failed to print calls of overloads, got:\n{}", vert);
+}
+
+/// Compares the shaders printed for the examples against the snapshots in examples/<version>
+/// Set FDSSL_UPDATE_SNAPSHOTS to rewrite the snapshots instead
+#[test]
+fn test_print_examples() {
+    for (version, dir) in [(Version::Es100, "examples/es100"), (Version::Glsl330, "examples/glsl330"), (Version::Glsl450, "examples/glsl450")] {
+        let dir = Path::new(dir);
+        // the composed vertex shader of shaderCompExampleProg sets gl_Position twice, as v2 . v1 runs both bodies
+        for f in ["e0", "e2", "e3", "e4", "e5", "e6", "shaderCompExampleProg"] {
+            let src = fs::read_to_string(format!("examples/{f}.fdssl")).expect("Failed to read example");
+            for p in compile_str(&src) {
+                if std::env::var("FDSSL_UPDATE_SNAPSHOTS").is_ok() {
+                    fs::create_dir_all(dir).expect("Failed to create snapshot directory");
+                    write_program(&p, dir, version).expect("Failed to write snapshots");
+                }
+                for (ext, s) in [("vert", &p.vert), ("frag", &p.frag)] {
+                    let expected = fs::read_to_string(dir.join(format!("{}.{}", p.name, ext))).expect("Failed to read snapshot");
+                    assert_eq!(print_shader(s, version), Ok(expected), "Shader '{}.{}' does not match its {} snapshot", p.name, ext, version);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1732 @@
+/*
+
+Emitter from the GLSL AST to SPIR-V binary modules, for Vulkan
+
+Modules are written word by word, w/out an external compiler, and target SPIR-V 1.0 w/ the
+Shader capability & the GLSL.std.450 extended instructions. Each shader becomes its own module
+w/ a single entry point, 'main'. Every expression in the GLSL AST carries its type, so each
+operation can be lowered to the instruction for its operand types.
+
+Interfaces are laid out as in the GLSL 4.50 printer:
+
+- Uniforms are members of a 'Uniforms' block, w/ std140 offsets, at descriptor set 0 & binding 0.
+- Inputs & outputs are located in the order they are declared.
+- 'gl_Position' is an output decorated as the Position built-in, & 'gl_FragColor' an output located at 0, before the others.
+- Other built-in variables, such as 'gl_FragCoord', are only declared by shaders that use them, decorated as their built-ins.
+
+Local variables are function storage, declared at the start of their function, & control flow is structured
+w/ merge blocks. A disassembler prints modules in the style of spirv-dis, so tests can assert on their contents.
+
+*/
+
+use crate::glsl_syntax as glsl;
+use crate::pretty::decl_uses;
+use crate::syntax::Stage;
+
+use glsl::{BinOp, Decl, Expr, ExprKind, Qualifier, Stmt, Type, UnOp};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+pub type Word = u32;
+type Id = Word;
+
+// Simple emitter error, for constructs that cannot be expressed in SPIR-V
+pub type SpirvError = String;
+
+const MAGIC: Word = 0x0723_0203;
+const VERSION: Word = 0x0001_0000;
+
+// Storage classes
+const INPUT: Word = 1;
+const UNIFORM: Word = 2;
+const OUTPUT: Word = 3;
+const PRIVATE: Word = 6;
+const FUNCTION: Word = 7;
+
+// Decorations
+const BLOCK: Word = 2;
+const COL_MAJOR: Word = 5;
+const MATRIX_STRIDE: Word = 7;
+const BUILT_IN: Word = 11;
+const FLAT: Word = 14;
+const LOCATION: Word = 30;
+const BINDING: Word = 33;
+const DESCRIPTOR_SET: Word = 34;
+const OFFSET: Word = 35;
+
+// Built-in variables
+const POSITION: Word = 0;
+const POINT_SIZE: Word = 1;
+const FRAG_COORD: Word = 15;
+const POINT_COORD: Word = 16;
+const FRONT_FACING: Word = 17;
+
+// Built-in variables other than 'gl_Position', w/ the stage they are used in, their storage class & type
+const BUILT_IN_VARS: &[(&str, Word, Stage, Word, Type)] = &[
+    ("gl_PointSize", POINT_SIZE, Stage::Vertex, OUTPUT, Type::Float),
+    ("gl_FragCoord", FRAG_COORD, Stage::Fragment, INPUT, Type::Vec(4)),
+    ("gl_PointCoord", POINT_COORD, Stage::Fragment, INPUT, Type::Vec(2)),
+    ("gl_FrontFacing", FRONT_FACING, Stage::Fragment, INPUT, Type::Bool),
+];
+
+/// Kinds of operands, used to disassemble each instruction
+#[derive(Clone, Copy)]
+enum Kind {
+    // result type & result id
+    Ty,
+    Res,
+    Id,
+    // all remaining operands are ids
+    Ids,
+    Lit,
+    // all remaining operands are literals
+    Lits,
+    Str,
+    Cap,
+    Addr,
+    Mem,
+    Model,
+    Mode,
+    Storage,
+    // decoration, followed by its literal operands
+    Deco,
+    // literal value of a constant, read according to the constant's type
+    Value,
+    // instruction of the GLSL.std.450 set
+    Ext,
+    // function, selection or loop control
+    Mask,
+}
+
+// Declares the opcodes that are emitted, w/ the operands of each for the disassembler
+macro_rules! ops {
+    ( $( $name:ident = $code:expr => [ $($kind:ident),* ] ),* $(,)? ) => {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Op {
+            $( $name = $code ),*
+        }
+
+        impl Op {
+            /// Returns the opcode w/ a given number, if it is known
+            fn from_code(c: Word) -> Option<Op> {
+                match c {
+                    $( $code => Some(Op::$name), )*
+                    _ => None
+                }
+            }
+
+            /// Returns the kinds of the operands of this opcode
+            fn operands(self) -> &'static [Kind] {
+                match self {
+                    $( Op::$name => &[ $(Kind::$kind),* ], )*
+                }
+            }
+        }
+    };
+}
+
+ops! {
+    Name = 5 => [Id, Str],
+    MemberName = 6 => [Id, Lit, Str],
+    ExtInstImport = 11 => [Res, Str],
+    ExtInst = 12 => [Ty, Res, Id, Ext, Ids],
+    MemoryModel = 14 => [Addr, Mem],
+    EntryPoint = 15 => [Model, Id, Str, Ids],
+    ExecutionMode = 16 => [Id, Mode, Lits],
+    Capability = 17 => [Cap],
+    TypeVoid = 19 => [Res],
+    TypeBool = 20 => [Res],
+    TypeInt = 21 => [Res, Lit, Lit],
+    TypeFloat = 22 => [Res, Lit],
+    TypeVector = 23 => [Res, Id, Lit],
+    TypeMatrix = 24 => [Res, Id, Lit],
+    TypeStruct = 30 => [Res, Ids],
+    TypePointer = 32 => [Res, Storage, Id],
+    TypeFunction = 33 => [Res, Id, Ids],
+    ConstantTrue = 41 => [Ty, Res],
+    ConstantFalse = 42 => [Ty, Res],
+    Constant = 43 => [Ty, Res, Value],
+    Function = 54 => [Ty, Res, Mask, Id],
+    FunctionParameter = 55 => [Ty, Res],
+    FunctionEnd = 56 => [],
+    FunctionCall = 57 => [Ty, Res, Id, Ids],
+    Variable = 59 => [Ty, Res, Storage, Ids],
+    Load = 61 => [Ty, Res, Id],
+    Store = 62 => [Id, Id],
+    AccessChain = 65 => [Ty, Res, Id, Ids],
+    Decorate = 71 => [Id, Deco],
+    MemberDecorate = 72 => [Id, Lit, Deco],
+    VectorExtractDynamic = 77 => [Ty, Res, Id, Id],
+    VectorShuffle = 79 => [Ty, Res, Id, Id, Lits],
+    CompositeConstruct = 80 => [Ty, Res, Ids],
+    CompositeExtract = 81 => [Ty, Res, Id, Lits],
+    ConvertFToS = 110 => [Ty, Res, Id],
+    ConvertSToF = 111 => [Ty, Res, Id],
+    SNegate = 126 => [Ty, Res, Id],
+    FNegate = 127 => [Ty, Res, Id],
+    IAdd = 128 => [Ty, Res, Id, Id],
+    FAdd = 129 => [Ty, Res, Id, Id],
+    ISub = 130 => [Ty, Res, Id, Id],
+    FSub = 131 => [Ty, Res, Id, Id],
+    IMul = 132 => [Ty, Res, Id, Id],
+    FMul = 133 => [Ty, Res, Id, Id],
+    SDiv = 135 => [Ty, Res, Id, Id],
+    FDiv = 136 => [Ty, Res, Id, Id],
+    SMod = 139 => [Ty, Res, Id, Id],
+    FMod = 141 => [Ty, Res, Id, Id],
+    VectorTimesScalar = 142 => [Ty, Res, Id, Id],
+    MatrixTimesScalar = 143 => [Ty, Res, Id, Id],
+    VectorTimesMatrix = 144 => [Ty, Res, Id, Id],
+    MatrixTimesVector = 145 => [Ty, Res, Id, Id],
+    MatrixTimesMatrix = 146 => [Ty, Res, Id, Id],
+    Dot = 148 => [Ty, Res, Id, Id],
+    Any = 154 => [Ty, Res, Id],
+    All = 155 => [Ty, Res, Id],
+    LogicalEqual = 164 => [Ty, Res, Id, Id],
+    LogicalNotEqual = 165 => [Ty, Res, Id, Id],
+    LogicalOr = 166 => [Ty, Res, Id, Id],
+    LogicalAnd = 167 => [Ty, Res, Id, Id],
+    LogicalNot = 168 => [Ty, Res, Id],
+    IEqual = 170 => [Ty, Res, Id, Id],
+    INotEqual = 171 => [Ty, Res, Id, Id],
+    SGreaterThan = 173 => [Ty, Res, Id, Id],
+    SGreaterThanEqual = 175 => [Ty, Res, Id, Id],
+    SLessThan = 177 => [Ty, Res, Id, Id],
+    SLessThanEqual = 179 => [Ty, Res, Id, Id],
+    FOrdEqual = 180 => [Ty, Res, Id, Id],
+    FOrdNotEqual = 182 => [Ty, Res, Id, Id],
+    FOrdLessThan = 184 => [Ty, Res, Id, Id],
+    FOrdGreaterThan = 186 => [Ty, Res, Id, Id],
+    FOrdLessThanEqual = 188 => [Ty, Res, Id, Id],
+    FOrdGreaterThanEqual = 190 => [Ty, Res, Id, Id],
+    BitwiseOr = 197 => [Ty, Res, Id, Id],
+    BitwiseXor = 198 => [Ty, Res, Id, Id],
+    BitwiseAnd = 199 => [Ty, Res, Id, Id],
+    DPdx = 207 => [Ty, Res, Id],
+    DPdy = 208 => [Ty, Res, Id],
+    Phi = 245 => [Ty, Res, Ids],
+    LoopMerge = 246 => [Id, Id, Mask],
+    SelectionMerge = 247 => [Id, Mask],
+    Label = 248 => [Res],
+    Branch = 249 => [Id],
+    BranchConditional = 250 => [Id, Id, Id],
+    Kill = 252 => [],
+    Return = 253 => [],
+    ReturnValue = 254 => [Id],
+    Unreachable = 255 => [],
+}
+
+// Instructions of the GLSL.std.450 set that are used, by number
+const GLSL_STD_450: &[(Word, &str)] = &[
+    (4, "FAbs"), (5, "SAbs"), (6, "FSign"), (7, "SSign"), (8, "Floor"), (9, "Ceil"), (10, "Fract"),
+    (11, "Radians"), (12, "Degrees"), (13, "Sin"), (14, "Cos"), (15, "Tan"), (16, "Asin"), (17, "Acos"),
+    (18, "Atan"), (25, "Atan2"), (26, "Pow"), (27, "Exp"), (28, "Log"), (29, "Exp2"), (30, "Log2"),
+    (31, "Sqrt"), (32, "InverseSqrt"), (37, "FMin"), (39, "SMin"), (40, "FMax"), (42, "SMax"),
+    (43, "FClamp"), (45, "SClamp"), (46, "FMix"), (48, "Step"), (49, "SmoothStep"), (66, "Length"),
+    (67, "Distance"), (68, "Cross"), (69, "Normalize"), (70, "FaceForward"), (71, "Reflect"), (72, "Refract"),
+];
+
+/// Returns the GLSL.std.450 instruction for a GLSL built-in, w/ integer variants for integer operands
+/// Also returns whether the built-in is component-wise, so scalar operands are splat to the result's size
+fn glsl_std_450(n: &str, int: bool, args: usize) -> Option<(Word, bool)> {
+    Some(match (n, int) {
+        ("abs", false)          => (4, true),
+        ("abs", true)           => (5, true),
+        ("sign", false)         => (6, true),
+        ("sign", true)          => (7, true),
+        ("floor", _)            => (8, true),
+        ("ceil", _)             => (9, true),
+        ("fract", _)            => (10, true),
+        ("radians", _)          => (11, true),
+        ("degrees", _)          => (12, true),
+        ("sin", _)              => (13, true),
+        ("cos", _)              => (14, true),
+        ("tan", _)              => (15, true),
+        ("asin", _)             => (16, true),
+        ("acos", _)             => (17, true),
+        ("atan", _) if args < 2 => (18, true),
+        ("atan", _)             => (25, true),
+        ("pow", _)              => (26, true),
+        ("exp", _)              => (27, true),
+        ("log", _)              => (28, true),
+        ("exp2", _)             => (29, true),
+        ("log2", _)             => (30, true),
+        ("sqrt", _)             => (31, true),
+        ("inversesqrt", _)      => (32, true),
+        ("min", false)          => (37, true),
+        ("min", true)           => (39, true),
+        ("max", false)          => (40, true),
+        ("max", true)           => (42, true),
+        ("clamp", false)        => (43, true),
+        ("clamp", true)         => (45, true),
+        ("mix", _)              => (46, true),
+        ("step", _)             => (48, true),
+        ("smoothstep", _)       => (49, true),
+        ("length", _)           => (66, false),
+        ("distance", _)         => (67, false),
+        ("cross", _)            => (68, false),
+        ("normalize", _)        => (69, false),
+        ("faceforward", _)      => (70, false),
+        ("reflect", _)          => (71, false),
+        ("refract", _)          => (72, false),
+        _                       => return None
+    })
+}
+
+/// Types, pointer types & function types, as keys for the ids they are declared w/
+#[derive(PartialEq, Eq, Hash)]
+enum TypeKey {
+    Value(Type),
+    Pointer(Word, Id),
+    Function(Id, Vec<Id>),
+}
+
+/// What a name refers to in a shader
+#[derive(Clone)]
+enum Binding {
+    // variable of a given storage class
+    Var(Id, Word),
+    // member of the uniform block variable
+    Uniform(Id, Word),
+    // constant value
+    Value(Id),
+}
+
+/// State of the function being emitted
+struct FnState {
+    // variables, which must be declared at the start of the first block
+    vars: Vec<Word>,
+    code: Vec<Word>,
+    // label of the current block, & whether it has been terminated
+    label: Id,
+    terminated: bool,
+    scopes: Vec<HashMap<String, Binding>>,
+}
+
+/// State of the module being emitted, w/ each of its sections in order
+struct Emitter {
+    bound: Id,
+    header: Vec<Word>,
+    entry: Vec<Word>,
+    debug: Vec<Word>,
+    annotations: Vec<Word>,
+    // types, constants & global variables
+    globals: Vec<Word>,
+    code: Vec<Word>,
+    types: HashMap<TypeKey, Id>,
+    constants: HashMap<(Id, Vec<Word>), Id>,
+    structs: HashMap<String, Vec<(String, Type)>>,
+    names: HashMap<String, Binding>,
+    // ids & types of every function, so they may be called before they are emitted
+    // functions are keyed by their params as well as their names, as overloads share a name
+    functions: HashMap<(String, Vec<Type>), (Id, Id)>,
+    glsl_ext: Id,
+}
+
+/// Appends an instruction to a section
+fn inst(out: &mut Vec<Word>, op: Op, operands: &[Word]) {
+    out.push(((operands.len() as Word + 1) << 16) | op as Word);
+    out.extend_from_slice(operands);
+}
+
+/// Encodes a string as nul terminated UTF-8, packed little endian into words
+fn encode_str(s: &str) -> Vec<Word> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.push(0);
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+    bytes.chunks(4).map(|c| Word::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+}
+
+/// Returns the scalar type of a scalar or vector type
+fn scalar_of(t: &Type) -> Type {
+    match t {
+        Type::Vec(_) | Type::Mat(_) => Type::Float,
+        Type::IVec(_)               => Type::Int,
+        Type::BVec(_)               => Type::Bool,
+        t                           => t.clone()
+    }
+}
+
+/// Returns the number of components of a vector type, or None for anything else
+fn vec_size(t: &Type) -> Option<u8> {
+    match t {
+        Type::Vec(n) | Type::IVec(n) | Type::BVec(n) => Some(*n),
+        _                                            => None
+    }
+}
+
+/// Returns the component indices of a swizzle, such as 'xyz' or 'rgba'
+fn swizzle(s: &str) -> Option<Vec<Word>> {
+    s.chars().map(|c| match c {
+        'x' | 'r' | 's' => Some(0),
+        'y' | 'g' | 't' => Some(1),
+        'z' | 'b' | 'p' => Some(2),
+        'w' | 'a' | 'q' => Some(3),
+        _               => None
+    }).collect()
+}
+
+/// Returns the name & param types of a function, which tell it apart from any overloads of it
+fn signature(f: &glsl::Function) -> (String, Vec<Type>) {
+    (f.name.clone(), f.params.iter().map(|(_,t)| t.clone()).collect())
+}
+
+/// Returns whether an expression calls any function, which may have side effects
+fn has_call(e: &Expr) -> bool {
+    match &e.kind {
+        ExprKind::Call{..}              => true,
+        ExprKind::Binary{lhs, rhs, ..}  => has_call(lhs) || has_call(rhs),
+        ExprKind::Unary{e, ..}          => has_call(e),
+        ExprKind::Index(b, i)           => has_call(b) || has_call(i),
+        ExprKind::Field(b, _)           => has_call(b),
+        _                               => false
+    }
+}
+
+/// Returns the std140 alignment & size of a uniform block member
+fn std140(t: &Type) -> Result<(Word, Word),SpirvError> {
+    match t {
+        Type::Float | Type::Int     => Ok((4, 4)),
+        Type::Vec(2) | Type::IVec(2) => Ok((8, 8)),
+        Type::Vec(3) | Type::IVec(3) => Ok((16, 12)),
+        Type::Vec(_) | Type::IVec(_) => Ok((16, 16)),
+        // columns are vec4 aligned, so every column takes 16 bytes
+        Type::Mat(n)                => Ok((16, 16 * *n as Word)),
+        _                           => Err(format!("Type '{:?}' cannot be a member of a SPIR-V uniform block", t))
+    }
+}
+
+/// Emits a shader as a SPIR-V module
+pub fn emit_shader(s: &glsl::Shader) -> Result<Vec<Word>,SpirvError> {
+    let mut e = Emitter{
+        bound: 1,
+        header: vec![],
+        entry: vec![],
+        debug: vec![],
+        annotations: vec![],
+        globals: vec![],
+        code: vec![],
+        types: HashMap::new(),
+        constants: HashMap::new(),
+        structs: HashMap::new(),
+        names: HashMap::new(),
+        functions: HashMap::new(),
+        glsl_ext: 0,
+    };
+
+    inst(&mut e.header, Op::Capability, &[1]);
+    e.glsl_ext = e.id();
+    let mut ext = vec![e.glsl_ext];
+    ext.extend(encode_str("GLSL.std.450"));
+    inst(&mut e.header, Op::ExtInstImport, &ext);
+    inst(&mut e.header, Op::MemoryModel, &[0, 1]);
+
+    for d in &s.decls {
+        if let Decl::Struct(st) = d {
+            e.structs.insert(st.name.clone(), st.fields.clone());
+        }
+    }
+
+    let interface = e.interface(s)?;
+
+    // globals that are not part of the interface
+    for d in &s.decls {
+        if let Decl::Var(v) = d {
+            match v.qualifier {
+                Some(Qualifier::Const) => {
+                    let value = v.value.as_ref().and_then(|c| e.literal(c).transpose()).transpose()?;
+                    let c = value.ok_or(format!("Constant '{}' must be a literal in SPIR-V", v.name))?;
+                    e.names.insert(v.name.clone(), Binding::Value(c));
+                },
+                None => {
+                    let init = v.value.as_ref().map(|c| e.literal(c)).transpose()?.flatten();
+                    if v.value.is_some() && init.is_none() {
+                        return Err(format!("Global '{}' must be initialized w/ a literal in SPIR-V", v.name));
+                    }
+                    let var = e.variable(PRIVATE, &v.typ, &v.name, init)?;
+                    e.names.insert(v.name.clone(), Binding::Var(var, PRIVATE));
+                },
+                _ => ()
+            }
+        }
+    }
+
+    // functions are declared up front, so they may be called in any order
+    let mut main = 0;
+    for d in &s.decls {
+        if let Decl::Function(f) = d {
+            let ret = e.type_id(&f.ret)?;
+            let params = f.params.iter().map(|(_,t)| e.type_id(t)).collect::<Result<Vec<_>,SpirvError>>()?;
+            let ft = e.fn_type(ret, params);
+            let id = e.id();
+            e.name(id, &f.name);
+            e.functions.insert(signature(f), (id, ft));
+            if f.name == "main" {
+                main = id;
+            }
+        }
+    }
+    for d in &s.decls {
+        if let Decl::Function(f) = d {
+            e.function(f)?;
+        }
+    }
+
+    let model = match s.stage {
+        Stage::Vertex   => 0,
+        Stage::Fragment => 4
+    };
+    let mut ep = vec![model, main];
+    ep.extend(encode_str("main"));
+    ep.extend(interface);
+    inst(&mut e.entry, Op::EntryPoint, &ep);
+    if s.stage == Stage::Fragment {
+        // origin upper left is required by Vulkan
+        inst(&mut e.entry, Op::ExecutionMode, &[main, 7]);
+    }
+
+    let mut words = vec![MAGIC, VERSION, 0, e.bound, 0];
+    for section in [e.header, e.entry, e.debug, e.annotations, e.globals, e.code] {
+        words.extend(section);
+    }
+    Ok(words)
+}
+
+/// Writes both shaders of a program to `<dir>/<name>.vert.spv` & `<dir>/<name>.frag.spv`
+pub fn write_program(p: &glsl::Program, dir: &Path) -> Result<(),SpirvError> {
+    for (ext, s) in [("vert", &p.vert), ("frag", &p.frag)] {
+        let path = dir.join(format!("{}.{}.spv", p.name, ext));
+        let bytes : Vec<u8> = emit_shader(s)?.iter().flat_map(|w| w.to_le_bytes()).collect();
+        fs::write(&path, bytes).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+impl Emitter {
+
+    /// Returns a fresh id
+    fn id(&mut self) -> Id {
+        self.bound += 1;
+        self.bound - 1
+    }
+
+    /// Names an id for debugging
+    fn name(&mut self, id: Id, n: &str) {
+        let mut ops = vec![id];
+        ops.extend(encode_str(n));
+        inst(&mut self.debug, Op::Name, &ops);
+    }
+
+    /// Decorates an id w/ a decoration & its operands
+    fn decorate(&mut self, id: Id, ops: &[Word]) {
+        let mut all = vec![id];
+        all.extend_from_slice(ops);
+        inst(&mut self.annotations, Op::Decorate, &all);
+    }
+
+    /// Returns the id of a type, declaring it on first use
+    fn type_id(&mut self, t: &Type) -> Result<Id,SpirvError> {
+        if let Some(id) = self.types.get(&TypeKey::Value(t.clone())) {
+            return Ok(*id);
+        }
+
+        // component & member types are declared first
+        let (op, ops) = match t {
+            Type::Void      => (Op::TypeVoid, vec![]),
+            Type::Bool      => (Op::TypeBool, vec![]),
+            Type::Int       => (Op::TypeInt, vec![32, 1]),
+            Type::Float     => (Op::TypeFloat, vec![32]),
+            Type::Vec(n) | Type::IVec(n) | Type::BVec(n) => (Op::TypeVector, vec![self.type_id(&scalar_of(t))?, *n as Word]),
+            Type::Mat(n)    => (Op::TypeMatrix, vec![self.type_id(&Type::Vec(*n))?, *n as Word]),
+            Type::Struct(n) => {
+                let fields = self.structs.get(n).cloned().ok_or(format!("Struct '{n}' is not declared"))?;
+                (Op::TypeStruct, fields.iter().map(|(_,ft)| self.type_id(ft)).collect::<Result<Vec<_>,SpirvError>>()?)
+            },
+            Type::Double    => return Err("Type 'double' is not supported by the SPIR-V backend".to_string()),
+            Type::Sampler2D => return Err("Type 'sampler2D' is not supported by the SPIR-V backend".to_string()),
+            Type::Array(..) => return Err("Arrays are not supported by the SPIR-V backend".to_string()),
+        };
+
+        let id = self.id();
+        let mut all = vec![id];
+        all.extend(ops);
+        inst(&mut self.globals, op, &all);
+        self.types.insert(TypeKey::Value(t.clone()), id);
+
+        if let Type::Struct(n) = t {
+            self.name(id, n);
+            for (i,(f,_)) in self.structs[n].clone().iter().enumerate() {
+                let mut ops = vec![id, i as Word];
+                ops.extend(encode_str(f));
+                inst(&mut self.debug, Op::MemberName, &ops);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Returns the id of a pointer type, declaring it on first use
+    fn ptr_type(&mut self, class: Word, t: &Type) -> Result<Id,SpirvError> {
+        let ty = self.type_id(t)?;
+        Ok(self.ptr_to(class, ty))
+    }
+
+    /// Returns the id of a pointer to a type id, declaring it on first use
+    fn ptr_to(&mut self, class: Word, ty: Id) -> Id {
+        if let Some(id) = self.types.get(&TypeKey::Pointer(class, ty)) {
+            return *id;
+        }
+        let id = self.id();
+        inst(&mut self.globals, Op::TypePointer, &[id, class, ty]);
+        self.types.insert(TypeKey::Pointer(class, ty), id);
+        id
+    }
+
+    /// Returns the id of a function type, declaring it on first use
+    fn fn_type(&mut self, ret: Id, params: Vec<Id>) -> Id {
+        let key = TypeKey::Function(ret, params.clone());
+        if let Some(id) = self.types.get(&key) {
+            return *id;
+        }
+        let id = self.id();
+        let mut ops = vec![id, ret];
+        ops.extend(params);
+        inst(&mut self.globals, Op::TypeFunction, &ops);
+        self.types.insert(key, id);
+        id
+    }
+
+    /// Returns the id of a scalar constant, declaring it on first use
+    fn constant(&mut self, t: &Type, value: Word) -> Result<Id,SpirvError> {
+        let ty = self.type_id(t)?;
+        if let Some(id) = self.constants.get(&(ty, vec![value])) {
+            return Ok(*id);
+        }
+        let id = self.id();
+        match t {
+            Type::Bool if value == 0 => inst(&mut self.globals, Op::ConstantFalse, &[ty, id]),
+            Type::Bool               => inst(&mut self.globals, Op::ConstantTrue, &[ty, id]),
+            _                        => inst(&mut self.globals, Op::Constant, &[ty, id, value]),
+        }
+        self.constants.insert((ty, vec![value]), id);
+        Ok(id)
+    }
+
+    /// Returns the id of an int constant, such as an index into a struct
+    fn int(&mut self, i: Word) -> Result<Id,SpirvError> {
+        self.constant(&Type::Int, i)
+    }
+
+    /// Returns the constant for a literal expression, or None for anything else
+    fn literal(&mut self, e: &Expr) -> Result<Option<Id>,SpirvError> {
+        match e.kind {
+            ExprKind::Int(i)   => self.constant(&Type::Int, i as Word).map(Some),
+            ExprKind::Float(f) => self.constant(&Type::Float, f.to_bits()).map(Some),
+            ExprKind::Bool(b)  => self.constant(&Type::Bool, b as Word).map(Some),
+            _                  => Ok(None)
+        }
+    }
+
+    /// Declares a global variable, w/ an optional constant initializer
+    fn variable(&mut self, class: Word, t: &Type, n: &str, init: Option<Id>) -> Result<Id,SpirvError> {
+        let ty = self.ptr_type(class, t)?;
+        let id = self.id();
+        let mut ops = vec![ty, id, class];
+        ops.extend(init);
+        inst(&mut self.globals, Op::Variable, &ops);
+        self.name(id, n);
+        Ok(id)
+    }
+
+    /// Declares the uniform block, inputs & outputs of a shader, returning the ids of the inputs & outputs
+    fn interface(&mut self, s: &glsl::Shader) -> Result<Vec<Id>,SpirvError> {
+        let vars : Vec<&glsl::VarDecl> = s.decls.iter().filter_map(|d| match d {
+            Decl::Var(v) => Some(v),
+            _            => None
+        }).collect();
+
+        // uniforms are members of a single block
+        let uniforms : Vec<&&glsl::VarDecl> = vars.iter().filter(|v| v.qualifier == Some(Qualifier::Uniform)).collect();
+        if !uniforms.is_empty() {
+            let members = uniforms.iter().map(|v| self.type_id(&v.typ)).collect::<Result<Vec<_>,SpirvError>>()?;
+            let block = self.id();
+            let mut ops = vec![block];
+            ops.extend(members);
+            inst(&mut self.globals, Op::TypeStruct, &ops);
+            self.name(block, "Uniforms");
+            self.decorate(block, &[BLOCK]);
+
+            let mut offset : Word = 0;
+            for (i,v) in uniforms.iter().enumerate() {
+                let i = i as Word;
+                let mut name = vec![block, i];
+                name.extend(encode_str(&v.name));
+                inst(&mut self.debug, Op::MemberName, &name);
+
+                let (align, size) = std140(&v.typ)?;
+                offset = offset.div_ceil(align) * align;
+                inst(&mut self.annotations, Op::MemberDecorate, &[block, i, OFFSET, offset]);
+                if let Type::Mat(_) = v.typ {
+                    inst(&mut self.annotations, Op::MemberDecorate, &[block, i, COL_MAJOR]);
+                    inst(&mut self.annotations, Op::MemberDecorate, &[block, i, MATRIX_STRIDE, 16]);
+                }
+                offset += size;
+            }
+
+            let ty = self.ptr_to(UNIFORM, block);
+            let var = self.id();
+            inst(&mut self.globals, Op::Variable, &[ty, var, UNIFORM]);
+            self.name(var, "uniforms");
+            self.decorate(var, &[DESCRIPTOR_SET, 0]);
+            self.decorate(var, &[BINDING, 0]);
+            for (i,v) in uniforms.iter().enumerate() {
+                self.names.insert(v.name.clone(), Binding::Uniform(var, i as Word));
+            }
+        }
+
+        let mut interface = vec![];
+        let mut locations = [0, 0];
+        let mut located = |e: &mut Emitter, class: Word, t: &Type, n: &str| -> Result<Id,SpirvError> {
+            if matches!(scalar_of(t), Type::Bool) {
+                return Err(format!("Input or output '{n}' is a bool, which is not supported by SPIR-V"));
+            }
+            let var = e.variable(class, t, n, None)?;
+            let l = &mut locations[(class == OUTPUT) as usize];
+            e.decorate(var, &[LOCATION, *l]);
+            *l += 1;
+            // integers passed between stages cannot be interpolated
+            let varying = (class == OUTPUT && s.stage == Stage::Vertex) || (class == INPUT && s.stage == Stage::Fragment);
+            if varying && scalar_of(t) == Type::Int {
+                e.decorate(var, &[FLAT]);
+            }
+            e.names.insert(n.to_string(), Binding::Var(var, class));
+            Ok(var)
+        };
+
+        // 'gl_FragColor' is located at 0, as in the GLSL printers, so it comes before the other outputs
+        if s.stage == Stage::Fragment && s.decls.iter().any(|d| decl_uses(d, "gl_FragColor")) {
+            interface.push(located(self, OUTPUT, &Type::Vec(4), "gl_FragColor")?);
+        }
+        for v in &vars {
+            match v.qualifier {
+                Some(Qualifier::In)  => interface.push(located(self, INPUT, &v.typ, &v.name)?),
+                Some(Qualifier::Out) => interface.push(located(self, OUTPUT, &v.typ, &v.name)?),
+                _                    => ()
+            }
+        }
+
+        // built-in outputs
+        if s.stage == Stage::Vertex {
+            let var = self.variable(OUTPUT, &Type::Vec(4), "gl_Position", None)?;
+            self.decorate(var, &[BUILT_IN, POSITION]);
+            self.names.insert("gl_Position".to_string(), Binding::Var(var, OUTPUT));
+            interface.push(var);
+        }
+        for (n, b, stage, class, t) in BUILT_IN_VARS {
+            if *stage == s.stage && s.decls.iter().any(|d| decl_uses(d, n)) {
+                let var = self.variable(*class, t, n, None)?;
+                self.decorate(var, &[BUILT_IN, *b]);
+                self.names.insert(n.to_string(), Binding::Var(var, *class));
+                interface.push(var);
+            }
+        }
+        Ok(interface)
+    }
+
+    /// Emits a function definition
+    fn function(&mut self, func: &glsl::Function) -> Result<(),SpirvError> {
+        let (id, ft) = self.functions[&signature(func)];
+        let ret = self.type_id(&func.ret)?;
+        inst(&mut self.code, Op::Function, &[ret, id, 0, ft]);
+
+        let entry = self.id();
+        let mut f = FnState{ vars: vec![], code: vec![], label: entry, terminated: false, scopes: vec![HashMap::new()] };
+
+        // parameters are copied into variables, so they can be used like any other
+        for (n,t) in &func.params {
+            let ty = self.type_id(t)?;
+            let p = self.id();
+            inst(&mut self.code, Op::FunctionParameter, &[ty, p]);
+            let var = self.local(t, n, &mut f)?;
+            inst(&mut f.code, Op::Store, &[var, p]);
+        }
+
+        self.block(&func.body, &mut f)?;
+        if !f.terminated {
+            if func.ret == Type::Void {
+                inst(&mut f.code, Op::Return, &[]);
+            } else {
+                // every path has already returned a value
+                inst(&mut f.code, Op::Unreachable, &[]);
+            }
+        }
+
+        inst(&mut self.code, Op::Label, &[entry]);
+        self.code.extend(f.vars);
+        self.code.extend(f.code);
+        inst(&mut self.code, Op::FunctionEnd, &[]);
+        Ok(())
+    }
+
+    /// Declares a local variable in the current scope
+    fn local(&mut self, t: &Type, n: &str, f: &mut FnState) -> Result<Id,SpirvError> {
+        let ty = self.ptr_type(FUNCTION, t)?;
+        let var = self.id();
+        inst(&mut f.vars, Op::Variable, &[ty, var, FUNCTION]);
+        self.name(var, n);
+        if let Some(scope) = f.scopes.last_mut() {
+            scope.insert(n.to_string(), Binding::Var(var, FUNCTION));
+        }
+        Ok(var)
+    }
+
+    /// Starts a new block w/ a given label
+    fn label(&mut self, l: Id, f: &mut FnState) {
+        inst(&mut f.code, Op::Label, &[l]);
+        f.label = l;
+        f.terminated = false;
+    }
+
+    /// Ends the current block w/ a branch, unless it has already been terminated
+    fn branch(&mut self, to: Id, f: &mut FnState) {
+        if !f.terminated {
+            inst(&mut f.code, Op::Branch, &[to]);
+            f.terminated = true;
+        }
+    }
+
+    /// Emits a sequence of statements in a new scope
+    fn block(&mut self, body: &[Stmt], f: &mut FnState) -> Result<(),SpirvError> {
+        f.scopes.push(HashMap::new());
+        for s in body {
+            self.stmt(s, f)?;
+        }
+        f.scopes.pop();
+        Ok(())
+    }
+
+    /// Emits a statement, unless its block has already been terminated, making it unreachable
+    fn stmt(&mut self, s: &Stmt, f: &mut FnState) -> Result<(),SpirvError> {
+        if f.terminated {
+            return Ok(());
+        }
+        match s {
+            Stmt::Decl(v) => {
+                // the value is evaluated before the variable shadows anything of the same name
+                let value = v.value.as_ref().map(|e| self.expr(e, f)).transpose()?;
+                let var = self.local(&v.typ, &v.name, f)?;
+                if let Some(value) = value {
+                    inst(&mut f.code, Op::Store, &[var, value]);
+                }
+            },
+            Stmt::Assign{target, value} => {
+                let value = self.expr(value, f)?;
+                self.assign(target, value, f)?;
+            },
+            Stmt::Expr(e) => {
+                self.expr(e, f)?;
+            },
+            Stmt::If{condition, b1, b2} => {
+                let c = self.expr(condition, f)?;
+                let (then, merge) = (self.id(), self.id());
+                let els = if b2.is_empty() { merge } else { self.id() };
+                inst(&mut f.code, Op::SelectionMerge, &[merge, 0]);
+                inst(&mut f.code, Op::BranchConditional, &[c, then, els]);
+
+                self.label(then, f);
+                self.block(b1, f)?;
+                self.branch(merge, f);
+                if !b2.is_empty() {
+                    self.label(els, f);
+                    self.block(b2, f)?;
+                    self.branch(merge, f);
+                }
+                self.label(merge, f);
+            },
+            Stmt::For{init, cond, post, body} => {
+                f.scopes.push(HashMap::new());
+                self.stmt(init, f)?;
+                let (header, check, inner, cont, merge) = (self.id(), self.id(), self.id(), self.id(), self.id());
+                self.branch(header, f);
+
+                self.label(header, f);
+                inst(&mut f.code, Op::LoopMerge, &[merge, cont, 0]);
+                self.branch(check, f);
+
+                self.label(check, f);
+                let c = self.expr(cond, f)?;
+                inst(&mut f.code, Op::BranchConditional, &[c, inner, merge]);
+
+                self.label(inner, f);
+                self.block(body, f)?;
+                self.branch(cont, f);
+
+                self.label(cont, f);
+                self.stmt(post, f)?;
+                self.branch(header, f);
+
+                self.label(merge, f);
+                f.scopes.pop();
+            },
+            Stmt::While{cond, body} => {
+                let (header, check, inner, cont, merge) = (self.id(), self.id(), self.id(), self.id(), self.id());
+                self.branch(header, f);
+
+                self.label(header, f);
+                inst(&mut f.code, Op::LoopMerge, &[merge, cont, 0]);
+                self.branch(check, f);
+
+                self.label(check, f);
+                let c = self.expr(cond, f)?;
+                inst(&mut f.code, Op::BranchConditional, &[c, inner, merge]);
+
+                self.label(inner, f);
+                self.block(body, f)?;
+                self.branch(cont, f);
+
+                self.label(cont, f);
+                self.branch(header, f);
+
+                self.label(merge, f);
+            },
+            // the condition is checked in the continue block, which branches back to the header while it holds
+            Stmt::DoWhile{body, cond} => {
+                let (header, inner, cont, merge) = (self.id(), self.id(), self.id(), self.id());
+                self.branch(header, f);
+
+                self.label(header, f);
+                inst(&mut f.code, Op::LoopMerge, &[merge, cont, 0]);
+                self.branch(inner, f);
+
+                self.label(inner, f);
+                self.block(body, f)?;
+                self.branch(cont, f);
+
+                self.label(cont, f);
+                let c = self.expr(cond, f)?;
+                inst(&mut f.code, Op::BranchConditional, &[c, header, merge]);
+
+                self.label(merge, f);
+            },
+            Stmt::Return(None) => {
+                inst(&mut f.code, Op::Return, &[]);
+                f.terminated = true;
+            },
+            Stmt::Return(Some(e)) => {
+                let v = self.expr(e, f)?;
+                inst(&mut f.code, Op::ReturnValue, &[v]);
+                f.terminated = true;
+            },
+            Stmt::Discard => {
+                inst(&mut f.code, Op::Kill, &[]);
+                f.terminated = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores a value to the target of an assignment
+    /// Swizzles of several components are written by shuffling the value into the whole vector
+    fn assign(&mut self, target: &Expr, value: Id, f: &mut FnState) -> Result<(),SpirvError> {
+        if let ExprKind::Field(base, name) = &target.kind {
+            if let (Some(n), Some(comps)) = (vec_size(&base.typ), swizzle(name)) {
+                if comps.len() > 1 {
+                    let (p, _) = self.pointer(base, f)?.ok_or("Swizzles may only be assigned to on variables".to_string())?;
+                    let ty = self.type_id(&base.typ)?;
+                    let old = self.id();
+                    inst(&mut f.code, Op::Load, &[ty, old, p]);
+
+                    let mut ops = vec![ty, self.id(), old, value];
+                    ops.extend((0..n as Word).map(|i| comps.iter().position(|c| *c == i).map_or(i, |k| n as Word + k as Word)));
+                    let new = ops[1];
+                    inst(&mut f.code, Op::VectorShuffle, &ops);
+                    inst(&mut f.code, Op::Store, &[p, new]);
+                    return Ok(());
+                }
+            }
+        }
+        let (p, _) = self.pointer(target, f)?.ok_or(format!("Cannot assign to '{:?}'", target.kind))?;
+        inst(&mut f.code, Op::Store, &[p, value]);
+        Ok(())
+    }
+
+    /// Looks up what a name refers to, innermost scope first
+    fn lookup(&self, n: &str, f: &FnState) -> Result<Binding,SpirvError> {
+        f.scopes.iter().rev().find_map(|s| s.get(n)).or(self.names.get(n)).cloned()
+            .ok_or(format!("Variable '{n}' is not declared"))
+    }
+
+    /// Returns a pointer to an expression, & its storage class, if it refers to (part of) a variable
+    fn pointer(&mut self, e: &Expr, f: &mut FnState) -> Result<Option<(Id, Word)>,SpirvError> {
+        let (base, class, index) = match &e.kind {
+            ExprKind::Var(n) => match self.lookup(n, f)? {
+                Binding::Var(v, class)   => return Ok(Some((v, class))),
+                Binding::Uniform(v, i)   => (v, UNIFORM, self.int(i)?),
+                Binding::Value(_)        => return Ok(None)
+            },
+            ExprKind::Field(b, name) => {
+                let i = match &b.typ {
+                    Type::Struct(s) => self.structs.get(s).and_then(|fs| fs.iter().position(|(fname,_)| fname == name)).map(|i| i as Word),
+                    t if vec_size(t).is_some() => swizzle(name).filter(|c| c.len() == 1).map(|c| c[0]),
+                    _ => None
+                };
+                match (i, self.pointer(b, f)?) {
+                    (Some(i), Some((p, class))) => (p, class, self.int(i)?),
+                    _                           => return Ok(None)
+                }
+            },
+            ExprKind::Index(b, i) => match self.pointer(b, f)? {
+                Some((p, class)) => (p, class, self.expr(i, f)?),
+                None             => return Ok(None)
+            },
+            _ => return Ok(None)
+        };
+        let ty = self.ptr_type(class, &e.typ)?;
+        let id = self.id();
+        inst(&mut f.code, Op::AccessChain, &[ty, id, base, index]);
+        Ok(Some((id, class)))
+    }
+
+    /// Emits an instruction w/ a result of the given type, returning the result
+    fn op(&mut self, op: Op, t: &Type, operands: &[Id], f: &mut FnState) -> Result<Id,SpirvError> {
+        let ty = self.type_id(t)?;
+        let id = self.id();
+        let mut ops = vec![ty, id];
+        ops.extend_from_slice(operands);
+        inst(&mut f.code, op, &ops);
+        Ok(id)
+    }
+
+    /// Splats a scalar to a vector of the given type
+    fn splat(&mut self, v: Id, t: &Type, f: &mut FnState) -> Result<Id,SpirvError> {
+        let n = vec_size(t).ok_or(format!("Cannot splat a scalar to '{:?}'", t))?;
+        self.op(Op::CompositeConstruct, t, &vec![v; n as usize], f)
+    }
+
+    /// Emits an expression, returning the id of its value
+    fn expr(&mut self, e: &Expr, f: &mut FnState) -> Result<Id,SpirvError> {
+        if let Some((p, _)) = self.pointer(e, f)? {
+            return self.op(Op::Load, &e.typ, &[p], f);
+        }
+
+        match &e.kind {
+            ExprKind::Int(_) | ExprKind::Float(_) | ExprKind::Bool(_) => self.literal(e)?.ok_or("Expected a literal".to_string()),
+            ExprKind::Double(_) => Err("Doubles are not supported by the SPIR-V backend".to_string()),
+            ExprKind::Var(n) => match self.lookup(n, f)? {
+                Binding::Value(v) => Ok(v),
+                _                 => Err(format!("Variable '{n}' cannot be loaded"))
+            },
+            ExprKind::Call{name, args} => self.call(name, args, &e.typ, f),
+            ExprKind::Binary{op, lhs, rhs} => self.binary(*op, lhs, rhs, &e.typ, f),
+            ExprKind::Unary{op, e: inner} => {
+                let v = self.expr(inner, f)?;
+                let op = match (op, scalar_of(&inner.typ)) {
+                    (UnOp::Not, _)          => Op::LogicalNot,
+                    (UnOp::Neg, Type::Int)  => Op::SNegate,
+                    (UnOp::Neg, _)          => Op::FNegate
+                };
+                self.op(op, &e.typ, &[v], f)
+            },
+            ExprKind::Index(b, i) => {
+                let v = self.expr(b, f)?;
+                match (&i.kind, vec_size(&b.typ)) {
+                    (ExprKind::Int(k), _) => self.op(Op::CompositeExtract, &e.typ, &[v, *k as Word], f),
+                    (_, Some(_))          => {
+                        let i = self.expr(i, f)?;
+                        self.op(Op::VectorExtractDynamic, &e.typ, &[v, i], f)
+                    },
+                    _                     => Err("Only vectors may be indexed dynamically, unless they are variables".to_string())
+                }
+            },
+            ExprKind::Field(b, name) => {
+                let v = self.expr(b, f)?;
+                match &b.typ {
+                    Type::Struct(s) => {
+                        let i = self.structs.get(s).and_then(|fs| fs.iter().position(|(fname,_)| fname == name))
+                            .ok_or(format!("Struct '{s}' has no field '{name}'"))?;
+                        self.op(Op::CompositeExtract, &e.typ, &[v, i as Word], f)
+                    },
+                    t => match (vec_size(t), swizzle(name)) {
+                        (Some(_), Some(c)) if c.len() == 1 => self.op(Op::CompositeExtract, &e.typ, &[v, c[0]], f),
+                        (Some(_), Some(c)) => {
+                            let mut ops = vec![v, v];
+                            ops.extend(c);
+                            self.op(Op::VectorShuffle, &e.typ, &ops, f)
+                        },
+                        _ => Err(format!("Cannot access '{name}' of '{:?}'", t))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Emits a call of a user function, a constructor or a built-in
+    fn call(&mut self, name: &str, args: &[Expr], ret: &Type, f: &mut FnState) -> Result<Id,SpirvError> {
+        let key = (name.to_string(), args.iter().map(|a| a.typ.clone()).collect());
+        if let Some((id, _)) = self.functions.get(&key).copied() {
+            let mut ops = vec![id];
+            for a in args {
+                ops.push(self.expr(a, f)?);
+            }
+            return self.op(Op::FunctionCall, ret, &ops, f);
+        }
+
+        let constructor = self.structs.contains_key(name) || matches!(name,
+            "bool" | "int" | "float" | "vec2" | "vec3" | "vec4" | "ivec2" | "ivec3" | "ivec4" |
+            "bvec2" | "bvec3" | "bvec4" | "mat2" | "mat3" | "mat4");
+        if constructor {
+            return self.construct(args, ret, f);
+        }
+
+        let mut vs = vec![];
+        for a in args {
+            vs.push(self.expr(a, f)?);
+        }
+        match name {
+            "dot"  => return self.op(Op::Dot, ret, &vs, f),
+            "dFdx" => return self.op(Op::DPdx, ret, &vs, f),
+            "dFdy" => return self.op(Op::DPdy, ret, &vs, f),
+            "any"  => return self.op(Op::Any, ret, &vs, f),
+            "all"  => return self.op(Op::All, ret, &vs, f),
+            "not"  => return self.op(Op::LogicalNot, ret, &vs, f),
+            _      => ()
+        }
+
+        let int = args.first().is_some_and(|a| scalar_of(&a.typ) == Type::Int);
+        let (code, componentwise) = match name {
+            "mod" => (None, true),
+            _     => match glsl_std_450(name, int, args.len()) {
+                Some((c, cw)) => (Some(c), cw),
+                None          => return Err(format!("Built-in '{name}' is not supported by the SPIR-V backend"))
+            }
+        };
+
+        // scalar arguments of component-wise built-ins are splat to the size of the result, i.e. min(v, 1.0)
+        if componentwise && vec_size(ret).is_some() {
+            for (v,a) in vs.iter_mut().zip(args) {
+                if vec_size(&a.typ).is_none() {
+                    *v = self.splat(*v, ret, f)?;
+                }
+            }
+        }
+
+        match code {
+            Some(c) => {
+                let mut ops = vec![self.glsl_ext, c];
+                ops.extend(vs);
+                self.op(Op::ExtInst, ret, &ops, f)
+            },
+            None => self.op(Op::FMod, ret, &vs, f)
+        }
+    }
+
+    /// Emits a constructor, flattening vector arguments into components as GLSL does
+    fn construct(&mut self, args: &[Expr], ret: &Type, f: &mut FnState) -> Result<Id,SpirvError> {
+        let mut vs = vec![];
+        for a in args {
+            vs.push(self.expr(a, f)?);
+        }
+        if let Type::Struct(_) = ret {
+            return self.op(Op::CompositeConstruct, ret, &vs, f);
+        }
+
+        // matrices from columns
+        if let Type::Mat(n) = ret {
+            if args.len() == *n as usize && args.iter().all(|a| a.typ == Type::Vec(*n)) {
+                return self.op(Op::CompositeConstruct, ret, &vs, f);
+            }
+        }
+
+        // everything else is built from scalar components
+        let component = scalar_of(ret);
+        let mut comps = vec![];
+        for (v,a) in vs.into_iter().zip(args) {
+            let at = scalar_of(&a.typ);
+            let mut scalars = match vec_size(&a.typ) {
+                Some(n) => (0..n as Word).map(|i| self.op(Op::CompositeExtract, &at, &[v, i], f)).collect::<Result<Vec<_>,SpirvError>>()?,
+                None if matches!(a.typ, Type::Mat(_)) => return Err("Matrices cannot be flattened into a constructor".to_string()),
+                None    => vec![v]
+            };
+            for s in scalars.iter_mut() {
+                *s = match (&at, &component) {
+                    (Type::Int, Type::Float) => self.op(Op::ConvertSToF, &component, &[*s], f)?,
+                    (Type::Float, Type::Int) => self.op(Op::ConvertFToS, &component, &[*s], f)?,
+                    (a, c) if a == c         => *s,
+                    (a, c)                   => return Err(format!("Cannot convert '{:?}' to '{:?}'", a, c))
+                };
+            }
+            comps.extend(scalars);
+        }
+
+        match ret {
+            Type::Vec(n) | Type::IVec(n) | Type::BVec(n) => match comps.len() {
+                1 => self.splat(comps[0], ret, f),
+                l if l >= *n as usize => self.op(Op::CompositeConstruct, ret, &comps[..*n as usize], f),
+                _ => Err(format!("Too few components to construct '{:?}'", ret))
+            },
+            Type::Mat(n) => {
+                let n = *n as usize;
+                let zero = self.constant(&Type::Float, 0.0f32.to_bits())?;
+                // a single scalar fills the diagonal
+                let cells : Vec<Id> = match comps.len() {
+                    1                  => (0..n * n).map(|i| if i % (n + 1) == 0 { comps[0] } else { zero }).collect(),
+                    l if l == n * n    => comps,
+                    _                  => return Err(format!("Wrong number of components to construct '{:?}'", ret))
+                };
+                let mut cols = vec![];
+                for c in cells.chunks(n) {
+                    cols.push(self.op(Op::CompositeConstruct, &Type::Vec(n as u8), c, f)?);
+                }
+                self.op(Op::CompositeConstruct, ret, &cols, f)
+            },
+            _ => comps.first().copied().ok_or(format!("Too few components to construct '{:?}'", ret))
+        }
+    }
+
+    /// Emits a binary operation, picking the instruction for the types of its operands
+    fn binary(&mut self, op: BinOp, lhs: &Expr, rhs: &Expr, ret: &Type, f: &mut FnState) -> Result<Id,SpirvError> {
+        // the right hand side of a logical operator is only evaluated when needed, if it could have effects
+        if matches!(op, BinOp::And | BinOp::Or) && has_call(rhs) {
+            return self.short_circuit(op, lhs, rhs, f);
+        }
+
+        let (lt, rt) = (&lhs.typ, &rhs.typ);
+        let mut a = self.expr(lhs, f)?;
+        let mut b = self.expr(rhs, f)?;
+
+        // products w/ matrices & scalings of vectors have their own instructions
+        if op == BinOp::Mul {
+            let special = match (lt, rt) {
+                (Type::Mat(_), Type::Vec(_))    => Some((Op::MatrixTimesVector, a, b)),
+                (Type::Vec(_), Type::Mat(_))    => Some((Op::VectorTimesMatrix, a, b)),
+                (Type::Mat(_), Type::Mat(_))    => Some((Op::MatrixTimesMatrix, a, b)),
+                (Type::Mat(_), Type::Float)     => Some((Op::MatrixTimesScalar, a, b)),
+                (Type::Float, Type::Mat(_))     => Some((Op::MatrixTimesScalar, b, a)),
+                (Type::Vec(_), Type::Float)     => Some((Op::VectorTimesScalar, a, b)),
+                (Type::Float, Type::Vec(_))     => Some((Op::VectorTimesScalar, b, a)),
+                _                               => None
+            };
+            if let Some((o, x, y)) = special {
+                return self.op(o, ret, &[x, y], f);
+            }
+        }
+        if matches!(lt, Type::Mat(_)) || matches!(rt, Type::Mat(_)) {
+            return Err(format!("Operator '{}' on matrices is not supported by the SPIR-V backend", crate::pretty::bin_op(op)));
+        }
+
+        // mixed scalar & vector operands are component-wise
+        let operand = if vec_size(lt).is_some() { lt.clone() } else { rt.clone() };
+        if vec_size(lt).is_none() && vec_size(rt).is_some() {
+            a = self.splat(a, rt, f)?;
+        }
+        if vec_size(rt).is_none() && vec_size(lt).is_some() {
+            b = self.splat(b, lt, f)?;
+        }
+
+        let scalar = scalar_of(&operand);
+        let float = scalar == Type::Float;
+        let o = match op {
+            BinOp::Add    => if float { Op::FAdd } else { Op::IAdd },
+            BinOp::Sub    => if float { Op::FSub } else { Op::ISub },
+            BinOp::Mul    => if float { Op::FMul } else { Op::IMul },
+            BinOp::Div    => if float { Op::FDiv } else { Op::SDiv },
+            BinOp::Mod    => if float { Op::FMod } else { Op::SMod },
+            BinOp::And    => Op::LogicalAnd,
+            BinOp::Or     => Op::LogicalOr,
+            BinOp::BitAnd => Op::BitwiseAnd,
+            BinOp::BitOr  => Op::BitwiseOr,
+            BinOp::BitXor => Op::BitwiseXor,
+            BinOp::Lt     => if float { Op::FOrdLessThan } else { Op::SLessThan },
+            BinOp::Gt     => if float { Op::FOrdGreaterThan } else { Op::SGreaterThan },
+            BinOp::Lte    => if float { Op::FOrdLessThanEqual } else { Op::SLessThanEqual },
+            BinOp::Gte    => if float { Op::FOrdGreaterThanEqual } else { Op::SGreaterThanEqual },
+            BinOp::Eq | BinOp::Neq => {
+                let eq = op == BinOp::Eq;
+                let o = match scalar {
+                    Type::Float => if eq { Op::FOrdEqual } else { Op::FOrdNotEqual },
+                    Type::Bool  => if eq { Op::LogicalEqual } else { Op::LogicalNotEqual },
+                    Type::Int   => if eq { Op::IEqual } else { Op::INotEqual },
+                    t           => return Err(format!("Cannot compare values of '{:?}' in SPIR-V", t))
+                };
+                // vectors are compared component-wise, & are equal if all components are
+                return match vec_size(&operand) {
+                    Some(n) => {
+                        let each = self.op(o, &Type::BVec(n), &[a, b], f)?;
+                        self.op(if eq { Op::All } else { Op::Any }, ret, &[each], f)
+                    },
+                    None => self.op(o, ret, &[a, b], f)
+                };
+            }
+        };
+        self.op(o, ret, &[a, b], f)
+    }
+
+    /// Emits a logical operator that only evaluates its right hand side when the left does not decide it
+    fn short_circuit(&mut self, op: BinOp, lhs: &Expr, rhs: &Expr, f: &mut FnState) -> Result<Id,SpirvError> {
+        let a = self.expr(lhs, f)?;
+        let from = f.label;
+        let (right, merge) = (self.id(), self.id());
+        inst(&mut f.code, Op::SelectionMerge, &[merge, 0]);
+        let (t, e) = if op == BinOp::And { (right, merge) } else { (merge, right) };
+        inst(&mut f.code, Op::BranchConditional, &[a, t, e]);
+
+        self.label(right, f);
+        let b = self.expr(rhs, f)?;
+        let from_right = f.label;
+        self.branch(merge, f);
+
+        self.label(merge, f);
+        self.op(Op::Phi, &Type::Bool, &[a, from, b, from_right], f)
+    }
+}
+
+/// Returns the name of an enumerated operand, or its number if it is not one that is emitted
+fn enum_name(k: Kind, w: Word) -> String {
+    let n = match (k, w) {
+        (Kind::Cap, 1)      => "Shader",
+        (Kind::Addr, 0)     => "Logical",
+        (Kind::Mem, 1)      => "GLSL450",
+        (Kind::Model, 0)    => "Vertex",
+        (Kind::Model, 4)    => "Fragment",
+        (Kind::Mode, 7)     => "OriginUpperLeft",
+        (Kind::Storage, 1)  => "Input",
+        (Kind::Storage, 2)  => "Uniform",
+        (Kind::Storage, 3)  => "Output",
+        (Kind::Storage, 6)  => "Private",
+        (Kind::Storage, 7)  => "Function",
+        (Kind::Deco, 2)     => "Block",
+        (Kind::Deco, 5)     => "ColMajor",
+        (Kind::Deco, 7)     => "MatrixStride",
+        (Kind::Deco, 11)    => "BuiltIn",
+        (Kind::Deco, 14)    => "Flat",
+        (Kind::Deco, 30)    => "Location",
+        (Kind::Deco, 33)    => "Binding",
+        (Kind::Deco, 34)    => "DescriptorSet",
+        (Kind::Deco, 35)    => "Offset",
+        (Kind::Mask, 0)     => "None",
+        (Kind::Ext, _)      => match GLSL_STD_450.iter().find(|(c,_)| *c == w) {
+            Some((_, n)) => n,
+            None         => return w.to_string()
+        },
+        _                   => return w.to_string()
+    };
+    n.to_string()
+}
+
+/// Decodes a nul terminated string, returning it & the number of words it takes
+fn decode_str(words: &[Word]) -> Result<(String, usize),SpirvError> {
+    let bytes : Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let end = bytes.iter().position(|b| *b == 0).ok_or("Unterminated string".to_string())?;
+    let s = String::from_utf8(bytes[..end].to_vec()).map_err(|e| e.to_string())?;
+    Ok((s, end / 4 + 1))
+}
+
+/// Disassembles a SPIR-V module into text, in the style of spirv-dis
+pub fn disassemble(words: &[Word]) -> Result<String,SpirvError> {
+    if words.len() < 5 || words[0] != MAGIC {
+        return Err("Not a SPIR-V module".to_string());
+    }
+    let mut out = format!(
+        "; SPIR-V\n; Version: {}.{}\n; Generator: {}\n; Bound: {}\n; Schema: {}\n",
+        (words[1] >> 16) & 0xff, (words[1] >> 8) & 0xff, words[2], words[3], words[4]
+    );
+
+    // float types, so their constants are printed as floats
+    let mut floats = HashSet::new();
+    let mut i = 5;
+    while i < words.len() {
+        let count = (words[i] >> 16) as usize;
+        let op = Op::from_code(words[i] & 0xffff).ok_or(format!("Unknown opcode {} at word {}", words[i] & 0xffff, i))?;
+        if count == 0 || i + count > words.len() {
+            return Err(format!("Truncated instruction at word {i}"));
+        }
+        let operands = &words[i + 1..i + count];
+
+        let mut res = None;
+        let mut ty = None;
+        let mut parts = vec![];
+        let mut j = 0;
+        for k in op.operands() {
+            match k {
+                // the remaining operands may be empty
+                Kind::Ids | Kind::Lits if j >= operands.len() => break,
+                _ if j >= operands.len() => return Err(format!("Missing operands for Op{:?} at word {}", op, i)),
+                _ => ()
+            }
+            match k {
+                Kind::Res   => res = Some(operands[j]),
+                Kind::Ty    => {
+                    ty = Some(operands[j]);
+                    parts.push(format!("%{}", operands[j]));
+                },
+                Kind::Id    => parts.push(format!("%{}", operands[j])),
+                Kind::Lit   => parts.push(operands[j].to_string()),
+                Kind::Str   => {
+                    let (s, n) = decode_str(&operands[j..])?;
+                    parts.push(format!("{:?}", s));
+                    j += n;
+                    continue;
+                },
+                Kind::Ids   => {
+                    parts.extend(operands[j..].iter().map(|w| format!("%{w}")));
+                    j = operands.len();
+                    continue;
+                },
+                Kind::Lits  => {
+                    parts.extend(operands[j..].iter().map(|w| w.to_string()));
+                    j = operands.len();
+                    continue;
+                },
+                Kind::Value => {
+                    let v = operands[j];
+                    parts.push(if ty.is_some_and(|t| floats.contains(&t)) { format!("{:?}", f32::from_bits(v)) } else { (v as i32).to_string() });
+                },
+                Kind::Deco  => {
+                    parts.push(enum_name(*k, operands[j]));
+                    // built-ins are named, other decorations only have literals
+                    let built_in = match operands.get(j + 1) {
+                        Some(&POSITION)     => Some("Position"),
+                        Some(&POINT_SIZE)   => Some("PointSize"),
+                        Some(&FRAG_COORD)   => Some("FragCoord"),
+                        Some(&POINT_COORD)  => Some("PointCoord"),
+                        Some(&FRONT_FACING) => Some("FrontFacing"),
+                        _                   => None
+                    };
+                    if let (BUILT_IN, Some(b)) = (operands[j], built_in) {
+                        parts.push(b.to_string());
+                    } else {
+                        parts.extend(operands[j + 1..].iter().map(|w| w.to_string()));
+                    }
+                    j = operands.len();
+                    continue;
+                },
+                _           => parts.push(enum_name(*k, operands[j]))
+            }
+            j += 1;
+        }
+
+        if op == Op::TypeFloat {
+            floats.extend(res);
+        }
+        let line = std::iter::once(format!("Op{:?}", op)).chain(parts).collect::<Vec<_>>().join(" ");
+        match res {
+            Some(r) => out.push_str(&format!("%{r} = {line}\n")),
+            None    => out.push_str(&format!("{line}\n"))
+        }
+        i += count;
+    }
+    Ok(out)
+}
+
+//
+//
+// SPIR-V TESTS
+//
+//
+
+/// Used during testing to parse, typecheck & compile a program
+#[cfg(test)]
+fn compile_str(prog: &str) -> Vec<glsl::Program> {
+    let (_, p) = crate::parser::program(prog).expect("Failed to parse program");
+    let (_, env) = crate::typechecker::tc_program(p.clone()).expect("Failed to typecheck program");
+    crate::compiler::compile(&p, &env).expect("Failed to compile program")
+}
+
+/// Encodes instructions & strings as words
+#[test]
+fn test_spirv_encoding() {
+    assert_eq!(encode_str("main"), vec![0x6e69_616d, 0], "Failed to nul terminate a string of 4 bytes");
+    assert_eq!(encode_str("GLSL.std.450").len(), 4, "Failed to pad a string");
+    assert_eq!(decode_str(&encode_str("uTime")), Ok(("uTime".to_string(), 2)), "Failed to decode a string");
+
+    let mut out = vec![];
+    inst(&mut out, Op::TypeVector, &[3, 2, 4]);
+    assert_eq!(out, vec![(4 << 16) | 23, 3, 2, 4], "Failed to encode an instruction");
+
+    assert!(disassemble(&[MAGIC, VERSION, 0, 1]).is_err(), "Failed to reject a truncated header");
+    assert!(disassemble(&[MAGIC, VERSION, 0, 1, 0, (2 << 16) | 9999, 0]).is_err(), "Failed to reject an unknown opcode");
+}
+
+/// Emits a whole module for a small shader
+#[test]
+fn test_spirv_shader() {
+    let progs = compile_str("\
+uniform Vec4 uColor
+uniform Float uTime
+vert v : (Vec3 aPos) -> (Float vT) = {
+  set gl_Position uColor
+  out vT uTime
+}
+frag f : (Float vT) -> (Vec4 normal) = {
+  set gl_FragColor uColor
+  out normal uColor
+}
+p : Prog = mkProg v f
+");
+    let words = emit_shader(&progs[0].vert).unwrap();
+    assert_eq!(&words[..5], &[MAGIC, VERSION, 0, 27, 0], "Failed to emit header");
+    assert_eq!(disassemble(&words), Ok("\
+; SPIR-V
+; Version: 1.0
+; Generator: 0
+; Bound: 27
+; Schema: 0
+OpCapability Shader
+%1 = OpExtInstImport \"GLSL.std.450\"
+OpMemoryModel Logical GLSL450
+OpEntryPoint Vertex %16 \"main\" %9 %11 %13
+OpName %4 \"Uniforms\"
+OpMemberName %4 0 \"uColor\"
+OpMemberName %4 1 \"uTime\"
+OpName %6 \"uniforms\"
+OpName %9 \"aPos\"
+OpName %11 \"vT\"
+OpName %13 \"gl_Position\"
+OpName %16 \"main\"
+OpDecorate %4 Block
+OpMemberDecorate %4 0 Offset 0
+OpMemberDecorate %4 1 Offset 16
+OpDecorate %6 DescriptorSet 0
+OpDecorate %6 Binding 0
+OpDecorate %9 Location 0
+OpDecorate %11 Location 0
+OpDecorate %13 BuiltIn Position
+%2 = OpTypeFloat 32
+%3 = OpTypeVector %2 4
+%4 = OpTypeStruct %3 %2
+%5 = OpTypePointer Uniform %4
+%6 = OpVariable %5 Uniform
+%7 = OpTypeVector %2 3
+%8 = OpTypePointer Input %7
+%9 = OpVariable %8 Input
+%10 = OpTypePointer Output %2
+%11 = OpVariable %10 Output
+%12 = OpTypePointer Output %3
+%13 = OpVariable %12 Output
+%14 = OpTypeVoid
+%15 = OpTypeFunction %14
+%18 = OpTypeInt 32 1
+%19 = OpConstant %18 0
+%20 = OpTypePointer Uniform %3
+%23 = OpConstant %18 1
+%24 = OpTypePointer Uniform %2
+%16 = OpFunction %14 None %15
+%17 = OpLabel
+%21 = OpAccessChain %20 %6 %19
+%22 = OpLoad %3 %21
+OpStore %13 %22
+%25 = OpAccessChain %24 %6 %23
+%26 = OpLoad %2 %25
+OpStore %11 %26
+OpReturn
+OpFunctionEnd
+".to_string()), "Failed to emit vertex shader");
+
+    let frag = disassemble(&emit_shader(&progs[0].frag).unwrap()).unwrap();
+    assert!(frag.contains("OpExecutionMode %14 OriginUpperLeft\n"), "Failed to set the origin of a fragment shader");
+    assert!(frag.contains("OpName %8 \"gl_FragColor\"\n") && frag.contains("OpDecorate %8 Location 0\n"), "Failed to locate gl_FragColor at 0, got:\n{}", frag);
+    assert!(frag.contains("OpName %11 \"normal\"\n") && frag.contains("OpDecorate %11 Location 1\n"), "Failed to locate an output after gl_FragColor, got:\n{}", frag);
+}
+
+/// Lowers loops & short circuiting operators to structured control flow, w/ merge blocks
+#[test]
+fn test_spirv_control_flow() {
+    let e = |kind: ExprKind, typ: Type| Expr{ kind, typ };
+    let i = || e(ExprKind::Var("i".to_string()), Type::Int);
+    let int = |v: i32| e(ExprKind::Int(v), Type::Int);
+    let bin = |op: BinOp, lhs: Expr, rhs: Expr, typ: Type| e(ExprKind::Binary{ op, lhs: Box::new(lhs), rhs: Box::new(rhs) }, typ);
+    let decl = |name: &str, typ: Type, value: Option<Expr>| Stmt::Decl(glsl::VarDecl{ qualifier: None, typ, name: name.to_string(), value });
+
+    let ok = glsl::Function{
+        name: "ok".to_string(),
+        ret: Type::Bool,
+        params: vec![("x".to_string(), Type::Int)],
+        body: vec![Stmt::Return(Some(bin(BinOp::Lt, e(ExprKind::Var("x".to_string()), Type::Int), int(3), Type::Bool)))]
+    };
+    let call = e(ExprKind::Call{ name: "ok".to_string(), args: vec![i()] }, Type::Bool);
+    let main = glsl::Function{
+        name: "main".to_string(),
+        ret: Type::Void,
+        params: vec![],
+        body: vec![
+            decl("b", Type::Bool, Some(e(ExprKind::Bool(false), Type::Bool))),
+            Stmt::For{
+                init: Box::new(decl("i", Type::Int, Some(int(0)))),
+                cond: bin(BinOp::Lt, i(), int(4), Type::Bool),
+                post: Box::new(Stmt::Assign{ target: i(), value: bin(BinOp::Add, i(), int(1), Type::Int) }),
+                body: vec![Stmt::Assign{
+                    target: e(ExprKind::Var("b".to_string()), Type::Bool),
+                    value: bin(BinOp::And, bin(BinOp::Gt, i(), int(1), Type::Bool), call, Type::Bool)
+                }]
+            }
+        ]
+    };
+    let s = glsl::Shader{ stage: Stage::Vertex, decls: vec![Decl::Function(ok), Decl::Function(main)] };
+    let dis = disassemble(&emit_shader(&s).unwrap()).unwrap();
+    let main = &dis[dis.find("%12 = OpFunction").expect("Failed to emit main")..];
+    assert_eq!(main, "\
+%12 = OpFunction %10 None %11
+%20 = OpLabel
+%23 = OpVariable %22 Function
+%25 = OpVariable %15 Function
+OpStore %23 %21
+OpStore %25 %24
+OpBranch %26
+%26 = OpLabel
+OpLoopMerge %30 %29 None
+OpBranch %27
+%27 = OpLabel
+%31 = OpLoad %7 %25
+%33 = OpSLessThan %6 %31 %32
+OpBranchConditional %33 %28 %30
+%28 = OpLabel
+%34 = OpLoad %7 %25
+%36 = OpSGreaterThan %6 %34 %35
+OpSelectionMerge %38 None
+OpBranchConditional %36 %37 %38
+%37 = OpLabel
+%39 = OpLoad %7 %25
+%40 = OpFunctionCall %6 %9 %39
+OpBranch %38
+%38 = OpLabel
+%41 = OpPhi %6 %36 %28 %40 %37
+OpStore %23 %41
+OpBranch %29
+%29 = OpLabel
+%42 = OpLoad %7 %25
+%43 = OpIAdd %7 %42 %35
+OpStore %25 %43
+OpBranch %26
+%30 = OpLabel
+OpReturn
+OpFunctionEnd
+", "Failed to emit a loop w/ a short circuiting operator");
+}
+
+/// Lowers while loops w/ the condition in the header, & do-while loops w/ the condition in the continue block
+#[test]
+fn test_spirv_loops() {
+    let progs = compile_str("\
+vert v : () -> (Float vT) = {
+  mut t : Float = 0.0f
+  while t > 1.0f {
+    t -= 1.0f
+  }
+  do {
+    t *= 2.0f
+  } while t < 1.0f
+  out vT t
+}
+frag f : () -> () = {
+  1
+}
+p : Prog = mkProg v f
+");
+    let dis = disassemble(&emit_shader(&progs[0].vert).unwrap()).unwrap();
+    let loops = &dis[dis.find("OpBranch").expect("Failed to emit a loop")..];
+    assert_eq!(loops, "\
+OpBranch %15
+%15 = OpLabel
+OpLoopMerge %19 %18 None
+OpBranch %16
+%16 = OpLabel
+%20 = OpLoad %2 %14
+%23 = OpFOrdGreaterThan %22 %20 %21
+OpBranchConditional %23 %17 %19
+%17 = OpLabel
+%24 = OpLoad %2 %14
+%25 = OpFSub %2 %24 %21
+OpStore %14 %25
+OpBranch %18
+%18 = OpLabel
+OpBranch %15
+%19 = OpLabel
+OpBranch %26
+%26 = OpLabel
+OpLoopMerge %29 %28 None
+OpBranch %27
+%27 = OpLabel
+%30 = OpLoad %2 %14
+%32 = OpFMul %2 %30 %31
+OpStore %14 %32
+OpBranch %28
+%28 = OpLabel
+%33 = OpLoad %2 %14
+%34 = OpFOrdLessThan %22 %33 %21
+OpBranchConditional %34 %26 %29
+%29 = OpLabel
+%35 = OpLoad %2 %14
+OpStore %4 %35
+OpReturn
+OpFunctionEnd
+", "Failed to emit while & do-while loops");
+}
+
+/// Declares the built-in variables that a shader uses, & ends an invocation w/ a discard
+#[test]
+fn test_spirv_stages() {
+    let progs = compile_str("\
+vert v : (Vec2 aUV) -> (Vec2 vUV) = {
+  set gl_Position vec4(aUV, 0.0f, 1.0f)
+  set gl_PointSize 4.0f
+  out vUV aUV
+}
+frag f : (Vec2 vUV) -> () = {
+  if gl_FrontFacing { discard } else { set gl_FragColor vec4(gl_FragCoord.xy * vUV, 0.0f, 1.0f) }
+}
+p : Prog = mkProg v f
+");
+    let vert = disassemble(&emit_shader(&progs[0].vert).unwrap()).unwrap();
+    assert!(vert.contains("BuiltIn PointSize\n"), "Failed to declare 'gl_PointSize', got:\n{}", vert);
+    let frag = disassemble(&emit_shader(&progs[0].frag).unwrap()).unwrap();
+    assert!(frag.contains("BuiltIn FragCoord\n"), "Failed to declare 'gl_FragCoord', got:\n{}", frag);
+    assert!(frag.contains("BuiltIn FrontFacing\n"), "Failed to declare 'gl_FrontFacing', got:\n{}", frag);
+    assert!(!frag.contains("PointCoord"), "Declared 'gl_PointCoord' w/out using it, got:\n{}", frag);
+    assert!(frag.contains("OpKill\n"), "Failed to emit a discard, got:\n{}", frag);
+}
+
+/// Emits every overload of a function as its own function, & resolves each call to one of them
+#[test]
+fn test_spirv_overloads() {
+    let progs = compile_str("\
+let scale : Float -> Float = (x : Float) { x * 2.0f }
+let scale : Vec4 -> Vec4 = (v : Vec4) { v * 2.0f }
+vert v : (Vec4 aPos) -> () = {
+  set gl_Position scale(aPos) * scale(0.5f)
+}
+frag f : () -> () = {
+  set gl_FragColor vec4(1.0f)
+}
+p : Prog = mkProg v f
+");
+    let vert = disassemble(&emit_shader(&progs[0].vert).unwrap()).unwrap();
+    let ids : Vec<&str> = vert.lines().filter_map(|l| l.strip_prefix("OpName ")?.strip_suffix(" \"scale\"")).collect();
+    assert_eq!(ids.len(), 2, "Failed to emit both overloads, got:\n{}", vert);
+    for id in ids {
+        assert!(vert.lines().any(|l| l.contains("OpFunctionCall") && l.contains(&format!(" {id} "))), "Failed to call overload {id}, got:\n{}", vert);
+    }
+}
+
+/// Emits the examples, comparing their disassembly w/ snapshots in examples/spirv
+/// Set FDSSL_UPDATE_SNAPSHOTS to rewrite the snapshots instead
+#[test]
+fn test_spirv_examples() {
+    let dir = Path::new("examples/spirv");
+    for f in ["e0", "e2", "e3", "e4", "e5", "e6", "shaderCompExampleProg"] {
+        let src = fs::read_to_string(format!("examples/{f}.fdssl")).expect("Failed to read example");
+        for p in compile_str(&src) {
+            for (ext, s) in [("vert", &p.vert), ("frag", &p.frag)] {
+                let path = dir.join(format!("{}.{}.spvasm", p.name, ext));
+                let dis = emit_shader(s).and_then(|w| disassemble(&w)).expect("Failed to emit shader");
+                if std::env::var("FDSSL_UPDATE_SNAPSHOTS").is_ok() {
+                    fs::create_dir_all(dir).expect("Failed to create snapshot directory");
+                    fs::write(&path, &dis).expect("Failed to write snapshot");
+                }
+                let expected = fs::read_to_string(&path).expect("Failed to read snapshot");
+                assert_eq!(dis, expected, "Shader '{}' does not match its snapshot", path.display());
+            }
+        }
+    }
+}
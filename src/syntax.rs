@@ -1,5 +1,52 @@
 use std::fmt;
+use std::ops::Range;
 
+/// Span of source that a node was parsed from
+///
+/// Parsers only ever see the rest of the source, so spans are counted back from its end:
+/// 'rest' is the length of the source from the start of the span onwards, & 'len' the length of the span.
+/// The byte range, line & column are recovered from the source the span was parsed from.
+/// Spans are ignored when comparing nodes, so ASTs are equal when their structure is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub rest: usize,
+    pub len: usize,
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _: &Span) -> bool {
+        true
+    }
+}
+
+impl Span {
+    pub fn new(rest: usize, len: usize) -> Span {
+        Span{ rest, len }
+    }
+
+    /// Returns the smallest span that covers both this span & a later one
+    pub fn to(self, later: Span) -> Span {
+        let end = later.rest.saturating_sub(later.len).min(self.rest.saturating_sub(self.len));
+        Span{ rest: self.rest, len: self.rest.saturating_sub(end) }
+    }
+
+    /// Returns the byte range of this span in the source it was parsed from
+    pub fn range(&self, src: &str) -> Range<usize> {
+        let start = src.len().saturating_sub(self.rest);
+        start..(start + self.len).min(src.len())
+    }
+
+    /// Returns the line & column this span starts at in its source, both counted from 1
+    /// Columns count characters, not bytes
+    pub fn line_col(&self, src: &str) -> (usize, usize) {
+        let before = &src[..self.range(src).start];
+        let line = before.matches('\n').count() + 1;
+        let col = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+        (line, col)
+    }
+}
+
+#[allow(dead_code)]
 #[derive(Debug,PartialEq)]
 pub enum Type {
     Uint,
@@ -24,35 +71,43 @@ pub enum ParsedType {
     Tuple(Vec<ParsedType>),
     NamedTuple(Vec<(String, Box<ParsedType>)>), // stores indexed types for named tuples
     Function(Box<ParsedType>, Box<ParsedType>),
+    Shader(Stage, Box<ParsedType>, Box<ParsedType>), // stage w/ named inputs & named outputs
+    Array(Box<ParsedType>, usize), // fixed number of elements of the same type, i.e. `float[4]`
+    Overloaded(Vec<ParsedType>), // function types of a name w/ several signatures, such as the built-in `sin`
+    Poison, // type of an ill-typed expr, which agrees w/ any other type so its error is only reported once
 }
 
 /// User friendly dipslyaing of parsed types in TypeChecker errors
 impl fmt::Display for ParsedType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &*self {
+        match self {
             ParsedType::BaseType(s)     => write!(f, "{}", s),
             ParsedType::Tuple(v)        => {
-                write!(f, "(");
-                for (i,t) in v.into_iter().enumerate() {
-                    write!(f, "{}", t);
+                write!(f, "(")?;
+                for (i,t) in v.iter().enumerate() {
+                    write!(f, "{}", t)?;
                     if i < (v.len()-1) {
-                        write!(f, ", ");
+                        write!(f, ", ")?;
                     }
                 }
                 write!(f, ")")
             },
-            ParsedType::Function(t1,t2) => write!(f, "{} -> {}", format!("{}",t1), format!("{}",t2)),
+            ParsedType::Function(t1,t2) => write!(f, "{} -> {}", t1, t2),
             ParsedType::NamedTuple(v)   => {
-                write!(f, "(");
-                for (i,t) in v.into_iter().enumerate() {
-                    write!(f, "{}:{}", t.0, *t.1);
+                write!(f, "(")?;
+                for (i,t) in v.iter().enumerate() {
+                    write!(f, "{}:{}", t.0, *t.1)?;
                     if i < (v.len()-1) {
-                        write!(f, ", ");
+                        write!(f, ", ")?;
                     }
                 }
                 write!(f, ")")
                 // write!(f, "({})", format!("{:?}",v))
-            }
+            },
+            ParsedType::Shader(s,i,o)   => write!(f, "{} {} -> {}", s, i, o),
+            ParsedType::Array(t,n)      => write!(f, "{}[{}]", t, n),
+            ParsedType::Overloaded(v)   => write!(f, "{}", v.iter().map(|t| format!("({})", t)).collect::<Vec<_>>().join(" | ")),
+            ParsedType::Poison          => write!(f, "{{error}}"),
         }
     }
 }
@@ -118,26 +173,109 @@ impl fmt::Display for UOp {
     }
 }
 
+/// Stage of the GLSL pipeline that a shader is written for
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Stage::Vertex   => write!(f, "vert"),
+            Stage::Fragment => write!(f, "frag")
+        }
+    }
+}
+
+/// Body of a shader, either written out as a block of exprs or composed from other shaders
+#[derive(Debug,PartialEq,Clone)]
+pub enum ShaderBody {
+    Block(Vec<Expr>),
+    // composition of shaders, i.e. `v2 . v1`, which runs v1 followed by v2
+    Composed(Box<Expr>),
+}
+
+#[allow(dead_code)]
 #[derive(Debug,PartialEq)]
 pub struct Parameter {
     name: String,
     datatype: Type,
 }
 
-#[derive(Debug,PartialEq)]
+/// A type annotation, w/ the span it was written at
+#[derive(Debug, PartialEq, Clone)]
+pub struct Annotation {
+    pub typ: ParsedType,
+    pub span: Span,
+}
+
+impl From<ParsedType> for Annotation {
+    fn from(typ: ParsedType) -> Annotation {
+        Annotation{ typ, span: Span::default() }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
 pub enum AccessType {
     Name(String),
     Idx(Box<Expr>)
 }
 
-#[derive(Debug,PartialEq)]
-pub enum Expr {
+/// An expression, w/ the span of source it was parsed from
+/// Declarations are exprs too, so they also carry a span
+#[derive(Debug,PartialEq,Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+/// Builds an expr w/out a known span, such as one made by a later stage
+impl From<ExprKind> for Expr {
+    fn from(kind: ExprKind) -> Expr {
+        Expr{ kind, span: Span::default() }
+    }
+}
+
+impl Expr {
+    /// Describes an expr that is accessed in error messages, i.e. 'light.color' or 'f(..)[0]'
+    /// Only names & accesses of them are written out in full, everything else is abbreviated
+    pub fn describe(&self) -> String {
+        let short = |e: &Expr| match &e.kind {
+            ExprKind::I(i)   => i.to_string(),
+            ExprKind::Ref(n) => n.clone(),
+            _                => "..".to_string()
+        };
+        match &self.kind {
+            ExprKind::Ref(n)                        => n.clone(),
+            ExprKind::Access(b, AccessType::Name(p)) => format!("{}.{}", b.describe(), p),
+            ExprKind::Access(b, AccessType::Idx(i))  => format!("{}[{}]", b.describe(), short(i)),
+            ExprKind::App{fname, ..}                => format!("{}(..)", fname),
+            _                                       => "(..)".to_string()
+        }
+    }
+
+    /// Returns the component that an index names, i.e. 'x' in `v[x]`, as the original FDSSL syntax names components in brackets
+    /// Only a bare x, y, z or w names one, & only where that name is not bound, as otherwise it is an index like any other
+    pub fn named_component(&self) -> Option<&str> {
+        match &self.kind {
+            ExprKind::Ref(c) if ["x","y","z","w"].contains(&c.as_str()) => Some(c),
+            _                                                          => None
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub enum ExprKind {
     I(i32),
     B(bool),
     F(f32),
     D(f64),
     Ref(String),
     Return(Box<Expr>),
+    // ends a fragment shader invocation w/out writing any of its outputs
+    Discard,
     Vect(Vec<Expr>),
     NamedVect(Vec<(String,Expr)>),
     // Vect {
@@ -145,13 +283,13 @@ pub enum Expr {
     // },
     Def {
         name: String,
-        typ: ParsedType,
+        typ: Annotation,
         value: Box<Expr>,
 
     },
     DefMut {
         name: String,
-        typ: ParsedType,
+        typ: Annotation,
         value: Box<Expr>,
     },
     App {
@@ -169,6 +307,7 @@ pub enum Expr {
     },
     Update {
         target: String,
+        swizzle: Option<String>, // components of a vector target that are written, i.e. the `xy` of `set v.xy ...`
         value: Box<Expr>,
     },
     Branch {
@@ -183,13 +322,45 @@ pub enum Expr {
         //variable: Option<String>,
         body: Vec<Expr>
     },
-    Access(String, AccessType),
+    // loop that checks its condition before each run of its body
+    While {
+        cond: Box<Expr>,
+        body: Vec<Expr>
+    },
+    // loop that checks its condition after each run of its body, so the body always runs at least once
+    DoWhile {
+        body: Vec<Expr>,
+        cond: Box<Expr>
+    },
+    // access of the element of an expr by name or index, i.e. `p.x` or `v[0]`
+    Access(Box<Expr>, AccessType),
     Comment(Vec<String>),
     // parameterized abstraction, where each param has an explicit type
     Abs {
-        params: Vec<(String,ParsedType)>,
+        params: Vec<(String,Annotation)>,
         body: Vec<Expr>
-    }
+    },
+    // uniform value, supplied to every shader of a program from outside the pipeline
+    Uniform {
+        name: String,
+        typ: Annotation,
+    },
+    // shader for a given stage, w/ named inputs from the prior stage & named outputs for the next
+    Shader {
+        stage: Stage,
+        name: String,
+        inputs: Vec<(String,Annotation)>,
+        outputs: Vec<(String,Annotation)>,
+        body: ShaderBody
+    },
+    // program that links a vertex shader to a fragment shader
+    Prog {
+        name: String,
+        vert: String,
+        frag: String,
+    },
+    // source that failed to parse, which the parser skipped over to recover
+    Error,
 }
 
 // program is a vector of expressions
@@ -0,0 +1,640 @@
+/*
+
+Errors produced by the typechecker
+
+Every kind of error carries the data it was found w/ (the names, types & operators involved),
+so tests & tooling can match on them rather than on their messages.
+Each kind also has a stable code, such as E0003, w/ a long-form explanation that can be looked up
+through 'explain', or from the command line w/ '--explain E0003'.
+
+Codes are never reused, so a code always refers to the same kind of error.
+
+*/
+
+use crate::syntax::{BOp, ParsedType, Span, Stage, UOp};
+use crate::typechecker::TCEnv;
+use std::fmt;
+
+/// A typechecker error, located at the innermost expr it was found in once it passes back through it
+/// The kind is boxed, as errors are passed back through every expr they were found in
+#[derive(Debug, PartialEq, Clone)]
+pub struct TCError {
+    pub kind: Box<TCErrorKind>,
+    pub span: Option<Span>,
+}
+
+/// The kinds of errors the typechecker can produce, each w/ its own code
+#[derive(Debug, PartialEq, Clone)]
+pub enum TCErrorKind {
+    // a body or program w/ nothing in it
+    EmptyBody,
+    UnboundName { name: String },
+    // a definition or update whose value does not have the type of its binding
+    BindingMismatch { name: String, expected: ParsedType, actual: ParsedType },
+    UnknownFunction { name: String },
+    NotAFunction { name: String, actual: ParsedType },
+    ArgCount { name: String, expected: usize, actual: usize },
+    ArgMismatch { name: String, expected: ParsedType, actual: ParsedType },
+    // an operand of a type that an operator can never take, such as a bool for '+'
+    UnsupportedOperand { op: BOp, actual: ParsedType },
+    BinOpMismatch { op: BOp, lhs: ParsedType, rhs: ParsedType },
+    UnOpMismatch { op: UOp, actual: ParsedType },
+    // in `f . g`, the return type of 'g' must be the argument type of 'f'
+    ComposeMismatch { expected: ParsedType, actual: ParsedType },
+    ConditionNotBool { actual: ParsedType },
+    BranchMismatch { then: ParsedType, otherwise: ParsedType },
+    UnknownProperty { name: String, prop: String },
+    NotANamedTuple { name: String, actual: ParsedType },
+    // indexing of a value that has no elements, such as an int
+    NotIndexable { name: String, actual: ParsedType },
+    // an expr that is not checked where it appears, such as a shader or program declared within a body
+    UnsupportedExpr { expr: String },
+    UniformRedeclared { name: String, expected: ParsedType, actual: ParsedType },
+    UndefinedShader { name: String },
+    NotComposable { expr: String },
+    WrongStage { name: String, expected: Stage, actual: ParsedType },
+    ShaderSignatureMismatch { name: String, expected: ParsedType, actual: ParsedType },
+    StageMismatch { lhs: Stage, rhs: Stage },
+    // an output of one composed shader used w/ another type as an input of the next
+    ComposedIOMismatch { name: String, expected: ParsedType, actual: ParsedType },
+    // the same input or output w/ different types in two composed shaders
+    ConflictingIO { name: String, first: ParsedType, second: ParsedType },
+    VaryingMismatch { prog: String, name: String, expected: ParsedType, actual: ParsedType },
+    MissingVarying { prog: String, name: String, vert: String, frag: String },
+    // shader inputs & outputs must be named tuples
+    MalformedShaderIO { actual: ParsedType },
+    // assignment to a 'let' binding or a parameter
+    ImmutableUpdate { name: String },
+    IndexNotInt { actual: ParsedType },
+    // a constant index outside of the elements of a value
+    IndexOutOfBounds { name: String, index: i32, len: usize },
+    // elements of a tuple have their own types, so its index must be known statically
+    TupleIndexNotConstant { name: String },
+    // a named tuple that gives a property more than once
+    DuplicateProperty { prop: String },
+    // a return of a value that is not of the type that its function returns
+    ReturnMismatch { expected: ParsedType, actual: ParsedType },
+    ReturnOutsideFunction,
+    // code after a return, which can never run
+    UnreachableCode,
+    // a vector or matrix constructor given too few or too many components
+    ConstructorArity { name: String, expected: usize, actual: usize },
+    // a vector or matrix constructor given an argument that is not a scalar, vector or matrix
+    ConstructorArg { name: String, actual: ParsedType },
+    // a swizzle w/ a selector that is not a component of any vector, or w/ more than 4 of them
+    InvalidSwizzle { name: String, swizzle: String },
+    // a swizzle that mixes the selectors of positions, colors & texture coordinates
+    MixedSwizzle { name: String, swizzle: String },
+    SwizzleOutOfRange { name: String, swizzle: String, len: usize },
+    // a swizzle that writes to the same component more than once
+    DuplicateSwizzleTarget { name: String, swizzle: String },
+    // a call of an overloaded function w/ args that none of its signatures take
+    NoMatchingOverload { name: String, actual: ParsedType, overloads: Vec<ParsedType> },
+    // a built-in used in a shader of a stage that it is not available in, such as 'dFdx' in a vertex shader
+    StageRestricted { name: String, stage: Stage, actual: Stage },
+    // a function called in a shader of a stage that a built-in it uses, directly or not, is not available in
+    StageRestrictedCall { name: String, builtin: String, stage: Stage, actual: Stage },
+    // a function defined again w/ a signature that it already has
+    FunctionRedefined { name: String, sig: ParsedType },
+    // an overload that takes the same args as another signature of its function, but returns another type
+    OverloadReturnMismatch { name: String, args: ParsedType, expected: ParsedType, actual: ParsedType },
+    // a function defined w/ the name of a built-in function
+    BuiltinRedefined { name: String },
+}
+
+use TCErrorKind::*;
+
+impl TCErrorKind {
+    /// Returns the stable code of this kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            EmptyBody                   => "E0001",
+            UnboundName{..}             => "E0002",
+            BindingMismatch{..}         => "E0003",
+            UnknownFunction{..}         => "E0004",
+            NotAFunction{..}            => "E0005",
+            ArgCount{..}                => "E0006",
+            ArgMismatch{..}             => "E0007",
+            UnsupportedOperand{..}      => "E0008",
+            BinOpMismatch{..}           => "E0009",
+            UnOpMismatch{..}            => "E0010",
+            ComposeMismatch{..}         => "E0011",
+            ConditionNotBool{..}        => "E0012",
+            BranchMismatch{..}          => "E0013",
+            UnknownProperty{..}         => "E0014",
+            NotANamedTuple{..}          => "E0015",
+            NotIndexable{..}            => "E0016",
+            UnsupportedExpr{..}         => "E0017",
+            UniformRedeclared{..}       => "E0018",
+            UndefinedShader{..}         => "E0019",
+            NotComposable{..}           => "E0020",
+            WrongStage{..}              => "E0021",
+            ShaderSignatureMismatch{..} => "E0022",
+            StageMismatch{..}           => "E0023",
+            ComposedIOMismatch{..}      => "E0024",
+            ConflictingIO{..}           => "E0025",
+            VaryingMismatch{..}         => "E0026",
+            MissingVarying{..}          => "E0027",
+            MalformedShaderIO{..}       => "E0028",
+            ImmutableUpdate{..}         => "E0029",
+            IndexNotInt{..}             => "E0030",
+            IndexOutOfBounds{..}        => "E0031",
+            TupleIndexNotConstant{..}   => "E0032",
+            DuplicateProperty{..}       => "E0033",
+            ReturnMismatch{..}          => "E0034",
+            ReturnOutsideFunction       => "E0035",
+            UnreachableCode             => "E0036",
+            ConstructorArity{..}        => "E0037",
+            ConstructorArg{..}          => "E0038",
+            InvalidSwizzle{..}          => "E0039",
+            MixedSwizzle{..}            => "E0040",
+            SwizzleOutOfRange{..}       => "E0041",
+            DuplicateSwizzleTarget{..}  => "E0042",
+            NoMatchingOverload{..}      => "E0043",
+            StageRestricted{..}         => "E0044",
+            StageRestrictedCall{..}     => "E0045",
+            // E0046 was for calls that several signatures take, which matching args exactly rules out
+            FunctionRedefined{..}       => "E0047",
+            OverloadReturnMismatch{..}  => "E0048",
+            BuiltinRedefined{..}        => "E0049",
+        }
+    }
+}
+
+impl fmt::Display for TCErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmptyBody => write!(f, "Unable to typecheck an empty body!"),
+            UnboundName{name} => write!(f, "Non-existant binding '{name}' referenced, perhaps you meant to declare it first with 'let' or 'mut'?"),
+            BindingMismatch{name, expected, actual} => write!(f, "Definition of '{name}' has type '{expected}', but was assigned to a value of type '{actual}'"),
+            UnknownFunction{name} => write!(f, "Function '{name}' does not exist"),
+            NotAFunction{name, actual} => write!(f, "'{name}' is not a function, it has type '{actual}'"),
+            ArgCount{name, expected, actual} => write!(f, "Function '{name}' takes {expected} argument(s), but was given {actual}"),
+            ArgMismatch{name, expected, actual} => write!(f, "Function '{name}' expects arguments of type '{expected}', but was given '{actual}'"),
+            UnsupportedOperand{op, actual} => write!(f, "Operator {op} does not support '{actual}' as an argument"),
+            BinOpMismatch{op, lhs, rhs} => write!(f, "Operator {op} does not support argument types '{lhs}' & '{rhs}'"),
+            UnOpMismatch{op, actual} => write!(f, "Unary operator '{op}' does not support an argument of type '{actual}'"),
+            ComposeMismatch{expected, actual} => write!(f, "Return type '{actual}' is not the same as argument type '{expected}' in function composition"),
+            ConditionNotBool{actual} => write!(f, "Condition was expected to be of type 'bool', but got an expression of type '{actual}' instead"),
+            BranchMismatch{then, otherwise} => write!(f, "'If' branches were expected to have the same type, but have differing types of '{then}' and '{otherwise}' instead"),
+            UnknownProperty{name, prop} => write!(f, "Property '{prop}' on tuple '{name}' is not defined"),
+            NotANamedTuple{name, actual} => write!(f, "Expected '{name}' to be a named tuple, but it has type '{actual}' instead"),
+            NotIndexable{name, actual} => write!(f, "'{name}' cannot be indexed, as it has type '{actual}'"),
+            UnsupportedExpr{expr} => write!(f, "Unrecognized expression '{expr}'"),
+            UniformRedeclared{name, expected, actual} => write!(f, "Uniform '{name}' was declared with type '{expected}', but was re-declared with type '{actual}'"),
+            UndefinedShader{name} => write!(f, "Shader '{name}' is not defined"),
+            NotComposable{expr} => write!(f, "Only shaders may be composed into a shader, but got '{expr}' instead"),
+            WrongStage{name, expected, actual} => write!(f, "Expected '{name}' to be a '{expected}' shader, but it has type '{actual}' instead"),
+            ShaderSignatureMismatch{name, expected, actual} => write!(f, "Shader '{name}' is declared as '{expected}', but its composition has type '{actual}'"),
+            StageMismatch{lhs, rhs} => write!(f, "Cannot compose a '{lhs}' shader w/ a '{rhs}' shader"),
+            ComposedIOMismatch{name, expected, actual} => write!(f, "Shader composition produces '{name}' w/ type '{actual}', but it is used w/ type '{expected}'"),
+            ConflictingIO{name, first, second} => write!(f, "'{name}' has type '{first}' in one shader, but type '{second}' in another"),
+            VaryingMismatch{prog, name, expected, actual} => write!(f, "Program '{prog}' links fragment input '{name}' of type '{expected}' to a vertex output of type '{actual}'"),
+            MissingVarying{prog, name, vert, frag} => write!(f, "Program '{prog}' is missing input '{name}' for fragment shader '{frag}', which is not an output of vertex shader '{vert}'"),
+            MalformedShaderIO{actual} => write!(f, "Expected shader inputs & outputs to be a named tuple, but got '{actual}' instead"),
+            ImmutableUpdate{name} => write!(f, "Cannot assign to '{name}', as it is immutable"),
+            IndexNotInt{actual} => write!(f, "Index was expected to be of type 'int', but got an expression of type '{actual}' instead"),
+            IndexOutOfBounds{name, index, len} => write!(f, "Index {index} is out of bounds for '{name}', which has {len} element(s)"),
+            TupleIndexNotConstant{name} => write!(f, "Tuple '{name}' may only be indexed by a constant"),
+            DuplicateProperty{prop} => write!(f, "Property '{prop}' is given more than once in a named tuple"),
+            ReturnMismatch{expected, actual} => write!(f, "Function was expected to return a value of type '{expected}', but returns '{actual}' instead"),
+            ReturnOutsideFunction => write!(f, "'return' can only be used within the body of a function"),
+            UnreachableCode => write!(f, "Code after a 'return' or 'discard' is unreachable"),
+            ConstructorArity{name, expected, actual} => write!(f, "Constructor '{name}' needs {expected} component(s), but was given {actual}"),
+            ConstructorArg{name, actual} => write!(f, "Constructor '{name}' cannot take components from a value of type '{actual}'"),
+            InvalidSwizzle{name, swizzle} => write!(f, "'{swizzle}' is not a swizzle of vector '{name}', which selects up to 4 of 'xyzw', 'rgba' or 'stpq'"),
+            MixedSwizzle{name, swizzle} => write!(f, "Swizzle '{swizzle}' of '{name}' mixes the selectors of 'xyzw', 'rgba' & 'stpq'"),
+            SwizzleOutOfRange{name, swizzle, len} => write!(f, "Swizzle '{swizzle}' selects a component outside of '{name}', which only has {len}"),
+            DuplicateSwizzleTarget{name, swizzle} => write!(f, "Cannot assign to '{name}.{swizzle}', as it writes to the same component more than once"),
+            NoMatchingOverload{name, actual, overloads} => {
+                write!(f, "Function '{name}' has no signature that takes args of type '{actual}', it takes one of:")?;
+                overloads.iter().try_for_each(|t| write!(f, "\n    {t}"))
+            },
+            StageRestricted{name, stage, actual} => write!(f, "'{name}' is only available in '{stage}' shaders, but was used in a '{actual}' shader"),
+            StageRestrictedCall{name, builtin, stage, actual} => write!(f, "Function '{name}' uses '{builtin}', which is only available in '{stage}' shaders, but was called in a '{actual}' shader"),
+            FunctionRedefined{name, sig} => write!(f, "Function '{name}' is already defined w/ signature '{sig}'"),
+            OverloadReturnMismatch{name, args, expected, actual} => {
+                write!(f, "Function '{name}' already takes args of type '{args}' & returns '{expected}', so it cannot be overloaded to return '{actual}'")
+            },
+            BuiltinRedefined{name} => write!(f, "'{name}' is a built-in function, which cannot be redefined or overloaded"),
+        }
+    }
+}
+
+impl TCError {
+    /// Locates an error at the span of an expr, unless it was already located within that expr
+    pub fn at(mut self, span: Span) -> TCError {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl From<TCErrorKind> for TCError {
+    fn from(kind: TCErrorKind) -> TCError {
+        TCError{ kind: Box::new(kind), span: None }
+    }
+}
+
+impl fmt::Display for TCError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code(), self.kind)
+    }
+}
+
+/// Every error found in a program, w/ the env it was typechecked to as best as possible
+/// Errors are in the order they were found, w/out any that only follow from an earlier one
+#[derive(Debug, PartialEq, Clone)]
+pub struct TCErrors {
+    pub errors: Vec<TCError>,
+    // boxed, as the env is large next to the errors
+    pub env: Box<TCEnv>,
+}
+
+impl TCErrors {
+    /// Returns the kinds of these errors, in order
+    #[cfg(test)]
+    pub fn kinds(&self) -> Vec<TCErrorKind> {
+        self.errors.iter().map(|e| (*e.kind).clone()).collect()
+    }
+}
+
+impl fmt::Display for TCErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))
+    }
+}
+
+/// Returns the long-form explanation of an error code, if there is an error w/ that code
+pub fn explain(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "E0001" => "\
+A body was empty, such as the body of a function or a branch, or the whole program.
+Every body has the type of its last expression, so it needs at least one.
+
+    let f : int -> int = (x : int) { }     // error, nothing to return
+
+Give the body an expression to produce its value.",
+
+        "E0002" => "\
+A name was used that is not bound at that point.
+Names must be declared w/ 'let', 'mut' or 'uniform', or be a parameter or shader input, before they are used.
+
+    let y : int = x + 1     // error, 'x' is not declared
+
+Declare the name first, or check it for typos.",
+
+        "E0003" => "\
+The value of a definition or update does not have the type of its binding.
+FDSSL never converts between types implicitly.
+
+    let x : float = 1       // error, '1' is an int, use '1.0f'
+
+Change the declared type, or the value so that it has the declared type.",
+
+        "E0004" => "\
+A function was called that is not declared.
+
+    let y : int = double(2)     // error, 'double' is not declared
+
+Declare the function before calling it, or check its name for typos.",
+
+        "E0005" => "\
+A name was called as a function, but it has some other type.
+
+    let x : int = 1
+    let y : int = x(2)      // error, 'x' is an int
+
+Only names w/ a function type can be called.",
+
+        "E0006" => "\
+A function was called w/ the wrong number of arguments.
+Functions of several parameters take a tuple, & must be given one argument per element.
+
+    let add : (int, int) -> int = (a : int, b : int) { a + b }
+    let y : int = add(1)        // error, 'add' takes 2 arguments",
+
+        "E0007" => "\
+A function was called w/ arguments that do not have the types of its parameters.
+
+    let double : int -> int = (x : int) { x * 2 }
+    let y : int = double(true)      // error, 'double' takes an int",
+
+        "E0008" => "\
+An operator was given an operand of a type it never accepts,
+such as a bool for an arithmetic operator or a tuple for any operator.
+
+    let x : int = 1 + true      // error, '+' does not take bools",
+
+        "E0009" => "\
+A binary operator was given operands it does not accept together.
+Both operands of most operators must have the same type, & some operators only take certain types,
+such as '%' & the bitwise operators, which only take ints.
+
+    let x : float = 1.0f + 2    // error, 'float' & 'int' do not mix
+
+Convert one operand, so that both have the same type.",
+
+        "E0010" => "\
+A unary operator was given an operand it does not accept.
+'-' takes an int, float or double, & '!' takes a bool.
+
+    let x : bool = !1       // error, '!' takes a bool",
+
+        "E0011" => "\
+Two functions were composed w/ '.', but the return type of the second is not the argument type of the first.
+In `f . g` the result of 'g' is passed on to 'f'.
+
+    let f : int -> int = ...
+    let g : int -> bool = ...
+    let h : int -> int = f . g      // error, 'g' returns a bool, but 'f' takes an int",
+
+        "E0012" => "\
+The condition of an 'if' or a loop is not a bool.
+FDSSL does not treat numbers as truthy.
+
+    if(1) { 2 } else { 3 }      // error, use a comparison such as 'x > 0' instead
+    while n { n -= 1 }          // error, likewise",
+
+        "E0013" => "\
+The branches of an 'if' have different types.
+An 'if' is an expression, so both of its branches must produce a value of the same type.
+
+    if(c) { 1 } else { true }       // error, 'int' & 'bool'",
+
+        "E0014" => "\
+A property was accessed on a named tuple that has no property w/ that name.
+
+    let p : (x : int, y : int) = ...
+    let z : int = p.z       // error, 'p' has no property 'z'",
+
+        "E0015" => "\
+A property was accessed on a value that is not a named tuple.
+Only named tuples have properties.
+
+    let x : int = 1
+    let y : int = x.y       // error, 'x' is an int",
+
+        "E0016" => "\
+A value was indexed that has no elements.
+Only tuples, vectors, matrices & arrays can be indexed.
+
+    let x : int = 1
+    let y : int = x[0]      // error, 'x' is an int",
+
+        "E0017" => "\
+An expression was used where the typechecker does not support it.
+Shaders & programs may only be declared at the top level of a program, which the parser already requires,
+so this is only found in programs that were built by other means.",
+
+        "E0018" => "\
+A uniform was declared more than once w/ different types.
+Uniforms are shared by every shader of a program, so each must have a single type.
+
+    uniform float uTime
+    uniform int uTime       // error, 'uTime' is a float",
+
+        "E0019" => "\
+A shader was referenced that is not declared, either in a composition or by a program.
+Shaders must be declared before they are composed or linked w/ 'mkProg'.",
+
+        "E0020" => "\
+Something other than a shader was composed into a shader.
+A composed shader may only compose other shaders w/ '.'.
+
+    vert v : (vec3 aPos) -> (vec4 pos) = v2 . 1     // error, '1' is not a shader",
+
+        "E0021" => "\
+A shader of the wrong stage was used, such as a fragment shader given to 'mkProg' as its vertex shader.
+'mkProg' takes a vertex shader ('vert') followed by a fragment shader ('frag').",
+
+        "E0022" => "\
+A composed shader was declared w/ inputs & outputs that differ from those of its composition.
+The inputs of `s2 . s1` are those of both shaders, less those produced by 's1', & its outputs are those of both.",
+
+        "E0023" => "\
+Shaders of different stages were composed.
+Only vertex shaders may be composed w/ vertex shaders, & fragment shaders w/ fragment shaders.",
+
+        "E0024" => "\
+In a shader composition `s2 . s1`, 's1' produces an output that 's2' takes as an input of another type.
+An output of the first shader is passed to the second, so both must agree on its type.",
+
+        "E0025" => "\
+Composed shaders declare the same input or output w/ different types.
+Inputs & outputs of composed shaders are merged by name, so each name must have a single type.",
+
+        "E0026" => "\
+A program links a vertex shader output to a fragment shader input of another type.
+Fragment inputs are passed along from the vertex outputs w/ the same name, so both must agree on its type.",
+
+        "E0027" => "\
+A program links a fragment shader w/ an input that its vertex shader does not output.
+Every input of the fragment shader must be produced by the vertex shader.",
+
+        "E0028" => "\
+The inputs or outputs of a shader are not a named tuple.
+This can only come from a malformed shader type, as shader declarations always name their inputs & outputs.",
+
+        "E0029" => "\
+A name was assigned to, but it is immutable.
+Bindings declared w/ 'let' & the parameters of a function cannot be updated once bound.
+
+    let x : int = 1
+    x = x + 1      // error, 'x' was declared w/ 'let'
+
+Declare the binding w/ 'mut' instead, or bind the new value to a name of its own.",
+
+        "E0030" => "\
+A value was indexed by something other than an int.
+
+    let v : vec3 = ...
+    let x : float = v[1.0f]     // error, '1.0f' is a float",
+
+        "E0031" => "\
+A value was indexed by a constant that is not the index of any of its elements.
+Indices start at 0, so the last element of a value w/ 'n' elements is at 'n - 1'.
+
+    let v : vec3 = ...
+    let z : float = v[3]        // error, 'v' only has elements 0 to 2",
+
+        "E0032" => "\
+A tuple was indexed by an expression that is not a constant.
+Each element of a tuple has its own type, so the element that is indexed must be known when typechecking.
+
+    let t : (int, bool) = (1, true)
+    let x : int = t[i]          // error, 'i' is not a constant
+
+Vectors, matrices & arrays have elements of a single type, so they may be indexed by any int.",
+
+        "E0033" => "\
+A named tuple was built w/ the same property more than once.
+Properties are accessed by name, so each must be unique.
+
+    let p : (x : int, x : int) = (x: 1, x: 2)     // error, 'x' is given twice",
+
+        "E0034" => "\
+A function returns a value of another type than the one it was declared to return.
+Every 'return' of a function, & the last expr of its body, must produce a value of its return type.
+A function w/out a declared type must return values of the same type as its body instead.
+
+    let f : int -> int = (x : int) {
+        if x > 0 { return 1.0f } else { x }     // error, '1.0f' is a float
+    }",
+
+        "E0035" => "\
+A 'return' was used outside of the body of a function, such as at the top level or in a shader.
+Only functions return a value, so there is nothing for the 'return' to return from.",
+
+        "E0036" => "\
+Code follows a 'return' or 'discard' in the same body, so it can never run.
+A body also returns when both branches of an 'if', or the body of a 'do-while', always return or discard.
+
+    let f : int -> int = (x : int) {
+        return x
+        x + 1       // error, never runs
+    }
+
+Remove the code, or move the 'return' after it.",
+
+        "E0037" => "\
+A vector or matrix was constructed from the wrong number of components.
+The components of every argument are used in order, so 'vec4' needs 4 of them & 'mat3' needs 9.
+Every argument must contribute to the result, so no more may be given than are needed.
+
+    let pos : vec3 = ...
+    let p : vec4 = vec4(pos, 1.0f)          // ok, 3 + 1 components
+    let q : vec4 = vec4(pos)                // error, only 3 components
+    let r : vec4 = vec4(pos, 1.0f, 1.0f)    // error, the last argument is not used
+
+A single scalar is used for every component instead, or for the diagonal of a matrix.
+A single vector w/ at least as many components is converted, i.e. 'vec2(pos)', as is a single matrix into another matrix.",
+
+        "E0038" => "\
+A vector or matrix was constructed from a value that has no components, such as a tuple or function.
+Constructors only take scalars, vectors & matrices, & a matrix only takes another matrix on its own.
+
+    let t : (float, float) = (1.0f, 2.0f)
+    let v : vec2 = vec2(t)      // error, 't' is a tuple",
+
+        "E0039" => "\
+A vector was accessed by a name that is not a swizzle.
+Swizzles select up to 4 components of a vector by name, from one of 3 sets of selectors:
+'xyzw' for positions, 'rgba' for colors & 'stpq' for texture coordinates.
+
+    let v : vec4 = ...
+    let a : vec2 = v.xy         // ok
+    let b : float = v.foo       // error, 'f' & 'o' are not selectors
+    let c : vec4 = v.xyzwx      // error, 5 components",
+
+        "E0040" => "\
+A swizzle used selectors from more than one set, such as 'x' & 'r'.
+Each set names the same components, so a swizzle must only use one of them.
+
+    let v : vec4 = ...
+    let a : vec2 = v.xr         // error, use 'v.xx' or 'v.rr' instead",
+
+        "E0041" => "\
+A swizzle selected a component that the vector does not have.
+A vec2 only has the components 'x' & 'y', & a vec3 does not have 'w'.
+
+    let v : vec2 = ...
+    let a : vec3 = v.xyz        // error, 'v' has no 'z'",
+
+        "E0042" => "\
+A swizzle that is assigned to writes to the same component more than once.
+A swizzle may repeat a component when it is read, but the value written to it would be ambiguous.
+
+    mut v : vec4 = ...
+    let a : vec2 = v.xx         // ok
+    set v.xx vec2(1.0f, 2.0f)   // error, 'x' is written twice",
+
+        "E0043" => "\
+An overloaded function was called w/ args that none of its signatures take.
+Most built-in functions are overloaded for floats & each size of vector, but their args must agree on one of them,
+as args are never converted from one type to another.
+
+    let a : float = sin(1.0f)               // ok
+    let b : vec3 = mix(v, w, 0.5f)          // ok, if 'v' & 'w' are vec3s
+    let c : float = sin(1)                  // error, no signature of 'sin' takes an int
+    let d : vec3 = mix(v, 1.0f, 0.5f)       // error, 'v' & '1.0f' are of different types",
+
+        "E0044" => "\
+A built-in was used in a shader of a stage that it is not available in.
+Vertex & fragment shaders run on different hardware, so some built-ins only exist in one of them:
+'gl_Position', 'gl_PointSize', 'texture2DLod' & 'texture2DProjLod' in vertex shaders, &
+'gl_FragColor', 'gl_FragCoord', 'gl_FrontFacing', 'gl_PointCoord', 'texture2D', 'texture2DProj',
+'dFdx', 'dFdy', 'fwidth' & 'discard' in fragment shaders.
+
+    vert v : (vec2 aUV) -> (vec4 vCol) = {
+        set vCol texture2D(uTex, aUV)       // error, 'texture2D' samples in fragment shaders
+    }",
+
+        "E0045" => "\
+A function was called in a shader of a stage that a built-in it uses is not available in.
+A function may use the built-ins of either stage, but may then only be called by shaders of that stage,
+including through any other function that calls it. See E0044 for the built-ins of each stage.
+
+    let shade : vec2 -> vec4 = (uv : vec2) { texture2D(uTex, uv) }
+    vert v : (vec2 aUV) -> (vec4 vCol) = {
+        set vCol shade(aUV)     // error, 'shade' samples a texture w/ 'texture2D'
+    }",
+
+        "E0047" => "\
+A function was defined again w/ a signature that it already has.
+Functions of the same name are overloaded, so each definition must take args of other types.
+
+    let scale : float -> float = (x : float) { x * 2.0f }
+    let scale : vec3 -> vec3 = (v : vec3) { v * 2.0f }     // ok, takes other args
+    let scale : float -> float = (x : float) { x * 3.0f }  // error, 'scale' already takes a float
+
+Give the new function another name, or remove the definition that is not needed.",
+
+        "E0048" => "\
+A function was overloaded w/ a signature that only differs from another in its return type.
+A call is resolved by the types of its args alone, so the two signatures could not be told apart.
+
+    let scale : float -> float = (x : float) { x * 2.0f }
+    let scale : float -> int = (x : float) { 2 }           // error, 'scale' already takes a float
+
+Give one of the functions another name, or have them take args of other types.",
+
+        "E0049" => "\
+A function was defined w/ the name of a built-in function.
+Built-in functions, such as 'sin' or 'mix', can neither be redefined nor overloaded.
+
+    let sin : float -> float = (x : float) { x }           // error, 'sin' is a built-in
+    let mySin : float -> float = (x : float) { sin(x) }    // ok",
+
+        _ => return None
+    })
+}
+
+//
+//
+// TC ERROR TESTS
+//
+//
+
+#[test]
+fn test_explain() {
+    // every code up to the last has an explanation, but for the retired E0046, & there are no others
+    for i in (1..=49).filter(|i| *i != 46) {
+        assert!(explain(&format!("E{:04}", i)).is_some(), "Missing an explanation for E{:04}", i);
+    }
+    assert_eq!(explain("E0046"), None, "Explained a retired code");
+    assert_eq!(explain("E0050"), None, "Explained an unknown code");
+    assert_eq!(explain("nonsense"), None, "Explained a nonsense code");
+
+    let e = TCError::from(UnboundName{ name: "x".to_string() });
+    assert_eq!(e.code(), "E0002", "Wrong code for an unbound name");
+    assert!(explain(e.code()).unwrap().contains("not bound"), "Wrong explanation for an unbound name");
+    assert_eq!(
+        e.to_string(),
+        "[E0002] Non-existant binding 'x' referenced, perhaps you meant to declare it first with 'let' or 'mut'?",
+        "Wrong message for an unbound name"
+    );
+}
@@ -48,7 +48,12 @@ Typing Relation
 
 use crate::syntax;
 
+use crate::tc_error::{TCError, TCErrorKind, TCErrors};
+use crate::builtins;
+
 use syntax::Expr;
+use syntax::ExprKind;
+use syntax::Annotation;
 use syntax::BOp;
 use syntax::UOp;
 use syntax::Program;
@@ -57,25 +62,134 @@ use syntax::ParsedType::Tuple;
 use syntax::ParsedType::NamedTuple;
 use syntax::ParsedType::Function;
 use syntax::ParsedType::BaseType;
+use syntax::ParsedType::Poison;
+use syntax::ParsedType::Array;
+use syntax::ParsedType::Overloaded;
+use syntax::Stage;
+use syntax::ShaderBody;
 use syntax::AccessType::Name;
+use syntax::AccessType::Idx;
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::OnceLock;
 
 
 // Type env, contains bindings of 'things' to types...these can be Exprs or Names
 // w/ a HashMap our insertions & lookups pretty bad at O(n), but that's in the worst possible case, on average we should be seeing O(1)
-type TCEnv = HashMap<String,ParsedType>;
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TCEnv {
+    types: HashMap<String,ParsedType>,
+    // names bound by 'let' or as a parameter, which may not be updated
+    immutable: HashSet<String>,
+    // signatures of functions that use a stage-specific built-in, directly or through the functions they call, w/ that built-in
+    // each signature is identified by its args, as an overloaded function may use a built-in in some signatures alone
+    staged: HashMap<String,Vec<(ParsedType,String)>>,
+}
+
+impl TCEnv {
+    pub fn new() -> TCEnv {
+        TCEnv::default()
+    }
+
+    /// Returns the type that a name is bound to
+    pub fn get(&self, n: &str) -> Option<&ParsedType> {
+        self.types.get(n)
+    }
+
+    pub fn contains_key(&self, n: &str) -> bool {
+        self.types.contains_key(n)
+    }
+
+    /// Binds a name to a type, w/out changing whether it is immutable
+    pub fn insert(&mut self, n: String, t: ParsedType) {
+        self.types.insert(n, t);
+    }
+
+    /// Returns whether a name was bound by 'let' or as a parameter
+    fn is_immutable(&self, n: &str) -> bool {
+        self.immutable.contains(n)
+    }
+
+    /// Marks a name as immutable, or clears the mark when it is rebound by 'mut'
+    /// The mark is part of the env, so it is scoped along w/ the binding it marks
+    fn set_immutable(&mut self, n: &str, immutable: bool) {
+        if immutable {
+            self.immutable.insert(n.to_string());
+        } else {
+            self.immutable.remove(n);
+        }
+    }
+
+    /// Returns the stage-specific built-in that the signature of a function w/ the given args is marked w/, see `mark_stage`
+    fn staged(&self, n: &str, args: &ParsedType) -> Option<&String> {
+        self.staged.get(n)?.iter().find(|(a,_)| a == args).map(|(_,b)| b)
+    }
+
+    /// Marks the signature of a function w/ the given args w/ a stage-specific built-in
+    /// Like the mark of immutability, it is scoped along w/ the binding it marks
+    fn set_staged(&mut self, n: &str, args: &ParsedType, builtin: String) {
+        self.staged.entry(n.to_string()).or_default().push((args.clone(), builtin));
+    }
+
+    /// Clears the marks of every signature of a function, once its name is bound to something else
+    fn clear_staged(&mut self, n: &str) {
+        self.staged.remove(n);
+    }
+}
+
+/// Context that an expr is checked in, apart from the bindings of its env
+/// Unlike the env, it is the same for a whole body, so it is passed down to the exprs of that body rather than returned
+#[derive(Debug, Clone, Default)]
+struct TCContext {
+    // type returned by the enclosing function, if the expr is within the body of one
+    ret: Option<ParsedType>,
+    // stage of the enclosing shader, if the expr is within the body of one
+    // only the bodies of shaders have a stage, so functions may use the built-ins of either stage until a shader calls them
+    stage: Option<Stage>,
+    // first stage-specific built-in that the value being bound reaches, directly or through a function it calls
+    // shared by the exprs of that value, as it is recorded while they are checked, see `mark_stage`
+    reached: Rc<RefCell<Option<String>>>,
+}
+
+impl TCContext {
+    /// Records that the value being bound reaches a stage-specific built-in, unless it already reached another
+    fn reach(&self, builtin: &str) {
+        self.reached.borrow_mut().get_or_insert_with(|| builtin.to_string());
+    }
+}
 
 // A positive type checked result
 pub type TypeChecked = (ParsedType,TCEnv);
 
-// A negative type checked result
-// Simple type check error, for now, should have locatily at some point
-type TCError = String;
-
 // Result from typechecking, either a valid typechecked result or an error
 pub type TCResult = Result<TypeChecked, TCError>;
 
+// Named types, such as the inputs & outputs of a shader
+pub type NamedTypes = Vec<(String, ParsedType)>;
+
+/// A vertex shader & a fragment shader linked together into a single program
+/// Produced once a program has typechecked, this is what later stages use to emit a pair of shaders
+#[derive(Debug, PartialEq, Clone)]
+pub struct LinkedProg {
+    pub name: String,
+    pub vert: String,
+    pub frag: String,
+    // inputs to the vertex shader, supplied per vertex
+    pub attributes: NamedTypes,
+    // outputs of the vertex shader, passed along to the fragment shader
+    pub varyings: NamedTypes,
+    // outputs of the fragment shader, in addition to the built-ins of its stage
+    pub outputs: NamedTypes,
+    // bodies of both shaders, w/ any compositions flattened into a single sequence
+    pub vert_body: Vec<Expr>,
+    pub frag_body: Vec<Expr>,
+}
+
+// Bodies of the shaders declared so far, w/ any compositions flattened
+type ShaderBodies = HashMap<String, Vec<Expr>>;
+
 //
 //
 // 1. Rewrote the 'TypeChecked' items above
@@ -90,50 +204,483 @@ fn tc_pass(p: ParsedType, t: TCEnv) -> TCResult {
     Ok((p,t))
 }
 
-/// Fails the typechecker w/ an error message
-fn tc_fail(e: String) -> TCResult {
-    Err(e)
+/// Fails the typechecker w/ a kind of error
+fn tc_fail(e: TCErrorKind) -> TCResult {
+    Err(e.into())
 }
 
 /// Looks up a parsed type in the TC env, potentially failing
 fn tc_lookup(n: &str, env: &TCEnv) -> Result<ParsedType,TCError> {
     match env.get(n) {
         Some(v) => Ok((*v).clone()),
-        None => Err(format!("Non-existant binding '{}' referenced, perhaps you meant to declare it first with 'let' or 'mut'?", n)),
+        None => Err(TCErrorKind::UnboundName{ name: n.to_string() }.into()),
     }
 }
 
 /// Attempts to typecheck a program
-pub fn tc_program(p: Program) -> TCResult {
-    let env : TCEnv = HashMap::new();
-    tc_body(p, &env)
+/// Shaders & programs may only be declared at the top level, so they are checked here rather than as exprs
+/// Typechecking carries on past errors, so every independent error in the program is returned together
+pub fn tc_program(p: Program) -> Result<TypeChecked,TCErrors> {
+    let mut errs = vec![];
+    let env : TCEnv = tc_uniforms(&p, &mut errs);
+    let mut bodies : ShaderBodies = HashMap::new();
+
+    if p.is_empty() {
+        return Err(TCErrors{ errors: vec![TCErrorKind::EmptyBody.into()], env: Box::new(env) });
+    }
+
+    let (t, env) = p.into_iter().fold(
+        (BaseType("".to_string()), env),
+        |(t, env), elt| match elt.kind {
+            ExprKind::Shader{stage, name, inputs, outputs, body} => {
+                tc_shader(stage, name, unannotated(inputs), unannotated(outputs), body, env, &mut bodies, &mut errs)
+            },
+            ExprKind::Prog{name, vert, frag} => match tc_prog(name, &vert, &frag, env.clone(), &bodies, &mut errs) {
+                Ok(res)  => res,
+                Err(err) => {
+                    errs.push(err.at(elt.span));
+                    (Poison, env)
+                }
+            },
+            // comments carry no type, so the prior type is kept
+            ExprKind::Comment(_)             => (t, env),
+            kind                             => tc_recover(Expr{kind, span: elt.span}, env, &TCContext::default(), &mut errs)
+        }
+    );
+
+    if errs.is_empty() {
+        Ok((t, env))
+    } else {
+        // a shader in several programs has its body checked for each, but its errors are only reported once
+        let mut errors : Vec<TCError> = vec![];
+        for e in errs {
+            let same_place = |e2: &TCError| e2.span.map(|s| (s.rest, s.len)) == e.span.map(|s| (s.rest, s.len));
+            if !errors.iter().any(|e2| e2.kind == e.kind && same_place(e2)) {
+                errors.push(e);
+            }
+        }
+        Err(TCErrors{ errors, env: Box::new(env) })
+    }
+}
+
+/// Returns the built-in functions, where a function w/ several signatures is overloaded
+/// These are in scope everywhere, unless shadowed by a binding of the same name, see `tc_lookup_fn`.
+/// The prelude is large, so it is kept apart from envs, which are cloned for every scope
+fn prelude() -> &'static HashMap<String,ParsedType> {
+    static PRELUDE: OnceLock<HashMap<String,ParsedType>> = OnceLock::new();
+    PRELUDE.get_or_init(|| builtins::prelude().into_iter().map(|(n, mut sigs)| {
+        let t = if sigs.len() == 1 { sigs.remove(0) } else { Overloaded(sigs) };
+        (n, t)
+    }).collect())
+}
+
+/// Looks up the type of a name in the TC env, or otherwise of a built-in function
+fn tc_lookup_fn<'a>(n: &str, env: &'a TCEnv) -> Option<&'a ParsedType> {
+    env.get(n).or_else(|| prelude().get(n))
+}
+
+/// Links the programs declared in a typechecked program
+/// Expects the env produced by typechecking that program
+pub fn link_programs(p: &Program, env: &TCEnv) -> Result<Vec<LinkedProg>,TCError> {
+    let mut bodies : ShaderBodies = HashMap::new();
+    let mut progs = vec![];
+    for e in p {
+        match &e.kind {
+            ExprKind::Shader{name, body, ..} => {
+                let b = shader_body(body, &bodies).map_err(|err| err.at(e.span))?;
+                bodies.insert(name.clone(), b);
+            },
+            ExprKind::Prog{name, vert, frag} => progs.push(link_prog(name, vert, frag, env, &bodies).map_err(|err| err.at(e.span))?),
+            _ => ()
+        }
+    }
+    Ok(progs)
+}
+
+/// Returns the body of a shader, flattening compositions into the sequence of the composed bodies
+/// In `v2 . v1` the body of v1 comes first, followed by the body of v2
+fn shader_body(body: &ShaderBody, bodies: &ShaderBodies) -> Result<Vec<Expr>,TCError> {
+    match body {
+        ShaderBody::Block(b)    => Ok(b.clone()),
+        ShaderBody::Composed(e) => flatten_composed(e, bodies)
+    }
+}
+
+/// Flattens a composition of shaders into the sequence of their bodies
+fn flatten_composed(e: &Expr, bodies: &ShaderBodies) -> Result<Vec<Expr>,TCError> {
+    let res = match &e.kind {
+        ExprKind::Ref(n) => bodies.get(n).cloned().ok_or(TCErrorKind::UndefinedShader{ name: n.clone() }.into()),
+        ExprKind::BinOp{operator: BOp::Compose, e1, e2} => {
+            let mut b = flatten_composed(e2, bodies)?;
+            b.extend(flatten_composed(e1, bodies)?);
+            Ok(b)
+        },
+        k => Err(TCErrorKind::NotComposable{ expr: format!("{:?}", k) }.into())
+    };
+    res.map_err(|err: TCError| err.at(e.span))
+}
+
+/// Looks up a shader of a given stage, returning its named inputs & outputs
+fn tc_lookup_shader(n: &str, stage: Stage, env: &TCEnv) -> Result<(NamedTypes, NamedTypes),TCError> {
+    match tc_lookup(n, env)? {
+        ParsedType::Shader(s, i, o) if s == stage => {
+            match (*i, *o) {
+                (NamedTuple(i), NamedTuple(o)) => Ok((
+                    i.into_iter().map(|(n,t)| (n,*t)).collect(),
+                    o.into_iter().map(|(n,t)| (n,*t)).collect()
+                )),
+                (NamedTuple(_), o) => Err(TCErrorKind::MalformedShaderIO{ actual: o }.into()),
+                (i, _) => Err(TCErrorKind::MalformedShaderIO{ actual: i }.into())
+            }
+        },
+        t => Err(TCErrorKind::WrongStage{ name: n.to_string(), expected: stage, actual: t }.into())
+    }
+}
+
+/// Links a vertex shader to a fragment shader
+/// Every input of the fragment shader must be an output of the vertex shader, w/ the same type
+fn link_prog(name: &str, vert: &str, frag: &str, env: &TCEnv, bodies: &ShaderBodies) -> Result<LinkedProg,TCError> {
+    let (attributes, varyings) = tc_lookup_shader(vert, Stage::Vertex, env)?;
+    let (frag_inputs, outputs) = tc_lookup_shader(frag, Stage::Fragment, env)?;
+
+    for (n,t) in frag_inputs {
+        match varyings.iter().find(|(vn,_)| *vn == n) {
+            Some((_,vt)) if *vt == t => (),
+            Some((_,vt)) => {
+                return Err(TCErrorKind::VaryingMismatch{ prog: name.to_string(), name: n.clone(), expected: t.clone(), actual: vt.clone() }.into());
+            },
+            None => {
+                return Err(TCErrorKind::MissingVarying{ prog: name.to_string(), name: n.clone(), vert: vert.to_string(), frag: frag.to_string() }.into());
+            }
+        }
+    }
+
+    let body = |n: &str| bodies.get(n).cloned().ok_or(TCError::from(TCErrorKind::UndefinedShader{ name: n.to_string() }));
+
+    Ok(LinkedProg{
+        name: name.to_string(),
+        vert: vert.to_string(),
+        frag: frag.to_string(),
+        attributes,
+        varyings,
+        outputs,
+        vert_body: body(vert)?,
+        frag_body: body(frag)?
+    })
+}
+
+/*
+## TC(Shader)
+-----------------------------------------------
+Γ ⊢ `stage s : (I) -> (O) = { e }` : stage I -> O
+
+    A shader is bound to its signature, w/out checking its body.
+    Shaders may be incomplete until they are composed w/ others, so bodies are checked by TC(Prog) instead.
+
+## TC(ShaderComp)
+Γ ⊢ s2 : stage I2 -> O2
+Γ ⊢ s1 : stage I1 -> O1
+I ≡ I1 ∪ (I2 \ O1)
+O ≡ O1 ∪ O2
+-----------------------------------------------
+Γ ⊢ `stage s : (I) -> (O) = s2 . s1` : stage I -> O
+
+IF
+    Gamma implies 's1' & 's2' are shaders of the same stage
+    AND the declared inputs 'I' are the union of the inputs of both, less those produced by 's1'
+    AND the declared outputs 'O' are the union of the outputs of both
+THEN
+    Gamma implies the composed shader 's' has type `stage I -> O`
+*/
+#[allow(clippy::too_many_arguments)]
+fn tc_shader(stage: Stage, name: String, inputs: NamedTypes, outputs: NamedTypes, body: ShaderBody, mut env: TCEnv, bodies: &mut ShaderBodies, errs: &mut Vec<TCError>) -> TypeChecked {
+    let t = ParsedType::Shader(stage, Box::new(mk_named_tuple(&inputs)), Box::new(mk_named_tuple(&outputs)));
+
+    if let ShaderBody::Composed(e) = &body {
+        // composition produces the union of the composed shaders, which must match the declaration
+        let (ct, _) = tc_recover((**e).clone(), env.clone(), &TCContext::default(), errs);
+        if ct == Poison {
+            // an ill-typed composition has no body, so programs using this shader are not checked any further
+            env.insert(name, Poison);
+            return (Poison, env);
+        }
+        if !same_shader_type(&t, &ct) {
+            errs.push(TCError::from(TCErrorKind::ShaderSignatureMismatch{ name: name.clone(), expected: t.clone(), actual: ct }).at(e.span));
+        }
+    }
+
+    match shader_body(&body, bodies) {
+        Ok(b) => {
+            bodies.insert(name.clone(), b);
+            env.insert(name, t.clone());
+            (t, env)
+        },
+        Err(err) => {
+            errs.push(err);
+            env.insert(name, Poison);
+            (Poison, env)
+        }
+    }
+}
+
+/*
+## TC(Prog)
+Γ ⊢ v : vert I1 -> O1
+Γ ⊢ f : frag I2 -> O2
+Γ,I1,O1 ⊢ ev : T1
+Γ,I2,O2 ⊢ ef : T2
+I2 ⊆ O1
+------------------------------
+Γ ⊢ `p : Prog = mkProg v f` : Prog
+
+IF
+    Gamma implies 'v' is a vertex shader & 'f' is a fragment shader
+    AND the bodies of both are well-typed w/ their inputs & outputs (& the built-ins of their stage)
+    AND the inputs of 'f' are satisfied by the outputs of 'v'
+THEN
+    Gamma implies `mkProg v f` is a program
+*/
+fn tc_prog(name: String, vert: &str, frag: &str, mut env: TCEnv, bodies: &ShaderBodies, errs: &mut Vec<TCError>) -> TCResult {
+    // shaders w/ errors of their own are not linked, as any further errors would only follow from those
+    if [vert, frag].iter().all(|n| env.get(n) != Some(&Poison)) {
+        let linked = link_prog(&name, vert, frag, &env, bodies)?;
+        let (frag_inputs, frag_outputs) = tc_lookup_shader(frag, Stage::Fragment, &env)?;
+
+        tc_shader_body(Stage::Vertex, linked.attributes, linked.varyings, linked.vert_body, &env, errs)?;
+        tc_shader_body(Stage::Fragment, frag_inputs, frag_outputs, linked.frag_body, &env, errs)?;
+    }
+
+    env.insert(name, typ("Prog"));
+    tc_pass(typ("Prog"), env)
+}
+
+/// Typechecks the body of a shader, w/ its inputs, outputs & the built-ins of its stage in scope
+/// The body is checked in its own env, so its bindings do not leak out
+fn tc_shader_body(stage: Stage, inputs: NamedTypes, outputs: NamedTypes, body: Vec<Expr>, env: &TCEnv, errs: &mut Vec<TCError>) -> TCResult {
+    tc_body(body, &shader_env(stage, inputs, outputs, env), &TCContext{ stage: Some(stage), ..TCContext::default() }, errs)
+}
+
+/// Extends an env w/ the inputs, outputs & built-ins visible to the body of a shader
+/// Inputs are the parameters of a shader, so like those of a function they are immutable, as are the read-only built-ins,
+/// while outputs are written to
+pub fn shader_env(stage: Stage, inputs: NamedTypes, outputs: NamedTypes, env: &TCEnv) -> TCEnv {
+    let mut shader_env = env.clone();
+    let inputs = inputs.into_iter().map(|i| (i, true));
+    let outputs = outputs.into_iter().map(|o| (o, false));
+    for ((n,t), immutable) in stage_builtins(stage).into_iter().chain(inputs).chain(outputs) {
+        shader_env.set_immutable(&n, immutable);
+        shader_env.insert(n, t);
+    }
+    shader_env
+}
+
+/// Returns the type of an expression in a given env, w/out any effect on that env
+/// Used by later stages to recover the types of expressions in a typechecked program
+pub fn tc_type_of(e: &Expr, env: &TCEnv) -> Result<ParsedType,TCError> {
+    tc_expr(e.clone(), env.clone()).map(|(t,_)| t)
+}
+
+/// Builds the env of all uniforms declared in a program
+/// Uniforms are collected up front, so they are visible to every function & shader regardless of where they are declared
+/// A uniform may be declared more than once, but only w/ the same type each time
+fn tc_uniforms(p: &Program, errs: &mut Vec<TCError>) -> TCEnv {
+    let mut env = TCEnv::new();
+    for e in p {
+        if let ExprKind::Uniform{name, typ: Annotation{typ, ..}} = &e.kind {
+            match env.get(name) {
+                Some(t) if t != typ => {
+                    errs.push(TCError::from(TCErrorKind::UniformRedeclared{ name: name.clone(), expected: t.clone(), actual: typ.clone() }).at(e.span));
+                },
+                _ => {
+                    env.insert(name.clone(), typ.clone());
+                }
+            }
+        }
+    }
+    env
 }
 
 /// Typechecks a vector of Exprs w/ a given environment
-/// Verifies all of them before returning the result of the last Expr
+/// Verifies all of them before returning the result of the last Expr, recording any errors along the way
 /// Expects a body of 1 or more Exprs to verify
-fn tc_body(body: Vec<Expr>, env: &TCEnv) -> TCResult {
+fn tc_body(body: Vec<Expr>, env: &TCEnv, ctx: &TCContext, errs: &mut Vec<TCError>) -> TCResult {
     if body.is_empty() {
         // must have something to work with
-        tc_fail("Unable to typecheck an empty body!".to_string())
+        tc_fail(TCErrorKind::EmptyBody)
 
     } else {
-        body.into_iter().fold(
-            Ok((BaseType("".to_string()), env.clone())),
-            |acc, elt| {
-                // Looks like `bind` huh?
-                if let Ok((_, env)) = acc {
-                    tc_expr(elt, env)
+        // anything after a return is unreachable, which is reported once for the first expr after it
+        let mut returned = false;
+        let mut reported = false;
+        Ok(body.into_iter().fold(
+            (BaseType("".to_string()), env.clone()),
+            // Looks like `bind` huh?
+            |(t, env), elt| match elt.kind {
+                // comments carry no type, so the prior type is kept
+                ExprKind::Comment(_) => (t, env),
+                _                    => {
+                    if returned && !reported {
+                        errs.push(TCError::from(TCErrorKind::UnreachableCode).at(elt.span));
+                        reported = true;
+                    }
+                    returned |= always_returns(&elt);
+                    tc_recover(elt, env, ctx, errs)
                 }
-                else {
-                    acc
+            }
+        ))
+
+    }
+}
+
+/// Returns whether an expr returns from its function, or discards, on every path through it
+/// Loops other than a do-while may not run at all, so only a do-while body that returns counts
+fn always_returns(e: &Expr) -> bool {
+    match &e.kind {
+        ExprKind::Return(_)             => true,
+        // a discard ends the shader altogether, so nothing after it runs either
+        ExprKind::Discard               => true,
+        ExprKind::Branch{b1, b2, ..}    => b1.iter().any(always_returns) && b2.iter().any(always_returns),
+        ExprKind::DoWhile{body, ..}     => body.iter().any(always_returns),
+        _                               => false
+    }
+}
+
+/// Typechecks the value of a binding, recovering from any error in it
+/// A function bound w/ a declared type must return the result of that type, so its returns are checked against it
+fn tc_value(e: Expr, t: &ParsedType, env: TCEnv, ctx: &TCContext, errs: &mut Vec<TCError>) -> TypeChecked {
+    let span = e.span;
+    match e.kind {
+        ExprKind::Abs{params, body} => {
+            let declared = match t {
+                Function(_, r) if !params.is_empty() => Some((**r).clone()),
+                t if params.is_empty()               => Some(t.clone()),
+                _                                    => None
+            };
+            match tc_abs(params, body, declared, env.clone(), ctx, errs) {
+                Ok(res)  => res,
+                Err(err) => {
+                    errs.push(err.at(span));
+                    (Poison, env)
                 }
             }
-        )
+        },
+        kind => tc_recover(Expr{kind, span}, env, ctx, errs)
+    }
+}
 
+/// Typechecks an abstraction, checking every return in its body against its return type
+/// W/out a declared return type, the returns must agree w/ the type of the body instead
+fn tc_abs(p: Vec<(String,Annotation)>, b: Vec<Expr>, declared: Option<ParsedType>, env: TCEnv, ctx: &TCContext, errs: &mut Vec<TCError>) -> TCResult {
+    // parameters are bound in the body alone, & are immutable there
+    let mut body_env = env.clone();
+    for (n,t) in &p {
+        body_env.set_immutable(n, true);
+        body_env.insert(n.clone(), t.typ.clone());
+    }
+
+    // the type of the body is only known once it is checked, so it is checked once w/ any return allowed to find it
+    let ret = match declared {
+        Some(t) => t,
+        None    => {
+            let mut any = ctx.clone();
+            any.ret = Some(Poison);
+            tc_body(b.clone(), &body_env, &any, &mut vec![]).map_or(Poison, |(t,_)| t)
+        }
+    };
+    let mut body_ctx = ctx.clone();
+    body_ctx.ret = Some(ret);
+
+    // get type of the body (return type), ignoring effect on env
+    let (tb,_) = tc_body(b, &body_env, &body_ctx, errs)?;
+
+    let p : Vec<ParsedType> = p.into_iter().map(|(_,t)| t.typ).collect();
+
+    match p.len() {
+        0 => {
+            // simple type w/ no params
+            tc_pass(tb, env)
+        },
+        1 => {
+            // single arg, no tuple needed for this function type
+            tc_pass(
+                Function(Box::new(p[0].clone()), Box::new(tb)),
+                env
+            )
+        },
+        _ => {
+            // function type to construct
+            tc_pass(
+                Function(Box::new(ParsedType::Tuple(p)), Box::new(tb)),
+                env
+            )
+        }
+    }
+}
+
+
+/// Typechecks the condition of a branch or loop, recording an error if it is not a bool
+fn tc_condition(c: Expr, env: TCEnv, ctx: &TCContext, errs: &mut Vec<TCError>) -> TCEnv {
+    let span = c.span;
+    let (t,env) = tc_recover(c, env, ctx, errs);
+    if !agrees(&t, &typ("bool")) {
+        errs.push(TCError::from(TCErrorKind::ConditionNotBool{ actual: t }).at(span));
+    }
+    env
+}
+
+/// Typechecks a call of a vector or matrix constructor, such as `vec4(pos, 1.0f)`, following GLSL
+/// A single scalar is used for every component, or the diagonal of a matrix, & a single matrix converts to another.
+/// Otherwise the components of every argument fill those of the result in order, w/out any argument left unused,
+/// so a single vector w/ more components than needed is truncated.
+/// Components are converted between scalar types, so `vec2(1, 2)` is a valid vec2
+fn tc_constructor(name: String, (_, cols, rows): (&str, usize, usize), args: Vec<ParsedType>, env: TCEnv) -> TCResult {
+    let mut sizes = vec![];
+    for a in &args {
+        match shape(a) {
+            // a matrix is only built from another on its own
+            Some(_) if cols > 1 && args.len() > 1 && is_matrix(a) => {
+                return tc_fail(TCErrorKind::ConstructorArg{ name, actual: a.clone() });
+            },
+            Some((_, c, r))        => sizes.push(c * r),
+            None if is_poisoned(a) => return tc_pass(typ(&name), env),
+            None                   => return tc_fail(TCErrorKind::ConstructorArg{ name, actual: a.clone() })
+        }
+    }
+    let expected = cols * rows;
+    let total : usize = sizes.iter().sum();
+    let converts = sizes == [1] || (cols > 1 && args.len() == 1 && is_matrix(&args[0]));
+    // the last argument must still be needed once all others are used
+    let last = sizes.last().copied().unwrap_or(0);
+    if converts || (total >= expected && total - last < expected) {
+        tc_pass(typ(&name), env)
+    } else {
+        tc_fail(TCErrorKind::ConstructorArity{ name, expected, actual: total })
     }
 }
 
+/// Returns whether a type is a matrix
+fn is_matrix(t: &ParsedType) -> bool {
+    matches!(shape(t), Some((_, c, _)) if c > 1)
+}
+
+/// Lifts the types of the args of an application into the type of the param of a function
+/// A single arg is passed as it is, & several are passed as a tuple
+fn args_type(mut args: Vec<ParsedType>) -> ParsedType {
+    if args.len() == 1 {
+        args.remove(0)
+    } else {
+        Tuple(args)
+    }
+}
+
+/// Returns the signatures of an overloaded function that take args of the given types
+fn matching_overloads<'a>(sigs: &'a [ParsedType], args: &[ParsedType]) -> Vec<&'a ParsedType> {
+    let arg = args_type(args.to_vec());
+    sigs.iter().filter(|sig| match sig {
+        Function(a, _) => type_arity((**a).clone()) == args.len() && agrees(&arg, a),
+        _              => false
+    }).collect()
+}
 
 /// Returns the immediate arity of a given type w/ a depth of 0
 /// All types return 1 except for tuples
@@ -162,18 +709,17 @@ fn mk_bin_func(t: ParsedType) -> ParsedType {
 
 /// Returns whether a binary operator produces a numeric value
 fn op_num_type(bop: &syntax::BOp) -> bool {
-    match bop {
-        syntax::BOp::Mod => true,
-        syntax::BOp::Gt  => true,
-        syntax::BOp::Gte => true,
-        syntax::BOp::Lte => true,
-        syntax::BOp::Lt  => true,
-        syntax::BOp::Add => true,
-        syntax::BOp::Sub => true,
-        syntax::BOp::Mul => true,
-        syntax::BOp::Div => true,
-        _ => false,
-    }
+    matches!(bop,
+        syntax::BOp::Mod |
+        syntax::BOp::Gt  |
+        syntax::BOp::Gte |
+        syntax::BOp::Lte |
+        syntax::BOp::Lt  |
+        syntax::BOp::Add |
+        syntax::BOp::Sub |
+        syntax::BOp::Mul |
+        syntax::BOp::Div
+    )
 }
 
 /// Returns the type that corresponds to a given BinOp
@@ -188,15 +734,13 @@ fn bop_type(bop: syntax::BOp, a1t: &ParsedType, a2t: &ParsedType) -> Result<Pars
     types.insert("bool".to_string());
 
     match (a1t, a2t) {
+        (BaseType(_), BaseType(_)) if !is_scalar(a1t) || !is_scalar(a2t) => vector_bop_type(bop, a1t, a2t),
         (BaseType(b1), BaseType(b2)) => {
-            if ! types.contains(b1) {
-                Err(format!("Type {} is not supported by binary operations", b1))
-            }
-            else if ! types.contains(b2) {
-                Err(format!("Type {} is not supported by binary operations", b2))
+            if ! types.contains(b1) || (b1 == "bool" && op_num_type(&bop)) {
+                Err(TCErrorKind::UnsupportedOperand{ op: bop, actual: a1t.clone() }.into())
             }
-            else if (b1 == "bool" || b2 == "bool") && op_num_type(&bop) {
-                Err(format!("Operator {} does not support bool as an argument", bop))
+            else if ! types.contains(b2) || (b2 == "bool" && op_num_type(&bop)) {
+                Err(TCErrorKind::UnsupportedOperand{ op: bop, actual: a2t.clone() }.into())
             }
             else {
                 let (arg1, arg2) = ((*a1t).clone(), (*a2t).clone());
@@ -217,24 +761,95 @@ fn bop_type(bop: syntax::BOp, a1t: &ParsedType, a2t: &ParsedType) -> Result<Pars
                     (syntax::BOp::Gte, true, _, _) => Ok(mk_func_typ(Tuple(vec![arg1,arg2]), typ("bool"))),
                     (syntax::BOp::Lte, true, _, _) => Ok(mk_func_typ(Tuple(vec![arg1,arg2]), typ("bool"))),
                     (syntax::BOp::Lt, true, _, _) => Ok(mk_func_typ(Tuple(vec![arg1,arg2]), typ("bool"))),
-                    _ => Err(format!("Operator does not support argument types {} {}", *a1t, *a2t)),
+                    _ => Err(TCErrorKind::BinOpMismatch{ op: bop, lhs: a1t.clone(), rhs: a2t.clone() }.into()),
                 }
             }
         }
         (Function(args1, ret1), Function(args2, ret2)) => {
             if bop == BOp::Compose {
                 if **ret2 == **args1 {
-                    Ok(mk_func_typ((**args2).clone(), (**ret1).clone()))
+                    Ok(mk_func_typ(Tuple(vec![a1t.clone(), a2t.clone()]), mk_func_typ((**args2).clone(), (**ret1).clone())))
                 }
                 else {
-                    Err(format!("Return type {} is not the same as argument type {} in function composition", **ret2, **args1))
+                    Err(TCErrorKind::ComposeMismatch{ expected: (**args1).clone(), actual: (**ret2).clone() }.into())
                 }
             }
             else {
-                Err("Only the composition operator can take function arguments".to_string())
+                Err(TCErrorKind::BinOpMismatch{ op: bop, lhs: a1t.clone(), rhs: a2t.clone() }.into())
+            }
+        },
+        (ParsedType::Shader(s2, i2, o2), ParsedType::Shader(s1, i1, o1)) => {
+            if bop != BOp::Compose {
+                Err(TCErrorKind::BinOpMismatch{ op: bop, lhs: a1t.clone(), rhs: a2t.clone() }.into())
+            }
+            else if s1 != s2 {
+                Err(TCErrorKind::StageMismatch{ lhs: *s2, rhs: *s1 }.into())
             }
+            else {
+                let (i1, o1, i2, o2) = (unpack_named(i1)?, unpack_named(o1)?, unpack_named(i2)?, unpack_named(o2)?);
+                // inputs of the second shader may be produced by the first, and are no longer needed from outside
+                let mut i2_outer = vec![];
+                for (n,t) in i2 {
+                    match o1.iter().find(|(on,_)| *on == n) {
+                        Some((_,ot)) if *ot != t => {
+                            return Err(TCErrorKind::ComposedIOMismatch{ name: n, expected: t, actual: ot.clone() }.into());
+                        },
+                        Some(_) => (),
+                        None    => i2_outer.push((n,t))
+                    }
+                }
+                let inputs = union_named(i1, i2_outer)?;
+                let outputs = union_named(o1, o2)?;
+                Ok(mk_func_typ(
+                    Tuple(vec![a1t.clone(), a2t.clone()]),
+                    ParsedType::Shader(*s1, Box::new(mk_named_tuple(&inputs)), Box::new(mk_named_tuple(&outputs)))
+                ))
+            }
+        },
+        _ => Err(TCErrorKind::BinOpMismatch{ op: bop, lhs: a1t.clone(), rhs: a2t.clone() }.into())
+    }
+}
+
+/// Returns the type of a binary operator w/ a vector or matrix operand, following GLSL
+/// Arithmetic is component-wise, w/ a scalar operand applied to every component,
+/// except that products of a matrix w/ a vector or another matrix are those of linear algebra
+fn vector_bop_type(bop: syntax::BOp, a1t: &ParsedType, a2t: &ParsedType) -> Result<ParsedType, TCError> {
+    let unsupported = |t: &ParsedType| Err(TCErrorKind::UnsupportedOperand{ op: bop, actual: t.clone() }.into());
+    let (s1, c1, r1) = match shape(a1t) { Some(s) => s, None => return unsupported(a1t) };
+    let (s2, c2, r2) = match shape(a2t) { Some(s) => s, None => return unsupported(a2t) };
+    // the operand that is not a scalar, which decides whether the operator is supported at all
+    let compound = if c1 * r1 > 1 { a1t } else { a2t };
+    let args = Tuple(vec![a1t.clone(), a2t.clone()]);
+
+    match bop {
+        BOp::Eq | BOp::Neq if a1t == a2t => Ok(mk_func_typ(args, typ("bool"))),
+        BOp::Eq | BOp::Neq => Err(TCErrorKind::BinOpMismatch{ op: bop, lhs: a1t.clone(), rhs: a2t.clone() }.into()),
+        // bool vectors may only be compared, & only int vectors have a remainder
+        _ if s1 == "bool" => unsupported(a1t),
+        _ if s2 == "bool" => unsupported(a2t),
+        BOp::Mod if s1 != "int" => unsupported(a1t),
+        BOp::Add | BOp::Sub | BOp::Mul | BOp::Div | BOp::Mod if s1 == s2 => {
+            let ret = if a1t == a2t {
+                a1t.clone()
+            } else if c1 * r1 == 1 {
+                // scalar, broadcast over the other operand
+                a2t.clone()
+            } else if c2 * r2 == 1 {
+                a1t.clone()
+            } else if bop == BOp::Mul && c2 == 1 && c1 == r2 {
+                // matrix * column vector
+                a2t.clone()
+            } else if bop == BOp::Mul && c1 == 1 && r1 == r2 {
+                // row vector * matrix
+                a1t.clone()
+            } else {
+                return Err(TCErrorKind::BinOpMismatch{ op: bop, lhs: a1t.clone(), rhs: a2t.clone() }.into());
+            };
+            Ok(mk_func_typ(args, ret))
         },
-        _ => Err("asd".to_string())
+        BOp::Add | BOp::Sub | BOp::Mul | BOp::Div | BOp::Mod => Err(TCErrorKind::BinOpMismatch{ op: bop, lhs: a1t.clone(), rhs: a2t.clone() }.into()),
+        // comparisons, logical & bitwise operators only apply to scalars
+        _ => unsupported(compound)
     }
 }
 
@@ -246,16 +861,288 @@ fn uop_type(uop: UOp, arg: ParsedType) -> Result<ParsedType, TCError> {
     match arg {
         BaseType(n) => {
             match (uop, n.as_str()) {
-                (Negative, "int")      => Ok(mk_func_typ(arg1, arg2)),
-                (Negative, "float")    => Ok(mk_func_typ(arg1, arg2)),
-                (Negative, "double")   => Ok(mk_func_typ(arg1, arg2)),
+                (UOp::Negative, "int")      => Ok(mk_func_typ(arg1, arg2)),
+                (UOp::Negative, "float")    => Ok(mk_func_typ(arg1, arg2)),
+                (UOp::Negative, "double")   => Ok(mk_func_typ(arg1, arg2)),
+                // vectors & matrices are negated component-wise
+                (UOp::Negative, _) if matches!(shape(&arg1), Some(("int" | "float", _, _))) => Ok(mk_func_typ(arg1, arg2)),
         
-                (Negate, "bool")   => Ok(mk_func_typ(arg1, arg2)),
+                (UOp::Negate, "bool")   => Ok(mk_func_typ(arg1, arg2)),
+
+                _ => Err(TCErrorKind::UnOpMismatch{ op: uop, actual: arg1 }.into())
+            }
+        },
+        _ => Err(TCErrorKind::UnOpMismatch{ op: uop, actual: arg1 }.into())
+    }
+}
+
+/// Returns the built-in variables that are visible to a shader of the given stage, w/ whether each is read-only
+fn stage_builtins(stage: Stage) -> Vec<((String, ParsedType), bool)> {
+    let vars = match stage {
+        Stage::Vertex   => vec![("gl_Position", "vec4", false), ("gl_PointSize", "float", false)],
+        Stage::Fragment => vec![("gl_FragColor", "vec4", false), ("gl_FragCoord", "vec4", true), ("gl_FrontFacing", "bool", true), ("gl_PointCoord", "vec2", true)],
+    };
+    vars.into_iter().map(|(n,t,read_only)| ((n.to_string(), typ(t)), read_only)).collect()
+}
+
+/// Checks that a built-in used in the body of a shader is available in the stage of that shader
+/// Built-in variables of the other stage are never bound in its env, & built-in functions are looked up in the prelude,
+/// so either is restricted to its stage unless a binding shadows it. A use is recorded for the value being bound, see `mark_stage`.
+fn tc_stage(n: &str, env: &TCEnv, ctx: &TCContext) -> Result<(),TCError> {
+    let stage = match builtins::stage(n) {
+        Some(stage) if !env.contains_key(n) => stage,
+        _                                   => return Ok(())
+    };
+    ctx.reach(n);
+    match ctx.stage {
+        Some(actual) if stage != actual => Err(TCErrorKind::StageRestricted{ name: n.to_string(), stage, actual }.into()),
+        _                               => Ok(())
+    }
+}
+
+/// Checks that a signature of a function used in the body of a shader is available in the stage of that shader
+/// A signature is restricted by the built-in it is marked w/, which is reached by the value being bound as well
+fn tc_staged_sig(n: &str, args: &ParsedType, env: &TCEnv, ctx: &TCContext) -> Result<(),TCError> {
+    let b = match env.staged(n, args) {
+        Some(b) => b,
+        None    => return Ok(())
+    };
+    ctx.reach(b);
+    match (builtins::stage(b), ctx.stage) {
+        (Some(stage), Some(actual)) if stage != actual => {
+            Err(TCErrorKind::StageRestrictedCall{ name: n.to_string(), builtin: b.clone(), stage, actual }.into())
+        },
+        _ => Ok(())
+    }
+}
+
+/// Marks a function bound w/ a given type by the stage-specific built-in that its value reaches, if any
+/// Only the signature that is bound is marked, so the other signatures of an overloaded function keep their own marks,
+/// while any other binding clears the marks of its name. A value that is not a function reaches its built-in as it is bound,
+/// so the built-in is reached by the enclosing value instead.
+fn mark_stage(n: &str, t: &ParsedType, bound: &ParsedType, reached: Option<String>, env: &mut TCEnv, ctx: &TCContext) {
+    if !matches!(bound, Overloaded(_)) {
+        env.clear_staged(n);
+    }
+    match (t, reached) {
+        (Function(args, _), Some(b)) => env.set_staged(n, args, b),
+        (Function(..), None)         => (),
+        (_, Some(b))                 => ctx.reach(&b),
+        (_, None)                    => ()
+    }
+}
+
+/// Returns the type that a name is bound to by a definition of a given type, over any prior binding of that name
+/// Like GLSL, functions are overloaded, so a function bound over others adds its signature to theirs.
+/// Signatures are told apart by their args, so one that takes the same args as another is rejected, whatever it returns.
+/// Any other binding shadows the prior one.
+fn overload(n: &str, prior: Option<&ParsedType>, t: &ParsedType) -> Result<ParsedType,TCError> {
+    let mut sigs = match (prior, t) {
+        (Some(Overloaded(sigs)), Function(..))  => sigs.clone(),
+        (Some(f @ Function(..)), Function(..))  => vec![f.clone()],
+        _                                       => return Ok(t.clone())
+    };
+    if let Function(args, ret) = t {
+        for sig in &sigs {
+            match sig {
+                Function(a, r) if a == args && r == ret => {
+                    return Err(TCErrorKind::FunctionRedefined{ name: n.to_string(), sig: t.clone() }.into());
+                },
+                Function(a, r) if a == args => {
+                    return Err(TCErrorKind::OverloadReturnMismatch{ name: n.to_string(), args: (**a).clone(), expected: (**r).clone(), actual: (**ret).clone() }.into());
+                },
+                _ => ()
+            }
+        }
+    }
+    sigs.push(t.clone());
+    Ok(Overloaded(sigs))
+}
+
+/// Checks that a function is not defined w/ the name of a built-in function, unless that name was bound to something else
+/// GLSL rejects redefinitions & overloads of its built-ins, so they are rejected here as well
+fn tc_builtin_name(n: &str, t: &ParsedType, env: &TCEnv) -> Result<(),TCError> {
+    match t {
+        Function(..) if !env.contains_key(n) && prelude().contains_key(n) => Err(TCErrorKind::BuiltinRedefined{ name: n.to_string() }.into()),
+        _                                                                  => Ok(())
+    }
+}
+
+/// Drops the source spans from a list of annotated names
+fn unannotated(v: Vec<(String, Annotation)>) -> NamedTypes {
+    v.into_iter().map(|(n,a)| (n, a.typ)).collect()
+}
+
+/// Helper to make a named tuple type from a list of named types
+fn mk_named_tuple(v: &[(String, ParsedType)]) -> ParsedType {
+    NamedTuple(v.iter().map(|(n,t)| (n.clone(), Box::new(t.clone()))).collect())
+}
+
+/// Unpacks the named types of a named tuple, such as the inputs or outputs of a shader
+fn unpack_named(t: &ParsedType) -> Result<NamedTypes,TCError> {
+    match t {
+        NamedTuple(v) => Ok(v.iter().map(|(n,t)| (n.clone(), (**t).clone())).collect()),
+        _             => Err(TCErrorKind::MalformedShaderIO{ actual: t.clone() }.into())
+    }
+}
+
+/// Unions two lists of named types, failing if a name is given two different types
+fn union_named(mut v1: NamedTypes, v2: NamedTypes) -> Result<NamedTypes,TCError> {
+    for (n,t) in v2 {
+        match v1.iter().find(|(n1,_)| *n1 == n) {
+            Some((_,t1)) if *t1 != t => {
+                return Err(TCErrorKind::ConflictingIO{ name: n, first: t1.clone(), second: t }.into());
+            },
+            Some(_) => (),
+            None    => v1.push((n,t))
+        }
+    }
+    Ok(v1)
+}
+
+/// Returns whether two types agree, where the poison type agrees w/ any other type
+/// Used in place of equality wherever an ill-typed sub-expr should not cause another error
+fn agrees(t1: &ParsedType, t2: &ParsedType) -> bool {
+    match (t1, t2) {
+        (Poison, _) | (_, Poison)          => true,
+        (Tuple(v1), Tuple(v2))             => v1.len() == v2.len() && v1.iter().zip(v2).all(|(a,b)| agrees(a, b)),
+        (NamedTuple(v1), NamedTuple(v2))   => v1.len() == v2.len() && v1.iter().zip(v2).all(|((n1,a),(n2,b))| n1 == n2 && agrees(a, b)),
+        (Function(a1,r1), Function(a2,r2)) => agrees(a1, a2) && agrees(r1, r2),
+        (Array(a1,n1), Array(a2,n2))       => n1 == n2 && agrees(a1, a2),
+        _                                  => t1 == t2
+    }
+}
+
+/// Returns the type of the elements of a vector, matrix or array, w/ how many of them there are
+fn element_type(t: &ParsedType) -> Option<(ParsedType, usize)> {
+    match t {
+        BaseType(b) => {
+            let (elt, n) = match b.as_str() {
+                "vec2" | "vec3" | "vec4"    => ("float".to_string(), &b[3..]),
+                "ivec2" | "ivec3" | "ivec4" => ("int".to_string(), &b[4..]),
+                "bvec2" | "bvec3" | "bvec4" => ("bool".to_string(), &b[4..]),
+                // matrices are indexed by column
+                "mat2" | "mat3" | "mat4"    => (format!("vec{}", &b[3..]), &b[3..]),
+                _                           => return None
+            };
+            Some((BaseType(elt), n.parse().ok()?))
+        },
+        Array(t, n) => Some(((**t).clone(), *n)),
+        _           => None
+    }
+}
+
+/// Returns the type of a swizzle of a vector, i.e. `v.xy`, or None if the type is not a vector
+/// Up to 4 components are selected by one of 'xyzw', 'rgba' or 'stpq', & a single component is a scalar
+fn swizzle_type(name: &str, t: &ParsedType, swizzle: &str) -> Option<Result<ParsedType,TCErrorKind>> {
+    let (scalar, len) = match shape(t)? {
+        (s, 1, n) if n > 1 => (s, n),
+        _                  => return None
+    };
+    let sets = ["xyzw", "rgba", "stpq"];
+    let invalid = || TCErrorKind::InvalidSwizzle{ name: name.to_string(), swizzle: swizzle.to_string() };
+    let set = match swizzle.chars().next().and_then(|c| sets.into_iter().find(|s| s.contains(c))) {
+        Some(set) if swizzle.len() <= 4 => set,
+        _                               => return Some(Err(invalid()))
+    };
+    for c in swizzle.chars() {
+        match set.find(c) {
+            Some(i) if i >= len => return Some(Err(TCErrorKind::SwizzleOutOfRange{ name: name.to_string(), swizzle: swizzle.to_string(), len })),
+            Some(_)             => (),
+            None if sets.iter().any(|s| s.contains(c)) => return Some(Err(TCErrorKind::MixedSwizzle{ name: name.to_string(), swizzle: swizzle.to_string() })),
+            None                => return Some(Err(invalid()))
+        }
+    }
+    Some(Ok(match (swizzle.len(), scalar) {
+        (1, s)       => typ(s),
+        (n, "float") => typ(&format!("vec{n}")),
+        // ivecN & bvecN
+        (n, s)       => typ(&format!("{}vec{n}", &s[..1]))
+    }))
+}
+
+/// Returns the shape of a scalar, vector or matrix type, as its scalar type w/ its # of columns & rows
+/// Scalars have a single component, & vectors a single column
+fn shape(t: &ParsedType) -> Option<(&'static str, usize, usize)> {
+    let scalar = |s: &str| ["int", "float", "double", "bool"].into_iter().find(|b| *b == s);
+    match (t, element_type(t)) {
+        (BaseType(b), None)                            => scalar(b).map(|s| (s, 1, 1)),
+        (BaseType(b), Some((_, n))) if b.starts_with("mat") => Some(("float", n, n)),
+        (BaseType(_), Some((BaseType(e), n)))          => scalar(&e).map(|s| (s, 1, n)),
+        _                                              => None
+    }
+}
+
+/// Returns whether a type is a scalar, as opposed to a vector, matrix or any other type
+fn is_scalar(t: &ParsedType) -> bool {
+    matches!(shape(t), Some((_, 1, 1)))
+}
+
+/// Returns whether a type is a float, or a vector or matrix of them
+fn is_float_shaped(t: &ParsedType) -> bool {
+    matches!(shape(t), Some(("float", _, _)))
+}
+
+/// Returns whether an expr is a decimal literal w/out an 'f', such as `0.5` or `-0.5`
+fn is_decimal_literal(e: &Expr) -> bool {
+    match &e.kind {
+        ExprKind::D(_)                                  => true,
+        ExprKind::UnaryOp{operator: UOp::Negative, e}   => is_decimal_literal(e),
+        _                                               => false
+    }
+}
+
+/// Returns whether an expr is a decimal literal used where a float is expected, which makes it a float
+/// Decimal literals are doubles on their own, but the original FDSSL syntax wrote floats w/out an 'f',
+/// i.e. `mut Float x = 0.5` or `uTime * 3.7`
+pub fn fits_float(e: &Expr, expected: &ParsedType) -> bool {
+    is_decimal_literal(e) && is_float_shaped(expected)
+}
 
-                _ => Err(format!("Invalid marghing of unary op {uop} with an argument type {n}"))
+/// Returns the value of an index that is known statically, i.e. `2` or `1 + 1`, if it is one
+pub fn const_index(e: &Expr) -> Option<i32> {
+    match &e.kind {
+        ExprKind::I(i)                                          => Some(*i),
+        ExprKind::UnaryOp{operator: UOp::Negative, e}           => const_index(e)?.checked_neg(),
+        ExprKind::BinOp{operator, e1, e2}                       => {
+            let (a, b) = (const_index(e1)?, const_index(e2)?);
+            match operator {
+                BOp::Add => a.checked_add(b),
+                BOp::Sub => a.checked_sub(b),
+                BOp::Mul => a.checked_mul(b),
+                BOp::Div => a.checked_div(b),
+                BOp::Mod => a.checked_rem(b),
+                _        => None
             }
         },
-        _ => Err("Cannot perform unary operation on anything other than a Base type!".to_string())
+        _                                                       => None
+    }
+}
+
+/// Returns whether a type is or contains the poison type
+fn is_poisoned(t: &ParsedType) -> bool {
+    match t {
+        Poison                      => true,
+        Tuple(v)                    => v.iter().any(is_poisoned),
+        NamedTuple(v)               => v.iter().any(|(_,t)| is_poisoned(t)),
+        Function(a,r)               => is_poisoned(a) || is_poisoned(r),
+        ParsedType::Shader(_, i, o) => is_poisoned(i) || is_poisoned(o),
+        Array(t,_)                  => is_poisoned(t),
+        Overloaded(v)               => v.iter().any(is_poisoned),
+        BaseType(_)                 => false
+    }
+}
+
+/// Returns whether two shader types are the same, disregarding the order of their inputs & outputs
+fn same_shader_type(t1: &ParsedType, t2: &ParsedType) -> bool {
+    // compares named tuples as sets
+    let same_named = |a: &ParsedType, b: &ParsedType| match (unpack_named(a), unpack_named(b)) {
+        (Ok(a), Ok(b)) => a.len() == b.len() && a.iter().all(|e| b.contains(e)),
+        _              => false
+    };
+    match (t1, t2) {
+        (ParsedType::Shader(s1, i1, o1), ParsedType::Shader(s2, i2, o2)) => s1 == s2 && same_named(i1, i2) && same_named(o1, o2),
+        _ => false
     }
 }
 
@@ -283,12 +1170,36 @@ THEN
     'c' has type 'T' in Gamma
 */
 
-// Typecheck general expressions
-fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
+/// Typecheck general expressions, failing w/ the first error found
+fn tc_expr(e: Expr, env: TCEnv) -> TCResult {
+    let mut errs = vec![];
+    let res = tc_recover(e, env, &TCContext::default(), &mut errs);
+    match errs.into_iter().next() {
+        Some(err) => Err(err),
+        None      => tc_pass(res.0, res.1)
+    }
+}
+
+/// Typecheck an expr, recovering from any error in it
+/// Errors are recorded, located at this expr unless they were found in one of its sub-exprs,
+/// & an ill-typed expr has the poison type w/out any effect on the env
+fn tc_recover(e: Expr, env: TCEnv, ctx: &TCContext, errs: &mut Vec<TCError>) -> TypeChecked {
+    match tc_expr_kind(e.kind, env.clone(), ctx, errs) {
+        Ok(res)  => res,
+        Err(err) => {
+            errs.push(err.at(e.span));
+            (Poison, env)
+        }
+    }
+}
+
+// Typecheck the kind of an expr
+// Errors in sub-exprs are recorded as they are found, while an error in the expr itself is returned
+fn tc_expr_kind(e: ExprKind, mut env: TCEnv, ctx: &TCContext, errs: &mut Vec<TCError>) -> TCResult {
     match e {
 
         // Int
-        Expr::I(_) => {
+        ExprKind::I(_) => {
             tc_pass(
                 ParsedType::BaseType("int".to_string()),
                 env
@@ -296,7 +1207,7 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
         },
 
         // Float
-        Expr::F(_) => {
+        ExprKind::F(_) => {
             tc_pass(
                 ParsedType::BaseType("float".to_string()),
                 env
@@ -304,7 +1215,7 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
         },
 
         // Double
-        Expr::D(_) => {
+        ExprKind::D(_) => {
             tc_pass(
                 ParsedType::BaseType("double".to_string()),
                 env
@@ -312,7 +1223,7 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
         },
 
         // Boolean
-        Expr::B(_) => {
+        ExprKind::B(_) => {
             tc_pass(
                 ParsedType::BaseType("bool".to_string()),
                 env
@@ -320,10 +1231,23 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
         },
 
         // Ref tc
-        Expr::Ref(r) => {
-            match env.get(&r) {
-                Some(t) => tc_pass(t.clone(), env),
-                None    => tc_fail(format!("Undefined reference '{r}'!"))
+        ExprKind::Ref(r) => {
+            tc_stage(&r, &env, ctx)?;
+            match tc_lookup_fn(&r, &env) {
+                Some(t) => {
+                    // a function that is not called may still be called w/ any of its signatures
+                    let sigs = match t {
+                        Overloaded(sigs) => sigs.clone(),
+                        t                => vec![t.clone()]
+                    };
+                    for sig in &sigs {
+                        if let Function(args, _) = sig {
+                            tc_staged_sig(&r, args, &env, ctx)?;
+                        }
+                    }
+                    tc_pass(t.clone(), env)
+                },
+                None    => tc_fail(TCErrorKind::UnboundName{ name: r })
             }
         },
 
@@ -339,22 +1263,33 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
             and Gamma entails term 'e' has type 'T'
         THEN
             Gamma entails 'let n : T = e' has type 'T'
+            (& 'n' is immutable from here on, so it may not be updated)
+            (a function bound over others of the same name is an overload of them, see `overload`)
+            (a decimal literal bound to a float is a float, see `fits_float`)
         */
-        Expr::Def{name: n, typ: t, value: e} => {
-            let (t1, mut env1) = tc_expr(*e, env)?;
-
-            if t != t1 {
-                // fail if the types don't match
-                tc_fail(format!("Definition of '{n}' has type '{t}', but was assigned to a value of type '{t1}'"))
-
-            } else {
-                // valid, add this name & type combo to our env & continue
-                env1.insert(n,t.clone());
-                tc_pass(
-                    t,
-                    env1
-                )
+        ExprKind::Def{name: n, typ: Annotation{typ: t, ..}, value: e} => {
+            let span = e.span;
+            let fits = fits_float(&e, &t);
+            let value_ctx = TCContext{ reached: Rc::default(), ..ctx.clone() };
+            let (t1, mut env1) = tc_value(*e, &t, env, &value_ctx, errs);
+            let t1 = if fits { typ("float") } else { t1 };
+
+            if !agrees(&t, &t1) {
+                // record if the types don't match, the name is still bound w/ its declared type
+                errs.push(TCError::from(TCErrorKind::BindingMismatch{ name: n.clone(), expected: t.clone(), actual: t1 }).at(span));
             }
+
+            // add this name & type combo to our env & continue, marking it as immutable
+            // a function is added to any others of the same name as an overload, see `overload`
+            tc_builtin_name(&n, &t, &env1)?;
+            let bound = overload(&n, env1.get(&n), &t)?;
+            mark_stage(&n, &t, &bound, value_ctx.reached.take(), &mut env1, ctx);
+            env1.set_immutable(&n, true);
+            env1.insert(n,bound);
+            tc_pass(
+                t,
+                env1
+            )
         },
 
         /*
@@ -371,44 +1306,75 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
             Gamma entails 'mut name : T = e' has type 'T'
             (however, updates to mut bindings are OK, just not w/ 'mut' again)
         */
-        // At the moment this is very much the same as constant bindings
-        // TODO, to distinguish this during updates,
-        //     we may need a 'mut' or 'let' modifier for bindings ?, or we'll handle this elsewhere...I'll come back to this
-        Expr::DefMut{name: n, typ: t, value: e} => {
-            let (t1,mut env1) = tc_expr(*e, env)?;
-
-            if t != t1 {
-                // fail if the types don't match
-                tc_fail(format!("Definition of '{n}' has type '{t}', but was assigned to a value of type '{t1}'"))
-
-            } else {
-                // valid, add this name & type combo to our env & continue
-                env1.insert(n,t.clone());
-                tc_pass(t, env1)
+        // Same as constant bindings, but w/out the mark of immutability
+        ExprKind::DefMut{name: n, typ: Annotation{typ: t, ..}, value: e} => {
+            let span = e.span;
+            let fits = fits_float(&e, &t);
+            let value_ctx = TCContext{ reached: Rc::default(), ..ctx.clone() };
+            let (t1,mut env1) = tc_value(*e, &t, env, &value_ctx, errs);
+            let t1 = if fits { typ("float") } else { t1 };
+
+            if !agrees(&t, &t1) {
+                // record if the types don't match, the name is still bound w/ its declared type
+                errs.push(TCError::from(TCErrorKind::BindingMismatch{ name: n.clone(), expected: t.clone(), actual: t1 }).at(span));
             }
+
+            // add this name & type combo to our env & continue, shadowing any immutable binding of it
+            tc_builtin_name(&n, &t, &env1)?;
+            mark_stage(&n, &t, &t, value_ctx.reached.take(), &mut env1, ctx);
+            env1.set_immutable(&n, false);
+            env1.insert(n,t.clone());
+            tc_pass(t, env1)
         },
 
         /*
         ## TC(Update)
         Γ ⊢ n : T
+        n mutable in Γ
         Γ ⊢ e : T
         ----------------
         Γ ⊢ `n = e` : T
 
         IF
             If Gamma implies term 'n' has type 'T'
+            AND 'n' was not bound by 'let' or as a parameter
             AND Gamma implies term 'e' has type 'T'
         THEN
             Gamma implies `n = e` has type 'T'
+            (a swizzle of a vector, `n.xy = e`, has the type of the swizzle, & may not write to a component twice)
         */
-        Expr::Update{target: n, value: v} => {
-            let t1 = tc_lookup(&n, &env)?;
-            let (t2,env) = tc_expr(*v, env)?;
-            if t1 == t2 {
-                tc_pass(t1,env)
-            } else {
-                tc_fail(format!("Definition of '{n}' has type '{t1}', but was assigned to a value of type '{t2}'"))
+        ExprKind::Update{target: n, swizzle, value: v} => {
+            tc_stage(&n, &env, ctx)?;
+            // built-in functions are never rebound
+            if !env.contains_key(&n) && prelude().contains_key(&n) {
+                return tc_fail(TCErrorKind::ImmutableUpdate{ name: n });
             }
+            let mut t1 = tc_lookup(&n, &env)?;
+            let name = match swizzle {
+                // only the components of the swizzle are written, each at most once
+                Some(s) => {
+                    t1 = match swizzle_type(&n, &t1, &s) {
+                        Some(st) => st?,
+                        None     => return tc_fail(TCErrorKind::NotANamedTuple{ name: n, actual: t1 })
+                    };
+                    if s.chars().duplicates().next().is_some() {
+                        return tc_fail(TCErrorKind::DuplicateSwizzleTarget{ name: n, swizzle: s });
+                    }
+                    format!("{n}.{s}")
+                },
+                None => n.clone()
+            };
+            let span = v.span;
+            let fits = fits_float(&v, &t1);
+            let (t2,env) = tc_recover(*v, env, ctx, errs);
+            let t2 = if fits { typ("float") } else { t2 };
+            if !agrees(&t1, &t2) {
+                errs.push(TCError::from(TCErrorKind::BindingMismatch{ name, expected: t1.clone(), actual: t2 }).at(span));
+            }
+            if env.is_immutable(&n) {
+                return tc_fail(TCErrorKind::ImmutableUpdate{ name: n });
+            }
+            tc_pass(t1,env)
         },
 
         /*
@@ -436,46 +1402,53 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
         */
         // Tricky, in order to typecheck abstractions they have to have a type declaration separate from their calling context?
         // Yeah need to think about that a bit more.
-        Expr::Abs{params: p, body: b} => {
-
-            let old_binding : Vec<(&String, ParsedType)> = p.iter().filter_map(
-                |(n,t)| {
-                    let oldval = env.insert(n.clone(), t.clone())?;
-                    Some((n, oldval))
-                }
-            ).collect();
+        // an abstraction bound by a definition is checked against its declared type instead, see `tc_value`
+        ExprKind::Abs{params: p, body: b} => tc_abs(p, b, None, env, ctx, errs),
 
-            // get type of the body (return type), ignoring effect on env
-            let (tb,_) = tc_body(b, &env)?;
-
-            // reset the env back
-            // Do not believe clippy. The iterator is collected above to insert the parameters into env.
-            old_binding.into_iter().for_each(|(n,t)| {
-                env.insert(n.clone(), t);
-            });
-
-            let p : Vec<ParsedType> = p.into_iter().map(|(_,t)| t).collect();
+        /*
+        ## TC(Return)
+        Γ ⊢ e : T
+        return : T ∈ Γ
+        ------------------
+        Γ ⊢ `return e` : T
 
-            match p.len() {
-                0 => {
-                    // simple type w/ no params
-                    tc_pass(tb, env)
-                },
-                1 => {
-                    // single arg, no tuple needed for this function type
-                    tc_pass(
-                        Function(Box::new(p[0].clone()), Box::new(tb)),
-                        env
-                    )
-                },
-                _ => {
-                    // function type to construct
-                    tc_pass(
-                        Function(Box::new(ParsedType::Tuple(p)), Box::new(tb)),
-                        env
-                    )
-                }
+        IF
+            Gamma implies term 'e' has type 'T'
+            AND Gamma is within the body of a function that returns type 'T'
+        THEN
+            Gamma implies `return e` has type 'T'
+            (a function w/ a declared type returns that type, otherwise it returns the type of its body)
+            (anything after a return in the same body is unreachable, see `tc_body`)
+        */
+        ExprKind::Return(v) => {
+            let span = v.span;
+            let (t,env) = tc_recover(*v, env, ctx, errs);
+            let expected = match &ctx.ret {
+                Some(r) => r.clone(),
+                None    => return tc_fail(TCErrorKind::ReturnOutsideFunction)
+            };
+            if !agrees(&expected, &t) {
+                errs.push(TCError::from(TCErrorKind::ReturnMismatch{ expected, actual: t.clone() }).at(span));
             }
+            tc_pass(t, env)
+        },
+
+        /*
+        ## TC(Discard)
+        stage : frag ∈ Γ
+        ------------------
+        Γ ⊢ `discard` : ⊥
+
+        IF
+            Gamma is within the body of a fragment shader, or of a function that is only called by them
+        THEN
+            Gamma implies `discard` is well-typed, w/ a type that agrees w/ any other
+            (a discard never produces a value, so `if c { discard } else { v }` has the type of 'v')
+            (functions that discard are marked as such, & may only be called by fragment shaders, see `tc_staged_sig`)
+        */
+        ExprKind::Discard => {
+            tc_stage("discard", &env, ctx)?;
+            tc_pass(Poison, env)
         },
 
         /*
@@ -490,75 +1463,71 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
             AND Gamma implies term 'e' has type 'T1'
         THEN
             Gamma implies `f(e)` (f applied to e) has type 'T2'
+            (the names of vector & matrix types are constructors, w/ their arguments checked by component, see `tc_constructor`)
+            (an overloaded function has exactly one signature that takes the args, as they are matched exactly)
         */
         // Function application
-        Expr::App{fname: f, arguments: args} => {
-            // verify that this name exists
-            let res = tc_lookup(&f, &env);
-            if res.is_err() {
-                return tc_fail(format!("Function {} does not exist", f));
+        ExprKind::App{fname: f, arguments: args} => {
+            // compute argument types first, as they are independent of the function
+            // any that do not typecheck are poisoned
+            // TODO @montymxb argument evaluation should update the environment instead of just cloning it
+            let arg_types_computed = args.into_iter().map(|e| tc_recover(e, env.clone(), ctx, errs).0).collect_vec();
+
+            // vectors & matrices are built by calling their type, unless that name was bound to something else
+            if !env.contains_key(&f) && !is_scalar(&typ(&f)) {
+                if let Some(target) = shape(&typ(&f)) {
+                    return tc_constructor(f, target, arg_types_computed, env);
+                }
             }
 
+            // verify that this name exists, & is available in the stage of any shader it is called in
+            // the signature of a function is only known once it is resolved, so its mark is checked after that
+            tc_stage(&f, &env, ctx)?;
+            let maybe_func = match tc_lookup_fn(&f, &env) {
+                Some(t) => t.clone(),
+                None    => return tc_fail(TCErrorKind::UnknownFunction{ name: f })
+            };
+
+            // an overloaded function is resolved to the one signature that takes these args
+            let maybe_func = match maybe_func {
+                Overloaded(sigs) => match matching_overloads(&sigs, &arg_types_computed)[..] {
+                    [sig] => sig.clone(),
+                    []    => return tc_fail(TCErrorKind::NoMatchingOverload{ name: f, actual: args_type(arg_types_computed), overloads: sigs }),
+                    // args are matched exactly & no two signatures take the same ones, so only ill-typed args match several,
+                    // & they were already reported
+                    _ => return tc_pass(Poison, env)
+                },
+                t => t
+            };
+
             // verify that our function is actually a function
             // and if so, extract the arg & return types
-            let maybeFunc = tc_lookup(&f, &env)?;
-            let (argType, retType) = if let Function(a,r) = maybeFunc {
+            let (arg_type, ret_type) = if let Function(a,r) = maybe_func {
                 (a,r)
             }
             else {
-                return tc_fail(format!("Variable {} is not a function!", f));
+                return tc_fail(TCErrorKind::NotAFunction{ name: f, actual: maybe_func });
             };
 
-            // verify that we have the correct # of args
-            if type_arity(*argType.clone()) != args.len() {
-                return tc_fail(format!("Incorrect number of arguments passed to f {}", f));
-            }
-            let l2 = args.len();
-
-            // compute argument types
-            // TODO @montymxb argument evaluation should update the environment instead of just cloning it
-            let mut mutEnv = env.clone();
-            let maybeArgTypesComputed: Vec<Result<(ParsedType, HashMap<String, ParsedType>), String>> = args.into_iter().map(|e| tc_expr(e, mutEnv.clone())).collect();
-
-            let maybeArgTypesComputedLen = maybeArgTypesComputed.as_slice().len();
-
-            // check that the lengths match up
-            if maybeArgTypesComputedLen != l2 {
-                return tc_fail(format!("Incorrect number of arguments supplied to function {}", f));
-            }
+            tc_staged_sig(&f, &arg_type, &env, ctx)?;
 
-            // verify that all of these are success (on any failures, we should just exit out)
-            for argResult in maybeArgTypesComputed.as_slice() {
-                if argResult.is_err() {
-                    return tc_fail(format!("Failed to typecheck argument of function {}", f));
-                }
+            // verify that we have the correct # of args
+            if type_arity(*arg_type.clone()) != arg_types_computed.len() {
+                return tc_fail(TCErrorKind::ArgCount{ name: f, expected: type_arity(*arg_type), actual: arg_types_computed.len() });
             }
 
             // before checking look to lift the supplied args into a tuple
             // this will let us easily check the two types in a single go
-            let argTypesComputed = maybeArgTypesComputed.into_iter().filter(|atc| atc.is_ok()).map(|atc| {
-                let (p,_) = atc.unwrap();
-                return p;
-            }).collect_vec();
-            let mut argPTComputed : ParsedType;
-            if maybeArgTypesComputedLen > 1 {
-                // unpack & wrap in a tuple
-                argPTComputed = Tuple(argTypesComputed);
-            } else {
-                // just the single value
-                argPTComputed = argTypesComputed[0].clone();
+            let arg_pt_computed : ParsedType = args_type(arg_types_computed);
 
-            }
-            
             // type check the args
-            if argPTComputed == *argType {
+            if agrees(&arg_pt_computed, &arg_type) {
                 // valid, return the function's return type
-                return tc_pass(*retType, env);
+                tc_pass(*ret_type, env)
             }
             else {
                 // fail, type mismatch!
-                // TODO, improve message
-                return tc_fail("Function typecheck failed".to_string());
+                tc_fail(TCErrorKind::ArgMismatch{ name: f, expected: *arg_type, actual: arg_pt_computed })
             }
         }
 
@@ -575,29 +1544,33 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
         THEN
             Gamma implies `e1 f e2` has type 'T'
             (NOTE: This is the restrictive case, it does NOT allow for binary ops w/ heterogenous types)
+            (vectors & matrices are the exception, w/ scalars applied to every component & products of linear algebra, see `vector_bop_type`)
+            (a decimal literal is a float when the other operand is made of floats, see `fits_float`)
         */
         // BinOp
-        Expr::BinOp{operator: b, e1: e11, e2: e22} => {
+        ExprKind::BinOp{operator: b, e1: e11, e2: e22} => {
+            let (lit1, lit2) = (is_decimal_literal(&e11), is_decimal_literal(&e22));
             // can use '?' here to help unwrap conditional value, using maybe or Either?
-            let (t1,e1) = tc_expr(*e11, env)?;
-            let (t2,e2) = tc_expr(*e22, e1)?;
+            let (t1,e1) = tc_recover(*e11, env, ctx, errs);
+            let (t2,e2) = tc_recover(*e22, e1, ctx, errs);
+            let t1 = if lit1 && is_float_shaped(&t2) { typ("float") } else { t1 };
+            let t2 = if lit2 && is_float_shaped(&t1) { typ("float") } else { t2 };
+            // operations on an ill-typed operand are ill-typed as well, w/out another error
+            if is_poisoned(&t1) || is_poisoned(&t2) {
+                return tc_pass(Poison, e2);
+            }
             // get param type & result type for this BinOp
             let btyp = bop_type(b, &t1, &t2)?;
 
             match btyp {
-                Function(tp, tr)    => {
-                    // compare w/ args
-                    if Tuple(vec![t1,t2]) == *tp {
-                        tc_pass(
-                            *tr,
-                            e2
-                        )
-                    } else {
-                        // TODO not a very descriptive error message, needs to be improved
-                        tc_fail("Bad binop!".to_string())
-                    }
+                Function(tp, tr) if Tuple(vec![t1.clone(),t2.clone()]) == *tp => {
+                    // matches the args
+                    tc_pass(
+                        *tr,
+                        e2
+                    )
                 },
-                _ => tc_fail("BinOps may only be function types, '{b}' was not!".to_string())
+                _ => tc_fail(TCErrorKind::BinOpMismatch{ op: b, lhs: t1, rhs: t2 })
             }
         },
 
@@ -610,14 +1583,17 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
 
         WLOG, same as TC(App) above, but w/ no parens
         */
-        Expr::UnaryOp{operator: b, e: e} => {
+        ExprKind::UnaryOp{operator: b, e} => {
             // TODO setup to get type of uop and match w/ expr type
-            let (t2,env1) = tc_expr(*e, env)?;
+            let (t2,env1) = tc_recover(*e, env, ctx, errs);
+            if is_poisoned(&t2) {
+                return tc_pass(Poison, env1);
+            }
             let t2c = t2.clone();
-            let uopTyp = uop_type(b, t2)?;
+            let uop_typ = uop_type(b, t2)?;
 
 
-            match uopTyp {
+            match uop_typ {
                 Function(f1,f2) => {
                     if *f1 == t2c {
                        // match
@@ -628,11 +1604,11 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
 
                     } else {
                         // mismatch
-                        tc_fail(format!("Unary operator '{b}' was expecting an argument of type '{f1}' but got an argument of type '{t2c}' instead"))
+                        tc_fail(TCErrorKind::UnOpMismatch{ op: b, actual: t2c })
 
                     }
                 },
-                _ => tc_fail(format!("Unary operator '{b}' was not a function type as expected, was instead '{uopTyp}'"))
+                _ => tc_fail(TCErrorKind::UnOpMismatch{ op: b, actual: t2c })
             }
         },
 
@@ -649,47 +1625,109 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
             Gamma implies `if(c) {e1} else {e2}` has type 'T'
         */
         // BRANCH
-        Expr::Branch{condition: c, b1: b1, b2: b2} => {
-            let (t1,env1) = tc_expr(*c, env)?;
-            if t1 != BaseType("bool".to_string()) {
-                return tc_fail(format!("'If' was expecting an expression of type 'bool', but got an expression of type '{t1}' instead"));
+        ExprKind::Branch{condition: c, b1, b2} => {
+            // the branches are still checked if the condition is not a bool, as they do not depend on it
+            let env1 = tc_condition(*c, env, ctx, errs);
 
-            } else {
-                // verify the types of b1 & b2 match
-                let (tb1,env2) = tc_body(b1, &env1)?;
-                let (tb2,env3) = tc_body(b2, &env2)?;
+            // verify the types of b1 & b2 match
+            let (tb1,env2) = tc_body(b1, &env1, ctx, errs)?;
+            let (tb2,env3) = tc_body(b2, &env2, ctx, errs)?;
 
-                if tb1 == tb2 {
-                    // body types match
-                    tc_pass(
-                        tb1,
-                        env3
-                    )
+            if agrees(&tb1, &tb2) {
+                // body types match, w/ the poison type only if both are poisoned
+                tc_pass(
+                    if tb1 == Poison { tb2 } else { tb1 },
+                    env3
+                )
 
-                } else {
-                    // body types do NOT match, fail
-                    return tc_fail(format!("'If' branches were expected to have the same type, but have differing types of '{tb1}' and '{tb2}' instead"));
+            } else {
+                // body types do NOT match, fail
+                tc_fail(TCErrorKind::BranchMismatch{ then: tb1, otherwise: tb2 })
 
-                }
             }
         },
 
 
+        /*
+        ## TC(For)
+        Γ ⊢ i : T1 ⊣ Γ'
+        Γ' ⊢ c : Bool
+        Γ' ⊢ p : T2
+        Γ' ⊢ e : T3
+        -----------------------------
+        Γ ⊢ `for (i, c, p) {e}` : ()
+
+        IF
+            Gamma implies the init 'i' is well-typed, extending Gamma to Gamma'
+            AND Gamma' implies the condition 'c' has type 'Bool'
+            AND Gamma' implies the post 'p' & the body 'e' are well-typed
+        THEN
+            Gamma implies the loop has the unit type '()', as a loop may run any number of times
+            (bindings of the init & body are scoped to the loop, so they do not leak out of it)
+        */
+        ExprKind::For{init, cond, post, body} => {
+            let (_,loop_env) = tc_recover(*init, env.clone(), ctx, errs);
+            let loop_env = tc_condition(*cond, loop_env, ctx, errs);
+            let (_,loop_env) = tc_recover(*post, loop_env, ctx, errs);
+            tc_body(body, &loop_env, ctx, errs)?;
+            tc_pass(Tuple(vec![]), env)
+        },
+
+        /*
+        ## TC(While)
+        Γ ⊢ c : Bool
+        Γ ⊢ e : T
+        ------------------------
+        Γ ⊢ `while c {e}` : ()
+
+        ## TC(DoWhile)
+        Γ ⊢ e : T
+        Γ ⊢ c : Bool
+        ---------------------------
+        Γ ⊢ `do {e} while c` : ()
+
+        IF
+            Gamma implies the condition 'c' has type 'Bool'
+            AND Gamma implies the body 'e' is well-typed
+        THEN
+            Gamma implies the loop has the unit type '()'
+            (the condition of a do-while is checked after the body, but cannot see any of its bindings)
+        */
+        ExprKind::While{cond, body} | ExprKind::DoWhile{body, cond} => {
+            let env = tc_condition(*cond, env, ctx, errs);
+            tc_body(body, &env, ctx, errs)?;
+            tc_pass(Tuple(vec![]), env)
+        },
+
+
         /*
         ## TC(AccessInx)
-        Γ ⊢ e : IVec(T1, ..., Tn)
-        Γ ⊢ i : Int
-        i ∈ e
-        -------------------------  Ti ∈ IVec(T1, ..., Tn)
+        Γ ⊢ e : (T0, ..., Tn)
+        i is a constant int
+        --------------------- 0 ≤ i ≤ n
         Γ ⊢ e[i] : Ti
 
+        ## TC(AccessIdx)
+        Γ ⊢ e : C
+        Γ ⊢ i : Int
+        ------------- C has n elements of type T, 0 ≤ i < n if i is a constant
+        Γ ⊢ e[i] : T
+
         IF
-            Gamma implies term 'e' has type 'IVec(T1, ..., Tn)'
-            AND Gamma implies term 'i' has type 'Int'
-            AND 'i' is an element of term 'e'
-            AND side condition Ti is an element of IVec(T1, ..., Tn) holds
+            Gamma implies term 'e' has a tuple type '(T0, ..., Tn)'
+            AND 'i' is a constant int, whose value is an index of the tuple
         THEN
             Gamma implies `e[i]` has type 'Ti'
+            (each element of a tuple has its own type, so the index must be known statically)
+
+        IF
+            Gamma implies term 'e' has type 'C', which is a vector, matrix or array of 'n' elements of type 'T'
+            AND Gamma implies term 'i' has type 'Int'
+            AND 'i' is an index of 'e' when it is a constant
+        THEN
+            Gamma implies `e[i]` has type 'T'
+            (a vecN has float elements, ivecN int, bvecN bool, & a matN has vecN columns)
+            (a dynamic index cannot be checked statically, & some versions of GLSL restrict them further, see conformance.rs)
 
 
         ## TC(AccessNam)
@@ -704,38 +1742,129 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
             AND side condition Ti is an element of NVec(T1, ..., Tn) holds
         THEN
             Gamma implies property i of e, `e.i`, has type 'Ti'
+            (e may be any expr, so accesses chain, i.e. `light.color.r` is `(light.color).r`)
+
+
+        ## TC(Swizzle)
+        Γ ⊢ e : VecN(T)
+        s1 ... sk selectors of a single set
+        ------------------------------------- 1 ≤ k ≤ 4, each si < N
+        Γ ⊢ e.s1...sk : VecK(T)
+
+        IF
+            Gamma implies term 'e' has a vector type w/ 'N' components of type 'T'
+            AND 's1' to 'sk' are all from one of 'xyzw', 'rgba' or 'stpq', & select one of those 'N' components
+        THEN
+            Gamma implies `e.s1...sk` has a vector type w/ 'k' components of type 'T', or type 'T' itself when 'k' is 1
+            (as in the original FDSSL syntax, `e[x]` is `e.x` when 'x' is a single selector that is not bound in Gamma)
         */
         // ACCESS by INDEX or NAME
-        Expr::Access(n, at) => {
+        ExprKind::Access(b, at) => {
+            let name = b.describe();
+            // a component named in brackets, `v[x]`, is the swizzle `v.x` unless 'x' is bound
+            let at = match at {
+                Idx(i) => match i.named_component() {
+                    Some(c) if !env.contains_key(c) => Name(c.to_string()),
+                    _                               => Idx(i)
+                },
+                at => at
+            };
+            let (t, env) = tc_recover(*b, env, ctx, errs);
             match at {
                 // access by name
                 Name(prop) => {
                     // return the property type for this item's name
-                    let t = tc_lookup(&n, &env)?;
                     match t {
-                        NamedTuple(namedTypes)  => {
-                            for (name,typ) in namedTypes {
-                                if *name == prop {
+                        // already reported where the accessed expr was checked
+                        Poison => tc_pass(Poison, env),
+                        NamedTuple(named_types)  => {
+                            for (pname,typ) in named_types {
+                                if *pname == prop {
                                     // immediately produce this type w/out any other work
                                     return tc_pass(*typ, env);
                                 }
                             }
-                            tc_fail(format!("Property '{}' on tuple '{}' is not defined", prop, n))
+                            tc_fail(TCErrorKind::UnknownProperty{ name, prop })
                         },
-                        // any other as a fail case
-                        _ => tc_fail(format!("Incorrect access of tuple '{}'!", n))
+                        // vectors are accessed by swizzles instead, any other as a fail case
+                        t => match swizzle_type(&name, &t, &prop) {
+                            Some(st) => tc_pass(st?, env),
+                            None     => tc_fail(TCErrorKind::NotANamedTuple{ name, actual: t })
+                        }
                     }
                 },
-                _ => tc_fail(format!("Invalid tuple index case for '{n}', not fully implemented yet!"))
+                // access by index
+                Idx(i) => {
+                    let span = i.span;
+                    let index = const_index(&i);
+                    let (it, env) = tc_recover(*i, env, ctx, errs);
+                    if !agrees(&it, &BaseType("int".to_string())) {
+                        return Err(TCError::from(TCErrorKind::IndexNotInt{ actual: it }).at(span));
+                    }
+
+                    let (elt, len) = match t {
+                        // already reported where the indexed expr was checked
+                        Poison => return tc_pass(Poison, env),
+                        Tuple(ref v) => match index {
+                            Some(k) => match usize::try_from(k).ok().and_then(|k| v.get(k)) {
+                                Some(et) => return tc_pass(et.clone(), env),
+                                None     => (Poison, v.len())
+                            },
+                            None => return Err(TCError::from(TCErrorKind::TupleIndexNotConstant{ name }).at(span))
+                        },
+                        ref t => match element_type(t) {
+                            Some(et) => et,
+                            None     => return tc_fail(TCErrorKind::NotIndexable{ name, actual: t.clone() })
+                        }
+                    };
+                    match index {
+                        Some(k) if k < 0 || k as usize >= len => Err(TCError::from(TCErrorKind::IndexOutOfBounds{ name, index: k, len }).at(span)),
+                        _ => tc_pass(elt, env)
+                    }
+                }
+            }
+        },
+
+        /*
+        ## TC(NamedVect)
+        Γ ⊢ e1 : T1, ..., Γ ⊢ en : Tn
+        -------------------------------------------------------- p1, ..., pn are distinct
+        Γ ⊢ `(p1: e1, ..., pn: en)` : NVec(p1:T1, ..., pn:Tn)
+
+        IF
+            Gamma implies each term 'ei' has type 'Ti'
+            AND no property 'pi' is given more than once
+        THEN
+            Gamma implies the named tuple has type 'NVec(p1:T1, ..., pn:Tn)', w/ its properties in the order they were given
+        */
+        ExprKind::NamedVect(v) => {
+            let mut named_types : Vec<(String, Box<ParsedType>)> = vec![];
+            let mut duplicated = false;
+
+            for (p, e) in v {
+                let span = e.span;
+                let (et,env1) = tc_recover(e, env, ctx, errs);
+                env = env1;
+                if named_types.iter().any(|(n,_)| *n == p) {
+                    errs.push(TCError::from(TCErrorKind::DuplicateProperty{ prop: p.clone() }).at(span));
+                    duplicated = true;
+                }
+                named_types.push((p, Box::new(et)));
             }
+
+            // the tuple has no sensible type, but its error was already reported
+            if duplicated {
+                return tc_pass(Poison, env);
+            }
+            tc_pass(NamedTuple(named_types), env)
         },
 
         // Vector of expressions
-        Expr::Vect(v) => {
+        ExprKind::Vect(v) => {
             let mut tup_type = vec![];
 
             for e in v {
-                let (et,env1) = tc_expr(e, env)?;
+                let (et,env1) = tc_recover(e, env, ctx, errs);
                 tup_type.push(et);
                 env = env1;
             }
@@ -745,23 +1874,29 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
         },
 
 
-        // fill in the rest here, and just call out the relevant handler
-        _ => tc_fail(format!("Unrecognized expression '{:?}'", e))
-    }
-}
-
-
-/*
+        /*
+        ## TC(Uniform)
+        u : T ∈ Γ
+        --------------------------
+        Γ ⊢ `uniform T u` : T
 
-#
-# Typing Rules
-#
+        IF
+            uniform 'u' has type 'T' in Gamma (all uniforms are gathered before typechecking)
+        THEN
+            Gamma implies the declaration `uniform T u` has type 'T'
+        */
+        ExprKind::Uniform{name, typ: Annotation{typ: t, ..}} => {
+            env.insert(name, t.clone());
+            tc_pass(t, env)
+        },
 
+        // source that failed to parse, which was already reported as a syntax error
+        ExprKind::Error => tc_pass(Poison, env),
 
-??? TC(For)
-.... current implementation is not capable of being type checked, does not guarantee a value ....
-.... should we consider a 'do while', given we can just guarantee a value then?
- */
+        // shaders & programs are only checked at the top level, see `tc_program`
+        _ => tc_fail(TCErrorKind::UnsupportedExpr{ expr: format!("{:?}", e) })
+    }
+}
 
 
 //
@@ -774,12 +1909,12 @@ fn tc_expr(e: Expr, mut env: TCEnv) -> TCResult {
 /// Tests typechecker env lookup behavior
 #[test]
 fn test_tc_lookup() {
-    let mut tcEnv: TCEnv = HashMap::new();
+    let mut tc_env = TCEnv::new();
     let bt: ParsedType = typ("SomeString");
-    tcEnv.insert("key".to_string(), bt.clone());
+    tc_env.insert("key".to_string(), bt.clone());
 
     // try a good lookup
-    let r1 = tc_lookup("key", &tcEnv);
+    let r1 = tc_lookup("key", &tc_env);
     assert_eq!(
         r1,
         Ok(bt),
@@ -787,28 +1922,30 @@ fn test_tc_lookup() {
     );
 
     // try a bad lookup
-    let r2 = tc_lookup("badKey", &tcEnv);
+    let r2 = tc_lookup("badKey", &tc_env);
     assert_eq!(
-        r2,
-        Err(
-            "Non-existant binding 'badKey' referenced, perhaps you meant to declare it first with 'let' or 'mut'?".to_string()
-        ),
+        r2.map_err(|e| *e.kind),
+        Err(TCErrorKind::UnboundName{ name: "badKey".to_string() }),
         "Failed to reject bad lookup"
     )
 }
 
 #[test]
 fn test_tc_body() {
-    let tcEnv: TCEnv = HashMap::new();
+    let tc_env = TCEnv::new();
 
     // typecheck an empty body, should fail
     let body: Vec<Expr> = vec![];
-    let r1: TCResult = tc_body(body, &tcEnv);
+    let r1: TCResult = tc_body(body, &tc_env, &TCContext::default(), &mut vec![]);
     assert_eq!(
-        r1,
-        Err("Unable to typecheck an empty body!".to_string()),
+        r1.map_err(|e| *e.kind),
+        Err(TCErrorKind::EmptyBody),
         "Should have rejected an empty program body"
     );
+
+    // comments are skipped over, keeping the type of the prior expr
+    let body: Vec<Expr> = vec![ExprKind::I(1).into(), ExprKind::Comment(vec![" one".to_string()]).into()];
+    assert_eq!(tc_body(body, &tc_env, &TCContext::default(), &mut vec![]), Ok((typ("int"), tc_env.clone())), "Should have kept the type before a comment");
 }
 
 #[test]
@@ -841,22 +1978,22 @@ fn test_type_arity() {
 #[test]
 fn test_op_num_type() {
     // verify basic binary ops are correctly classified as numeric (or not)
-    assert_eq!(op_num_type(&BOp::Mod), true, "Failed to identify Mod as numeric");
-    assert_eq!(op_num_type(&BOp::Add), true, "Failed to identify Add as numeric");
-    assert_eq!(op_num_type(&BOp::Sub), true, "Failed to identify Sub as numeric");
-    assert_eq!(op_num_type(&BOp::Mul), true, "Failed to identify Mul as numeric");
-    assert_eq!(op_num_type(&BOp::Div), true, "Failed to identify Div as numeric");
+    assert!(op_num_type(&BOp::Mod), "Failed to identify Mod as numeric");
+    assert!(op_num_type(&BOp::Add), "Failed to identify Add as numeric");
+    assert!(op_num_type(&BOp::Sub), "Failed to identify Sub as numeric");
+    assert!(op_num_type(&BOp::Mul), "Failed to identify Mul as numeric");
+    assert!(op_num_type(&BOp::Div), "Failed to identify Div as numeric");
 
-    assert_eq!(op_num_type(&BOp::Gt), true, "Failed to identify Gt as numeric");
-    assert_eq!(op_num_type(&BOp::Gte), true, "Failed to identify Gte as numeric");
-    assert_eq!(op_num_type(&BOp::Lt), true, "Failed to identify Lt as numeric");
-    assert_eq!(op_num_type(&BOp::Lte), true, "Failed to identify Lte as numeric");
+    assert!(op_num_type(&BOp::Gt), "Failed to identify Gt as numeric");
+    assert!(op_num_type(&BOp::Gte), "Failed to identify Gte as numeric");
+    assert!(op_num_type(&BOp::Lt), "Failed to identify Lt as numeric");
+    assert!(op_num_type(&BOp::Lte), "Failed to identify Lte as numeric");
 
     // some non numerics
-    assert_eq!(op_num_type(&BOp::Or), false, "Failed to identify Or as non-numeric");
-    assert_eq!(op_num_type(&BOp::And), false, "Failed to identify And as non-numeric");
-    assert_eq!(op_num_type(&BOp::Eq), false, "Failed to identify Eq as non-numeric");
-    assert_eq!(op_num_type(&BOp::Neq), false, "Failed to identify Neq as non-numeric");
+    assert!(!op_num_type(&BOp::Or), "Failed to identify Or as non-numeric");
+    assert!(!op_num_type(&BOp::And), "Failed to identify And as non-numeric");
+    assert!(!op_num_type(&BOp::Eq), "Failed to identify Eq as non-numeric");
+    assert!(!op_num_type(&BOp::Neq), "Failed to identify Neq as non-numeric");
 }
 
 /// Pulled from std (lib), seems we don't have this in our version?
@@ -882,25 +2019,1009 @@ macro_rules! assert_ok {
 /// Typecheck various expressions
 #[test]
 fn test_tc_expr() {
-    let mut tcEnv: TCEnv = HashMap::new();
-    tcEnv.insert("ref".to_string(), typ("int"));
-    tcEnv.insert("add".to_string(), mk_bin_func(typ("int")));
-
-    assert_eq!(tc_expr(Expr::I(32), tcEnv.clone()), Ok((typ("int"), tcEnv.clone())));
-    assert_eq!(tc_expr(Expr::F(32.32), tcEnv.clone()), Ok((typ("float"), tcEnv.clone())));
-    assert_eq!(tc_expr(Expr::D(32.32), tcEnv.clone()), Ok((typ("double"), tcEnv.clone())));
-    assert_eq!(tc_expr(Expr::B(true), tcEnv.clone()), Ok((typ("bool"), tcEnv.clone())));
-    assert_eq!(tc_expr(Expr::Ref("ref".to_string()), tcEnv.clone()), Ok((typ("int"), tcEnv.clone())));
+    let mut tc_env = TCEnv::new();
+    tc_env.insert("ref".to_string(), typ("int"));
+    tc_env.insert("add".to_string(), mk_bin_func(typ("int")));
+
+    assert_eq!(tc_expr(ExprKind::I(32).into(), tc_env.clone()), Ok((typ("int"), tc_env.clone())));
+    assert_eq!(tc_expr(ExprKind::F(32.32).into(), tc_env.clone()), Ok((typ("float"), tc_env.clone())));
+    assert_eq!(tc_expr(ExprKind::D(32.32).into(), tc_env.clone()), Ok((typ("double"), tc_env.clone())));
+    assert_eq!(tc_expr(ExprKind::B(true).into(), tc_env.clone()), Ok((typ("bool"), tc_env.clone())));
+    assert_eq!(tc_expr(ExprKind::Ref("ref".to_string()).into(), tc_env.clone()), Ok((typ("int"), tc_env.clone())));
     
     // verify func app
-    let app: Expr = Expr::App { fname: "add".to_string(), arguments: vec![
-        Expr::I(3),
-        Expr::I(7)
-    ]};
-    assert_eq!(tc_expr(app, tcEnv.clone()), Ok((typ("int"), tcEnv.clone())));
+    let app: Expr = ExprKind::App { fname: "add".to_string(), arguments: vec![
+        ExprKind::I(3).into(),
+        ExprKind::I(7).into()
+    ]}.into();
+    assert_eq!(tc_expr(app, tc_env.clone()), Ok((typ("int"), tc_env.clone())));
 
-    // TODO @montymxb add test for do/while (instead of for), we can desugar that as needed I think
     // TODO @montymxb test for Abstraction would be good
     // TODO @montymxb test for branch would be good here too
 
 }
+
+/// Used during testing to build a shader w/ a block body
+#[cfg(test)]
+fn mk_shader(stage: Stage, name: &str, inputs: Vec<(&str,&str)>, outputs: Vec<(&str,&str)>, body: Vec<Expr>) -> Expr {
+    let named = |v: Vec<(&str,&str)>| v.into_iter().map(|(n,t)| (n.to_string(), typ(t).into())).collect();
+    ExprKind::Shader{
+        stage,
+        name: name.to_string(),
+        inputs: named(inputs),
+        outputs: named(outputs),
+        body: ShaderBody::Block(body)
+    }.into()
+}
+
+/// Used during testing to build a program
+#[cfg(test)]
+fn mk_prog(name: &str, vert: &str, frag: &str) -> Expr {
+    ExprKind::Prog{name: name.to_string(), vert: vert.to_string(), frag: frag.to_string()}.into()
+}
+
+/// Used during testing to build an update
+#[cfg(test)]
+fn mk_update(target: &str, value: &str) -> Expr {
+    ExprKind::Update{target: target.to_string(), swizzle: None, value: Box::new(ExprKind::Ref(value.to_string()).into())}.into()
+}
+
+/// Typecheck shader declarations
+#[test]
+fn test_tc_shader() {
+    let uniform: Expr = ExprKind::Uniform{name: "c".to_string(), typ: typ("vec4").into()}.into();
+
+    // the shader is bound to its signature
+    let v = mk_shader(Stage::Vertex, "v", vec![("aVertPos","vec3")], vec![("vXYZ","vec3")], vec![mk_update("vXYZ", "aVertPos")]);
+    let vt = ParsedType::Shader(
+        Stage::Vertex,
+        Box::new(NamedTuple(vec![("aVertPos".to_string(), Box::new(typ("vec3")))])),
+        Box::new(NamedTuple(vec![("vXYZ".to_string(), Box::new(typ("vec3")))]))
+    );
+    let (t, env) = assert_ok!(tc_program(vec![v.clone()]));
+    assert_eq!(t, vt, "Wrong type for vertex shader");
+    assert_eq!(env.get("v"), Some(&vt), "Vertex shader was not bound in the env");
+    assert_eq!(env.get("aVertPos"), None, "Shader inputs leaked into the env");
+
+    // bodies are only checked once used in a program, as they may be incomplete until composed
+    let f = |stage: Stage| mk_shader(stage, "f", vec![], vec![], vec![mk_update("gl_FragColor", "c")]);
+    assert!(tc_program(vec![uniform.clone(), f(Stage::Vertex)]).is_ok(), "Failed to accept an unused incomplete shader");
+
+    // stage built-ins are only visible in their stage
+    let frag = mk_shader(Stage::Fragment, "f2", vec![], vec![], vec![ExprKind::I(0).into()]);
+    assert!(
+        tc_program(vec![uniform.clone(), v.clone(), f(Stage::Fragment), mk_prog("p", "v", "f")]).is_ok(),
+        "Failed to write gl_FragColor in a fragment shader"
+    );
+    assert!(
+        tc_program(vec![uniform.clone(), f(Stage::Vertex), frag, mk_prog("p", "f", "f2")]).is_err(),
+        "Failed to reject gl_FragColor in a vertex shader"
+    );
+}
+
+/// Typecheck uniforms in a program
+#[test]
+fn test_tc_uniforms() {
+    let uniform = |n: &str, t: &str| -> Expr { ExprKind::Uniform{name: n.to_string(), typ: typ(t).into()}.into() };
+
+    // uniforms are visible to functions & shaders, even before they are declared
+    let prog = vec![
+        ExprKind::Def{
+            name: "f".to_string(),
+            typ: mk_func_typ(typ("int"), typ("int")).into(),
+            value: Box::new(ExprKind::Abs{
+                params: vec![("x".to_string(), typ("int").into())],
+                body: vec![ExprKind::BinOp{operator: BOp::Add, e1: Box::new(ExprKind::Ref("x".to_string()).into()), e2: Box::new(ExprKind::Ref("uValue".to_string()).into())}.into()]
+            }.into())
+        }.into(),
+        mk_shader(Stage::Vertex, "v", vec![], vec![], vec![mk_update("gl_Position", "uColor")]),
+        mk_shader(Stage::Fragment, "s", vec![], vec![], vec![mk_update("gl_FragColor", "uColor")]),
+        mk_prog("p", "v", "s"),
+        uniform("uValue", "int"),
+        uniform("uColor", "vec4"),
+    ];
+    let (_, env) = assert_ok!(tc_program(prog));
+    assert_eq!(env.get("uValue"), Some(&typ("int")), "Uniform was not bound in the env");
+
+    // re-declaring w/ the same type is fine
+    assert!(tc_program(vec![uniform("uTime", "float"), uniform("uTime", "float")]).is_ok(), "Failed to accept identical uniforms");
+
+    // but not w/ a conflicting one
+    assert_eq!(
+        tc_program(vec![uniform("uTime", "float"), uniform("uTime", "int")]).map_err(|e| e.kinds()),
+        Err(vec![TCErrorKind::UniformRedeclared{ name: "uTime".to_string(), expected: typ("float"), actual: typ("int") }]),
+        "Failed to reject conflicting uniforms"
+    );
+}
+
+/// Typecheck & link programs
+#[test]
+fn test_tc_prog() {
+    let shaders = vec![
+        mk_shader(Stage::Vertex, "v1", vec![("aVertPos","vec3")], vec![("vXYZ","vec3"), ("pos","vec4")], vec![mk_update("vXYZ", "aVertPos")]),
+        mk_shader(Stage::Vertex, "v2", vec![("aVertPos","vec3")], vec![("vXYZ","vec4")], vec![ExprKind::I(0).into()]),
+        mk_shader(Stage::Fragment, "f1", vec![("vXYZ","vec3")], vec![], vec![ExprKind::I(1).into()]),
+        mk_shader(Stage::Fragment, "f2", vec![], vec![], vec![ExprKind::I(2).into()]),
+    ];
+
+    // several programs in one file
+    let mut p = shaders.clone();
+    p.push(mk_prog("p1", "v1", "f1"));
+    p.push(mk_prog("p2", "v1", "f2"));
+    let (_, env) = assert_ok!(tc_program(p.clone()));
+    assert_eq!(env.get("p1"), Some(&typ("Prog")), "Program was not bound in the env");
+    assert_eq!(
+        link_programs(&p, &env),
+        Ok(vec![
+            LinkedProg{
+                name: "p1".to_string(),
+                vert: "v1".to_string(),
+                frag: "f1".to_string(),
+                attributes: vec![("aVertPos".to_string(), typ("vec3"))],
+                varyings: vec![("vXYZ".to_string(), typ("vec3")), ("pos".to_string(), typ("vec4"))],
+                outputs: vec![],
+                vert_body: vec![mk_update("vXYZ", "aVertPos")],
+                frag_body: vec![ExprKind::I(1).into()],
+            },
+            LinkedProg{
+                name: "p2".to_string(),
+                vert: "v1".to_string(),
+                frag: "f2".to_string(),
+                attributes: vec![("aVertPos".to_string(), typ("vec3"))],
+                varyings: vec![("vXYZ".to_string(), typ("vec3")), ("pos".to_string(), typ("vec4"))],
+                outputs: vec![],
+                vert_body: vec![mk_update("vXYZ", "aVertPos")],
+                frag_body: vec![ExprKind::I(2).into()],
+            },
+        ]),
+        "Failed to link programs"
+    );
+
+    // mismatched types between stages
+    let mut p = shaders.clone();
+    p.push(mk_prog("p", "v2", "f1"));
+    assert_eq!(
+        tc_program(p).map_err(|e| e.kinds()),
+        Err(vec![TCErrorKind::VaryingMismatch{ prog: "p".to_string(), name: "vXYZ".to_string(), expected: typ("vec3"), actual: typ("vec4") }]),
+        "Failed to reject mismatched varying"
+    );
+
+    // missing an input for the fragment shader
+    let mut p = shaders.clone();
+    p.push(mk_shader(Stage::Vertex, "v3", vec![], vec![], vec![ExprKind::I(0).into()]));
+    p.push(mk_prog("p", "v3", "f1"));
+    assert!(matches!(tc_program(p).map_err(|e| e.kinds()).err().as_deref(), Some([TCErrorKind::MissingVarying{..}])), "Failed to reject missing varying");
+
+    // shaders in the wrong stage
+    let mut p = shaders.clone();
+    p.push(mk_prog("p", "f1", "v1"));
+    assert!(matches!(tc_program(p).map_err(|e| e.kinds()).err().as_deref(), Some([TCErrorKind::WrongStage{..}])), "Failed to reject shaders in the wrong stages");
+
+    // undefined shaders
+    assert!(matches!(tc_program(vec![mk_prog("p", "v", "f")]).map_err(|e| e.kinds()).err().as_deref(), Some([TCErrorKind::UnboundName{..}])), "Failed to reject undefined shaders");
+
+    // ill-typed body in a linked shader
+    let mut p = shaders.clone();
+    p.push(mk_shader(Stage::Vertex, "v4", vec![], vec![("vXYZ","vec3")], vec![mk_update("vXYZ", "undefined")]));
+    p.push(mk_prog("p", "v4", "f1"));
+    assert!(matches!(tc_program(p).map_err(|e| e.kinds()).err().as_deref(), Some([TCErrorKind::UnboundName{..}])), "Failed to reject ill-typed shader body");
+}
+
+/// Typecheck composed shaders
+#[test]
+fn test_tc_shader_compose() {
+    let compose = |s2: &str, s1: &str| -> Expr { ExprKind::BinOp{operator: BOp::Compose, e1: Box::new(ExprKind::Ref(s2.to_string()).into()), e2: Box::new(ExprKind::Ref(s1.to_string()).into())}.into() };
+    let composed = |name: &str, stage: Stage, inputs: Vec<(&str,&str)>, outputs: Vec<(&str,&str)>, body: Expr| {
+        let mut s = mk_shader(stage, name, inputs, outputs, vec![]);
+        if let ExprKind::Shader{body: ref mut b, ..} = s.kind {
+            *b = ShaderBody::Composed(Box::new(body));
+        }
+        s
+    };
+    // v2 reads 'pos', which is only produced by v1
+    let shaders = vec![
+        ExprKind::Uniform{name: "uMat".to_string(), typ: typ("vec4").into()}.into(),
+        mk_shader(Stage::Vertex, "v1", vec![("aVertPos","vec3")], vec![("pos","vec4")], vec![mk_update("pos", "uMat")]),
+        mk_shader(Stage::Vertex, "v2", vec![("aVertPos","vec3")], vec![("vXYZ","vec3")], vec![mk_update("gl_Position", "pos"), mk_update("vXYZ", "aVertPos")]),
+        mk_shader(Stage::Fragment, "f1", vec![("vXYZ","vec3")], vec![], vec![ExprKind::I(1).into()]),
+        mk_shader(Stage::Fragment, "f2", vec![("pos","vec4")], vec![], vec![mk_update("gl_FragColor", "pos")]),
+    ];
+
+    // compose both stages, w/ the declared signature in a different order than the union
+    let mut p = shaders.clone();
+    p.push(composed("finalVert", Stage::Vertex, vec![("aVertPos","vec3")], vec![("vXYZ","vec3"), ("pos","vec4")], compose("v2", "v1")));
+    p.push(composed("finalFrag", Stage::Fragment, vec![("vXYZ","vec3"), ("pos","vec4")], vec![], compose("f2", "f1")));
+    p.push(mk_prog("p", "finalVert", "finalFrag"));
+    let (_, env) = assert_ok!(tc_program(p.clone()));
+
+    // bodies are sequenced, running the right-hand shader first
+    let linked = assert_ok!(link_programs(&p, &env));
+    assert_eq!(
+        linked[0].vert_body,
+        vec![mk_update("pos", "uMat"), mk_update("gl_Position", "pos"), mk_update("vXYZ", "aVertPos")],
+        "Failed to sequence composed vertex shaders"
+    );
+    assert_eq!(linked[0].frag_body, vec![ExprKind::I(1).into(), mk_update("gl_FragColor", "pos")], "Failed to sequence composed fragment shaders");
+
+    // inputs of the second shader can be satisfied by outputs of the first
+    let mut p = shaders.clone();
+    p.push(mk_shader(Stage::Vertex, "v3", vec![("pos","vec4")], vec![("vXYZ","vec3")], vec![ExprKind::I(0).into()]));
+    p.push(composed("c", Stage::Vertex, vec![("aVertPos","vec3")], vec![("vXYZ","vec3"), ("pos","vec4")], compose("v3", "v1")));
+    assert!(tc_program(p).is_ok(), "Failed to satisfy input of the second shader w/ the first");
+
+    // the declared signature must match the union
+    let mut p = shaders.clone();
+    p.push(composed("c", Stage::Vertex, vec![("aVertPos","vec3")], vec![("vXYZ","vec3")], compose("v2", "v1")));
+    assert!(matches!(tc_program(p).map_err(|e| e.kinds()).err().as_deref(), Some([TCErrorKind::ShaderSignatureMismatch{..}])), "Failed to reject composition w/ missing output");
+
+    // the union must agree on types
+    let mut p = shaders.clone();
+    p.push(mk_shader(Stage::Vertex, "v4", vec![("aVertPos","vec4")], vec![], vec![ExprKind::I(0).into()]));
+    p.push(composed("c", Stage::Vertex, vec![("aVertPos","vec3")], vec![("pos","vec4")], compose("v4", "v1")));
+    assert!(matches!(tc_program(p).map_err(|e| e.kinds()).err().as_deref(), Some([TCErrorKind::ConflictingIO{..}])), "Failed to reject composition w/ conflicting inputs");
+
+    // shaders must be of the same stage
+    let mut p = shaders.clone();
+    p.push(composed("c", Stage::Vertex, vec![("aVertPos","vec3"), ("vXYZ","vec3")], vec![("pos","vec4")], compose("f1", "v1")));
+    assert!(matches!(tc_program(p).map_err(|e| e.kinds()).err().as_deref(), Some([TCErrorKind::StageMismatch{..}])), "Failed to reject composition across stages");
+}
+
+/// Tests that errors are located at the innermost expr they were found in
+#[test]
+fn test_tc_error_spans() {
+    let src = "let x : int = 1\nlet y : int = x * (2 + true)\n";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(es.errors.len(), 1, "Expected a single error");
+    let e = &es.errors[0];
+    assert_eq!(*e.kind, TCErrorKind::UnsupportedOperand{ op: BOp::Add, actual: typ("bool") }, "Wrong kind of error");
+    let span = e.span.expect("Expected the error to be located");
+    assert_eq!(&src[span.range(src)], "2 + true", "Error should be located at the offending operation");
+    assert_eq!(span.line_col(src), (2, 20), "Wrong line & column for the error");
+}
+
+/// Tests that typechecking carries on past errors, w/out reporting errors that follow from them
+#[test]
+fn test_tc_multiple_errors() {
+    let src = "\
+let x : int = 1 + true
+let y : int = x * 2
+let z : bool = y
+let w : int = f(undefined, 1 + false)
+if (x) { 1 } else { 2 }
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::UnsupportedOperand{ op: BOp::Add, actual: typ("bool") },
+            TCErrorKind::BindingMismatch{ name: "z".to_string(), expected: typ("bool"), actual: typ("int") },
+            TCErrorKind::UnboundName{ name: "undefined".to_string() },
+            TCErrorKind::UnsupportedOperand{ op: BOp::Add, actual: typ("bool") },
+            TCErrorKind::UnknownFunction{ name: "f".to_string() },
+            TCErrorKind::ConditionNotBool{ actual: typ("int") },
+        ],
+        "Wrong errors for a program w/ several independent errors"
+    );
+    let lines : Vec<usize> = es.errors.iter().map(|e| e.span.unwrap().line_col(src).0).collect();
+    assert_eq!(lines, vec![1, 3, 4, 4, 4, 5], "Errors should be located on their own lines");
+
+    // ill-typed definitions are still bound w/ their declared type
+    assert_eq!(es.env.get("x"), Some(&typ("int")), "Failed to bind an ill-typed definition");
+    assert_eq!(es.env.get("w"), Some(&typ("int")), "Failed to bind a definition w/ an ill-typed value");
+
+    // the arguments of a call are checked, even though the call itself is poisoned
+    let mut env = TCEnv::new();
+    env.insert("f".to_string(), mk_func_typ(Tuple(vec![typ("int"), typ("int")]), typ("int")));
+    let call : Expr = ExprKind::App{fname: "f".to_string(), arguments: vec![
+        ExprKind::Ref("a".to_string()).into(),
+        ExprKind::Ref("b".to_string()).into()
+    ]}.into();
+    let mut errs = vec![];
+    assert_eq!(tc_recover(call, env, &TCContext::default(), &mut errs).0, typ("int"), "Call w/ ill-typed arguments should keep its return type");
+    assert_eq!(
+        errs.into_iter().map(|e| *e.kind).collect::<Vec<_>>(),
+        vec![TCErrorKind::UnboundName{ name: "a".to_string() }, TCErrorKind::UnboundName{ name: "b".to_string() }],
+        "Failed to report both ill-typed arguments"
+    );
+}
+
+/// Tests that 'let' bindings & parameters cannot be updated, but 'mut' bindings can
+#[test]
+fn test_tc_immutable() {
+    let src = "\
+let x : int = 1
+x = x + 1
+mut y : int = 1
+y += 2
+let f : int -> int = (a : int) {
+\ta -= 1
+\tmut b : int = a
+\tb *= 2
+\tb
+}
+mut x : int = 2
+x = 3
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::ImmutableUpdate{ name: "x".to_string() },
+            TCErrorKind::ImmutableUpdate{ name: "a".to_string() },
+        ],
+        "Wrong errors for updates of immutable bindings"
+    );
+    let lines : Vec<usize> = es.errors.iter().map(|e| e.span.unwrap().line_col(src).0).collect();
+    assert_eq!(lines, vec![2, 6], "Updates of immutable bindings should be located on their own lines");
+
+    // parameters are only bound in the body of their function
+    assert_eq!(es.env.get("a"), None, "Parameter leaked out of its function");
+
+    // the inputs of a shader are its parameters, whether they are attributes or varyings
+    let src = "\
+vert v : (vec4 aPos) -> (vec4 vX) = {
+\tset aPos vec4(1.0f)
+\tset vX aPos
+\tset gl_Position aPos
+}
+frag f : (vec4 vX) -> () = {
+\tset vX vec4(0.0f)
+\tset gl_FragColor vX
+}
+p : Prog = mkProg v f
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::ImmutableUpdate{ name: "aPos".to_string() },
+            TCErrorKind::ImmutableUpdate{ name: "vX".to_string() },
+        ],
+        "Wrong errors for updates of shader inputs"
+    );
+    let lines : Vec<usize> = es.errors.iter().map(|e| e.span.unwrap().line_col(src).0).collect();
+    assert_eq!(lines, vec![2, 7], "Updates of shader inputs should be located on their own lines");
+}
+
+/// Typechecks for, while & do-while loops, whose conditions must be bools & whose bindings are scoped to the loop
+#[test]
+fn test_tc_loops() {
+    let src = "\
+mut t : float = 0.0f
+for (mut i : int = 0, i < 4, i += 1) {
+\tt += 1.0f
+}
+while t > 1.0f {
+\tt -= 1.0f
+}
+do {
+\tmut u : float = t
+\tt = u * 2.0f
+} while t < 1.0f
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (t, env) = tc_program(p).unwrap();
+    assert_eq!(t, Tuple(vec![]), "Loops should have the unit type");
+    assert_eq!(env.get("i"), None, "Loop index leaked out of its loop");
+    assert_eq!(env.get("u"), None, "Binding in the body of a loop leaked out of it");
+
+    let src = "\
+for (mut i : int = 0, i, i += 1) {
+\t1
+}
+while 1.0f {
+\t1
+}
+do {
+\tmut u : bool = true
+} while u
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::ConditionNotBool{ actual: typ("int") },
+            TCErrorKind::ConditionNotBool{ actual: typ("float") },
+            TCErrorKind::UnboundName{ name: "u".to_string() },
+        ],
+        "Wrong errors for loops w/ bad conditions"
+    );
+    let lines : Vec<usize> = es.errors.iter().map(|e| e.span.unwrap().line_col(src).0).collect();
+    assert_eq!(lines, vec![1, 4, 9], "Loop conditions should be located on their own lines");
+}
+
+/// Indexing gives the element type of tuples, vectors, matrices & arrays
+#[test]
+fn test_tc_index() {
+    let src = "\
+uniform float[4] uWeights
+uniform mat3 uNormal
+let v : vec3 = uNormal[2]
+let t : (int, bool) = (1, true)
+let b : bool = t[2 - 1]
+mut i : int = 0
+mut f : float = v[i] * uWeights[i + 1]
+let c : (int, vec3) -> float = (k : int, w : vec3) {
+\tw[k]
+}
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    let idx = |src: &str| tc_type_of(&crate::parser::program(src).unwrap().1[0], &env).unwrap();
+    assert_eq!(idx("uNormal[0]\n"), typ("vec3"), "Matrices should be indexed by column");
+    assert_eq!(idx("t[0]\n"), typ("int"), "Tuples should give the type of the indexed element");
+    assert_eq!(idx("b\n"), typ("bool"), "Tuples should be indexed by constant expressions");
+    assert_eq!(idx("uWeights[3]\n"), typ("float"), "Arrays should give the type of their elements");
+
+    let src = "\
+uniform float[4] uWeights
+uniform ivec2 x
+let t : (int, bool) = (1, true)
+mut i : int = 0
+let a : float = uWeights[4]
+let b : int = x[-1]
+let c : bool = t[2]
+let d : int = t[i]
+let e : int = i[0]
+let g : float = uWeights[1.0f]
+let h : float = uWeights[i]
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::IndexOutOfBounds{ name: "uWeights".to_string(), index: 4, len: 4 },
+            TCErrorKind::IndexOutOfBounds{ name: "x".to_string(), index: -1, len: 2 },
+            TCErrorKind::IndexOutOfBounds{ name: "t".to_string(), index: 2, len: 2 },
+            TCErrorKind::TupleIndexNotConstant{ name: "t".to_string() },
+            TCErrorKind::NotIndexable{ name: "i".to_string(), actual: typ("int") },
+            TCErrorKind::IndexNotInt{ actual: typ("float") },
+        ],
+        "Wrong errors for bad indices"
+    );
+    // errors in an index are located at the index itself
+    assert_eq!(&src[es.errors[0].span.unwrap().range(src)], "4", "Wrong span for an out of bounds index");
+    assert_eq!(&src[es.errors[5].span.unwrap().range(src)], "1.0f", "Wrong span for an index that is not an int");
+
+    // a component named in brackets is only a swizzle where that name is not bound, otherwise it is an index
+    let src = "\
+uniform vec4 uPos
+let a : float = uPos[x]
+let g : float -> float = (y : float) { uPos[y] }
+mut z : int = 2
+let c : float = uPos[z]
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(es.kinds(), vec![TCErrorKind::IndexNotInt{ actual: typ("float") }], "Wrong errors for components named in brackets");
+    assert_eq!(es.errors[0].span.unwrap().line_col(src).0, 3, "A bound name should index rather than name a component");
+}
+
+/// Named tuples are typed by their properties, which can be accessed through chains of accesses on any expr
+#[test]
+fn test_tc_named_access() {
+    let decls = "\
+let light : (pos: vec3, color: (r: float, g: float)) = (pos: uPos, color: (r: 1.0f, g: 0.5f))
+let f : int -> (x: int, y: bool) = (a : int) {
+\t(x: a, y: true)
+}
+uniform vec3 uPos
+uniform vec4[2] uColors
+";
+    let (_, p) = crate::parser::program(decls).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    let access = |src: &str| tc_type_of(&crate::parser::program(src).unwrap().1[0], &env);
+    assert_eq!(access("light.color.g\n"), Ok(typ("float")), "Failed to type a chain of named accesses");
+    assert_eq!(access("light.pos[2]\n"), Ok(typ("float")), "Failed to type an index of a named access");
+    assert_eq!(access("f(1).y\n"), Ok(typ("bool")), "Failed to type an access of an application");
+    assert_eq!(access("(light.pos[0] + 1.0f, 2)[1]\n"), Ok(typ("int")), "Failed to type an index of a tuple literal");
+    assert_eq!(access("uColors[1][3]\n"), Ok(typ("float")), "Failed to type a chain of indices");
+    assert_eq!(
+        access("(x: 1, y: (z: true)).y\n"),
+        Ok(NamedTuple(vec![("z".to_string(), Box::new(typ("bool")))])),
+        "Failed to type an access of a named tuple literal"
+    );
+
+    // errors name the accessed expr, & an ill-typed base is only reported once
+    let src = format!("{decls}\
+let a : float = light.color.b
+let b : float = light.color.g.x
+let c : int = f(1)[0]
+let d : int = nope.x.y
+let e : (x: int, x: bool) = (x: 1, x: true)
+");
+    let (_, p) = crate::parser::program(&src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::UnknownProperty{ name: "light.color".to_string(), prop: "b".to_string() },
+            TCErrorKind::NotANamedTuple{ name: "light.color.g".to_string(), actual: typ("float") },
+            TCErrorKind::NotIndexable{ name: "f(..)".to_string(), actual: NamedTuple(vec![("x".to_string(), Box::new(typ("int"))), ("y".to_string(), Box::new(typ("bool")))]) },
+            TCErrorKind::UnboundName{ name: "nope".to_string() },
+            TCErrorKind::DuplicateProperty{ prop: "x".to_string() },
+        ],
+        "Wrong errors for bad accesses"
+    );
+}
+
+/// Returns agree w/ the return type of their function, & nothing may follow them
+#[test]
+fn test_tc_return() {
+    let src = "\
+let signum : int -> int = (x : int) {
+\tif x < 0 {
+\t\treturn -1
+\t} else {
+\t\t1
+\t}
+}
+let first : (int, int) -> int = (x : int, y : int) {
+\treturn x
+}
+let g : int -> bool = (x : int) {
+\tif x > 0 { return true } else { return false }
+}
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    assert_eq!(env.get("first"), Some(&Function(Box::new(Tuple(vec![typ("int"), typ("int")])), Box::new(typ("int")))), "Wrong type for a function that returns");
+
+    let src = "\
+let f : int -> int = (x : int) {
+\tif x < 0 {
+\t\treturn 1.0f
+\t} else {
+\t\tx
+\t}
+}
+let g : int -> int = (x : int) {
+\treturn x
+\tx + 1
+\tx + 2
+}
+let h : int -> int = (x : int) {
+\tif x < 0 { return 0 } else { return 1 }
+\tx
+}
+apply((x : int) {
+\treturn true
+\tx
+}, 1)
+return 1
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::ReturnMismatch{ expected: typ("int"), actual: typ("float") },
+            TCErrorKind::BranchMismatch{ then: typ("float"), otherwise: typ("int") },
+            TCErrorKind::UnreachableCode,
+            TCErrorKind::UnreachableCode,
+            TCErrorKind::ReturnMismatch{ expected: typ("int"), actual: typ("bool") },
+            TCErrorKind::UnreachableCode,
+            TCErrorKind::UnknownFunction{ name: "apply".to_string() },
+            TCErrorKind::ReturnOutsideFunction,
+        ],
+        "Wrong errors for bad returns"
+    );
+    let lines : Vec<usize> = es.errors.iter().map(|e| e.span.unwrap().line_col(src).0).collect();
+    assert_eq!(lines, vec![3, 2, 10, 15, 18, 19, 17, 21], "Wrong locations for bad returns");
+}
+
+/// Vectors & matrices follow GLSL, w/ component-wise arithmetic, scalars applied to every component & linear algebra products
+#[test]
+fn test_tc_vectors() {
+    let src = "\
+uniform mat4 uMVP
+uniform mat3 uNormal
+uniform vec4 pos
+uniform vec3 n
+uniform ivec2 cell
+uniform bvec2 mask
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    let ty = |src: &str| tc_type_of(&crate::parser::program(src).unwrap().1[0], &env);
+    assert_eq!(ty("uMVP * pos\n"), Ok(typ("vec4")), "A matrix times a vector should be a vector");
+    assert_eq!(ty("pos * uMVP\n"), Ok(typ("vec4")), "A vector times a matrix should be a vector");
+    assert_eq!(ty("uMVP * uMVP * pos\n"), Ok(typ("vec4")), "Matrix products should chain");
+    assert_eq!(ty("uNormal * n + n / 2.0f\n"), Ok(typ("vec3")), "Vectors should be scaled by scalars");
+    assert_eq!(ty("2.0f * uNormal - uNormal\n"), Ok(typ("mat3")), "Matrices should be scaled by scalars");
+    assert_eq!(ty("cell % 2 + cell\n"), Ok(typ("ivec2")), "Int vectors should have a remainder");
+    assert_eq!(ty("-pos\n"), Ok(typ("vec4")), "Vectors should be negated component-wise");
+    assert_eq!(ty("pos == pos\n"), Ok(typ("bool")), "Vectors should be compared as a whole");
+    assert_eq!(ty("mask != mask\n"), Ok(typ("bool")), "Bool vectors should be compared");
+
+    let err = |src: &str| *ty(src).unwrap_err().kind;
+    let mismatch = |op, l: &str, r: &str| TCErrorKind::BinOpMismatch{ op, lhs: typ(l), rhs: typ(r) };
+    let unsupported = |op, t: &str| TCErrorKind::UnsupportedOperand{ op, actual: typ(t) };
+    assert_eq!(err("uNormal * pos\n"), mismatch(BOp::Mul, "mat3", "vec4"), "Products need matching sizes");
+    assert_eq!(err("pos + n\n"), mismatch(BOp::Add, "vec4", "vec3"), "Component-wise arithmetic needs matching sizes");
+    assert_eq!(err("cell * 2.0f\n"), mismatch(BOp::Mul, "ivec2", "float"), "Scalars should not be converted");
+    assert_eq!(err("pos == n\n"), mismatch(BOp::Eq, "vec4", "vec3"), "Only vectors of the same type should be compared");
+    assert_eq!(err("pos < pos\n"), unsupported(BOp::Lt, "vec4"), "Vectors should not be ordered");
+    assert_eq!(err("1.0f > pos\n"), unsupported(BOp::Gt, "vec4"), "Vectors should not be ordered");
+    assert_eq!(err("mask + mask\n"), unsupported(BOp::Add, "bvec2"), "Bool vectors should not have arithmetic");
+    assert_eq!(err("pos % 2.0f\n"), unsupported(BOp::Mod, "vec4"), "Float vectors should not have a remainder");
+    assert_eq!(err("-mask\n"), TCErrorKind::UnOpMismatch{ op: UOp::Negative, actual: typ("bvec2") }, "Bool vectors should not be negated");
+}
+
+/// Typechecks decimal literals w/out an 'f', which are floats wherever floats are expected
+#[test]
+fn test_tc_decimal_literals() {
+    let src = "\
+uniform float uTime
+uniform vec3 n
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    let ty = |src: &str| tc_type_of(&crate::parser::program(src).unwrap().1[0], &env);
+    assert_eq!(ty("uTime * 3.7\n"), Ok(typ("float")), "A decimal literal should be a float alongside a float");
+    assert_eq!(ty("-0.5 + uTime\n"), Ok(typ("float")), "A negative decimal literal should be a float alongside a float");
+    assert_eq!(ty("n * 0.5\n"), Ok(typ("vec3")), "A decimal literal should scale a vector of floats");
+    assert_eq!(ty("mut x : float = 0.1\n"), Ok(typ("float")), "A decimal literal should be bound to a float");
+    assert_eq!(ty("0.5 * 2.0\n"), Ok(typ("double")), "Decimal literals should be doubles on their own");
+    assert_eq!(ty("mut d : double = 0.5\n"), Ok(typ("double")), "A decimal literal should still be bound to a double");
+
+    let err = |src: &str| *ty(src).unwrap_err().kind;
+    assert_eq!(
+        err("mut i : int = 0.5\n"),
+        TCErrorKind::BindingMismatch{ name: "i".to_string(), expected: typ("int"), actual: typ("double") },
+        "A decimal literal should not be bound to an int"
+    );
+    assert_eq!(
+        err("uTime * (0.5 * 2.0)\n"),
+        TCErrorKind::BinOpMismatch{ op: BOp::Mul, lhs: typ("float"), rhs: typ("double") },
+        "Only a literal itself should be a float"
+    );
+}
+
+/// Constructors of vectors & matrices take the components of their arguments in order
+#[test]
+fn test_tc_constructors() {
+    let src = "\
+uniform vec3 pos
+uniform vec2 uv
+uniform mat3 uNormal
+let t : (float, float) = (1.0f, 2.0f)
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    let ty = |src: &str| tc_type_of(&crate::parser::program(src).unwrap().1[0], &env);
+    assert_eq!(ty("vec4(pos, 1.0f)\n"), Ok(typ("vec4")), "Arguments should be flattened into components");
+    assert_eq!(ty("vec4(uv, uv)\n"), Ok(typ("vec4")), "Arguments should be flattened into components");
+    assert_eq!(ty("vec4(1.0f)\n"), Ok(typ("vec4")), "A single scalar should fill every component");
+    assert_eq!(ty("vec2(pos)\n"), Ok(typ("vec2")), "A single larger vector should be truncated");
+    assert_eq!(ty("ivec3(1, 2, 3)\n"), Ok(typ("ivec3")), "Int vectors should be constructed");
+    assert_eq!(ty("vec2(1, true)\n"), Ok(typ("vec2")), "Components should be converted between scalar types");
+    assert_eq!(ty("mat2(uv, uv)\n"), Ok(typ("mat2")), "Matrices should be built from columns");
+    assert_eq!(ty("mat4(1.0f)\n"), Ok(typ("mat4")), "A single scalar should fill the diagonal");
+    assert_eq!(ty("mat4(uNormal)\n"), Ok(typ("mat4")), "A single matrix should be converted");
+
+    let arity = |n: &str, expected, actual| TCErrorKind::ConstructorArity{ name: n.to_string(), expected, actual };
+    let err = |src: &str| *ty(src).unwrap_err().kind;
+    assert_eq!(err("vec4(pos)\n"), arity("vec4", 4, 3), "Too few components");
+    assert_eq!(err("vec4(pos, 1.0f, 1.0f)\n"), arity("vec4", 4, 5), "An unused argument");
+    assert_eq!(err("vec3()\n"), arity("vec3", 3, 0), "No components");
+    assert_eq!(err("mat3(pos, pos)\n"), arity("mat3", 9, 6), "Too few columns");
+    assert_eq!(err("vec2(t)\n"), TCErrorKind::ConstructorArg{ name: "vec2".to_string(), actual: ParsedType::Tuple(vec![typ("float"), typ("float")]) }, "A tuple has no components");
+    assert_eq!(err("mat3(uNormal, 1.0f)\n"), TCErrorKind::ConstructorArg{ name: "mat3".to_string(), actual: typ("mat3") }, "A matrix alongside other arguments");
+}
+
+/// Swizzles select components of vectors by name, & may be assigned to if they write each component once
+#[test]
+fn test_tc_swizzle() {
+    let src = "\
+uniform vec4 pos
+uniform ivec3 cell
+uniform bvec2 mask
+mut v : vec3 = pos.xyz
+set v.zx pos.rg
+v.y += 1.0f
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    let ty = |src: &str| tc_type_of(&crate::parser::program(src).unwrap().1[0], &env);
+    assert_eq!(ty("pos.xy\n"), Ok(typ("vec2")), "Swizzles should select components");
+    assert_eq!(ty("pos.wzyx\n"), Ok(typ("vec4")), "Swizzles should reorder components");
+    assert_eq!(ty("pos.s\n"), Ok(typ("float")), "A single component should be a scalar");
+    assert_eq!(ty("cell.bgr\n"), Ok(typ("ivec3")), "Swizzles of int vectors should be int vectors");
+    assert_eq!(ty("mask.yy\n"), Ok(typ("bvec2")), "Swizzles should repeat components when read");
+    assert_eq!(ty("(pos * 2.0f).zw.x\n"), Ok(typ("float")), "Swizzles should chain on any expr");
+
+    let src = "\
+uniform vec4 pos
+uniform vec2 uv
+uniform mat2 m
+mut v : vec3 = pos.xyz
+let a : float = pos.xr
+let b : float = uv.z
+let c : float = pos.foo
+let d : vec4 = pos.xyzwx
+let e : float = m.x
+set v.xx uv
+set v.xy pos.xyz
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    let (n, s) = (|n: &str| n.to_string(), |s: &str| s.to_string());
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::MixedSwizzle{ name: n("pos"), swizzle: s("xr") },
+            TCErrorKind::SwizzleOutOfRange{ name: n("uv"), swizzle: s("z"), len: 2 },
+            TCErrorKind::InvalidSwizzle{ name: n("pos"), swizzle: s("foo") },
+            TCErrorKind::InvalidSwizzle{ name: n("pos"), swizzle: s("xyzwx") },
+            TCErrorKind::NotANamedTuple{ name: n("m"), actual: typ("mat2") },
+            TCErrorKind::DuplicateSwizzleTarget{ name: n("v"), swizzle: s("xx") },
+            TCErrorKind::BindingMismatch{ name: n("v.xy"), expected: typ("vec2"), actual: typ("vec3") },
+        ],
+        "Wrong errors for bad swizzles"
+    );
+}
+
+/// Built-in functions are in scope everywhere, w/ overloads resolved by the types of their args
+#[test]
+fn test_tc_builtins() {
+    let src = "\
+uniform vec3 n
+uniform sampler2D uTex
+let mix2 : float -> float = (x : float) {
+\tmix(x, 1.0f, 0.5f)
+}
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    let ty = |src: &str| tc_type_of(&crate::parser::program(src).unwrap().1[0], &env);
+    assert_eq!(ty("sin(1.0f)\n"), Ok(typ("float")), "Failed to resolve a genType for a float");
+    assert_eq!(ty("sin(n)\n"), Ok(typ("vec3")), "Failed to resolve a genType for a vector");
+    assert_eq!(ty("mix(n, n * 2.0f, 0.5f)\n"), Ok(typ("vec3")), "Failed to resolve an overload w/ a scalar param");
+    assert_eq!(ty("dot(n, normalize(n))\n"), Ok(typ("float")), "Failed to type a geometric built-in");
+    assert_eq!(ty("lessThan(n, n)\n"), Ok(typ("bvec3")), "Failed to type a vector relational built-in");
+    assert_eq!(ty("texture2D(uTex, n.xy)\n"), Ok(typ("vec4")), "Failed to type a texture lookup");
+    assert_eq!(ty("cross\n"), Ok(mk_func_typ(Tuple(vec![typ("vec3"), typ("vec3")]), typ("vec3"))), "A built-in w/ a single signature should be a plain function");
+
+    let err = |src: &str| *ty(src).unwrap_err().kind;
+    let sin = prelude().get("sin").cloned().unwrap();
+    assert_eq!(
+        err("sin(1)\n"),
+        TCErrorKind::NoMatchingOverload{ name: "sin".to_string(), actual: typ("int"), overloads: match sin { Overloaded(v) => v, t => vec![t] } },
+        "Failed to reject args of no signature"
+    );
+    assert!(matches!(err("clamp(n, 0.0f, n)\n"), TCErrorKind::NoMatchingOverload{..}), "Failed to reject args of mixed signatures");
+    assert!(matches!(err("cross(n)\n"), TCErrorKind::ArgCount{..}), "Failed to reject too few args");
+    // ill-typed args match any signature, so are not reported again
+    assert_eq!(ty("sin(nope)\n").unwrap_err().kind, Box::new(TCErrorKind::UnboundName{ name: "nope".to_string() }), "Reported an ill-typed arg twice");
+
+    // built-ins may neither be redefined nor assigned to, but other bindings shadow them
+    let src = "\
+let sin : int -> int = (x : int) { x }
+let mix : int = 1
+let a : int = mix + 1
+set cos 1.0f
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![TCErrorKind::BuiltinRedefined{ name: "sin".to_string() }, TCErrorKind::ImmutableUpdate{ name: "cos".to_string() }],
+        "Wrong errors for shadowed built-ins"
+    );
+    assert_eq!(es.errors[0].span.map(|s| s.line_col(src)), Some((1, 1)), "Failed to locate a redefined built-in at its definition");
+}
+
+#[test]
+fn test_tc_stages() {
+    let tc = |src: &str| tc_program(crate::parser::program(src).unwrap().1);
+    let src = "\
+uniform sampler2D uTex
+let shade : vec2 -> vec4 = (uv : vec2) { texture2D(uTex, uv) }
+let edge : vec2 -> vec4 = (uv : vec2) { shade(uv) * fwidth(uv.x) }
+let clip : float -> float = (a : float) {
+\tif a < 0.5f { discard } else { a }
+}
+let lod : vec2 -> vec4 = (uv : vec2) { texture2DLod(uTex, uv, 0.0f) }
+vert v : (vec2 aUV) -> (vec2 vUV, vec4 vCol) = {
+\tset gl_Position vec4(aUV, 0.0f, 1.0f)
+\tset gl_PointSize 2.0f
+\tout vUV aUV
+\tout vCol lod(aUV)
+}
+frag f : (vec2 vUV, vec4 vCol) -> () = {
+\tlet a : float = clip(vCol.a)
+\tif gl_FrontFacing { set gl_FragColor edge(vUV) } else { set gl_FragColor vec4(gl_FragCoord.xy * gl_PointCoord, dFdx(a), a) }
+}
+p : Prog = mkProg v f
+";
+    // functions may use the built-ins of either stage, so long as only the shaders of that stage call them
+    let (_, env) = assert_ok!(tc(src));
+    assert_eq!(env.staged("shade", &typ("vec2")), Some(&"texture2D".to_string()), "Failed to mark a function w/ a stage-specific built-in");
+    assert_eq!(env.staged("edge", &typ("vec2")), Some(&"texture2D".to_string()), "Failed to mark a function that reaches a stage-specific built-in");
+    assert_eq!(env.staged("clip", &typ("float")), Some(&"discard".to_string()), "Failed to mark a function that discards");
+
+    // built-ins of the other stage are rejected, w/ the stage they are available in
+    let es = tc("\
+uniform sampler2D uTex
+vert v : (vec2 aUV) -> (vec4 vCol) = {
+\tset gl_FragColor vec4(1.0f)
+\tset vCol texture2D(uTex, aUV) + gl_FragCoord
+\tdiscard
+}
+frag f : (vec4 vCol) -> () = {
+\tset gl_Position vCol
+\tset gl_FragColor texture2DLod(uTex, vCol.xy, 0.0f)
+}
+p : Prog = mkProg v f
+").unwrap_err();
+    let restricted = |n: &str, stage: Stage, actual: Stage| TCErrorKind::StageRestricted{ name: n.to_string(), stage, actual };
+    assert_eq!(
+        es.kinds(),
+        vec![
+            restricted("gl_FragColor", Stage::Fragment, Stage::Vertex),
+            restricted("texture2D", Stage::Fragment, Stage::Vertex),
+            restricted("gl_FragCoord", Stage::Fragment, Stage::Vertex),
+            restricted("discard", Stage::Fragment, Stage::Vertex),
+            restricted("gl_Position", Stage::Vertex, Stage::Fragment),
+            restricted("texture2DLod", Stage::Vertex, Stage::Fragment),
+        ],
+        "Wrong errors for built-ins of the other stage"
+    );
+
+    // as are functions that reach them, through any number of calls
+    let es = tc("\
+uniform sampler2D uTex
+let shade : vec2 -> vec4 = (uv : vec2) { texture2D(uTex, uv) }
+let tint : vec2 -> vec4 = (uv : vec2) { shade(uv) * 0.5f }
+let kill : float -> float = (a : float) { discard }
+vert v : (vec2 aUV) -> (vec4 vCol) = {
+\tset vCol tint(aUV)
+\tset gl_PointSize kill(1.0f)
+}
+frag f : (vec4 vCol) -> () = {
+\tset gl_FragColor tint(vCol.xy)
+}
+p : Prog = mkProg v f
+").unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::StageRestrictedCall{ name: "tint".to_string(), builtin: "texture2D".to_string(), stage: Stage::Fragment, actual: Stage::Vertex },
+            TCErrorKind::StageRestrictedCall{ name: "kill".to_string(), builtin: "discard".to_string(), stage: Stage::Fragment, actual: Stage::Vertex },
+        ],
+        "Wrong errors for functions that reach built-ins of the other stage"
+    );
+
+    // a binding that shadows a marked function clears its mark
+    let (_, env) = assert_ok!(tc("\
+uniform sampler2D uTex
+let shade : vec2 -> vec4 = (uv : vec2) { texture2D(uTex, uv) }
+mut shade : vec2 -> vec4 = (uv : vec2) { vec4(uv, 0.0f, 1.0f) }
+vert v : (vec2 aUV) -> (vec4 vCol) = {
+\tset vCol shade(aUV)
+}
+frag f : (vec4 vCol) -> () = {
+\tset gl_FragColor vCol
+}
+p : Prog = mkProg v f
+"));
+    assert_eq!(env.staged("shade", &typ("vec2")), None, "Failed to clear the mark of a shadowed function");
+
+    // nothing runs after a discard
+    let es = tc("\
+vert v : () -> () = {
+\tset gl_Position vec4(1.0f)
+}
+frag f : () -> () = {
+\tdiscard
+\tset gl_FragColor vec4(1.0f)
+}
+p : Prog = mkProg v f
+").unwrap_err();
+    assert_eq!(es.kinds(), vec![TCErrorKind::UnreachableCode], "Failed to reject code after a discard");
+
+    // the fragment inputs that GLSL provides are read-only
+    let es = tc("\
+vert v : () -> () = {
+\tset gl_Position vec4(1.0f)
+\tset gl_PointSize 2.0f
+}
+frag f : () -> () = {
+\tset gl_FragCoord vec4(0.0f)
+\tset gl_FrontFacing false
+\tset gl_PointCoord vec2(0.0f)
+\tset gl_FragColor vec4(1.0f)
+}
+p : Prog = mkProg v f
+").unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        ["gl_FragCoord", "gl_FrontFacing", "gl_PointCoord"].iter()
+            .map(|n| TCErrorKind::ImmutableUpdate{ name: n.to_string() }).collect::<Vec<_>>(),
+        "Failed to reject updates of read-only built-ins"
+    );
+
+    // each signature of an overloaded function is marked on its own, & a call is checked by the signature it resolves to
+    let src = "\
+let scale : float -> float = (x : float) { x * 2.0f }
+let scale : vec3 -> vec3 = (v : vec3) { dFdx(v) * 2.0f }
+let grow : float -> float = (x : float) { scale(x) + 1.0f }
+vert v : (vec3 aPos) -> (vec3 vPos) = {
+\tset gl_Position vec4(aPos, grow(scale(1.0f)))
+\tset vPos scale(aPos)
+}
+frag f : (vec3 vPos) -> () = {
+\tset gl_FragColor vec4(scale(vPos), scale(1.0f))
+}
+p : Prog = mkProg v f
+";
+    let es = tc(src).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![TCErrorKind::StageRestrictedCall{ name: "scale".to_string(), builtin: "dFdx".to_string(), stage: Stage::Fragment, actual: Stage::Vertex }],
+        "Failed to check a call of an overloaded function by the signature it resolves to"
+    );
+    assert_eq!(es.errors[0].span.unwrap().line_col(src).0, 6, "Wrong line for a call of a marked signature");
+    assert_eq!(es.env.staged("scale", &typ("vec3")), Some(&"dFdx".to_string()), "Failed to mark the signature that uses a built-in");
+    assert_eq!(es.env.staged("scale", &typ("float")), None, "Marked a signature that uses no built-in");
+    assert_eq!(es.env.staged("grow", &typ("float")), None, "Marked a function that only calls an unmarked signature");
+}
+
+#[test]
+fn test_tc_overloads() {
+    let src = "\
+uniform vec3 n
+let scale : float -> float = (x : float) { x * 2.0f }
+let scale : vec3 -> vec3 = (v : vec3) { v * 2.0f }
+let scale : (vec3, float) -> vec3 = (v : vec3, s : float) { v * s }
+";
+    let (_, p) = crate::parser::program(src).unwrap();
+    let (_, env) = tc_program(p).unwrap();
+    let ty = |src: &str, env: &TCEnv| tc_type_of(&crate::parser::program(src).unwrap().1[0], env);
+    let sigs = vec![
+        mk_func_typ(typ("float"), typ("float")),
+        mk_func_typ(typ("vec3"), typ("vec3")),
+        mk_func_typ(Tuple(vec![typ("vec3"), typ("float")]), typ("vec3")),
+    ];
+    assert_eq!(env.get("scale"), Some(&Overloaded(sigs.clone())), "Failed to overload a function w/ other params");
+    assert_eq!(ty("scale(1.0f)\n", &env), Ok(typ("float")), "Failed to resolve an overload for a float");
+    assert_eq!(ty("scale(n)\n", &env), Ok(typ("vec3")), "Failed to resolve an overload for a vector");
+    assert_eq!(ty("scale(n, 0.5f)\n", &env), Ok(typ("vec3")), "Failed to resolve an overload by its # of args");
+    assert_eq!(
+        *ty("scale(1)\n", &env).unwrap_err().kind,
+        TCErrorKind::NoMatchingOverload{ name: "scale".to_string(), actual: typ("int"), overloads: sigs.clone() },
+        "Failed to reject args of no overload"
+    );
+    assert_eq!(ty("scale(nope)\n", &env).unwrap_err().kind, Box::new(TCErrorKind::UnboundName{ name: "nope".to_string() }), "Reported an ill-typed arg twice");
+
+    // a function may not be defined again w/ a signature it has, nor one that only differs in its return type
+    let src = format!("{src}\
+let scale : float -> float = (x : float) {{ x }}
+let scale : float -> int = (x : float) {{ 2 }}
+let a : vec3 = scale(n)
+let b : float = scale(1.0f)
+");
+    let (_, p) = crate::parser::program(&src).unwrap();
+    let es = tc_program(p).unwrap_err();
+    assert_eq!(
+        es.kinds(),
+        vec![
+            TCErrorKind::FunctionRedefined{ name: "scale".to_string(), sig: sigs[0].clone() },
+            TCErrorKind::OverloadReturnMismatch{ name: "scale".to_string(), args: typ("float"), expected: typ("float"), actual: typ("int") }
+        ],
+        "Failed to reject redefined signatures"
+    );
+    assert_eq!(es.errors[0].span.map(|s| s.line_col(&src)), Some((5, 1)), "Failed to locate a redefinition at its definition");
+    assert_eq!(es.env.get("scale"), Some(&Overloaded(sigs.clone())), "Failed to keep the prior signatures of a redefined function");
+
+    // any other binding shadows every overload
+    let (_, p) = crate::parser::program(&format!("{src}let scale : int = 1\n")).unwrap();
+    assert_eq!(tc_program(p).unwrap_err().env.get("scale"), Some(&typ("int")), "Failed to shadow an overloaded function");
+}
@@ -0,0 +1,874 @@
+/*
+
+Printer from the GLSL AST to WGSL, for WebGPU
+
+Each shader is printed as its own WGSL module w/ a single entry point, 'main', marked '@vertex' or '@fragment'.
+Tuples were already lowered to structs by the compiler, so they print as WGSL structs.
+
+WGSL has no global inputs, outputs or uniforms, so these are rewritten:
+
+- Uniforms are gathered into a 'Uniforms' struct, bound at @group(0) @binding(0) as 'fdssl_u'.
+  Both stages declare the same struct, so they can share a single buffer.
+- Shader inputs become parameters of the entry point, w/ their locations in declaration order.
+  'gl_FragCoord' & 'gl_FrontFacing' are parameters as well, w/ the built-ins they stand for.
+- Shader outputs, including 'gl_Position' & 'gl_FragColor', are members of a 'VertexOutput'
+  or 'FragmentOutput' struct. The entry point writes to a local 'fdssl_out' & returns it.
+
+WGSL has no overloading either, so the overloads of a function are numbered in the order they are declared,
+i.e. 'fdssl_scale_0' & 'fdssl_scale_1', & each call is resolved to one of them by the types of its args.
+
+Output only depends on the AST, so printing the same program always produces the same shaders.
+
+*/
+
+use crate::glsl_syntax as glsl;
+use crate::pretty::{bin_op, decl_uses, PrintError};
+use crate::syntax::Stage;
+
+use glsl::{BinOp, Decl, Expr, ExprKind, Qualifier, Stmt, Type, UnOp};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// Indentation for each nested block
+const INDENT: &str = "    ";
+
+// Names introduced by the printer, prefixed so they never clash w/ those of a program
+const UNIFORMS: &str = "fdssl_u";
+const OUTPUT: &str = "fdssl_out";
+const POSITION: &str = "fdssl_Position";
+const FRAG_COLOR: &str = "fdssl_FragColor";
+
+// Built-in inputs of a fragment shader, w/ the entry point parameters that replace them & their WGSL built-ins
+const FRAG_INPUTS: &[(&str, &str, &str, Type)] = &[
+    ("gl_FragCoord", "fdssl_FragCoord", "position", Type::Vec(4)),
+    ("gl_FrontFacing", "fdssl_FrontFacing", "front_facing", Type::Bool),
+];
+
+// Variables that are accessed through a struct in WGSL, i.e. 'uTime' as 'fdssl_u.uTime'
+type Renames = HashMap<String, String>;
+
+// Names of the overloads of functions, by the name & param types of each
+type Overloads = HashMap<(String, Vec<Type>), String>;
+
+/// Prints a shader as a WGSL module
+pub fn print_shader(s: &glsl::Shader) -> Result<String,PrintError> {
+    let s = &rename_overloads(s);
+    let mut out = String::new();
+    let mut renames = Renames::new();
+    let mut uniforms = vec![];
+    let mut inputs = vec![];
+    let mut outputs = vec![];
+    // 'gl_FragColor' is located at 0, as in the GLSL printers, so the other outputs follow it
+    let frag_color = s.stage == Stage::Fragment && s.decls.iter().any(|d| decl_uses(d, "gl_FragColor"));
+
+    for d in &s.decls {
+        match d {
+            Decl::Struct(st) => {
+                out.push_str(&print_struct(&st.name, &st.fields.iter().map(|(n,t)| (String::new(), n.clone(), t.clone())).collect::<Vec<_>>())?);
+            },
+            Decl::Var(v) => match v.qualifier {
+                Some(Qualifier::Uniform) => {
+                    if matches!(v.typ, Type::Bool | Type::BVec(_)) {
+                        return Err(format!("Uniform '{}' is a bool, which cannot be stored in a WGSL uniform buffer", v.name));
+                    }
+                    // elements of an array in a uniform buffer must be 16 byte aligned
+                    if let Type::Array(t, _) = &v.typ {
+                        if !matches!(**t, Type::Vec(4) | Type::IVec(4) | Type::Mat(_)) {
+                            return Err(format!("Uniform '{}' is an array of '{}', which WGSL only allows in a uniform buffer for vec4 or matrix elements", v.name, print_type(t)?));
+                        }
+                    }
+                    renames.insert(v.name.clone(), format!("{UNIFORMS}.{}", v.name));
+                    uniforms.push((String::new(), v.name.clone(), v.typ.clone()));
+                },
+                Some(Qualifier::In)  => inputs.push(v),
+                Some(Qualifier::Out) => outputs.push((format!("@location({}) ", outputs.len() + frag_color as usize), v.name.clone(), v.typ.clone())),
+                _ => ()
+            },
+            Decl::Function(_) => ()
+        }
+    }
+
+    // WGSL has no point sprites, so neither their size nor the coordinates within them
+    for n in ["gl_PointSize", "gl_PointCoord"] {
+        if s.decls.iter().any(|d| decl_uses(d, n)) {
+            return Err(format!("'{n}' is not supported by WGSL, which only draws points of a single pixel"));
+        }
+    }
+
+    // built-in inputs are parameters of the entry point, alongside the others
+    let mut builtins = vec![];
+    if s.stage == Stage::Fragment {
+        for (n, param, b, t) in FRAG_INPUTS {
+            if s.decls.iter().any(|d| decl_uses(d, n)) {
+                renames.insert(n.to_string(), param.to_string());
+                builtins.push(format!("@builtin({b}) {param}: {}", print_type(t)?));
+            }
+        }
+    }
+
+    // built-in outputs go into the output struct as well
+    match s.stage {
+        Stage::Vertex => {
+            outputs.insert(0, ("@builtin(position) ".to_string(), POSITION.to_string(), Type::Vec(4)));
+            renames.insert("gl_Position".to_string(), format!("{OUTPUT}.{POSITION}"));
+        },
+        Stage::Fragment => if frag_color {
+            outputs.insert(0, ("@location(0) ".to_string(), FRAG_COLOR.to_string(), Type::Vec(4)));
+            renames.insert("gl_FragColor".to_string(), format!("{OUTPUT}.{FRAG_COLOR}"));
+        }
+    }
+
+    if !uniforms.is_empty() {
+        out.push_str(&print_struct("Uniforms", &uniforms)?);
+        out.push_str(&format!("@group(0) @binding(0) var<uniform> {UNIFORMS}: Uniforms;\n\n"));
+    }
+
+    let out_struct = match s.stage {
+        Stage::Vertex   => "VertexOutput",
+        Stage::Fragment => "FragmentOutput"
+    };
+    for (_, n, _) in &outputs {
+        renames.insert(n.clone(), format!("{OUTPUT}.{n}"));
+    }
+    if !outputs.is_empty() {
+        out.push_str(&print_struct(out_struct, &outputs)?);
+    }
+
+    for d in &s.decls {
+        match d {
+            Decl::Var(v) if v.qualifier == Some(Qualifier::Const) || v.qualifier.is_none() => {
+                let q = if v.qualifier.is_some() { "const" } else { "var<private>" };
+                out.push_str(&format!("{q} {}\n\n", print_var(v, &renames)?));
+            },
+            Decl::Function(f) if f.name == "main" => {
+                let mut params = inputs.iter().enumerate().map(|(i,v)| Ok(format!("@location({i}) {}: {}", v.name, print_type(&v.typ)?))).collect::<Result<Vec<_>,PrintError>>()?;
+                params.extend(builtins.iter().cloned());
+                let stage = match s.stage {
+                    Stage::Vertex   => "@vertex",
+                    Stage::Fragment => "@fragment"
+                };
+                if outputs.is_empty() {
+                    out.push_str(&format!("{stage}\nfn main({}) {{\n", params.join(", ")));
+                    print_block(&f.body, 1, None, &renames, &mut out)?;
+                } else {
+                    out.push_str(&format!("{stage}\nfn main({}) -> {out_struct} {{\n", params.join(", ")));
+                    out.push_str(&format!("{INDENT}var {OUTPUT}: {out_struct};\n"));
+                    print_block(&f.body, 1, Some(OUTPUT), &renames, &mut out)?;
+                    out.push_str(&format!("{INDENT}return {OUTPUT};\n"));
+                }
+                out.push_str("}\n");
+            },
+            Decl::Function(f) => {
+                // parameters shadow anything of the same name
+                let mut scope = renames.clone();
+                let params = f.params.iter().map(|(n,t)| {
+                    scope.remove(n);
+                    Ok(format!("{}: {}", n, print_type(t)?))
+                }).collect::<Result<Vec<_>,PrintError>>()?;
+                let ret = match f.ret {
+                    Type::Void => String::new(),
+                    ref t      => format!(" -> {}", print_type(t)?)
+                };
+                out.push_str(&format!("fn {}({}){} {{\n", f.name, params.join(", "), ret));
+                print_block(&f.body, 1, None, &scope, &mut out)?;
+                out.push_str("}\n\n");
+            },
+            _ => ()
+        }
+    }
+    Ok(out)
+}
+
+/// Renames every overload of a function declared more than once, along w/ the calls of each
+fn rename_overloads(s: &glsl::Shader) -> glsl::Shader {
+    let mut s = s.clone();
+    let names : Vec<String> = s.decls.iter().filter_map(|d| match d {
+        Decl::Function(f) => Some(f.name.clone()),
+        _                 => None
+    }).collect();
+
+    let mut overloads = Overloads::new();
+    let mut counts : HashMap<String, usize> = HashMap::new();
+    for d in &mut s.decls {
+        if let Decl::Function(f) = d {
+            if names.iter().filter(|n| **n == f.name).count() > 1 {
+                let i = counts.entry(f.name.clone()).or_insert(0);
+                let name = format!("fdssl_{}_{i}", f.name);
+                *i += 1;
+                // a redefinition w/ the same params replaces the prior one, so calls go to the last
+                overloads.insert((f.name.clone(), f.params.iter().map(|(_,t)| t.clone()).collect()), name.clone());
+                f.name = name;
+            }
+        }
+    }
+
+    if !overloads.is_empty() {
+        for d in &mut s.decls {
+            match d {
+                Decl::Function(f) => f.body.iter_mut().for_each(|st| rename_stmt_calls(st, &overloads)),
+                Decl::Var(v)      => if let Some(e) = &mut v.value {
+                    rename_calls(e, &overloads);
+                },
+                Decl::Struct(_)   => ()
+            }
+        }
+    }
+    s
+}
+
+/// Renames the calls of overloaded functions within a statement
+fn rename_stmt_calls(s: &mut Stmt, overloads: &Overloads) {
+    let block = |b: &mut Vec<Stmt>| b.iter_mut().for_each(|s| rename_stmt_calls(s, overloads));
+    match s {
+        Stmt::Decl(v)                     => if let Some(e) = &mut v.value {
+            rename_calls(e, overloads);
+        },
+        Stmt::Assign{target, value}       => {
+            rename_calls(target, overloads);
+            rename_calls(value, overloads);
+        },
+        Stmt::Expr(e) | Stmt::Return(Some(e)) => rename_calls(e, overloads),
+        Stmt::If{condition, b1, b2}       => {
+            rename_calls(condition, overloads);
+            block(b1);
+            block(b2);
+        },
+        Stmt::For{init, cond, post, body} => {
+            rename_stmt_calls(init, overloads);
+            rename_calls(cond, overloads);
+            rename_stmt_calls(post, overloads);
+            block(body);
+        },
+        Stmt::While{cond, body} | Stmt::DoWhile{body, cond} => {
+            rename_calls(cond, overloads);
+            block(body);
+        },
+        Stmt::Return(None) | Stmt::Discard => ()
+    }
+}
+
+/// Renames the calls of overloaded functions within an expression, resolving each by the types of its args
+fn rename_calls(e: &mut Expr, overloads: &Overloads) {
+    match &mut e.kind {
+        ExprKind::Call{name, args} => {
+            args.iter_mut().for_each(|a| rename_calls(a, overloads));
+            if let Some(n) = overloads.get(&(name.clone(), args.iter().map(|a| a.typ.clone()).collect())) {
+                *name = n.clone();
+            }
+        },
+        ExprKind::Binary{lhs, rhs, ..} | ExprKind::Index(lhs, rhs) => {
+            rename_calls(lhs, overloads);
+            rename_calls(rhs, overloads);
+        },
+        ExprKind::Unary{e, ..} | ExprKind::Field(e, _) => rename_calls(e, overloads),
+        _ => ()
+    }
+}
+
+/// Writes both shaders of a program to `<dir>/<name>.vert.wgsl` & `<dir>/<name>.frag.wgsl`
+pub fn write_program(p: &glsl::Program, dir: &Path) -> Result<(),PrintError> {
+    for (ext, s) in [("vert", &p.vert), ("frag", &p.frag)] {
+        let path = dir.join(format!("{}.{}.wgsl", p.name, ext));
+        fs::write(&path, print_shader(s)?).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Prints a struct, w/ an attribute (or none) for each member
+fn print_struct(name: &str, members: &[(String, String, Type)]) -> Result<String,PrintError> {
+    let mut out = format!("struct {name} {{\n");
+    for (a,n,t) in members {
+        out.push_str(&format!("{INDENT}{a}{n}: {},\n", print_type(t)?));
+    }
+    out.push_str("}\n\n");
+    Ok(out)
+}
+
+/// Returns the WGSL name of a type
+fn print_type(t: &Type) -> Result<String,PrintError> {
+    Ok(match t {
+        Type::Bool      => "bool".to_string(),
+        Type::Int       => "i32".to_string(),
+        Type::Float     => "f32".to_string(),
+        Type::Vec(n)    => format!("vec{n}<f32>"),
+        Type::IVec(n)   => format!("vec{n}<i32>"),
+        Type::BVec(n)   => format!("vec{n}<bool>"),
+        Type::Mat(n)    => format!("mat{n}x{n}<f32>"),
+        Type::Struct(n) => n.clone(),
+        Type::Array(t, n) => format!("array<{}, {n}>", print_type(t)?),
+        Type::Void      => return Err("Type 'void' can only be the result of a function in WGSL".to_string()),
+        Type::Double    => return Err("Type 'double' is not supported by WGSL".to_string()),
+        Type::Sampler2D => return Err("Type 'sampler2D' is not supported by WGSL, which separates textures from samplers".to_string()),
+    })
+}
+
+/// Returns the WGSL name of a built-in function w/ a given # of args, where it differs from GLSL
+fn builtin_fn(n: &str, args: usize) -> Result<&str,PrintError> {
+    match n {
+        "inversesqrt"                   => Ok("inverseSqrt"),
+        "faceforward"                   => Ok("faceForward"),
+        "atan" if args == 2             => Ok("atan2"),
+        "dFdx"                          => Ok("dpdx"),
+        "dFdy"                          => Ok("dpdy"),
+        "texture2D" | "textureCube" | "texture2DProj" | "texture2DLod" | "texture2DProjLod" => {
+            Err(format!("Built-in '{n}' is not supported by WGSL, which separates textures from samplers"))
+        },
+        "matrixCompMult"                => Err("Built-in 'matrixCompMult' has no equivalent in WGSL".to_string()),
+        // GLSL's mod differs from '%' for negative values, so there is no direct equivalent
+        "mod"                           => Err("Built-in 'mod' has no equivalent in WGSL".to_string()),
+        _                               => Ok(n)
+    }
+}
+
+/// Prints a variable declaration w/out a keyword, i.e. 'x: f32 = 1.0f', w/out the trailing ';'
+fn print_var(v: &glsl::VarDecl, renames: &Renames) -> Result<String,PrintError> {
+    let mut s = format!("{}: {}", v.name, print_type(&v.typ)?);
+    if let Some(e) = &v.value {
+        s.push_str(&format!(" = {}", print_expr(e, renames)?));
+    }
+    Ok(s)
+}
+
+/// Prints a sequence of statements at a given depth of indentation
+/// A local declaration shadows anything of the same name for the rest of its block
+/// Returns from an entry point return its output struct instead, if it has one
+fn print_block(body: &[Stmt], depth: usize, ret: Option<&str>, renames: &Renames, out: &mut String) -> Result<(),PrintError> {
+    let mut scope = renames.clone();
+    for s in body {
+        print_stmt(s, depth, ret, &scope, out)?;
+        if let Stmt::Decl(v) = s {
+            scope.remove(&v.name);
+        }
+    }
+    Ok(())
+}
+
+/// Prints a statement on its own line(s)
+fn print_stmt(s: &Stmt, depth: usize, ret: Option<&str>, renames: &Renames, out: &mut String) -> Result<(),PrintError> {
+    let indent = INDENT.repeat(depth);
+    match s {
+        Stmt::If{condition, b1, b2} => {
+            out.push_str(&format!("{indent}if ({}) {{\n", print_expr(condition, renames)?));
+            print_block(b1, depth + 1, ret, renames, out)?;
+            if !b2.is_empty() {
+                out.push_str(&format!("{indent}}} else {{\n"));
+                print_block(b2, depth + 1, ret, renames, out)?;
+            }
+            out.push_str(&format!("{indent}}}\n"));
+        },
+        Stmt::For{init, cond, post, body} => {
+            let mut scope = renames.clone();
+            if let Stmt::Decl(v) = &**init {
+                scope.remove(&v.name);
+            }
+            out.push_str(&format!("{indent}for ({}; {}; {}) {{\n", print_simple_stmt(init, ret, renames)?, print_expr(cond, &scope)?, print_simple_stmt(post, ret, &scope)?));
+            print_block(body, depth + 1, ret, &scope, out)?;
+            out.push_str(&format!("{indent}}}\n"));
+        },
+        Stmt::While{cond, body} => {
+            out.push_str(&format!("{indent}while ({}) {{\n", print_expr(cond, renames)?));
+            print_block(body, depth + 1, ret, renames, out)?;
+            out.push_str(&format!("{indent}}}\n"));
+        },
+        // WGSL has no do-while, so the condition is checked in the continuing block of a plain loop instead
+        Stmt::DoWhile{body, cond} => {
+            out.push_str(&format!("{indent}loop {{\n"));
+            print_block(body, depth + 1, ret, renames, out)?;
+            out.push_str(&format!("{indent}{INDENT}continuing {{\n"));
+            out.push_str(&format!("{indent}{INDENT}{INDENT}break if !({});\n", print_expr(cond, renames)?));
+            out.push_str(&format!("{indent}{INDENT}}}\n"));
+            out.push_str(&format!("{indent}}}\n"));
+        },
+        _ => out.push_str(&format!("{indent}{};\n", print_simple_stmt(s, ret, renames)?))
+    }
+    Ok(())
+}
+
+/// Prints a statement that fits on a single line, w/out the trailing ';'
+fn print_simple_stmt(s: &Stmt, ret: Option<&str>, renames: &Renames) -> Result<String,PrintError> {
+    match s {
+        Stmt::Decl(v)               => Ok(format!("var {}", print_var(v, renames)?)),
+        // WGSL only writes to a single component of a vector at a time
+        Stmt::Assign{target: Expr{kind: ExprKind::Field(b, f), ..}, ..} if f.len() > 1 && matches!(b.typ, Type::Vec(_) | Type::IVec(_) | Type::BVec(_)) => {
+            Err(format!("Swizzle '{f}' cannot be assigned to in WGSL, which only writes to a single component of a vector at a time"))
+        },
+        Stmt::Assign{target, value} => Ok(format!("{} = {}", print_expr(target, renames)?, print_expr(value, renames)?)),
+        // only calls may be statements in WGSL, other values must be explicitly discarded
+        Stmt::Expr(e)               => match (&e.kind, &e.typ) {
+            (ExprKind::Call{..}, Type::Void) => print_expr(e, renames),
+            _                                => Ok(format!("_ = {}", print_expr(e, renames)?))
+        },
+        Stmt::Return(e)             => match (ret, e) {
+            (Some(o), _)    => Ok(format!("return {o}")),
+            (None, Some(e)) => Ok(format!("return {}", print_expr(e, renames)?)),
+            (None, None)    => Ok("return".to_string())
+        },
+        Stmt::Discard               => Ok("discard".to_string()),
+        _                           => Err(format!("Expected a simple statement, but got '{:?}' instead", s))
+    }
+}
+
+/// Returns whether an operand needs parens in WGSL, which is stricter than GLSL
+/// Arithmetic follows the usual precedence & is left associative,
+/// comparisons take arithmetic operands but cannot be chained,
+/// and logical & bitwise operators only chain w/ themselves
+fn needs_parens(op: BinOp, operand: &Expr, left: bool) -> bool {
+    let inner = match &operand.kind {
+        ExprKind::Binary{op, ..} => *op,
+        _                        => return false
+    };
+    let multiplicative = |o: BinOp| matches!(o, BinOp::Mul | BinOp::Div | BinOp::Mod);
+    let additive = |o: BinOp| matches!(o, BinOp::Add | BinOp::Sub);
+    match op {
+        _ if multiplicative(op) => !(left && multiplicative(inner)),
+        _ if additive(op)       => !(multiplicative(inner) || (left && additive(inner))),
+        BinOp::Lt | BinOp::Gt | BinOp::Lte | BinOp::Gte | BinOp::Eq | BinOp::Neq => !(multiplicative(inner) || additive(inner)),
+        // short circuiting & bitwise operators
+        _                       => !(left && inner == op)
+    }
+}
+
+/// Prints an expression as WGSL
+fn print_expr(e: &Expr, renames: &Renames) -> Result<String,PrintError> {
+    Ok(match &e.kind {
+        ExprKind::Int(i)    => format!("{i}i"),
+        ExprKind::Float(f)  => format!("{:?}f", f),
+        ExprKind::Double(_) => return Err("Doubles are not supported by WGSL".to_string()),
+        ExprKind::Bool(b)   => b.to_string(),
+        ExprKind::Var(n)    => renames.get(n).cloned().unwrap_or(n.clone()),
+        ExprKind::Call{name, args} => {
+            // constructors are named after their type in WGSL
+            let is_constructor = matches!(name.as_str(),
+                "bool" | "int" | "float" | "vec2" | "vec3" | "vec4" | "ivec2" | "ivec3" | "ivec4" |
+                "bvec2" | "bvec3" | "bvec4" | "mat2" | "mat3" | "mat4");
+            let args = args.iter().map(|a| match scalar_type(&e.typ) {
+                Some(s) if is_constructor => print_component(a, &s, renames),
+                _                         => print_expr(a, renames)
+            }).collect::<Result<Vec<_>,PrintError>>()?;
+            // vector relational built-ins are operators in WGSL, which apply to vectors component-wise
+            let relational = match name.as_str() {
+                "lessThan"          => Some("<"),
+                "lessThanEqual"     => Some("<="),
+                "greaterThan"       => Some(">"),
+                "greaterThanEqual"  => Some(">="),
+                "equal"             => Some("=="),
+                "notEqual"          => Some("!="),
+                _                   => None
+            };
+            match (relational, name.as_str(), args.as_slice()) {
+                (Some(op), _, [a, b]) => format!("({a} {op} {b})"),
+                (None, "not", [a])    => format!("!({a})"),
+                _ => {
+                    let name = if is_constructor { print_type(&e.typ)? } else { builtin_fn(name, args.len())?.to_string() };
+                    format!("{}({})", name, args.join(", "))
+                }
+            }
+        },
+        ExprKind::Binary{op, lhs, rhs} => {
+            let operand = |o: &Expr, left: bool| -> Result<String,PrintError> {
+                let s = print_expr(o, renames)?;
+                Ok(if needs_parens(*op, o, left) { format!("({s})") } else { s })
+            };
+            format!("{} {} {}", operand(lhs, true)?, bin_op(*op), operand(rhs, false)?)
+        },
+        ExprKind::Unary{op, e: inner} => {
+            let op = match op {
+                UnOp::Neg => "-",
+                UnOp::Not => "!"
+            };
+            format!("{}{}", op, print_postfix_base(inner, renames)?)
+        },
+        ExprKind::Index(base, i) => format!("{}[{}]", print_postfix_base(base, renames)?, print_expr(i, renames)?),
+        // WGSL has no texture coordinate swizzles, so 'stpq' are the same components as 'xyzw'
+        ExprKind::Field(base, f) if matches!(base.typ, Type::Vec(_) | Type::IVec(_) | Type::BVec(_)) => {
+            let f : String = f.chars().map(|c| match c { 's' => 'x', 't' => 'y', 'p' => 'z', 'q' => 'w', c => c }).collect();
+            format!("{}.{}", print_postfix_base(base, renames)?, f)
+        },
+        ExprKind::Field(base, f) => format!("{}.{}", print_postfix_base(base, renames)?, f),
+    })
+}
+
+/// Returns the scalar type of the components of a scalar, vector or matrix type
+fn scalar_type(t: &Type) -> Option<Type> {
+    match t {
+        Type::Bool | Type::BVec(_)              => Some(Type::Bool),
+        Type::Int | Type::IVec(_)               => Some(Type::Int),
+        Type::Float | Type::Vec(_) | Type::Mat(_) => Some(Type::Float),
+        _                                       => None
+    }
+}
+
+/// Prints an arg of a constructor, converted to the scalar type of the components it fills
+/// GLSL converts these implicitly, so `vec2(1, 2)` is valid, but WGSL needs `vec2<f32>(1.0f, 2.0f)`
+fn print_component(a: &Expr, scalar: &Type, renames: &Renames) -> Result<String,PrintError> {
+    let converted = match (&a.typ, scalar) {
+        (t, s) if scalar_type(t).as_ref() == Some(s) || scalar_type(t).is_none() => return print_expr(a, renames),
+        (Type::BVec(n) | Type::IVec(n) | Type::Vec(n), Type::Float) => Type::Vec(*n),
+        (Type::BVec(n) | Type::IVec(n) | Type::Vec(n), Type::Int)   => Type::IVec(*n),
+        (Type::BVec(n) | Type::IVec(n) | Type::Vec(n), _)           => Type::BVec(*n),
+        (_, s)                                                      => s.clone()
+    };
+    Ok(match (&a.kind, &converted) {
+        // literals are written in the converted type directly
+        (ExprKind::Int(i), Type::Float) => format!("{:?}f", *i as f32),
+        _                               => format!("{}({})", print_type(&converted)?, print_expr(a, renames)?)
+    })
+}
+
+/// Prints the operand of a unary or postfix operator, wrapping anything but a primary expression in parens
+/// A negative literal is wrapped as well, so '-(-1.0f)' is never printed as the decrement '--1.0f'
+fn print_postfix_base(e: &Expr, renames: &Renames) -> Result<String,PrintError> {
+    let s = print_expr(e, renames)?;
+    Ok(match e.kind {
+        ExprKind::Binary{..} | ExprKind::Unary{..} => format!("({s})"),
+        ExprKind::Int(i) if i < 0                  => format!("({s})"),
+        ExprKind::Float(f) if f.is_sign_negative() => format!("({s})"),
+        _                                          => s
+    })
+}
+
+//
+//
+// WGSL PRINTER TESTS
+//
+//
+
+/// Used during testing to parse, typecheck & compile a program
+#[cfg(test)]
+fn compile_str(prog: &str) -> Vec<glsl::Program> {
+    let (_, p) = crate::parser::program(prog).expect("Failed to parse program");
+    let (_, env) = crate::typechecker::tc_program(p.clone()).expect("Failed to typecheck program");
+    crate::compiler::compile(&p, &env).expect("Failed to compile program")
+}
+
+/// Used during testing to build a typed GLSL expr
+#[cfg(test)]
+fn gexpr(kind: ExprKind, typ: Type) -> Expr {
+    Expr{ kind, typ }
+}
+
+/// Prints expressions w/ WGSL literals, constructors & the parens WGSL requires
+#[test]
+fn test_wgsl_expr() {
+    let v = |n: &str| gexpr(ExprKind::Var(n.to_string()), Type::Int);
+    let bin = |op: BinOp, lhs: Expr, rhs: Expr| gexpr(ExprKind::Binary{ op, lhs: Box::new(lhs), rhs: Box::new(rhs) }, Type::Int);
+    let r = Renames::new();
+
+    assert_eq!(print_expr(&gexpr(ExprKind::Int(2), Type::Int), &r), Ok("2i".to_string()));
+    assert_eq!(print_expr(&gexpr(ExprKind::Float(1.0), Type::Float), &r), Ok("1.0f".to_string()));
+    assert_eq!(
+        print_expr(&gexpr(ExprKind::Call{ name: "vec4".to_string(), args: vec![gexpr(ExprKind::Var("a".to_string()), Type::Float)] }, Type::Vec(4)), &r),
+        Ok("vec4<f32>(a)".to_string()),
+        "Failed to print a constructor"
+    );
+    // args are converted to the type of the components, as WGSL does not convert them implicitly
+    assert_eq!(
+        print_expr(&gexpr(ExprKind::Call{ name: "vec4".to_string(), args: vec![
+            gexpr(ExprKind::Int(0), Type::Int),
+            v("a"),
+            gexpr(ExprKind::Var("b".to_string()), Type::IVec(2))
+        ] }, Type::Vec(4)), &r),
+        Ok("vec4<f32>(0.0f, f32(a), vec2<f32>(b))".to_string()),
+        "Failed to convert the args of a constructor"
+    );
+    assert_eq!(
+        print_expr(&gexpr(ExprKind::Call{ name: "ivec2".to_string(), args: vec![gexpr(ExprKind::Float(1.5), Type::Float)] }, Type::IVec(2)), &r),
+        Ok("vec2<i32>(i32(1.5f))".to_string()),
+        "Failed to convert a float arg of an int vector"
+    );
+    assert_eq!(
+        print_expr(&gexpr(ExprKind::Call{ name: "dFdx".to_string(), args: vec![v("a")] }, Type::Float), &r),
+        Ok("dpdx(a)".to_string()),
+        "Failed to rename a built-in"
+    );
+    assert_eq!(
+        print_expr(&gexpr(ExprKind::Call{ name: "atan".to_string(), args: vec![v("a"), v("b")] }, Type::Float), &r),
+        Ok("atan2(a, b)".to_string()),
+        "Failed to rename the 2 arg 'atan'"
+    );
+    assert_eq!(
+        print_expr(&gexpr(ExprKind::Call{ name: "lessThan".to_string(), args: vec![v("a"), v("b")] }, Type::BVec(3)), &r),
+        Ok("(a < b)".to_string()),
+        "Failed to print a vector relational built-in as an operator"
+    );
+    assert_eq!(
+        print_expr(&gexpr(ExprKind::Call{ name: "not".to_string(), args: vec![v("a")] }, Type::BVec(3)), &r),
+        Ok("!(a)".to_string()),
+        "Failed to print 'not' as an operator"
+    );
+
+    // arithmetic follows precedence, as in GLSL
+    assert_eq!(print_expr(&bin(BinOp::Add, bin(BinOp::Mul, v("a"), v("b")), v("c")), &r), Ok("a * b + c".to_string()));
+    assert_eq!(print_expr(&bin(BinOp::Sub, v("a"), bin(BinOp::Sub, v("b"), v("c"))), &r), Ok("a - (b - c)".to_string()));
+    assert_eq!(print_expr(&bin(BinOp::Lt, bin(BinOp::Add, v("a"), v("b")), v("c")), &r), Ok("a + b < c".to_string()));
+
+    // but logical & bitwise operators may not be mixed
+    assert_eq!(print_expr(&bin(BinOp::And, bin(BinOp::And, v("a"), v("b")), v("c")), &r), Ok("a && b && c".to_string()));
+    assert_eq!(print_expr(&bin(BinOp::Or, bin(BinOp::And, v("a"), v("b")), v("c")), &r), Ok("(a && b) || c".to_string()));
+    assert_eq!(print_expr(&bin(BinOp::BitAnd, v("a"), bin(BinOp::Add, v("b"), v("c"))), &r), Ok("a & (b + c)".to_string()));
+    assert_eq!(print_expr(&bin(BinOp::Eq, bin(BinOp::Lt, v("a"), v("b")), v("c")), &r), Ok("(a < b) == c".to_string()));
+
+    // a negated negative literal is never printed as a decrement
+    let neg = |e: Expr| gexpr(ExprKind::Unary{ op: UnOp::Neg, e: Box::new(e.clone()) }, e.typ);
+    assert_eq!(print_expr(&neg(neg(v("a"))), &r), Ok("-(-a)".to_string()));
+    assert_eq!(print_expr(&neg(gexpr(ExprKind::Float(-1.0), Type::Float)), &r), Ok("-(-1.0f)".to_string()), "Failed to wrap a negative float");
+    assert_eq!(print_expr(&neg(gexpr(ExprKind::Int(-2), Type::Int)), &r), Ok("-(-2i)".to_string()), "Failed to wrap a negative int");
+}
+
+/// Prints shaders w/ entry points, a uniform struct & output structs
+#[test]
+fn test_wgsl_shader() {
+    let progs = compile_str("\
+uniform Vec4 uColor
+uniform Float uTime
+let swap : (Float, Float) -> (Float, Float) = (x : Float, y : Float) {
+  (y, x)
+}
+vert v : (Vec3 aPos) -> (Float vT) = {
+  mut t : (Float, Float) = swap(uTime, 1.0f)
+  set gl_Position uColor
+  out vT uTime
+}
+frag f : (Float vT) -> () = {
+  set gl_FragColor uColor
+}
+p : Prog = mkProg v f
+");
+    assert_eq!(print_shader(&progs[0].vert), Ok("\
+struct Tuple0 {
+    _0: f32,
+    _1: f32,
+}
+
+struct Uniforms {
+    uColor: vec4<f32>,
+    uTime: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) fdssl_Position: vec4<f32>,
+    @location(0) vT: f32,
+}
+
+fn swap(x: f32, y: f32) -> Tuple0 {
+    return Tuple0(y, x);
+}
+
+@vertex
+fn main(@location(0) aPos: vec3<f32>) -> VertexOutput {
+    var fdssl_out: VertexOutput;
+    var t: Tuple0 = swap(fdssl_u.uTime, 1.0f);
+    fdssl_out.fdssl_Position = fdssl_u.uColor;
+    fdssl_out.vT = fdssl_u.uTime;
+    return fdssl_out;
+}
+".to_string()), "Failed to print vertex shader");
+    assert_eq!(print_shader(&progs[0].frag), Ok("\
+struct Uniforms {
+    uColor: vec4<f32>,
+    uTime: f32,
+}
+
+@group(0) @binding(0) var<uniform> fdssl_u: Uniforms;
+
+struct FragmentOutput {
+    @location(0) fdssl_FragColor: vec4<f32>,
+}
+
+@fragment
+fn main(@location(0) vT: f32) -> FragmentOutput {
+    var fdssl_out: FragmentOutput;
+    fdssl_out.fdssl_FragColor = fdssl_u.uColor;
+    return fdssl_out;
+}
+".to_string()), "Failed to print fragment shader");
+
+    // 'gl_FragColor' is located at 0, ahead of the other outputs
+    let progs = compile_str("\
+vert v : () -> () = {
+  set gl_Position vec4(1.0f)
+}
+frag f : () -> (Vec4 normal) = {
+  set gl_FragColor vec4(1.0f)
+  out normal vec4(0.0f)
+}
+p : Prog = mkProg v f
+");
+    let frag = print_shader(&progs[0].frag).unwrap();
+    assert!(
+        frag.contains("    @location(0) fdssl_FragColor: vec4<f32>,\n    @location(1) normal: vec4<f32>,\n"),
+        "Failed to locate gl_FragColor before the other outputs, got:\n{}", frag
+    );
+
+    // parameters shadow uniforms, & values are discarded explicitly
+    let progs = compile_str("\
+uniform Float x
+let id : Float -> Float = (x : Float) {
+  x
+}
+vert v : () -> () = {
+  id(x)
+}
+frag f : () -> () = {
+  1
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert).unwrap();
+    assert!(vert.contains("fn id(x: f32) -> f32 {\n    return x;\n}"), "Failed to shadow a uniform w/ a parameter");
+    assert!(vert.contains("    _ = id(fdssl_u.x);\n"), "Failed to discard a value");
+    assert!(print_shader(&progs[0].frag).unwrap().ends_with("@fragment\nfn main() {\n    _ = 1i;\n}\n"), "Failed to print a fragment shader w/out outputs");
+}
+
+/// Prints while loops as they are, & do-while loops as a loop that breaks in its continuing block
+#[test]
+fn test_wgsl_loops() {
+    let progs = compile_str("\
+uniform Vec4 uColor
+vert v : () -> (Float vT) = {
+  mut t : Float = 0.0f
+  for (mut i : Int = 0, i < 4, i += 1) {
+    t += 1.0f
+  }
+  while t > 1.0f {
+    t -= 1.0f
+  }
+  do {
+    t *= 2.0f
+  } while t < 1.0f
+  out vT t
+}
+frag f : (Float vT) -> () = {
+  set gl_FragColor uColor
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert).unwrap();
+    assert!(vert.contains("\
+    while (t > 1.0f) {
+        t = t - 1.0f;
+    }
+    loop {
+        t = t * 2.0f;
+        continuing {
+            break if !(t < 1.0f);
+        }
+    }
+"), "Failed to print loops, got:\n{}", vert);
+}
+
+/// Tests that arrays print as WGSL arrays, & that uniform arrays must have 16 byte aligned elements
+#[test]
+fn test_wgsl_arrays() {
+    let prog = |t: &str| compile_str(&format!("\
+uniform {t}[4] uColors
+uniform Vec4 uTint
+vert v : (Int aIdx) -> ({t} vColor) = {{
+  out vColor uColors[aIdx]
+}}
+frag f : () -> () = {{
+  set gl_FragColor uTint
+}}
+p : Prog = mkProg v f
+"));
+    let vert = print_shader(&prog("Vec4")[0].vert).unwrap();
+    assert!(vert.contains("    uColors: array<vec4<f32>, 4>,\n"), "Failed to print a uniform array, got:\n{}", vert);
+    assert!(vert.contains("fdssl_out.vColor = fdssl_u.uColors[aIdx];\n"), "Failed to print an indexed uniform, got:\n{}", vert);
+    assert_eq!(
+        print_shader(&prog("Float")[0].vert),
+        Err("Uniform 'uColors' is an array of 'f32', which WGSL only allows in a uniform buffer for vec4 or matrix elements".to_string()),
+        "Failed to reject a uniform array w/ unaligned elements"
+    );
+}
+
+/// Swizzles are read w/ any set of selectors, but only a single component is assigned to at a time
+#[test]
+fn test_wgsl_swizzles() {
+    let prog = |update: &str| compile_str(&format!("\
+uniform Vec4 uTint
+vert v : (Vec3 aPos) -> () = {{
+  mut p : Vec4 = vec4(aPos.stp, 1.0f)
+  {update}
+  set gl_Position p
+}}
+frag f : () -> () = {{
+  set gl_FragColor uTint.bgra
+}}
+p : Prog = mkProg v f
+"));
+    let progs = prog("set p.z 0.5f");
+    let vert = print_shader(&progs[0].vert).unwrap();
+    assert!(vert.contains("var p: vec4<f32> = vec4<f32>(aPos.xyz, 1.0f);\n"), "Failed to print a texture coordinate swizzle, got:\n{}", vert);
+    assert!(vert.contains("p.z = 0.5f;\n"), "Failed to print an assignment to a component, got:\n{}", vert);
+    let frag = print_shader(&progs[0].frag).unwrap();
+    assert!(frag.contains("fdssl_u.uTint.bgra"), "Failed to print a color swizzle, got:\n{}", frag);
+    assert_eq!(
+        print_shader(&prog("set p.xy aPos.zy")[0].vert),
+        Err("Swizzle 'xy' cannot be assigned to in WGSL, which only writes to a single component of a vector at a time".to_string()),
+        "Failed to reject an assignment to a swizzle of several components"
+    );
+}
+
+/// Passes built-in inputs of a fragment shader to its entry point, & rejects those of point sprites
+#[test]
+fn test_wgsl_stages() {
+    let progs = compile_str("\
+vert v : (Vec2 aUV) -> (Vec2 vUV) = {
+  set gl_Position vec4(aUV, 0.0f, 1.0f)
+  set gl_PointSize 4.0f
+  out vUV aUV
+}
+frag f : (Vec2 vUV) -> () = {
+  if gl_FrontFacing { discard } else { set gl_FragColor vec4(gl_FragCoord.xy * vUV, 0.0f, 1.0f) }
+}
+p : Prog = mkProg v f
+");
+    assert!(print_shader(&progs[0].vert).unwrap_err().contains("'gl_PointSize' is not supported by WGSL"), "Failed to reject 'gl_PointSize'");
+    let frag = print_shader(&progs[0].frag).unwrap();
+    assert!(frag.contains("fn main(@location(0) vUV: vec2<f32>, @builtin(position) fdssl_FragCoord: vec4<f32>, @builtin(front_facing) fdssl_FrontFacing: bool) -> FragmentOutput {\n"), "Failed to pass built-in inputs, got:\n{}", frag);
+    assert!(frag.contains("if (fdssl_FrontFacing) {\n        discard;\n    }"), "Failed to print a discard, got:\n{}", frag);
+    assert!(frag.contains("fdssl_FragCoord.xy * vUV"), "Failed to rename 'gl_FragCoord', got:\n{}", frag);
+}
+
+/// Numbers the overloads of a function, which WGSL does not allow, & resolves each call to one of them
+#[test]
+fn test_wgsl_overloads() {
+    let progs = compile_str("\
+let scale : Float -> Float = (x : Float) { x * 2.0f }
+let scale : Vec4 -> Vec4 = (v : Vec4) { v * 2.0f }
+vert v : (Vec4 aPos) -> () = {
+  set gl_Position scale(aPos) * scale(0.5f)
+}
+frag f : () -> () = {
+  set gl_FragColor vec4(1.0f)
+}
+p : Prog = mkProg v f
+");
+    let vert = print_shader(&progs[0].vert).unwrap();
+    assert!(vert.contains("fn fdssl_scale_0(x: f32) -> f32 {\n"), "Failed to rename the first overload, got:\n{}", vert);
+    assert!(vert.contains("fn fdssl_scale_1(v: vec4<f32>) -> vec4<f32> {\n"), "Failed to rename the second overload, got:\n{}", vert);
+    assert!(vert.contains("fdssl_scale_1(aPos) * fdssl_scale_0(0.5f)"), "Failed to resolve calls of overloads, got:\n{}", vert);
+}
+
+/// Compares the shaders printed for the examples against the snapshots in examples/wgsl
+/// Set FDSSL_UPDATE_SNAPSHOTS to rewrite the snapshots instead
+#[test]
+fn test_wgsl_examples() {
+    let dir = Path::new("examples/wgsl");
+    for f in ["e0", "e2", "e3", "e4", "e5", "e6", "shaderCompExampleProg"] {
+        let src = fs::read_to_string(format!("examples/{f}.fdssl")).expect("Failed to read example");
+        for p in compile_str(&src) {
+            if std::env::var("FDSSL_UPDATE_SNAPSHOTS").is_ok() {
+                fs::create_dir_all(dir).expect("Failed to create snapshot directory");
+                write_program(&p, dir).expect("Failed to write snapshots");
+            }
+            for (ext, s) in [("vert", &p.vert), ("frag", &p.frag)] {
+                let expected = fs::read_to_string(dir.join(format!("{}.{}.wgsl", p.name, ext))).expect("Failed to read snapshot");
+                assert_eq!(print_shader(s), Ok(expected), "Shader '{}.{}.wgsl' does not match its snapshot", p.name, ext);
+            }
+        }
+    }
+}